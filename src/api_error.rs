@@ -0,0 +1,197 @@
+//! `#[derive(ApiError)]` implementation.
+//!
+//! Maps each variant of an error enum to an HTTP status
+//! (`#[api_error(status = 404)]`), answers with an RFC 9457 problem body and
+//! documents the statuses through `utoipa::IntoResponses`, which controllers
+//! pick up for routes returning the error.
+
+use darling::{FromDeriveInput, FromVariant};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{DeriveInput, parse2};
+
+#[derive(FromDeriveInput)]
+#[darling(attributes(api_error), supports(enum_any))]
+struct ApiErrorInput {
+    ident: syn::Ident,
+    generics: syn::Generics,
+    data: darling::ast::Data<ApiErrorVariant, ()>,
+}
+
+#[derive(FromVariant)]
+#[darling(attributes(api_error), forward_attrs(doc))]
+struct ApiErrorVariant {
+    ident: syn::Ident,
+    fields: darling::ast::Fields<()>,
+    attrs: Vec<syn::Attribute>,
+    /// HTTP status (default 500)
+    #[darling(default)]
+    status: Option<syn::LitInt>,
+    /// Problem title (default: the status' reason phrase)
+    #[darling(default)]
+    title: Option<String>,
+}
+
+impl ApiErrorVariant {
+    /// First line of the variant's doc comment
+    fn doc(&self) -> Option<String> {
+        self.attrs.iter().find_map(|attr| match &attr.meta {
+            syn::Meta::NameValue(nv) if nv.path.is_ident("doc") => match &nv.value {
+                syn::Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Str(s),
+                    ..
+                }) => Some(s.value().trim().to_string()).filter(|s| !s.is_empty()),
+                _ => None,
+            },
+            _ => None,
+        })
+    }
+}
+
+/// Process `#[derive(ApiError)]`
+pub fn derive_api_error_impl(input: TokenStream) -> TokenStream {
+    let input: DeriveInput = match parse2(input) {
+        Ok(input) => input,
+        Err(e) => return e.to_compile_error(),
+    };
+    let input = match ApiErrorInput::from_derive_input(&input) {
+        Ok(input) => input,
+        Err(e) => return e.write_errors(),
+    };
+    let Some(variants) = input.data.take_enum() else {
+        return syn::Error::new_spanned(&input.ident, "`ApiError` can only be derived for enums")
+            .to_compile_error();
+    };
+
+    let mut errors = Vec::new();
+    let mut status_arms = Vec::new();
+    let mut title_arms = Vec::new();
+    // Documented statuses with the descriptions of their variants
+    let mut documented: Vec<(u16, Vec<String>)> = Vec::new();
+
+    for variant in &variants {
+        let ident = &variant.ident;
+        let pattern = match variant.fields.style {
+            darling::ast::Style::Unit => quote! { Self::#ident },
+            darling::ast::Style::Tuple => quote! { Self::#ident(..) },
+            darling::ast::Style::Struct => quote! { Self::#ident { .. } },
+        };
+        let status = match &variant.status {
+            Some(lit) => match lit.base10_parse::<u16>() {
+                Ok(status) if (100..=999).contains(&status) => status,
+                _ => {
+                    errors.push(syn::Error::new_spanned(lit, "expected an HTTP status code"));
+                    continue;
+                }
+            },
+            None => 500,
+        };
+        status_arms.push(quote! { #pattern => #status, });
+        if let Some(title) = &variant.title {
+            title_arms.push(quote! { #pattern => Some(#title), });
+        }
+
+        let description = variant
+            .doc()
+            .or_else(|| variant.title.clone())
+            .unwrap_or_else(|| ident.to_string());
+        match documented.iter_mut().find(|(s, _)| *s == status) {
+            Some((_, descriptions)) => descriptions.push(description),
+            None => documented.push((status, vec![description])),
+        }
+    }
+    if !errors.is_empty() {
+        let errors = errors.iter().map(syn::Error::to_compile_error);
+        return quote! { #(#errors)* };
+    }
+    documented.sort_by_key(|(status, _)| *status);
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let title_match = if title_arms.is_empty() {
+        quote! { None::<&'static str> }
+    } else {
+        quote! {
+            match self {
+                #(#title_arms)*
+                #[allow(unreachable_patterns)]
+                _ => None,
+            }
+        }
+    };
+    let responses = documented.iter().map(|(status, descriptions)| {
+        let status = status.to_string();
+        let description = descriptions.join("; ");
+        quote! {
+            responses.insert(
+                #status.to_string(),
+                eywa_axum::utoipa::openapi::ResponseBuilder::new()
+                    .description(#description)
+                    .content(
+                        "application/problem+json",
+                        eywa_axum::utoipa::openapi::ContentBuilder::new()
+                            .schema(Some(problem.clone()))
+                            .build(),
+                    )
+                    .build()
+                    .into(),
+            );
+        }
+    });
+
+    quote! {
+        impl #impl_generics #ident #ty_generics #where_clause {
+            /// HTTP status of this error
+            pub fn status_code(&self) -> eywa_axum::axum::http::StatusCode {
+                let status: u16 = match self {
+                    #(#status_arms)*
+                };
+                eywa_axum::axum::http::StatusCode::from_u16(status)
+                    .unwrap_or(eywa_axum::axum::http::StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        }
+
+        impl #impl_generics eywa_axum::axum::response::IntoResponse for #ident #ty_generics #where_clause {
+            fn into_response(self) -> eywa_axum::axum::response::Response {
+                let status = self.status_code();
+                let title = #title_match
+                    .or(status.canonical_reason())
+                    .unwrap_or("Error");
+                (
+                    status,
+                    [(eywa_axum::axum::http::header::CONTENT_TYPE, "application/problem+json")],
+                    eywa_axum::serde_json::json!({
+                        "type": "about:blank",
+                        "title": title,
+                        "status": status.as_u16(),
+                    })
+                    .to_string(),
+                )
+                    .into_response()
+            }
+        }
+
+        impl #impl_generics eywa_axum::utoipa::IntoResponses for #ident #ty_generics #where_clause {
+            fn responses() -> std::collections::BTreeMap<
+                String,
+                eywa_axum::utoipa::openapi::RefOr<eywa_axum::utoipa::openapi::response::Response>,
+            > {
+                use eywa_axum::utoipa::openapi::schema::{ObjectBuilder, Type};
+
+                let problem: eywa_axum::utoipa::openapi::RefOr<eywa_axum::utoipa::openapi::schema::Schema> =
+                    ObjectBuilder::new()
+                        .property("type", ObjectBuilder::new().schema_type(Type::String))
+                        .property("title", ObjectBuilder::new().schema_type(Type::String))
+                        .property("status", ObjectBuilder::new().schema_type(Type::Integer))
+                        .property("detail", ObjectBuilder::new().schema_type(Type::String))
+                        .required("type")
+                        .required("title")
+                        .required("status")
+                        .into();
+                let mut responses = std::collections::BTreeMap::new();
+                #(#responses)*
+                responses
+            }
+        }
+    }
+}
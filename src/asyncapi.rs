@@ -0,0 +1,144 @@
+//! AsyncAPI document generation.
+//!
+//! Routes taking a `WebSocketUpgrade` or returning `Sse<_>` become channels of
+//! an AsyncAPI 2.6 document built by the controller's `asyncapi_json()`, with
+//! the payloads from `messages(send = ..., receive = ...)` as component schemas.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use crate::parse::MessagesInfo;
+
+/// Realtime transport of a route
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RealtimeKind {
+    WebSocket,
+    Sse,
+}
+
+/// A realtime route documented as an AsyncAPI channel
+pub struct Channel<'a> {
+    pub operation_id: String,
+    /// Full route path template (e.g., "/api/projects/{id}/events")
+    pub path: String,
+    pub kind: RealtimeKind,
+    pub messages: Option<&'a MessagesInfo>,
+}
+
+/// Detects WebSocket (a `WebSocketUpgrade` argument) and SSE (`Sse<_>` or
+/// `Result<Sse<_>, _>` return type) routes
+pub fn realtime_kind(sig: &syn::Signature) -> Option<RealtimeKind> {
+    let last_ident = |ty: &syn::Type| match ty {
+        syn::Type::Path(tp) => tp.path.segments.last().map(|s| s.ident.to_string()),
+        _ => None,
+    };
+    let upgrades = sig.inputs.iter().any(|arg| match arg {
+        syn::FnArg::Typed(pat) => last_ident(&pat.ty).as_deref() == Some("WebSocketUpgrade"),
+        syn::FnArg::Receiver(_) => false,
+    });
+    if upgrades {
+        return Some(RealtimeKind::WebSocket);
+    }
+
+    let syn::ReturnType::Type(_, ty) = &sig.output else {
+        return None;
+    };
+    let mut ty: &syn::Type = ty;
+    if let syn::Type::Path(tp) = ty
+        && let Some(seg) = tp.path.segments.last()
+        && seg.ident == "Result"
+        && let syn::PathArguments::AngleBracketed(args) = &seg.arguments
+        && let Some(syn::GenericArgument::Type(ok)) = args.args.first()
+    {
+        ty = ok;
+    }
+    (last_ident(ty).as_deref() == Some("Sse")).then_some(RealtimeKind::Sse)
+}
+
+/// Generates the `asyncapi_json()` builder for the controller's inherent impl
+pub fn generate_asyncapi_json(title: &str, channels: &[Channel]) -> TokenStream {
+    let entries = channels.iter().map(|channel| {
+        let path = &channel.path;
+        let operation_id = &channel.operation_id;
+        let send = channel.messages.and_then(|m| m.send.as_ref());
+        let receive = channel.messages.and_then(|m| m.receive.as_ref());
+
+        let subscribe = message_operation(operation_id, send);
+        let publish = match receive {
+            Some(_) => {
+                let operation = message_operation(&format!("{}_receive", operation_id), receive);
+                quote! { channel.insert("publish".to_string(), #operation); }
+            }
+            None => quote! {},
+        };
+        let bindings = match channel.kind {
+            RealtimeKind::WebSocket => quote! { { "ws": { "method": "GET" } } },
+            RealtimeKind::Sse => {
+                quote! { { "http": { "method": "GET", "contentType": "text/event-stream" } } }
+            }
+        };
+
+        quote! {
+            {
+                let mut channel = eywa_axum::serde_json::Map::new();
+                channel.insert("subscribe".to_string(), #subscribe);
+                #publish
+                channel.insert(
+                    "bindings".to_string(),
+                    eywa_axum::serde_json::json!(#bindings),
+                );
+                channels.insert(#path.to_string(), channel.into());
+            }
+        }
+    });
+
+    quote! {
+        /// Builds an AsyncAPI 2.6 document for this controller's WebSocket and SSE routes.
+        pub fn asyncapi_json() -> eywa_axum::serde_json::Value {
+            let mut schemas = eywa_axum::serde_json::Map::new();
+            let mut channels = eywa_axum::serde_json::Map::new();
+            #(#entries)*
+            eywa_axum::serde_json::json!({
+                "asyncapi": "2.6.0",
+                "info": { "title": #title, "version": env!("CARGO_PKG_VERSION") },
+                "channels": channels,
+                "components": { "schemas": schemas },
+            })
+        }
+    }
+}
+
+/// An AsyncAPI operation carrying `payload` (registered in `schemas`), if any
+fn message_operation(operation_id: &str, payload: Option<&syn::Type>) -> TokenStream {
+    let payload = match payload {
+        Some(ty) => quote! {
+            {
+                use utoipa::{PartialSchema, ToSchema};
+                let mut nested = Vec::new();
+                <#ty as ToSchema>::schemas(&mut nested);
+                for (name, schema) in nested {
+                    schemas.insert(name, eywa_axum::serde_json::to_value(schema).unwrap_or_default());
+                }
+                let name = <#ty as ToSchema>::name().to_string();
+                schemas.insert(
+                    name.clone(),
+                    eywa_axum::serde_json::to_value(<#ty as PartialSchema>::schema())
+                        .unwrap_or_default(),
+                );
+                message.insert(
+                    "payload".to_string(),
+                    eywa_axum::serde_json::json!({ "$ref": format!("#/components/schemas/{}", name) }),
+                );
+                message.insert("name".to_string(), name.into());
+            }
+        },
+        None => quote! {},
+    };
+    quote! {
+        {
+            let mut message = eywa_axum::serde_json::Map::new();
+            #payload
+            eywa_axum::serde_json::json!({ "operationId": #operation_id, "message": message })
+        }
+    }
+}
@@ -0,0 +1,188 @@
+//! Batch endpoint generation.
+//!
+//! `#[controller(batch)]` adds `POST {prefix}/_batch`, which accepts an array of
+//! sub-requests naming this controller's operations by operation id and runs
+//! each of them through the controller's own router (layers included). That
+//! router is built once, along with the one serving the batch endpoint.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use crate::wrappers::encode_path_segment;
+
+/// An operation reachable through the batch endpoint
+pub struct BatchOperation {
+    pub operation_id: String,
+    /// Upper-case HTTP method (e.g., "GET")
+    pub method: &'static str,
+    /// Full route path template (e.g., "/api/projects/{id}")
+    pub path: String,
+}
+
+/// Generates the `__eywa_batch` handler for the controller's inherent impl
+///
+/// Sub-requests run through the router given by [`generate_batch_router`]; their response
+/// bodies are read up to `body_limit` bytes (axum's default request body limit otherwise).
+pub fn generate_batch_handler(
+    operations: &[BatchOperation],
+    body_limit: Option<usize>,
+) -> TokenStream {
+    let arms = operations.iter().map(|op| {
+        let id = &op.operation_id;
+        let method = &op.method;
+        let path = &op.path;
+        quote! { #id => Some((#method, #path)), }
+    });
+    let body_limit = body_limit.unwrap_or(2 * 1024 * 1024);
+    let encode = encode_path_segment();
+
+    quote! {
+        /// Dispatches a batch of sub-requests through this controller's router.
+        #[doc(hidden)]
+        async fn __eywa_batch(
+            eywa_axum::axum::Extension(router): eywa_axum::axum::Extension<eywa_axum::axum::Router>,
+            headers: eywa_axum::axum::http::HeaderMap,
+            eywa_axum::Json(requests): eywa_axum::Json<Vec<self::__UTOIPA_PATHS__::__BatchRequest>>,
+        ) -> eywa_axum::Json<Vec<self::__UTOIPA_PATHS__::__BatchResponse>> {
+            use eywa_axum::tower::ServiceExt;
+
+            let encode = #encode;
+            let mut responses = Vec::with_capacity(requests.len());
+
+            for request in requests {
+                let operation: Option<(&str, &str)> = match request.operation_id.as_str() {
+                    #(#arms)*
+                    _ => None,
+                };
+                let Some((method, template)) = operation else {
+                    responses.push(self::__UTOIPA_PATHS__::__BatchResponse {
+                        operation_id: request.operation_id,
+                        status: 404,
+                        body: eywa_axum::serde_json::Value::Null,
+                    });
+                    continue;
+                };
+
+                let mut uri = template.to_string();
+                for (name, value) in &request.params {
+                    let value = encode(value);
+                    uri = uri
+                        .replace(&format!("{{{}}}", name), &value)
+                        .replace(&format!(":{}", name), &value);
+                }
+                if let Some(query) = &request.query {
+                    uri.push('?');
+                    uri.push_str(query);
+                }
+
+                let mut builder = eywa_axum::axum::http::Request::builder().method(method).uri(uri);
+                for (name, value) in &headers {
+                    if name != eywa_axum::axum::http::header::CONTENT_LENGTH
+                        && name != eywa_axum::axum::http::header::CONTENT_TYPE
+                    {
+                        builder = builder.header(name, value);
+                    }
+                }
+                let body = match &request.body {
+                    Some(body) => {
+                        builder = builder.header(
+                            eywa_axum::axum::http::header::CONTENT_TYPE,
+                            "application/json",
+                        );
+                        eywa_axum::axum::body::Body::from(body.to_string())
+                    }
+                    None => eywa_axum::axum::body::Body::empty(),
+                };
+
+                let (status, body) = match builder.body(body) {
+                    Ok(sub_request) => match router.clone().oneshot(sub_request).await {
+                        Ok(response) => {
+                            let status = response.status().as_u16();
+                            match eywa_axum::axum::body::to_bytes(response.into_body(), #body_limit).await {
+                                Ok(bytes) => {
+                                    let body = eywa_axum::serde_json::from_slice(&bytes)
+                                        .unwrap_or(eywa_axum::serde_json::Value::Null);
+                                    (status, body)
+                                }
+                                // Too large (or broken) to be included in the batch response
+                                Err(_) => (500, eywa_axum::serde_json::Value::Null),
+                            }
+                        }
+                        Err(never) => match never {},
+                    },
+                    Err(_) => (400, eywa_axum::serde_json::Value::Null),
+                };
+
+                responses.push(self::__UTOIPA_PATHS__::__BatchResponse {
+                    operation_id: request.operation_id,
+                    status,
+                    body,
+                });
+            }
+
+            eywa_axum::Json(responses)
+        }
+    }
+}
+
+/// Generates statements building the router of batch sub-requests from `routes` and `state`,
+/// handed to the batch handler as an extension of `routes`
+///
+/// Its own batch endpoint lacks the extension, so batches can't be nested.
+pub fn generate_batch_router() -> TokenStream {
+    quote! {
+        let batch: eywa_axum::axum::Router =
+            Self::__eywa_layers(routes.clone(), &state).with_state(state.clone());
+        let routes = routes.layer(eywa_axum::axum::Extension(batch));
+    }
+}
+
+/// Generates the documentation stub and schema structs for `__UTOIPA_PATHS__`
+pub fn generate_batch_docs(path: &str, tag: &str, operations: &[BatchOperation]) -> TokenStream {
+    let ids: Vec<_> = operations
+        .iter()
+        .map(|op| op.operation_id.as_str())
+        .collect();
+    let id_doc = format!("Operation to invoke, one of: {}", ids.join(", "));
+
+    quote! {
+        /// A sub-request of a batch call
+        #[derive(eywa_axum::Serialize, eywa_axum::Deserialize, eywa_axum::utoipa::ToSchema)]
+        pub struct __BatchRequest {
+            #[doc = #id_doc]
+            pub operation_id: String,
+            /// Path parameters by name
+            #[serde(default)]
+            pub params: std::collections::HashMap<String, String>,
+            /// Raw query string
+            #[serde(default)]
+            pub query: Option<String>,
+            /// JSON request body
+            #[serde(default)]
+            #[schema(value_type = Option<Object>)]
+            pub body: Option<eywa_axum::serde_json::Value>,
+        }
+
+        /// Result of a single sub-request
+        #[derive(eywa_axum::Serialize, eywa_axum::Deserialize, eywa_axum::utoipa::ToSchema)]
+        pub struct __BatchResponse {
+            pub operation_id: String,
+            pub status: u16,
+            #[schema(value_type = Object)]
+            pub body: eywa_axum::serde_json::Value,
+        }
+
+        #[utoipa::path(
+            post,
+            path = #path,
+            tag = #tag,
+            summary = "Run several operations in one request",
+            request_body = Vec<__BatchRequest>,
+            responses(
+                (status = 200, description = "Per-operation results, in request order", body = Vec<__BatchResponse>),
+            ),
+        )]
+        #[allow(dead_code)]
+        pub async fn __eywa_batch() {}
+    }
+}
@@ -1,7 +1,14 @@
 //! Code generation utilities
 
 use proc_macro2::TokenStream;
-use quote::quote;
+use quote::{format_ident, quote, quote_spanned};
+use syn::spanned::Spanned;
+
+use crate::parse::{
+    CircuitBreakerSettings, LogField, LogOptions, OAuth2Args, ParamConstraint, RangeOptions,
+    TenantStrategy, status_name,
+};
+use crate::paths::path_params;
 
 /// Generates the IntoRouter trait implementation
 #[allow(dead_code)]
@@ -23,3 +30,1458 @@ pub fn generate_into_router_trait() -> TokenStream {
         }
     }
 }
+
+/// Generates a route layer capping a streamed request body at `max` bytes: 413 when the
+/// declared `Content-Length` is larger, and a body error once more is read
+pub fn generate_upload_limit_layer(max: usize) -> TokenStream {
+    quote! {
+        .layer(eywa_axum::tower_http::limit::RequestBodyLimitLayer::new(#max))
+    }
+}
+
+/// Registers the `oauth2` security scheme of a validated `oauth2(...)` in `components`
+pub fn register_oauth2_scheme(oauth2: &OAuth2Args) -> TokenStream {
+    let auth_url = oauth2.auth_url.as_deref().unwrap_or_default();
+    let token_url = oauth2.token_url.as_deref().unwrap_or_default();
+    let names = oauth2.scopes.0.iter().map(|(name, _)| name);
+    let descriptions = oauth2.scopes.0.iter().map(|(_, description)| description);
+    let scopes = quote! {
+        utoipa::openapi::security::Scopes::from_iter([#((#names, #descriptions)),*])
+    };
+    let flow = match (oauth2.flow.value().as_str(), &oauth2.refresh_url) {
+        ("authorization_code", None) => quote! {
+            AuthorizationCode(utoipa::openapi::security::AuthorizationCode::new(
+                #auth_url, #token_url, #scopes,
+            ))
+        },
+        ("authorization_code", Some(refresh_url)) => quote! {
+            AuthorizationCode(utoipa::openapi::security::AuthorizationCode::with_refresh_url(
+                #auth_url, #token_url, #scopes, #refresh_url,
+            ))
+        },
+        ("implicit", None) => quote! {
+            Implicit(utoipa::openapi::security::Implicit::new(#auth_url, #scopes))
+        },
+        ("implicit", Some(refresh_url)) => quote! {
+            Implicit(utoipa::openapi::security::Implicit::with_refresh_url(
+                #auth_url, #scopes, #refresh_url,
+            ))
+        },
+        ("password", None) => quote! {
+            Password(utoipa::openapi::security::Password::new(#token_url, #scopes))
+        },
+        ("password", Some(refresh_url)) => quote! {
+            Password(utoipa::openapi::security::Password::with_refresh_url(
+                #token_url, #scopes, #refresh_url,
+            ))
+        },
+        (_, None) => quote! {
+            ClientCredentials(utoipa::openapi::security::ClientCredentials::new(#token_url, #scopes))
+        },
+        (_, Some(refresh_url)) => quote! {
+            ClientCredentials(utoipa::openapi::security::ClientCredentials::with_refresh_url(
+                #token_url, #scopes, #refresh_url,
+            ))
+        },
+    };
+    quote! {
+        components.add_security_scheme(
+            "oauth2",
+            utoipa::openapi::security::SecurityScheme::OAuth2(
+                utoipa::openapi::security::OAuth2::new([
+                    utoipa::openapi::security::Flow::#flow,
+                ]),
+            ),
+        );
+    }
+}
+
+/// Documents the streamed upload body of an operation as binary
+pub fn operation_binary_body(content_type: &str) -> TokenStream {
+    quote! {
+        if let Some(content) = operation
+            .request_body
+            .as_mut()
+            .and_then(|body| body.content.get_mut(#content_type))
+        {
+            use eywa_axum::utoipa::openapi::schema::{KnownFormat, ObjectBuilder, SchemaFormat, Type};
+            content.schema = Some(
+                ObjectBuilder::new()
+                    .schema_type(Type::String)
+                    .format(Some(SchemaFormat::KnownFormat(KnownFormat::Binary)))
+                    .into(),
+            );
+        }
+    }
+}
+
+/// Generates a layer recording request and response body sizes per matched route
+pub fn generate_body_size_layer() -> TokenStream {
+    quote! {
+        .layer(eywa_axum::axum::middleware::from_fn(
+            |req: eywa_axum::axum::extract::Request, next: eywa_axum::axum::middleware::Next| async move {
+                use eywa_axum::axum::body::HttpBody;
+
+                let route = req
+                    .extensions()
+                    .get::<eywa_axum::axum::extract::MatchedPath>()
+                    .map(|p| p.as_str().to_owned())
+                    .unwrap_or_default();
+                let method = req.method().clone();
+                let request_bytes = req.body().size_hint().exact();
+
+                let response = next.run(req).await;
+                let response_bytes = response.body().size_hint().exact();
+
+                eywa_axum::tracing::info!(
+                    target: "eywa_axum::body_size",
+                    %route,
+                    %method,
+                    request_bytes = ?request_bytes,
+                    response_bytes = ?response_bytes,
+                    "body sizes"
+                );
+                response
+            }
+        ))
+    }
+}
+
+/// Generates a route layer logging one structured line per request with the
+/// route template, status and latency (plus the selected headers, request
+/// extensions and body). `sensitive` fields are redacted from JSON and form bodies
+/// and from headers
+///
+/// Logged bodies are read within the controller's `max_request_size` (axum's default
+/// otherwise), or `body_limit` for routes with their own limit.
+pub fn generate_log_layer(
+    template: &str,
+    log: &LogOptions,
+    sensitive: &[String],
+    fields: &[LogField],
+    body_limit: Option<usize>,
+) -> TokenStream {
+    let level = &log.level;
+    let is_sensitive = |name: &str| sensitive.iter().any(|s| s.eq_ignore_ascii_case(name));
+    let header_entries = log.headers.iter().map(|name| {
+        let redact = is_sensitive(name);
+        quote! { (#name, #redact) }
+    });
+    let capture_body = if log.body {
+        let buffer_body = buffer_request_body();
+        let redact_body = redact_body(sensitive);
+        quote! {
+            #buffer_body
+            let body = #redact_body;
+            let req = eywa_axum::axum::extract::Request::from_parts(
+                parts,
+                eywa_axum::axum::body::Body::from(bytes),
+            );
+        }
+    } else {
+        quote! {}
+    };
+    let capture_headers = (!log.headers.is_empty()).then(|| {
+        quote! {
+            let headers: Vec<(&str, String)> = [#(#header_entries),*]
+                .into_iter()
+                .filter_map(|(name, redact)| {
+                    let value = req.headers().get(name)?.to_str().ok()?;
+                    Some((name, if redact { "[REDACTED]".to_owned() } else { value.to_owned() }))
+                })
+                .collect();
+        }
+    });
+    // Read before the handler consumes the request; `-` when the extension is missing
+    let capture_fields = fields.iter().map(|field| {
+        let local = quote::format_ident!("__log_{}", field.name);
+        let extension = &field.extension;
+        // A missing `Debug` impl is reported at the extension
+        quote_spanned! {extension.span()=>
+            let #local = req
+                .extensions()
+                .get::<#extension>()
+                .map_or_else(|| "-".to_owned(), |value| format!("{:?}", value));
+        }
+    });
+    let extension_fields = fields.iter().map(|field| {
+        let name = &field.name;
+        let local = quote::format_ident!("__log_{}", name);
+        quote! { #name = %#local, }
+    });
+    let body_field = log.body.then(|| quote! { %body, });
+    let route_limit = body_limit.filter(|_| log.body).map(route_body_limit);
+    let headers_field = (!log.headers.is_empty()).then(|| quote! { headers = ?headers, });
+
+    quote! {
+        .layer(eywa_axum::axum::middleware::from_fn(
+            |req: eywa_axum::axum::extract::Request, next: eywa_axum::axum::middleware::Next| async move {
+                let started = std::time::Instant::now();
+                let method = req.method().clone();
+                #capture_headers
+                #(#capture_fields)*
+                #capture_body
+
+                let response = next.run(req).await;
+
+                eywa_axum::tracing::#level!(
+                    target: "eywa_axum::request",
+                    route = #template,
+                    %method,
+                    status = response.status().as_u16(),
+                    latency_ms = started.elapsed().as_millis() as u64,
+                    #(#extension_fields)*
+                    #headers_field
+                    #body_field
+                    "request"
+                );
+                response
+            }
+        ))
+        #route_limit
+    }
+}
+
+/// Generates a route layer recording each request on the `eywa_axum::audit` target once the
+/// handler answered: route template, method, status, path parameters and body, with
+/// `sensitive` fields redacted
+///
+/// The body is read within the same limits as logged bodies (see [`generate_log_layer`]).
+pub fn generate_audit_layer(
+    template: &str,
+    sensitive: &[String],
+    body_limit: Option<usize>,
+) -> TokenStream {
+    let buffer_body = buffer_request_body();
+    let redact_body = redact_body(sensitive);
+    let route_limit = body_limit.map(route_body_limit);
+
+    quote! {
+        .layer(eywa_axum::axum::middleware::from_fn(
+            |req: eywa_axum::axum::extract::Request, next: eywa_axum::axum::middleware::Next| async move {
+                use eywa_axum::axum::extract::FromRequestParts;
+
+                const SENSITIVE: &[&str] = &[#(#sensitive),*];
+                let method = req.method().clone();
+                let (mut parts, body) = req.into_parts();
+                let params: Vec<(String, String)> =
+                    eywa_axum::axum::extract::RawPathParams::from_request_parts(&mut parts, &())
+                        .await
+                        .map(|params| {
+                            params
+                                .iter()
+                                .map(|(name, value)| {
+                                    let value = if SENSITIVE.contains(&name) { "[REDACTED]" } else { value };
+                                    (name.to_owned(), value.to_owned())
+                                })
+                                .collect()
+                        })
+                        .unwrap_or_default();
+                let req = eywa_axum::axum::extract::Request::from_parts(parts, body);
+                #buffer_body
+                let body = #redact_body;
+                let req = eywa_axum::axum::extract::Request::from_parts(
+                    parts,
+                    eywa_axum::axum::body::Body::from(bytes),
+                );
+
+                let response = next.run(req).await;
+
+                eywa_axum::tracing::info!(
+                    target: "eywa_axum::audit",
+                    route = #template,
+                    %method,
+                    status = response.status().as_u16(),
+                    params = ?params,
+                    %body,
+                    "audit"
+                );
+                response
+            }
+        ))
+        #route_limit
+    }
+}
+
+/// Generates statements buffering the body of `req` into `bytes`, leaving its `parts`
+///
+/// Reads within the body limit in effect, answering 413 or 400 like the handler's extractor.
+pub fn buffer_request_body() -> TokenStream {
+    quote! {
+        let (parts, body) = req.into_parts();
+        let bytes = match <eywa_axum::axum::body::Bytes as eywa_axum::axum::extract::FromRequest<()>>::from_request(
+            eywa_axum::axum::extract::Request::from_parts(parts.clone(), body),
+            &(),
+        )
+        .await
+        {
+            Ok(bytes) => bytes,
+            Err(rejection) => {
+                return eywa_axum::axum::response::IntoResponse::into_response(rejection);
+            }
+        };
+    }
+}
+
+/// Generates an expression rendering the buffered `bytes` as a string, with `sensitive` fields
+/// of a JSON body, or keys of a form body (percent-decoded), replaced by `[REDACTED]`
+fn redact_body(sensitive: &[String]) -> TokenStream {
+    if sensitive.is_empty() {
+        return quote! { String::from_utf8_lossy(&bytes).into_owned() };
+    }
+    quote! {
+        {
+            const SENSITIVE: &[&str] = &[#(#sensitive),*];
+            fn redact(value: &mut eywa_axum::serde_json::Value) {
+                match value {
+                    eywa_axum::serde_json::Value::Object(map) => {
+                        for (key, value) in map.iter_mut() {
+                            if SENSITIVE.contains(&key.as_str()) {
+                                *value = "[REDACTED]".into();
+                            } else {
+                                redact(value);
+                            }
+                        }
+                    }
+                    eywa_axum::serde_json::Value::Array(items) => items.iter_mut().for_each(redact),
+                    _ => {}
+                }
+            }
+            // `application/x-www-form-urlencoded` decoding, `pass%77ord` being `password`
+            fn decode(key: &str) -> String {
+                let mut decoded = Vec::with_capacity(key.len());
+                let mut rest = key.as_bytes();
+                while let Some((&byte, tail)) = rest.split_first() {
+                    let escaped = tail
+                        .get(..2)
+                        .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+                        .and_then(|hex| std::str::from_utf8(hex).ok())
+                        .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+                    rest = match (byte, escaped) {
+                        (b'%', Some(escaped)) => {
+                            decoded.push(escaped);
+                            &tail[2..]
+                        }
+                        (b'+', _) => {
+                            decoded.push(b' ');
+                            tail
+                        }
+                        _ => {
+                            decoded.push(byte);
+                            tail
+                        }
+                    };
+                }
+                String::from_utf8_lossy(&decoded).into_owned()
+            }
+            match eywa_axum::serde_json::from_slice::<eywa_axum::serde_json::Value>(&bytes) {
+                Ok(mut value) => {
+                    redact(&mut value);
+                    value.to_string()
+                }
+                // Not JSON: treat as a form body
+                Err(_) => String::from_utf8_lossy(&bytes)
+                    .split('&')
+                    .map(|pair| match pair.split_once('=') {
+                        Some((key, _)) if SENSITIVE.contains(&decode(key).as_str()) => {
+                            format!("{}=[REDACTED]", key)
+                        }
+                        _ => pair.to_string(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join("&"),
+            }
+        }
+    }
+}
+
+/// Generates a route layer setting the body limit of the layers inside it, for routes with
+/// their own limit
+fn route_body_limit(max: usize) -> TokenStream {
+    quote! { .layer(eywa_axum::axum::extract::DefaultBodyLimit::max(#max)) }
+}
+
+/// Generates a layer taking the request's `X-Request-Id` (or generating one),
+/// storing it as an `eywa_axum::RequestId` extension and echoing it on the response
+pub fn generate_request_id_layer() -> TokenStream {
+    quote! {
+        .layer(eywa_axum::axum::middleware::from_fn(
+            |mut req: eywa_axum::axum::extract::Request, next: eywa_axum::axum::middleware::Next| async move {
+                use eywa_axum::axum::http::HeaderValue;
+                use std::sync::atomic::{AtomicU64, Ordering};
+
+                static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+                let incoming = req
+                    .headers()
+                    .get("x-request-id")
+                    .filter(|v| v.to_str().is_ok_and(|v| !v.trim().is_empty() && v.len() <= 128))
+                    .cloned();
+                let value = incoming.unwrap_or_else(|| {
+                    let nanos = std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .map(|d| d.as_nanos() as u64)
+                        .unwrap_or_default();
+                    let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+                    let id = format!("{:016x}{:08x}{:08x}", nanos, std::process::id(), count as u32);
+                    HeaderValue::from_str(&id).expect("hex is a valid header value")
+                });
+                let id = value.to_str().unwrap_or_default().trim().to_owned();
+
+                req.extensions_mut().insert(eywa_axum::RequestId(id));
+                req.headers_mut().insert("x-request-id", value.clone());
+                let mut response = next.run(req).await;
+                response.headers_mut().insert("x-request-id", value);
+                response
+            }
+        ))
+    }
+}
+
+/// Generates a route layer logging a warning for requests slower than `threshold_ms`
+pub fn generate_slow_warn_layer(template: &str, threshold_ms: u64) -> TokenStream {
+    quote! {
+        .layer(eywa_axum::axum::middleware::from_fn(
+            |req: eywa_axum::axum::extract::Request, next: eywa_axum::axum::middleware::Next| async move {
+                let started = std::time::Instant::now();
+                let method = req.method().clone();
+
+                let response = next.run(req).await;
+
+                let latency_ms = started.elapsed().as_millis() as u64;
+                if latency_ms > #threshold_ms {
+                    eywa_axum::tracing::warn!(
+                        target: "eywa_axum::slow_request",
+                        route = #template,
+                        %method,
+                        status = response.status().as_u16(),
+                        latency_ms,
+                        threshold_ms = #threshold_ms,
+                        "slow request"
+                    );
+                }
+                response
+            }
+        ))
+    }
+}
+
+/// Generates a route layer logging every call of a deprecated route (target
+/// `eywa_axum::deprecated`) with its operation id and running hit count
+pub fn generate_deprecation_layer(template: &str, operation_id: &str) -> TokenStream {
+    quote! {
+        .layer(eywa_axum::axum::middleware::from_fn(
+            |req: eywa_axum::axum::extract::Request, next: eywa_axum::axum::middleware::Next| async move {
+                use std::sync::atomic::{AtomicU64, Ordering};
+
+                static HITS: AtomicU64 = AtomicU64::new(0);
+                let hits = HITS.fetch_add(1, Ordering::Relaxed) + 1;
+                eywa_axum::tracing::info!(
+                    target: "eywa_axum::deprecated",
+                    operation_id = #operation_id,
+                    route = #template,
+                    method = %req.method(),
+                    hits,
+                    "deprecated route called"
+                );
+                next.run(req).await
+            }
+        ))
+    }
+}
+
+/// Generates a route layer running a `rate` fraction of the route's requests inside a
+/// `request` span. Requests are picked evenly by a counter, so the rate holds at any volume
+pub fn generate_trace_sample_layer(template: &str, rate: f64) -> TokenStream {
+    let per_million = (rate * 1_000_000.0).round() as u64;
+    quote! {
+        .layer(eywa_axum::axum::middleware::from_fn(
+            |req: eywa_axum::axum::extract::Request, next: eywa_axum::axum::middleware::Next| async move {
+                use eywa_axum::tracing::Instrument;
+                use std::sync::atomic::{AtomicU64, Ordering};
+
+                static REQUESTS: AtomicU64 = AtomicU64::new(0);
+                let n = REQUESTS.fetch_add(1, Ordering::Relaxed);
+                // Sampled whenever the expected number of sampled requests reaches a new integer
+                if (n + 1) * #per_million / 1_000_000 == n * #per_million / 1_000_000 {
+                    return next.run(req).await;
+                }
+
+                let span = eywa_axum::tracing::info_span!(
+                    target: "eywa_axum::trace",
+                    "request",
+                    route = #template,
+                    method = %req.method(),
+                    sample_rate = #rate,
+                    status = eywa_axum::tracing::field::Empty,
+                );
+                let response = next.run(req).instrument(span.clone()).await;
+                span.record("status", response.status().as_u16());
+                response
+            }
+        ))
+    }
+}
+
+/// Generates a route layer counting the route's 5xx responses per window and,
+/// once `ratio` of at least `min_requests` failed, answering 503 for a window
+/// before letting requests through again
+pub fn generate_circuit_breaker_layer(
+    template: &str,
+    settings: &CircuitBreakerSettings,
+) -> TokenStream {
+    let ratio = settings.failure_ratio;
+    let window_ms = settings.window_ms;
+    let min_requests = settings.min_requests;
+    let retry_after = match settings.retry_after {
+        Some(secs) => quote! { #secs.to_string() },
+        None => quote! { remaining.as_millis().div_ceil(1000).to_string() },
+    };
+    quote! {
+        .layer(eywa_axum::axum::middleware::from_fn(
+            |req: eywa_axum::axum::extract::Request, next: eywa_axum::axum::middleware::Next| async move {
+                use eywa_axum::axum::http::{StatusCode, header};
+                use eywa_axum::axum::response::IntoResponse;
+                use std::sync::Mutex;
+                use std::time::{Duration, Instant};
+
+                struct Breaker {
+                    window_start: Instant,
+                    total: u64,
+                    failures: u64,
+                    open_until: Option<Instant>,
+                }
+                const WINDOW: Duration = Duration::from_millis(#window_ms);
+                static BREAKER: Mutex<Option<Breaker>> = Mutex::new(None);
+
+                let now = Instant::now();
+                let remaining = {
+                    let mut guard = BREAKER.lock().unwrap_or_else(|e| e.into_inner());
+                    let breaker = guard.get_or_insert_with(|| Breaker {
+                        window_start: now,
+                        total: 0,
+                        failures: 0,
+                        open_until: None,
+                    });
+                    match breaker.open_until {
+                        Some(until) if until > now => Some(until - now),
+                        // Closed again: start counting afresh
+                        Some(_) => {
+                            *breaker = Breaker { window_start: now, total: 0, failures: 0, open_until: None };
+                            None
+                        }
+                        None => None,
+                    }
+                };
+                if let Some(remaining) = remaining {
+                    let retry_after = #retry_after;
+                    return (
+                        StatusCode::SERVICE_UNAVAILABLE,
+                        [(header::RETRY_AFTER, retry_after)],
+                        "Service temporarily unavailable",
+                    )
+                        .into_response();
+                }
+
+                let response = next.run(req).await;
+
+                let failed = response.status().is_server_error();
+                let mut guard = BREAKER.lock().unwrap_or_else(|e| e.into_inner());
+                if let Some(breaker) = guard.as_mut()
+                    && breaker.open_until.is_none()
+                {
+                    let now = Instant::now();
+                    if now.duration_since(breaker.window_start) >= WINDOW {
+                        *breaker = Breaker { window_start: now, total: 0, failures: 0, open_until: None };
+                    }
+                    breaker.total += 1;
+                    breaker.failures += u64::from(failed);
+                    if breaker.total >= #min_requests
+                        && breaker.failures as f64 >= #ratio * breaker.total as f64
+                    {
+                        breaker.open_until = Some(now + WINDOW);
+                        eywa_axum::tracing::warn!(
+                            target: "eywa_axum::circuit_breaker",
+                            route = #template,
+                            failures = breaker.failures,
+                            total = breaker.total,
+                            "circuit opened"
+                        );
+                    }
+                }
+                response
+            }
+        ))
+    }
+}
+
+/// Generates a route layer checking the `eywa_axum::Permissions` extension set by
+/// authentication: 401 without it, 403 when a required permission is missing
+pub fn generate_require_layer(permissions: &[String]) -> TokenStream {
+    quote! {
+        .layer(eywa_axum::axum::middleware::from_fn(
+            |req: eywa_axum::axum::extract::Request, next: eywa_axum::axum::middleware::Next| async move {
+                use eywa_axum::axum::response::IntoResponse;
+
+                const REQUIRED: &[&str] = &[#(#permissions),*];
+                let Some(granted) = req.extensions().get::<eywa_axum::Permissions>() else {
+                    return (
+                        eywa_axum::axum::http::StatusCode::UNAUTHORIZED,
+                        "Authentication required",
+                    )
+                        .into_response();
+                };
+                if let Some(missing) = REQUIRED
+                    .iter()
+                    .find(|required| !granted.0.iter().any(|p| p == *required))
+                {
+                    return (
+                        eywa_axum::axum::http::StatusCode::FORBIDDEN,
+                        format!("Missing permission `{}`", missing),
+                    )
+                        .into_response();
+                }
+                next.run(req).await
+            }
+        ))
+    }
+}
+
+/// Generates a route layer routing the request's queries to a read replica through
+/// an `eywa_axum::DbRouting::Replica` extension
+pub fn generate_read_only_layer() -> TokenStream {
+    quote! {
+        .layer(eywa_axum::axum::middleware::from_fn(
+            |mut req: eywa_axum::axum::extract::Request, next: eywa_axum::axum::middleware::Next| async move {
+                req.extensions_mut().insert(eywa_axum::DbRouting::Replica);
+                next.run(req).await
+            }
+        ))
+    }
+}
+
+/// Generates a route layer answering 428 to requests without `If-Match`; the
+/// handler compares the validator and answers 412 on a mismatch
+pub fn generate_if_match_layer() -> TokenStream {
+    quote! {
+        .layer(eywa_axum::axum::middleware::from_fn(
+            |req: eywa_axum::axum::extract::Request, next: eywa_axum::axum::middleware::Next| async move {
+                use eywa_axum::axum::response::IntoResponse;
+
+                if !req.headers().contains_key(eywa_axum::axum::http::header::IF_MATCH) {
+                    return (
+                        eywa_axum::axum::http::StatusCode::PRECONDITION_REQUIRED,
+                        "Missing `If-Match` header",
+                    )
+                        .into_response();
+                }
+                next.run(req).await
+            }
+        ))
+    }
+}
+
+/// Generates a route layer answering 400 when a path or query parameter is out of its
+/// bounds. Values that aren't numbers are left to the handler's extractors
+pub fn generate_param_constraints_layer(constraints: &[ParamConstraint]) -> TokenStream {
+    let checks = constraints.iter().map(|constraint| {
+        let name = constraint.name.to_string();
+        let minimum = constraint.minimum.map(|minimum| {
+            let message = format!("`{}` must be at least {}", name, minimum);
+            quote! {
+                if value < #minimum {
+                    return (eywa_axum::axum::http::StatusCode::BAD_REQUEST, #message).into_response();
+                }
+            }
+        });
+        let maximum = constraint.maximum.map(|maximum| {
+            let message = format!("`{}` must be at most {}", name, maximum);
+            quote! {
+                if value > #maximum {
+                    return (eywa_axum::axum::http::StatusCode::BAD_REQUEST, #message).into_response();
+                }
+            }
+        });
+        quote! {
+            if let Some(value) = value(#name) {
+                #minimum
+                #maximum
+            }
+        }
+    });
+    quote! {
+        .layer(eywa_axum::axum::middleware::from_fn(
+            |path: eywa_axum::axum::extract::RawPathParams,
+             req: eywa_axum::axum::extract::Request,
+             next: eywa_axum::axum::middleware::Next| async move {
+                use eywa_axum::axum::response::IntoResponse;
+
+                let query = eywa_axum::axum::extract::Query::<
+                    std::collections::HashMap<String, String>,
+                >::try_from_uri(req.uri())
+                .map(|query| query.0)
+                .unwrap_or_default();
+                let value = |name: &str| -> Option<f64> {
+                    path.iter()
+                        .find(|(key, _)| *key == name)
+                        .map(|(_, value)| value)
+                        .or_else(|| query.get(name).map(String::as_str))?
+                        .parse()
+                        .ok()
+                };
+                #(#checks)*
+                next.run(req).await
+            }
+        ))
+    }
+}
+
+/// Documents parameter bounds on the operation's parameters; bounded path parameters
+/// that aren't documented yet are added
+pub fn operation_param_constraints(constraints: &[ParamConstraint], path: &str) -> TokenStream {
+    let path_params = path_params(path);
+    let number = |value: f64| {
+        if value.fract() == 0.0 {
+            let value = value as isize;
+            quote! { eywa_axum::utoipa::Number::Int(#value) }
+        } else {
+            quote! { eywa_axum::utoipa::Number::Float(#value) }
+        }
+    };
+    let stmts = constraints.iter().map(|constraint| {
+        let name = constraint.name.to_string();
+        let in_path = path_params.contains(&name.as_str());
+        let integer = [constraint.minimum, constraint.maximum]
+            .into_iter()
+            .flatten()
+            .all(|bound| bound.fract() == 0.0);
+        let schema_type = if integer {
+            quote! { Type::Integer }
+        } else {
+            quote! { Type::Number }
+        };
+        let minimum = constraint.minimum.map(|minimum| {
+            let minimum = number(minimum);
+            quote! { schema.minimum = Some(#minimum); }
+        });
+        let maximum = constraint.maximum.map(|maximum| {
+            let maximum = number(maximum);
+            quote! { schema.maximum = Some(#maximum); }
+        });
+        let add_path_param = in_path.then(|| add_path_param(&name, schema_type));
+        quote! {
+            #add_path_param
+            for parameter in parameters.iter_mut().filter(|parameter| parameter.name == #name) {
+                if let Some(RefOr::T(Schema::Object(schema))) = parameter.schema.as_mut() {
+                    #minimum
+                    #maximum
+                }
+            }
+        }
+    });
+    quote! {
+        {
+            use eywa_axum::utoipa::openapi::path::{ParameterBuilder, ParameterIn};
+            use eywa_axum::utoipa::openapi::schema::{ObjectBuilder, Schema, Type};
+            use eywa_axum::utoipa::openapi::{RefOr, Required};
+
+            let parameters = operation.parameters.get_or_insert_with(Vec::new);
+            #(#stmts)*
+        }
+    }
+}
+
+/// Documents the string format (`uuid`, `date-time`, ...) of the operation's parameters;
+/// path parameters that aren't documented yet are added
+pub fn operation_param_formats(formats: &[(String, String)], path: &str) -> TokenStream {
+    let path_params = path_params(path);
+    let stmts = formats.iter().map(|(name, format)| {
+        let add_path_param = path_params
+            .contains(&name.as_str())
+            .then(|| add_path_param(name, quote! { Type::String }));
+        quote! {
+            #add_path_param
+            for parameter in parameters.iter_mut().filter(|parameter| parameter.name == #name) {
+                if let Some(RefOr::T(Schema::Object(schema))) = parameter.schema.as_mut() {
+                    schema.format = Some(SchemaFormat::Custom(#format.to_string()));
+                }
+            }
+        }
+    });
+    quote! {
+        {
+            use eywa_axum::utoipa::openapi::path::{ParameterBuilder, ParameterIn};
+            use eywa_axum::utoipa::openapi::schema::{ObjectBuilder, Schema, SchemaFormat, Type};
+            use eywa_axum::utoipa::openapi::{RefOr, Required};
+
+            let parameters = operation.parameters.get_or_insert_with(Vec::new);
+            #(#stmts)*
+        }
+    }
+}
+
+/// Adds a required path parameter of `schema_type` unless the operation documents it already
+fn add_path_param(name: &str, schema_type: TokenStream) -> TokenStream {
+    quote! {
+        if !parameters.iter().any(|parameter| parameter.name == #name) {
+            parameters.push(
+                ParameterBuilder::new()
+                    .name(#name)
+                    .parameter_in(ParameterIn::Path)
+                    .required(Required::True)
+                    .schema(Some(ObjectBuilder::new().schema_type(#schema_type)))
+                    .build(),
+            );
+        }
+    }
+}
+
+/// Generates a route layer answering `status` instead of 200; a 204 also drops the body
+pub fn generate_success_status_layer(status: u16) -> TokenStream {
+    // Route parsing only accepts 2xx statuses with a `StatusCode` constant
+    let name = format_ident!("{}", status_name(status).unwrap_or("OK"));
+    let drop_body = (status == 204).then(|| {
+        quote! {
+            *response.body_mut() = eywa_axum::axum::body::Body::empty();
+            response.headers_mut().remove(eywa_axum::axum::http::header::CONTENT_TYPE);
+            response.headers_mut().remove(eywa_axum::axum::http::header::CONTENT_LENGTH);
+        }
+    });
+    quote! {
+        .layer(eywa_axum::axum::middleware::from_fn(
+            |req: eywa_axum::axum::extract::Request, next: eywa_axum::axum::middleware::Next| async move {
+                let mut response = next.run(req).await;
+                if response.status() == eywa_axum::axum::http::StatusCode::OK {
+                    *response.status_mut() = eywa_axum::axum::http::StatusCode::#name;
+                    #drop_body
+                }
+                response
+            }
+        ))
+    }
+}
+
+/// Generates a route layer parsing the query string into `filters` and
+/// inserting it as a request extension (400 on malformed filters)
+pub fn generate_filter_layer(filters: &syn::Type) -> TokenStream {
+    quote! {
+        .layer(eywa_axum::axum::middleware::from_fn(
+            |mut req: eywa_axum::axum::extract::Request, next: eywa_axum::axum::middleware::Next| async move {
+                use eywa_axum::axum::response::IntoResponse;
+
+                match eywa_axum::axum::extract::Query::<#filters>::try_from_uri(req.uri()) {
+                    Ok(eywa_axum::axum::extract::Query(filters)) => {
+                        req.extensions_mut().insert(filters);
+                        next.run(req).await
+                    }
+                    Err(rejection) => rejection.into_response(),
+                }
+            }
+        ))
+    }
+}
+
+/// Generates a route layer rejecting `sort` query values outside `fields` with 400
+pub fn generate_sort_layer(fields: &[String]) -> TokenStream {
+    let allowed = fields.join(", ");
+    quote! {
+        .layer(eywa_axum::axum::middleware::from_fn(
+            |req: eywa_axum::axum::extract::Request, next: eywa_axum::axum::middleware::Next| async move {
+                use eywa_axum::axum::response::IntoResponse;
+
+                let query = eywa_axum::axum::extract::Query::<
+                    std::collections::HashMap<String, String>,
+                >::try_from_uri(req.uri());
+                let query = match query {
+                    Ok(eywa_axum::axum::extract::Query(query)) => query,
+                    Err(rejection) => return rejection.into_response(),
+                };
+                if let Some(sort) = query.get("sort")
+                    && ![#(#fields),*].contains(&sort.as_str())
+                {
+                    return (
+                        eywa_axum::axum::http::StatusCode::BAD_REQUEST,
+                        format!("Unknown sort field `{}`, expected one of: {}", sort, #allowed),
+                    )
+                        .into_response();
+                }
+                next.run(req).await
+            }
+        ))
+    }
+}
+
+/// Generates a route layer compressing responses with the listed encodings
+/// (`"gzip,br"`), independent of any controller-wide compression
+pub fn generate_compression_layer(encodings: &syn::LitStr) -> syn::Result<TokenStream> {
+    let value = encodings.value();
+    let mut enabled = [false; 4];
+    for name in value
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+    {
+        let idx = match name {
+            "gzip" => 0,
+            "br" => 1,
+            "deflate" => 2,
+            "zstd" => 3,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    encodings,
+                    format!(
+                        "unknown encoding `{}`, expected `gzip`, `br`, `deflate` or `zstd`",
+                        name
+                    ),
+                ));
+            }
+        };
+        enabled[idx] = true;
+    }
+    if !enabled.contains(&true) {
+        return Err(syn::Error::new_spanned(
+            encodings,
+            "`compress` needs at least one encoding, use `no_compress` to disable compression",
+        ));
+    }
+    let [gzip, br, deflate, zstd] = enabled;
+    Ok(quote! {
+        .layer(
+            eywa_axum::tower_http::compression::CompressionLayer::new()
+                .gzip(#gzip)
+                .br(#br)
+                .deflate(#deflate)
+                .zstd(#zstd)
+        )
+    })
+}
+
+/// Generates a route layer keeping responses out of compression: outer
+/// compression layers leave responses with a `Content-Encoding` alone
+pub fn generate_no_compress_layer() -> TokenStream {
+    quote! {
+        .layer(eywa_axum::axum::middleware::map_response(
+            |mut response: eywa_axum::axum::response::Response| async move {
+                response
+                    .headers_mut()
+                    .entry(eywa_axum::axum::http::header::CONTENT_ENCODING)
+                    .or_insert(eywa_axum::axum::http::HeaderValue::from_static("identity"));
+                response
+            }
+        ))
+    }
+}
+
+/// Generates a route layer answering single `Range: bytes=...` requests on
+/// `GET` with 206 (or 416), advertising `Accept-Ranges` on full responses.
+///
+/// Only bodies of an exact size within `max` are buffered to cut the range out
+/// of; streams of unknown length and larger bodies are served whole with 200.
+/// `If-Range` follows RFC 9110 §13.1.5: an entity tag must strongly match the
+/// `ETag` (weak tags never do) and a date must equal a `Last-Modified` at least
+/// a second old, otherwise the full representation is sent.
+pub fn generate_range_layer(ranges: &RangeOptions) -> TokenStream {
+    let max = ranges.max as u64;
+    quote! {
+        .layer(eywa_axum::axum::middleware::from_fn(
+            |req: eywa_axum::axum::extract::Request, next: eywa_axum::axum::middleware::Next| async move {
+                use eywa_axum::axum::body::HttpBody;
+                use eywa_axum::axum::http::{HeaderValue, Method, StatusCode, header};
+                use eywa_axum::axum::response::{IntoResponse, Response};
+
+                let range = if req.method() == Method::GET {
+                    req.headers()
+                        .get(header::RANGE)
+                        .and_then(|v| v.to_str().ok())
+                        .map(str::to_owned)
+                } else {
+                    None
+                };
+                let if_range = req.headers().get(header::IF_RANGE).cloned();
+
+                let mut response = next.run(req).await;
+                if response.status() != StatusCode::OK {
+                    return response;
+                }
+                response
+                    .headers_mut()
+                    .insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+                let Some(range) = range else {
+                    return response;
+                };
+                if let Some(validator) = if_range {
+                    let validator = validator.to_str().unwrap_or_default().trim();
+                    let header_value = |name| {
+                        response
+                            .headers()
+                            .get(name)
+                            .and_then(|v: &HeaderValue| v.to_str().ok())
+                    };
+                    let current = if validator.starts_with("W/") {
+                        false
+                    } else if validator.starts_with('"') {
+                        header_value(header::ETAG) == Some(validator)
+                    } else {
+                        // A date is only a strong validator a second after the modification
+                        match (
+                            eywa_axum::httpdate::parse_http_date(validator).ok(),
+                            header_value(header::LAST_MODIFIED)
+                                .and_then(|v| eywa_axum::httpdate::parse_http_date(v).ok()),
+                        ) {
+                            (Some(date), Some(modified)) => {
+                                date == modified
+                                    && modified + std::time::Duration::from_secs(1)
+                                        <= std::time::SystemTime::now()
+                            }
+                            _ => false,
+                        }
+                    };
+                    if !current {
+                        return response;
+                    }
+                }
+
+                let Some(len) = response.body().size_hint().exact().filter(|len| *len <= #max) else {
+                    return response;
+                };
+
+                // None: malformed or multi-range, ignored; Some(None): unsatisfiable
+                let bounds = match range
+                    .strip_prefix("bytes=")
+                    .filter(|spec| !spec.contains(','))
+                    .and_then(|spec| spec.trim().split_once('-'))
+                {
+                    None => None,
+                    Some(("", suffix)) => suffix
+                        .parse::<u64>()
+                        .ok()
+                        .map(|n| (n > 0 && len > 0).then(|| (len.saturating_sub(n), len - 1))),
+                    Some((start, end)) => match (start.parse::<u64>(), end) {
+                        (Ok(start), "") => Some((start < len).then(|| (start, len - 1))),
+                        (Ok(start), end) => end
+                            .parse::<u64>()
+                            .ok()
+                            .filter(|end| *end >= start)
+                            .map(|end| (start < len).then(|| (start, end.min(len - 1)))),
+                        _ => None,
+                    },
+                };
+
+                match bounds {
+                    None => response,
+                    Some(None) => (
+                        StatusCode::RANGE_NOT_SATISFIABLE,
+                        [(header::CONTENT_RANGE, format!("bytes */{}", len))],
+                    )
+                        .into_response(),
+                    Some(Some((start, end))) => {
+                        let (mut parts, body) = response.into_parts();
+                        // A body breaking its size hint cannot be sliced consistently
+                        let bytes = match eywa_axum::axum::body::to_bytes(body, len as usize).await {
+                            Ok(bytes) if bytes.len() as u64 == len => bytes,
+                            _ => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+                        };
+                        parts.status = StatusCode::PARTIAL_CONTENT;
+                        parts.headers.remove(header::CONTENT_LENGTH);
+                        if let Ok(value) = HeaderValue::from_str(&format!("bytes {}-{}/{}", start, end, len)) {
+                            parts.headers.insert(header::CONTENT_RANGE, value);
+                        }
+                        let slice = bytes.slice(start as usize..=end as usize);
+                        Response::from_parts(parts, eywa_axum::axum::body::Body::from(slice))
+                    }
+                }
+            }
+        ))
+    }
+}
+
+/// Generates a layer resolving the request's tenant into an `eywa_axum::Tenant`
+/// extension, rejecting requests without one with 400
+pub fn generate_tenant_layer(strategy: &TenantStrategy) -> TokenStream {
+    let resolve = match strategy {
+        TenantStrategy::Header(header) => {
+            let missing = format!("Missing `{}` header", header);
+            quote! {
+                let tenant = req
+                    .headers()
+                    .get(#header)
+                    .and_then(|v| v.to_str().ok())
+                    .map(str::trim)
+                    .filter(|v| !v.is_empty())
+                    .map(str::to_owned);
+                let missing = #missing;
+            }
+        }
+        TenantStrategy::Subdomain => quote! {
+            // First label of a host with at least three labels, e.g. `acme` in `acme.api.example.com`
+            let tenant = req
+                .headers()
+                .get(eywa_axum::axum::http::header::HOST)
+                .and_then(|v| v.to_str().ok())
+                .map(|host| host.split(':').next().unwrap_or(host))
+                .filter(|host| host.split('.').count() >= 3)
+                .and_then(|host| host.split('.').next())
+                .filter(|label| !label.is_empty())
+                .map(str::to_owned);
+            let missing = "Missing tenant subdomain";
+        },
+    };
+    quote! {
+        .layer(eywa_axum::axum::middleware::from_fn(
+            |mut req: eywa_axum::axum::extract::Request, next: eywa_axum::axum::middleware::Next| async move {
+                use eywa_axum::axum::response::IntoResponse;
+
+                // Preflight and capability requests carry no tenant
+                if req.method() == eywa_axum::axum::http::Method::OPTIONS {
+                    return next.run(req).await;
+                }
+
+                #resolve
+                match tenant {
+                    Some(tenant) => {
+                        req.extensions_mut().insert(eywa_axum::Tenant(tenant));
+                        next.run(req).await
+                    }
+                    None => (eywa_axum::axum::http::StatusCode::BAD_REQUEST, missing).into_response(),
+                }
+            }
+        ))
+    }
+}
+
+/// Generates a fixed-window rate limiting layer answering 429 with `Retry-After`
+///
+/// Clients are keyed by the `eywa_axum::Principal` extension set by
+/// authentication when `by_principal`, and by peer IP otherwise or as a fallback.
+pub fn generate_throttle_layer(
+    per_minute: u32,
+    by_principal: bool,
+    retry_after: Option<u64>,
+) -> TokenStream {
+    let retry_after = match retry_after {
+        Some(secs) => quote! { #secs.to_string() },
+        None => quote! { reset.as_secs().max(1).to_string() },
+    };
+    let principal = if by_principal {
+        quote! {
+            req.extensions()
+                .get::<eywa_axum::Principal>()
+                .map(|p| format!("user:{}", p.0))
+        }
+    } else {
+        quote! { None }
+    };
+    quote! {
+        .layer(eywa_axum::axum::middleware::from_fn(
+            |req: eywa_axum::axum::extract::Request, next: eywa_axum::axum::middleware::Next| async move {
+                use eywa_axum::axum::response::IntoResponse;
+
+                static WINDOWS: std::sync::OnceLock<
+                    std::sync::Mutex<std::collections::HashMap<String, (std::time::Instant, u32)>>,
+                > = std::sync::OnceLock::new();
+                const WINDOW: std::time::Duration = std::time::Duration::from_secs(60);
+
+                let key: Option<String> = #principal;
+                let key = key
+                    .or_else(|| {
+                        req.extensions()
+                            .get::<eywa_axum::axum::extract::ConnectInfo<std::net::SocketAddr>>()
+                            .map(|info| format!("ip:{}", info.0.ip()))
+                    })
+                    .unwrap_or_else(|| "unknown".to_string());
+
+                let now = std::time::Instant::now();
+                let (count, reset) = {
+                    let mut windows = WINDOWS
+                        .get_or_init(Default::default)
+                        .lock()
+                        .unwrap_or_else(std::sync::PoisonError::into_inner);
+                    if windows.len() > 10_000 {
+                        windows.retain(|_, (start, _)| now.duration_since(*start) < WINDOW);
+                    }
+                    let window = windows.entry(key).or_insert((now, 0));
+                    if now.duration_since(window.0) >= WINDOW {
+                        *window = (now, 0);
+                    }
+                    window.1 += 1;
+                    (window.1, WINDOW.saturating_sub(now.duration_since(window.0)))
+                };
+
+                let limit = (eywa_axum::axum::http::HeaderName::from_static("x-ratelimit-limit"), #per_minute.to_string());
+                if count > #per_minute {
+                    return (
+                        eywa_axum::axum::http::StatusCode::TOO_MANY_REQUESTS,
+                        [
+                            limit,
+                            (eywa_axum::axum::http::header::RETRY_AFTER, #retry_after),
+                        ],
+                    )
+                        .into_response();
+                }
+                let remaining = (
+                    eywa_axum::axum::http::HeaderName::from_static("x-ratelimit-remaining"),
+                    (#per_minute - count).to_string(),
+                );
+                ([limit, remaining], next.run(req).await).into_response()
+            }
+        ))
+    }
+}
+
+/// Generates a layer adding `headers` to responses that don't set them already
+pub fn generate_security_headers_layer(headers: &[(&str, String)]) -> TokenStream {
+    let names = headers.iter().map(|(name, _)| name);
+    let values = headers.iter().map(|(_, value)| value);
+    quote! {
+        .layer(eywa_axum::axum::middleware::from_fn(
+            |req: eywa_axum::axum::extract::Request, next: eywa_axum::axum::middleware::Next| async move {
+                let mut response = next.run(req).await;
+                let headers = response.headers_mut();
+                #(
+                    headers
+                        .entry(eywa_axum::axum::http::HeaderName::from_static(#names))
+                        .or_insert(eywa_axum::axum::http::HeaderValue::from_static(#values));
+                )*
+                response
+            }
+        ))
+    }
+}
+
+/// Generates a layer answering CORS preflights and adding
+/// `Access-Control-Allow-Origin` to responses for allowed origins (any when `origins` is empty)
+pub fn generate_cors_layer(
+    origins: &[String],
+    methods: &str,
+    headers: &str,
+    max_age: Option<u64>,
+) -> TokenStream {
+    let allow_headers = (!headers.is_empty()).then(|| {
+        quote! {
+            response_headers.insert(
+                eywa_axum::axum::http::header::ACCESS_CONTROL_ALLOW_HEADERS,
+                eywa_axum::axum::http::HeaderValue::from_static(#headers),
+            );
+        }
+    });
+    let max_age = max_age.map(|secs| {
+        quote! {
+            response_headers.insert(
+                eywa_axum::axum::http::header::ACCESS_CONTROL_MAX_AGE,
+                eywa_axum::axum::http::HeaderValue::from(#secs),
+            );
+        }
+    });
+    quote! {
+        .layer(eywa_axum::axum::middleware::from_fn(
+            |req: eywa_axum::axum::extract::Request, next: eywa_axum::axum::middleware::Next| async move {
+                use eywa_axum::axum::http::{header, HeaderValue, Method, StatusCode};
+                use eywa_axum::axum::response::IntoResponse;
+
+                const ORIGINS: &[&str] = &[#(#origins),*];
+                let origin = req
+                    .headers()
+                    .get(header::ORIGIN)
+                    .filter(|origin| {
+                        ORIGINS.is_empty()
+                            || origin.to_str().is_ok_and(|origin| ORIGINS.contains(&origin))
+                    })
+                    .cloned();
+                let preflight = req.method() == Method::OPTIONS
+                    && req.headers().contains_key(header::ACCESS_CONTROL_REQUEST_METHOD);
+
+                let mut response = if preflight {
+                    StatusCode::NO_CONTENT.into_response()
+                } else {
+                    next.run(req).await
+                };
+                let response_headers = response.headers_mut();
+                response_headers.append(header::VARY, HeaderValue::from_static("origin"));
+                let Some(origin) = origin else {
+                    return response;
+                };
+                response_headers.insert(
+                    header::ACCESS_CONTROL_ALLOW_ORIGIN,
+                    if ORIGINS.is_empty() { HeaderValue::from_static("*") } else { origin },
+                );
+                if preflight {
+                    response_headers.insert(
+                        header::ACCESS_CONTROL_ALLOW_METHODS,
+                        HeaderValue::from_static(#methods),
+                    );
+                    #allow_headers
+                    #max_age
+                }
+                response
+            }
+        ))
+    }
+}
+
+/// Generates a `CatchPanicLayer` answering panics with a 500 ProblemDetails body
+pub fn generate_catch_panic_layer() -> TokenStream {
+    quote! {
+        .layer(eywa_axum::tower_http::catch_panic::CatchPanicLayer::custom(
+            |_panic: Box<dyn std::any::Any + Send + 'static>| {
+                use eywa_axum::axum::response::IntoResponse;
+
+                (
+                    eywa_axum::axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                    [(eywa_axum::axum::http::header::CONTENT_TYPE, "application/problem+json")],
+                    eywa_axum::serde_json::json!({
+                        "type": "about:blank",
+                        "title": "Internal Server Error",
+                        "status": 500,
+                    })
+                    .to_string(),
+                )
+                    .into_response()
+            },
+        ))
+    }
+}
+
+/// Generates a statement adding an `x-` extension to `operation` inside `register_paths`
+pub fn operation_extension(key: &str, value: TokenStream) -> TokenStream {
+    quote! {
+        operation
+            .extensions
+            .get_or_insert_with(Default::default)
+            .insert(#key.to_string(), (#value).into());
+    }
+}
+
+/// Generates statements setting the example of the operation's request body or, for
+/// `request = false`, of its first 2xx response with content
+///
+/// `value` is a JSON string literal, validated here and parsed when the document is built, or
+/// a path to a `Serialize` const, left out if it fails to serialize.
+pub fn operation_example(value: &syn::Expr, request: bool) -> syn::Result<TokenStream> {
+    let example = match value {
+        syn::Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Str(lit),
+            ..
+        }) => {
+            serde_json::from_str::<serde_json::Value>(&lit.value()).map_err(|e| {
+                syn::Error::new_spanned(lit, format!("invalid JSON example: {}", e))
+            })?;
+            quote! { eywa_axum::serde_json::from_str::<eywa_axum::serde_json::Value>(#lit) }
+        }
+        syn::Expr::Path(path) => quote! { eywa_axum::serde_json::to_value(&#path) },
+        other => {
+            return Err(syn::Error::new_spanned(
+                other,
+                "expected a JSON string literal or a path to a const",
+            ));
+        }
+    };
+    let contents = if request {
+        quote! {
+            operation
+                .request_body
+                .iter_mut()
+                .flat_map(|body| body.content.values_mut())
+        }
+    } else {
+        quote! {
+            operation
+                .responses
+                .responses
+                .iter_mut()
+                .filter(|(status, _)| status.starts_with('2'))
+                .find_map(|(_, response)| match response {
+                    utoipa::openapi::RefOr::T(response) if !response.content.is_empty() => {
+                        Some(response)
+                    }
+                    _ => None,
+                })
+                .into_iter()
+                .flat_map(|response| response.content.values_mut())
+        }
+    };
+    Ok(quote! {
+        {
+            if let Ok(example) = #example {
+                for content in #contents {
+                    content.example = Some(example.clone());
+                }
+            }
+        }
+    })
+}
+
+/// Generates statements merging the responses documented by a route's error type
+/// (`#[derive(ApiError)]` or any `utoipa::IntoResponses`) into `operation`.
+///
+/// Autoref specialization keeps error types without the impl compiling: the
+/// inherent-receiver impl wins when `E: IntoResponses`, the `&` fallback otherwise.
+pub fn generate_error_responses(error: &syn::Type, compact: bool) -> TokenStream {
+    if compact {
+        // Same probe, with the traits from the runtime crate
+        return quote! {
+            {
+                use eywa_axum::__private::{DocumentedErrors as _, UndocumentedErrors as _};
+                let probe = eywa_axum::__private::ErrorProbe::<#error>::new();
+                eywa_axum::__private::merge_responses(operation, (&probe).responses());
+            }
+        };
+    }
+    quote! {
+        {
+            type __Responses = std::collections::BTreeMap<
+                String,
+                utoipa::openapi::RefOr<utoipa::openapi::response::Response>,
+            >;
+            struct __ErrorProbe<T>(std::marker::PhantomData<T>);
+            trait __Documented {
+                fn responses(&self) -> __Responses;
+            }
+            impl<T: utoipa::IntoResponses> __Documented for __ErrorProbe<T> {
+                fn responses(&self) -> __Responses {
+                    T::responses()
+                }
+            }
+            trait __Undocumented {
+                fn responses(&self) -> __Responses;
+            }
+            impl<T> __Undocumented for &__ErrorProbe<T> {
+                fn responses(&self) -> __Responses {
+                    Default::default()
+                }
+            }
+            let probe = __ErrorProbe::<#error>(std::marker::PhantomData);
+            for (status, response) in (&probe).responses() {
+                // Responses declared on the route take precedence
+                operation.responses.responses.entry(status).or_insert(response);
+            }
+        }
+    }
+}
+
+/// Readable schema name for a type: `Project`, `ProjectList` for `Vec<Project>`,
+/// `ProjectCursorPage` for `CursorPage<Project>`
+pub fn schema_type_name(ty: &syn::Type) -> Option<String> {
+    match ty {
+        syn::Type::Path(tp) => {
+            let seg = tp.path.segments.last()?;
+            let mut args = Vec::new();
+            if let syn::PathArguments::AngleBracketed(generics) = &seg.arguments {
+                for arg in &generics.args {
+                    if let syn::GenericArgument::Type(ty) = arg {
+                        args.push(schema_type_name(ty)?);
+                    }
+                }
+            }
+            let ident = if seg.ident == "Vec" {
+                "List".to_string()
+            } else {
+                seg.ident.to_string()
+            };
+            Some(format!("{}{}", args.concat(), ident))
+        }
+        syn::Type::Reference(r) => schema_type_name(&r.elem),
+        syn::Type::Paren(p) => schema_type_name(&p.elem),
+        syn::Type::Group(g) => schema_type_name(&g.elem),
+        _ => None,
+    }
+}
@@ -0,0 +1,250 @@
+//! Project-wide controller defaults.
+//!
+//! An optional `eywa-controllers.toml` next to the crate's `Cargo.toml` holds defaults for
+//! controller arguments, read at expansion time. It is a flat table of `key = value` lines with
+//! string or boolean values; a controller's own arguments take precedence.
+//!
+//! ```toml
+//! security_scheme = "api_key"
+//! auto_responses = "minimal"
+//! path_case = "snake"
+//! schema_naming = "short"
+//! rest_conventions = true
+//! crate = "acme_web::eywa_axum"
+//! ```
+//!
+//! `crate` is the path generated code uses for the runtime crate, for projects that only reach
+//! it through a re-export.
+
+use std::path::{Path, PathBuf};
+
+use proc_macro2::{Group, Span, TokenStream, TokenTree};
+use quote::{ToTokens, quote};
+use syn::LitStr;
+
+use crate::messages::parse_string;
+use crate::parse::{ControllerArgs, check_security_scheme};
+
+/// Name of the configuration file, relative to `CARGO_MANIFEST_DIR`
+const CONFIG_FILE: &str = "eywa-controllers.toml";
+
+/// Defaults read from `eywa-controllers.toml`
+#[derive(Default)]
+pub struct Config {
+    pub file: Option<PathBuf>,
+    /// Scheme of security requirements that don't name one, instead of `"bearer"`
+    pub security_scheme: Option<LitStr>,
+    pub auto_responses: Option<LitStr>,
+    pub path_case: Option<LitStr>,
+    pub schema_naming: Option<LitStr>,
+    pub rest_conventions: Option<bool>,
+    /// Path of the runtime crate replacing `eywa_axum` in generated code
+    pub crate_path: Option<syn::Path>,
+}
+
+impl Config {
+    /// Fills in the arguments the controller leaves out
+    pub fn apply(&self, controller_args: &mut ControllerArgs) {
+        if controller_args.auto_responses.is_none() {
+            controller_args.auto_responses = self.auto_responses.clone();
+        }
+        if controller_args.path_case.is_none() {
+            controller_args.path_case = self.path_case.clone();
+        }
+        if controller_args.schema_naming.is_none() {
+            controller_args.schema_naming = self.schema_naming.clone();
+        }
+        if controller_args.rest_conventions.is_none() {
+            controller_args.rest_conventions = self.rest_conventions;
+        }
+        controller_args.default_security_scheme = self.security_scheme.clone();
+    }
+
+    /// Rebuilds the expanding crate when the file changes
+    pub fn track(&self) -> TokenStream {
+        match &self.file {
+            Some(file) => {
+                let file = file.to_string_lossy();
+                quote! { const _: &[u8] = include_bytes!(#file); }
+            }
+            None => quote! {},
+        }
+    }
+
+    /// Points paths starting at `eywa_axum` to the configured `crate`
+    pub fn resolve_crate_path(&self, tokens: TokenStream) -> TokenStream {
+        match &self.crate_path {
+            Some(path) => replace_crate_path(tokens, &path.to_token_stream()),
+            None => tokens,
+        }
+    }
+}
+
+/// Expands a macro with the configured crate path, or reports an invalid configuration file
+pub fn with_crate_path(expand: impl FnOnce() -> TokenStream) -> TokenStream {
+    match load_config() {
+        Ok(config) => config.resolve_crate_path(expand()),
+        Err(e) => e.to_compile_error(),
+    }
+}
+
+/// Replaces every `eywa_axum` starting a path (not following `::`) with `path`
+fn replace_crate_path(tokens: TokenStream, path: &TokenStream) -> TokenStream {
+    let mut out = TokenStream::new();
+    // `:` puncts right before the current token, two of them being a path separator
+    let mut colons = 0;
+    for tree in tokens {
+        let after_separator = colons >= 2;
+        colons = match &tree {
+            TokenTree::Punct(punct) if punct.as_char() == ':' => colons + 1,
+            _ => 0,
+        };
+        match tree {
+            TokenTree::Ident(ident) if ident == "eywa_axum" && !after_separator => {
+                out.extend(path.clone());
+            }
+            TokenTree::Group(group) => {
+                let mut replaced =
+                    Group::new(group.delimiter(), replace_crate_path(group.stream(), path));
+                replaced.set_span(group.span());
+                out.extend([TokenTree::Group(replaced)]);
+            }
+            other => out.extend([other]),
+        }
+    }
+    out
+}
+
+/// Reads `eywa-controllers.toml`; a missing file means no defaults
+pub fn load_config() -> syn::Result<Config> {
+    let root = std::env::var("CARGO_MANIFEST_DIR").unwrap_or_default();
+    let file = Path::new(&root).join(CONFIG_FILE);
+    if !file.is_file() {
+        return Ok(Config::default());
+    }
+    let error = |message: String| syn::Error::new(Span::call_site(), message);
+    let source = std::fs::read_to_string(&file)
+        .map_err(|e| error(format!("cannot read `{}`: {}", file.display(), e)))?;
+    parse_config(&source, file.clone()).map_err(|e| error(format!("`{}`: {}", file.display(), e)))
+}
+
+fn parse_config(source: &str, file: PathBuf) -> Result<Config, String> {
+    let mut config = Config {
+        file: Some(file),
+        ..Config::default()
+    };
+    for (number, line) in source.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let invalid = |expected: &str| format!("line {}: expected {}", number + 1, expected);
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| invalid("`key = value`"))?;
+        let value = value.trim();
+        let string = || {
+            parse_string(value)
+                .map(|value| LitStr::new(&value, Span::call_site()))
+                .ok_or_else(|| invalid("a string"))
+        };
+        match key.trim() {
+            "security_scheme" => {
+                let scheme = string()?;
+                check_security_scheme(&scheme)
+                    .map_err(|e| format!("line {}: {}", number + 1, e))?;
+                config.security_scheme = Some(scheme);
+            }
+            "auto_responses" => config.auto_responses = Some(string()?),
+            "path_case" => config.path_case = Some(string()?),
+            "schema_naming" => config.schema_naming = Some(string()?),
+            "rest_conventions" => {
+                let value = value.split('#').next().unwrap_or(value).trim();
+                config.rest_conventions = match value {
+                    "true" => Some(true),
+                    "false" => Some(false),
+                    _ => return Err(invalid("`true` or `false`")),
+                };
+            }
+            "crate" => {
+                let path = string()?;
+                config.crate_path =
+                    Some(syn::parse_str(&path.value()).map_err(|_| invalid("a crate path"))?);
+            }
+            other => {
+                return Err(format!(
+                    "line {}: unknown key `{}`, expected `security_scheme`, `auto_responses`, \
+                     `path_case`, `schema_naming`, `rest_conventions` or `crate`",
+                    number + 1,
+                    other
+                ));
+            }
+        }
+    }
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> Result<Config, String> {
+        parse_config(source, PathBuf::from(CONFIG_FILE))
+    }
+
+    fn parse_error(source: &str) -> String {
+        match parse(source) {
+            Ok(_) => panic!("`{}` parsed", source),
+            Err(e) => e,
+        }
+    }
+
+    #[test]
+    fn parse_config_reads_every_key() {
+        let config = parse(
+            "# Defaults\n\
+             security_scheme = \"api_key\"\n\
+             auto_responses = \"minimal\"\n\
+             path_case = \"snake\"\n\
+             schema_naming = \"short\"\n\
+             rest_conventions = false # for now\n\
+             crate = \"acme_web::eywa_axum\"\n",
+        )
+        .unwrap();
+        assert_eq!(config.security_scheme.unwrap().value(), "api_key");
+        assert_eq!(config.auto_responses.unwrap().value(), "minimal");
+        assert_eq!(config.path_case.unwrap().value(), "snake");
+        assert_eq!(config.schema_naming.unwrap().value(), "short");
+        assert_eq!(config.rest_conventions, Some(false));
+        let crate_path = config.crate_path.unwrap().to_token_stream().to_string();
+        assert_eq!(crate_path, "acme_web :: eywa_axum");
+    }
+
+    #[test]
+    fn parse_config_leaves_missing_keys_unset() {
+        let config = parse("\n# nothing yet\n").unwrap();
+        assert!(config.security_scheme.is_none());
+        assert!(config.rest_conventions.is_none());
+        assert!(config.crate_path.is_none());
+    }
+
+    #[test]
+    fn parse_config_reports_the_offending_line() {
+        let error = parse_error("path_case = \"snake\"\nrest_conventions = yes");
+        assert_eq!(error, "line 2: expected `true` or `false`");
+        let error = parse_error("schema_naming = short");
+        assert_eq!(error, "line 1: expected a string");
+        let error = parse_error("crate = \"not a path!\"");
+        assert_eq!(error, "line 1: expected a crate path");
+        let error = parse_error("security_scheme");
+        assert_eq!(error, "line 1: expected `key = value`");
+        let error = parse_error("security_scheme = \"basic\"");
+        assert!(error.starts_with("line 1: "), "{}", error);
+        let error = parse_error("colour = \"blue\"");
+        assert!(
+            error.starts_with("line 1: unknown key `colour`"),
+            "{}",
+            error
+        );
+    }
+}
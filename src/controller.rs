@@ -1,429 +1,875 @@
 //! Controller macro implementation
 
-use darling::ast::NestedMeta;
+use convert_case::Case;
 use darling::FromMeta;
+use darling::ast::NestedMeta;
 use proc_macro2::TokenStream;
-use quote::{format_ident, quote};
-use syn::{parse2, Attribute, ImplItem, ItemImpl};
+use quote::{ToTokens, format_ident, quote};
+use syn::spanned::Spanned;
+use syn::{Attribute, ImplItem, ItemImpl, Path, parse2};
+
+use self::docs::GeneratedDocs;
+use crate::asyncapi::{Channel, RealtimeKind, generate_asyncapi_json, realtime_kind};
+use crate::batch::{
+    BatchOperation, generate_batch_docs, generate_batch_handler, generate_batch_router,
+};
+use crate::codegen::{operation_extension, register_oauth2_scheme};
+use crate::config::load_config;
+use crate::graphql::{
+    generate_graphql_docs, generate_graphql_handlers, generate_graphql_registration,
+};
+use crate::manifest::gateway_manifest;
+use crate::parse::{ControllerArgs, RouteInfo, check_security_scheme, split_middleware_arg};
+use crate::paths::{axum_path, controller_prefix, join_paths, validate_path};
+use crate::wrappers::extract_result_error;
+
+mod docs;
+mod extensions;
+mod handlers;
+mod registration;
+
+/// Controller-wide facts shared by the expansion phases
+struct Controller<'a> {
+    args: &'a ControllerArgs,
+    /// Version and path prefix combined (e.g., "/api/v1/projects")
+    full_prefix: String,
+    tag: String,
+    /// Resource name from the controller type (e.g., "Projects" for ProjectsController)
+    resource: String,
+    path_case: Case,
+    /// Full path of every route, so routes can refer to each other by handler name
+    route_paths: Vec<(syn::Ident, String)>,
+    /// Error type of every route returning `Result<T, E>`, before wrappers rewrite outputs
+    route_errors: Vec<(syn::Ident, syn::Type)>,
+    /// Error type whose variants `error_map` maps to statuses
+    mapped_error: Option<Path>,
+    short_schema_names: bool,
+    minimal_responses: bool,
+}
 
-use crate::parse::{ControllerArgs, RouteInfo};
+impl Controller<'_> {
+    fn full_path(&self, route_info: &RouteInfo) -> String {
+        join_paths(&self.full_prefix, &route_info.path)
+    }
+
+    /// Full path of the route handled by `name`
+    fn resolve_route(&self, name: &syn::Ident) -> Option<String> {
+        self.route_paths
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, path)| path.clone())
+    }
+}
 
 /// Process the #[controller(...)] attribute macro
 pub fn controller_impl(args: TokenStream, input: TokenStream) -> TokenStream {
+    // Conditional middleware entries aren't valid meta, pull them out first
+    let (args, middleware) = match split_middleware_arg(args) {
+        Ok(split) => split,
+        Err(e) => return e.to_compile_error(),
+    };
+
     // Parse controller args
     let meta_list = match NestedMeta::parse_meta_list(args) {
         Ok(m) => m,
         Err(e) => return e.to_compile_error(),
     };
 
-    let controller_args = match ControllerArgs::from_list(&meta_list) {
+    let mut controller_args = match ControllerArgs::from_list(&meta_list) {
         Ok(a) => a,
         Err(e) => return e.write_errors(),
     };
+    controller_args.middleware = middleware;
+    let config = match load_config() {
+        Ok(config) => config,
+        Err(e) => return e.to_compile_error(),
+    };
+    config.apply(&mut controller_args);
 
     // Parse the impl block
     let mut impl_block: ItemImpl = match parse2(input) {
         Ok(i) => i,
         Err(e) => return e.to_compile_error(),
     };
+    // Generated items, such as the route table cached by `into_router_cached`, are
+    // shared by every instantiation of a generic impl
+    if !impl_block.generics.params.is_empty() {
+        return syn::Error::new_spanned(
+            &impl_block.generics,
+            "generic controllers are not supported, declare the routes on a concrete type",
+        )
+        .to_compile_error();
+    }
 
     // Extract the controller type name
     let self_ty = &impl_block.self_ty;
     let state_ty = &controller_args.state;
 
-    // Build version prefix (e.g., "/v1")
-    let version_prefix = if let Some(ref version) = controller_args.version {
-        format!("/{}", version)
-    } else {
-        String::new()
-    };
+    // `prefix` is the legacy spelling of `path`
+    let legacy_prefix_span = meta_list.iter().find_map(|m| match m {
+        NestedMeta::Meta(meta) if meta.path().is_ident("prefix") => Some(meta.path().span()),
+        _ => None,
+    });
+    if let Some(span) = legacy_prefix_span
+        && controller_args.path.is_some()
+    {
+        return syn::Error::new(
+            span,
+            "`prefix` and `path` are mutually exclusive, use `path`",
+        )
+        .to_compile_error();
+    }
+
+    // Emit a deprecation warning at the `prefix` argument by referencing a deprecated item
+    let prefix_deprecation = legacy_prefix_span.map(|span| {
+        let ident = syn::Ident::new("prefix", span);
+        quote! {
+            const _: () = {
+                #[deprecated(note = "`prefix` on #[controller] is deprecated, use `path` instead")]
+                #[allow(non_upper_case_globals)]
+                const prefix: () = ();
+                #ident
+            };
+        }
+    });
+
+    // Reject prefixes axum and the spec can't represent, at the offending value
+    let prefix_args = [
+        ("version", controller_args.version.as_deref()),
+        ("path", controller_args.path.as_deref()),
+        ("prefix", controller_args.prefix.as_deref()),
+    ];
+    for (name, value) in prefix_args {
+        let Some(value) = value else { continue };
+        if let Err(msg) = validate_path(&format!("controller {}", name), value) {
+            let span = meta_list
+                .iter()
+                .find_map(|m| match m {
+                    NestedMeta::Meta(syn::Meta::NameValue(nv)) if nv.path.is_ident(name) => {
+                        Some(nv.value.span())
+                    }
+                    _ => None,
+                })
+                .unwrap_or_else(proc_macro2::Span::call_site);
+            return syn::Error::new(span, msg).to_compile_error();
+        }
+    }
 
-    // Prefer path, fallback to prefix (legacy), default to empty
     let path_prefix = controller_args
         .path
-        .or(controller_args.prefix)
+        .clone()
+        .or_else(|| controller_args.prefix.clone())
         .unwrap_or_default();
 
-    // Combine version and path prefix (e.g., "/v1/projects")
-    let full_prefix = format!("/api{}{}", version_prefix, path_prefix);
-
-    let tag = controller_args.tag.clone().unwrap_or_else(|| {
-        // Extract name from type
-        if let syn::Type::Path(tp) = self_ty.as_ref() {
-            tp.path
-                .segments
-                .last()
-                .map(|s| s.ident.to_string().replace("Controller", ""))
-                .unwrap_or_else(|| "API".to_string())
-        } else {
-            "API".to_string()
+    // Combine version and path prefix (e.g., "/api/v1/projects")
+    let full_prefix = match controller_prefix(controller_args.version.as_deref(), &path_prefix) {
+        Ok(prefix) => prefix,
+        Err(msg) => {
+            return syn::Error::new(proc_macro2::Span::call_site(), msg).to_compile_error();
         }
-    });
+    };
+
+    // Resource name from the controller type (e.g., "Projects" for ProjectsController)
+    let resource = if let syn::Type::Path(tp) = self_ty.as_ref() {
+        tp.path
+            .segments
+            .last()
+            .map(|s| s.ident.to_string().replace("Controller", ""))
+            .unwrap_or_else(|| "API".to_string())
+    } else {
+        "API".to_string()
+    };
+    let tag = controller_args
+        .tag
+        .clone()
+        .unwrap_or_else(|| resource.clone());
 
     // Controller-level security - applies to all routes
-    let controller_security = controller_args.security;
+    let controller_security = controller_args.security.as_ref();
 
     // Schema types to register
     let schema_types = &controller_args.schemas.0;
 
-    // Phase 1: HATEOAS Transformation
-    let mut new_items = Vec::new();
+    let mut errors = Vec::new();
+    let path_case = controller_args.path_case().unwrap_or_else(|e| {
+        errors.push(e);
+        Case::Kebab
+    });
+    let short_schema_names = controller_args
+        .short_schema_names()
+        .map_err(|e| errors.push(e))
+        .unwrap_or(false);
+    let minimal_responses = controller_args
+        .minimal_auto_responses()
+        .map_err(|e| errors.push(e))
+        .unwrap_or(false);
     let original_items: Vec<_> = impl_block.items.drain(..).collect();
 
-    for item in original_items {
-        if let syn::ImplItem::Fn(mut method) = item {
-            // Find route attr
-            let route_attr_idx = method.attrs.iter().position(|a| a.path().is_ident("route"));
+    // Full path of every route, so routes can refer to each other by handler name
+    let route_paths: Vec<(syn::Ident, String)> = original_items
+        .iter()
+        .filter_map(|item| match item {
+            ImplItem::Fn(method) => method
+                .attrs
+                .iter()
+                .find(|a| a.path().is_ident("route"))
+                .and_then(|a| parse_route_attr(a, &method.sig.ident, path_case).ok())
+                .map(|info| {
+                    (
+                        method.sig.ident.clone(),
+                        join_paths(&full_prefix, &info.path),
+                    )
+                }),
+            _ => None,
+        })
+        .collect();
+    // Error type of every route returning `Result<T, E>`, before wrappers rewrite outputs
+    let route_errors: Vec<(syn::Ident, syn::Type)> = original_items
+        .iter()
+        .filter_map(|item| match item {
+            ImplItem::Fn(method) if method.attrs.iter().any(|a| a.path().is_ident("route")) => {
+                extract_result_error(&method.sig.output)
+                    .map(|error| (method.sig.ident.clone(), error.clone()))
+            }
+            _ => None,
+        })
+        .collect();
 
-            let mut links = Vec::new();
-            if let Some(idx) = route_attr_idx {
-                if let Ok(info) = parse_route_attr(&method.attrs[idx]) {
-                    if !info.links.is_empty() {
-                        links = info.links;
-                    }
-                }
+    // error_map: status per variant of the controller's error type
+    let error_map = &controller_args.error_map;
+    let mapped_error: Option<Path> = if error_map.mappings.is_empty() {
+        None
+    } else if let Some(error) = error_map.error_type() {
+        Some(error)
+    } else {
+        let mut candidates: Vec<&syn::Type> = Vec::new();
+        for (_, error) in &route_errors {
+            if !candidates
+                .iter()
+                .any(|c| c.to_token_stream().to_string() == error.to_token_stream().to_string())
+            {
+                candidates.push(error);
+            }
+        }
+        match candidates.as_slice() {
+            [syn::Type::Path(tp)] => Some(tp.path.clone()),
+            _ => {
+                errors.push(syn::Error::new(
+                    proc_macro2::Span::call_site(),
+                    "`error_map`: cannot infer the error type, name it first: `error_map(MyError, ...)`",
+                ));
+                None
             }
+        }
+    };
 
-            if !links.is_empty() {
-                let original_ident = method.sig.ident.clone();
-                let impl_ident = quote::format_ident!("__impl_{}", original_ident);
-
-                // 1. Implementation Method (renamed, hidden, no route attr)
-                let mut impl_method = method.clone();
-                impl_method.sig.ident = impl_ident.clone();
-                impl_method.attrs.retain(|a| !a.path().is_ident("route"));
-                impl_method.attrs.push(syn::parse_quote!(#[doc(hidden)]));
-                impl_method
-                    .attrs
-                    .push(syn::parse_quote!(#[allow(non_snake_case)]));
-
-                new_items.push(syn::ImplItem::Fn(impl_method));
-
-                // 2. Wrapper Method (original name, wrapped logic)
-                let mut wrapper_method = method.clone();
-
-                // Extract return type inner T
-                // Extract return type inner T
-                let inner_type_opt =
-                    if let syn::ReturnType::Type(_, ty) = &wrapper_method.sig.output {
-                        extract_inner_type(ty)
-                    } else {
-                        None
-                    };
-
-                if let Some(inner_type) = inner_type_opt {
-                    // Change return type to Result<Json<HateoasResponse<T>>>
-                    // Use short names assuming they are in scope (via prelude) to help Utoipa resolution
-                    let new_output: syn::ReturnType = syn::parse_quote! {
-                        -> Result<Json<HateoasResponse<#inner_type>>>
-                    };
-                    wrapper_method.sig.output = new_output;
-
-                    // forward args
-                    let args: Vec<_> = wrapper_method
-                        .sig
-                        .inputs
-                        .iter()
-                        .flat_map(|arg| match arg {
-                            syn::FnArg::Typed(pat) => collect_pat_idents(&pat.pat),
-                            _ => Vec::new(),
-                        })
-                        .collect();
-
-                    // Links statements
-                    let link_stmts = links.iter().map(|l| {
-                        let rel = &l.rel;
-                        let href = &l.href;
-                        let method = l.method.as_deref().unwrap_or("GET");
-                        quote! {
-                            h = h.add_link(#rel, Link::new(#href).method(#method));
-                        }
-                    });
-
-                    let wrapper_body = quote! {
-                        {
-                            let resp = Self::#impl_ident( #(#args),* ).await?;
-                            let Json(data) = resp;
-                            let mut h = HateoasResponse::new(data);
-                            #(#link_stmts)*
-                            Ok(Json(h))
-                        }
-                    };
-                    wrapper_method.block = syn::parse2(wrapper_body).expect("Invalid wrapper body");
-                    new_items.push(syn::ImplItem::Fn(wrapper_method));
+    let ctx = Controller {
+        args: &controller_args,
+        full_prefix,
+        tag,
+        resource,
+        path_case,
+        route_paths,
+        route_errors,
+        mapped_error,
+        short_schema_names,
+        minimal_responses,
+    };
+    let full_prefix = &ctx.full_prefix;
+    let tag = &ctx.tag;
+
+    let error_response_impl = match &ctx.mapped_error {
+        Some(error) => {
+            let arms = error_map.mappings.iter().map(|m| {
+                let variant = if m.variant.segments.len() > 1 {
+                    m.variant.clone()
                 } else {
-                    new_items.push(syn::ImplItem::Fn(method));
+                    let mut path = error.clone();
+                    path.segments.push(m.variant.segments[0].clone());
+                    path
+                };
+                let name = m.variant.segments.last().map(|s| s.ident.to_string());
+                let status = &m.status;
+                quote! { #variant { .. } => (#status, #name), }
+            });
+            quote! {
+                impl From<#error> for eywa_axum::axum::response::Response {
+                    fn from(error: #error) -> Self {
+                        use eywa_axum::axum::response::IntoResponse;
+
+                        #[allow(unreachable_patterns)]
+                        let (status, code) = match error {
+                            #(#arms)*
+                            _ => (500, "InternalError"),
+                        };
+                        let status = eywa_axum::axum::http::StatusCode::from_u16(status)
+                            .unwrap_or(eywa_axum::axum::http::StatusCode::INTERNAL_SERVER_ERROR);
+                        (status, eywa_axum::Json(eywa_axum::serde_json::json!({ "error": code })))
+                            .into_response()
+                    }
                 }
-            } else {
-                new_items.push(syn::ImplItem::Fn(method));
             }
-        } else {
-            new_items.push(item);
         }
-    }
-    impl_block.items = new_items;
+        None => quote! {},
+    };
+
+    // Phase 1: handler wrappers (HATEOAS links, CSV export, response envelope, guards, ...)
+    let (items, handlers) = handlers::wrap_handlers(&ctx, original_items, &mut errors);
+    impl_block.items = items;
 
-    // Collect route information from methods
-    let mut routes = Vec::new();
+    let (routes, any_routes) =
+        registration::collect_routes(&ctx, &mut impl_block.items, &handlers, &mut errors);
+
+    // Generate route registrations
+    let route_registrations: Vec<_> = routes
+        .iter()
+        .map(|(fn_name, route_info, _)| (fn_name, route_info))
+        .chain(
+            any_routes
+                .iter()
+                .map(|(fn_name, route_info)| (fn_name, route_info)),
+        )
+        .map(|(fn_name, route_info)| {
+            registration::route_registration(&ctx, fn_name, route_info, &mut errors)
+        })
+        .collect();
 
-    for item in &mut impl_block.items {
-        if let ImplItem::Fn(method) = item {
-            // Look for #[route(...)] attribute
-            let route_attr_idx = method.attrs.iter().position(|a| a.path().is_ident("route"));
+    // Client generators and the batch endpoint key off operation ids
+    let mut operation_ids: Vec<String> = Vec::new();
+    for (fn_name, route_info) in routes
+        .iter()
+        .map(|(fn_name, route_info, _)| (fn_name, route_info))
+        .chain(
+            any_routes
+                .iter()
+                .map(|(fn_name, route_info)| (fn_name, route_info)),
+        )
+    {
+        let operation_id = route_info.operation_id(fn_name);
+        if operation_id.is_empty() || operation_ids.contains(&operation_id) {
+            let message = if operation_id.is_empty() {
+                "`operation_id` must not be empty".to_string()
+            } else {
+                format!("duplicate operation id `{}`", operation_id)
+            };
+            match &route_info.operation_id {
+                Some(lit) => errors.push(syn::Error::new_spanned(lit, message)),
+                None => errors.push(syn::Error::new_spanned(fn_name, message)),
+            }
+        }
+        operation_ids.push(operation_id);
+    }
 
-            if let Some(idx) = route_attr_idx {
-                let attr = method.attrs.remove(idx);
+    // Batch endpoint dispatching to this controller's operations by operation id
+    let batch_path = join_paths(full_prefix, "/_batch");
+    let batch_operations: Vec<BatchOperation> = if controller_args.batch {
+        routes
+            .iter()
+            .map(|(fn_name, route_info, _)| BatchOperation {
+                operation_id: route_info.operation_id(fn_name),
+                method: route_info.method.as_str(),
+                path: join_paths(full_prefix, &route_info.path),
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let batch_route = axum_path(&batch_path);
+    let (batch_registration, batch_handler, batch_router, batch_docs) = if controller_args.batch {
+        (
+            quote! { .route(#batch_route, eywa_axum::axum::routing::post(Self::__eywa_batch)) },
+            generate_batch_handler(&batch_operations, controller_args.max_request_size),
+            generate_batch_router(),
+            generate_batch_docs(&batch_path, tag, &batch_operations),
+        )
+    } else {
+        (quote! {}, quote! {}, quote! {}, quote! {})
+    };
 
-                // Parse route info
-                if let Ok(route_info) = parse_route_attr(&attr) {
-                    let fn_name = &method.sig.ident;
-                    routes.push((fn_name.clone(), route_info, method.sig.clone()));
+    // GraphQL endpoint executing against the schema from state
+    let graphql_path = controller_args.graphql.as_ref().map(|graphql| {
+        let path = graphql
+            .path
+            .as_ref()
+            .map_or("/graphql".to_string(), syn::LitStr::value);
+        join_paths(full_prefix, &path)
+    });
+    let (graphql_registration, graphql_handlers, graphql_docs) =
+        match (&controller_args.graphql, &graphql_path) {
+            (Some(graphql), Some(path)) => (
+                generate_graphql_registration(&axum_path(path), graphql.playground),
+                generate_graphql_handlers(&graphql.schema, path, graphql.playground),
+                generate_graphql_docs(path, tag),
+            ),
+            _ => (quote! {}, quote! {}, quote! {}),
+        };
+
+    // AsyncAPI channels for WebSocket and SSE routes
+    let mut channels = Vec::new();
+    for (fn_name, route_info, sig) in &routes {
+        let kind = realtime_kind(sig);
+        if let Some(messages) = &route_info.messages {
+            match kind {
+                None => errors.push(syn::Error::new_spanned(
+                    fn_name,
+                    "`messages` requires a `WebSocketUpgrade` argument or an `Sse<_>` return type",
+                )),
+                Some(RealtimeKind::Sse) if messages.receive.is_some() => {
+                    errors.push(syn::Error::new_spanned(
+                        fn_name,
+                        "`messages(receive = ...)` only applies to WebSocket routes",
+                    ))
                 }
+                _ => {}
             }
         }
+        if let Some(kind) = kind {
+            channels.push(Channel {
+                operation_id: route_info.operation_id(fn_name),
+                path: join_paths(full_prefix, &route_info.path),
+                kind,
+                messages: route_info.messages.as_ref(),
+            });
+        }
     }
+    let asyncapi_json = if channels.is_empty() {
+        quote! {}
+    } else {
+        generate_asyncapi_json(tag, &channels)
+    };
 
-    // Generate route registrations
-    let route_registrations: Vec<_> = routes
+    let options_registrations = registration::options_registrations(&ctx, &routes);
+    let layers = registration::controller_layers(&ctx, &routes, &mut errors);
+    let middleware_layers = &layers.statements;
+
+    // Generate utoipa wrapper functions
+    let mut generated = GeneratedDocs::default();
+    let utoipa_wrappers: Vec<_> = routes
         .iter()
-        .map(|(fn_name, route_info, _)| {
-            let method = format_ident!("{}", route_info.method.to_axum_method());
-            let full_path = format!("{}{}", full_prefix, route_info.path);
+        .map(|route| {
+            docs::utoipa_stub(&ctx, &handlers, &layers, &mut generated, route, &mut errors)
+        })
+        .collect();
+
+    // Route metadata, emitted as a static table behind `openapi_routes()`
+    let openapi_paths: Vec<_> = routes
+        .iter()
+        .map(|(_fn_name, route_info, _method_sig)| {
+            let full_path = join_paths(full_prefix, &route_info.path);
+            let method_str = route_info.method.as_str();
+            let summary = route_info.summary.as_deref().unwrap_or("");
+            let description = route_info.description.as_deref().unwrap_or("");
+            let tag = &tag;
 
             quote! {
-                .route(#full_path, eywa_axum::axum::routing::#method(Self::#fn_name))
+                eywa_axum::OpenApiPath {
+                    path: #full_path,
+                    method: #method_str,
+                    summary: #summary,
+                    description: #description,
+                    tag: #tag,
+                }
             }
         })
         .collect();
+    let route_count = openapi_paths.len();
 
-    // Generate middleware layers
-    let middleware_layers: Vec<_> = controller_args
-        .middleware
+    // Vendor extensions added to each operation in register_paths
+    let operation_extensions: Vec<_> = routes
         .iter()
-        .map(|m| {
-            quote! {
-                .layer(eywa_axum::axum::middleware::from_fn_with_state(state.clone(), #m))
-            }
+        .map(|route| {
+            extensions::operation_extensions(
+                &ctx,
+                &handlers,
+                &layers,
+                &generated,
+                route,
+                &mut errors,
+            )
         })
         .collect();
 
-    // Generate utoipa wrapper functions
-    let utoipa_wrappers: Vec<_> = routes
+    // Webhooks: one entry per emitted event, listing the operations triggering it
+    let mut events: Vec<(&str, &syn::Type, Vec<String>)> = Vec::new();
+    for (fn_name, route_info, _) in &routes {
+        for emit in &route_info.emits {
+            match events.iter_mut().find(|(event, _, _)| *event == emit.event) {
+                Some((_, _, triggers)) => triggers.push(fn_name.to_string()),
+                None => events.push((&emit.event, &emit.payload, vec![fn_name.to_string()])),
+            }
+        }
+    }
+    // Schemas named by route attributes: event payloads and multipart forms
+    let route_schemas: Vec<_> = events
         .iter()
-        .map(|(fn_name, route_info, method_sig)| {
-            let full_path = format!("{}{}", full_prefix, route_info.path);
-            let method_ident = syn::Ident::new(
-                route_info.method.to_axum_method(),
-                proc_macro2::Span::call_site(),
-            );
-            let summary = route_info.summary.as_deref().unwrap_or("");
-            // Append HATEOAS links to description
-            let mut desc_string = route_info.description.as_deref().unwrap_or("").to_string();
-            if !route_info.links.is_empty() {
-                if !desc_string.is_empty() {
-                    desc_string.push_str("\n\n");
-                }
-                desc_string.push_str("**Available Links:**\n");
-                for link in &route_info.links {
-                    let method = link.method.as_deref().unwrap_or("GET");
-                    desc_string
-                        .push_str(&format!("- `{}`: `{} {}`\n", link.rel, method, link.href));
-                }
+        .map(|(_, payload, _)| *payload)
+        .chain(
+            routes
+                .iter()
+                .filter_map(|(_, route_info, _)| route_info.multipart.as_ref()),
+        )
+        .fold(Vec::<&syn::Type>::new(), |mut acc, schema| {
+            if !acc
+                .iter()
+                .any(|s| s.to_token_stream().to_string() == schema.to_token_stream().to_string())
+            {
+                acc.push(schema);
             }
-            let description = desc_string.as_str();
-            let deprecated = route_info.deprecated;
-
-            // Build utoipa::path attribute body
-            let mut utoipa_body = quote! {
-                #method_ident,
-                path = #full_path,
-            };
-
-            if !summary.is_empty() {
-                utoipa_body = quote! {
-                    #utoipa_body
-                    summary = #summary,
-                };
+            acc
+        });
+    let webhook_entries: Vec<_> = events
+        .iter()
+        .map(|(event, payload, triggers)| {
+            let summary = format!("`{}` event", event);
+            quote! {
+                webhooks.insert(
+                    #event.to_string(),
+                    eywa_axum::serde_json::json!({
+                        "post": {
+                            "summary": #summary,
+                            "tags": [#tag],
+                            "requestBody": {
+                                "required": true,
+                                "content": {
+                                    "application/json": {
+                                        "schema": {
+                                            "$ref": format!(
+                                                "#/components/schemas/{}",
+                                                <#payload as utoipa::ToSchema>::name()
+                                            ),
+                                        },
+                                    },
+                                },
+                            },
+                            "responses": {
+                                "200": { "description": "Event received" },
+                            },
+                            "x-emitted-by": [#(#triggers),*],
+                        },
+                    }),
+                );
             }
-
-            if !description.is_empty() {
-                utoipa_body = quote! {
-                    #utoipa_body
-                    description = #description,
-                };
+        })
+        .collect();
+    let register_webhooks = if webhook_entries.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            // utoipa has no `webhooks` field; extensions serialize at the top level
+            let webhooks = openapi
+                .extensions
+                .get_or_insert_with(Default::default)
+                .entry("webhooks".to_string())
+                .or_insert_with(|| eywa_axum::serde_json::json!({}));
+            if let Some(webhooks) = webhooks.as_object_mut() {
+                #(#webhook_entries)*
             }
+        }
+    };
 
-            // Handle tags with priority: route tags array > route single tag > controller tag
-            if let Some(ref tags_array) = route_info.tags {
-                // Multiple tags from route
-                utoipa_body = quote! {
-                    #utoipa_body
-                    tags = [#(#tags_array),*],
-                };
-            } else if let Some(ref single_tag) = route_info.tag {
-                // Single tag from route (legacy support)
-                utoipa_body = quote! {
-                    #utoipa_body
-                    tag = #single_tag,
-                };
-            } else {
-                // Fallback to controller tag
-                utoipa_body = quote! {
-                    #utoipa_body
-                    tag = #tag,
-                };
+    // Body of panic responses, documented on every route
+    let problem_details = if controller_args.catch_panic {
+        generated.schemas.push(format_ident!("__ProblemDetails"));
+        quote! {
+            /// RFC 9457 problem details
+            #[derive(eywa_axum::Serialize, eywa_axum::Deserialize, eywa_axum::utoipa::ToSchema)]
+            #[schema(as = ProblemDetails)]
+            pub struct __ProblemDetails {
+                #[serde(rename = "type")]
+                pub problem_type: String,
+                pub title: String,
+                pub status: u16,
+                #[serde(skip_serializing_if = "Option::is_none")]
+                pub detail: Option<String>,
             }
+        }
+    } else {
+        quote! {}
+    };
 
-            if deprecated {
-                utoipa_body = quote! {
-                    #utoipa_body
-                    deprecated,
-                };
+    let json_patch_schema = if generated.uses_json_patch {
+        generated
+            .schemas
+            .push(format_ident!("__JsonPatchOperation"));
+        quote! {
+            /// JSON Patch operation kind
+            #[derive(eywa_axum::Serialize, eywa_axum::Deserialize, eywa_axum::utoipa::ToSchema)]
+            #[serde(rename_all = "lowercase")]
+            pub enum __JsonPatchOp {
+                Add,
+                Remove,
+                Replace,
+                Move,
+                Copy,
+                Test,
             }
 
-            // Add security if specified at route OR controller level
-            // Route security takes precedence, but if controller has security, all routes get it
-            let needs_security = route_info.security || controller_security;
-            if needs_security {
-                utoipa_body = quote! {
-                    #utoipa_body
-                    security(("bearer" = [])),
-                };
+            /// RFC 6902 JSON Patch operation
+            #[derive(eywa_axum::Serialize, eywa_axum::Deserialize, eywa_axum::utoipa::ToSchema)]
+            #[schema(as = JsonPatchOperation)]
+            pub struct __JsonPatchOperation {
+                #[schema(inline)]
+                pub op: __JsonPatchOp,
+                /// JSON Pointer to the target location
+                pub path: String,
+                /// Source location of `move` and `copy`
+                #[serde(skip_serializing_if = "Option::is_none")]
+                pub from: Option<String>,
+                /// Value of `add`, `replace` and `test`
+                #[serde(skip_serializing_if = "Option::is_none")]
+                #[schema(value_type = Option<Object>)]
+                pub value: Option<eywa_axum::serde_json::Value>,
             }
+        }
+    } else {
+        quote! {}
+    };
 
-            // Inject other attributes (like responses(...), params(...))
-            let other_tokens = route_info.other_attrs.iter().map(|(id, toks)| {
-                quote! { #id #toks, }
-            });
-
-            utoipa_body = quote! {
-                #utoipa_body
-                #(#other_tokens)*
-            };
-
-            let mut extra_structs = quote! {};
-            let mut success_body_type = quote! {};
-            let mut override_stub_output: Option<syn::ReturnType> = None;
-
-            // Check if generic HATEOAS response
-            let auto_success = if let syn::ReturnType::Type(_, ty) = &method_sig.output {
-                 if let Some(inner) = extract_inner_type(ty) {
-                     // Check if inner is HateoasResponse<T>
-                     if let Some(hateoas_inner) = extract_hateoas_inner_type(&inner) {
-                         // Generate concrete struct for Utoipa
-                         let struct_name = quote::format_ident!("__HateoasSchema_{}", fn_name);
-                         extra_structs = quote! {
-                             #[derive(eywa_axum::Serialize, eywa_axum::Deserialize, eywa_axum::utoipa::ToSchema)]
-                             #[allow(non_camel_case_types)]
-                             pub struct #struct_name {
-                                 pub data: #hateoas_inner,
-                                 pub links: std::collections::HashMap<String, eywa_axum::Link>,
-                             }
-                         };
-                         success_body_type = quote! { #struct_name };
-                         override_stub_output = Some(syn::parse_quote! { -> eywa_axum::Json<#struct_name> });
-                         quote! { (status = 200, body = #struct_name), }
-                     } else {
-                         // Standard response
-                         quote! { (status = 200, body = #inner), }
-                     }
-                 } else {
-                     quote! {}
-                 }
-            } else {
-                 quote! {}
-            };
+    // Prepare generated struct names for register_paths
+    // Utoipa generates structs like __path_functionName
+    let mut path_structs: Vec<_> = routes
+        .iter()
+        .map(|(ident, _, _)| quote::format_ident!("__path_{}", ident))
+        .collect();
 
-            // Override auto_success if user provided 200 manually... (logic below)
-            let user_resp = &route_info.responses;
-            let user_token_str = user_resp.as_ref().map(|t| t.to_string()).unwrap_or_default();
+    // PathItem field for each route's method (get, post, ...)
+    let mut path_item_fields: Vec<_> = routes
+        .iter()
+        .map(|(_, route_info, _)| format_ident!("{}", route_info.method.to_axum_method()))
+        .collect();
 
-            let final_success = if !user_token_str.contains("200") && !user_token_str.contains("OK") {
-                auto_success
-            } else {
-                quote! {}
-            };
+    // Controller tag per operation, "" for routes that opted out with `inherit_tag = false`
+    let mut inherited_tags: Vec<_> = routes
+        .iter()
+        .map(|(_, route_info, _)| match &route_info.inherit_tag {
+            Some(inherit) if !inherit.value => String::new(),
+            _ => tag.clone(),
+        })
+        .collect();
 
-            let auto_401 =
-                if !user_token_str.contains("401") && !user_token_str.contains("Unauthorized") {
-                    quote! { (status = 401, description = "Unauthorized"), }
-                } else {
-                    quote! {}
-                };
+    let mut operation_extensions = operation_extensions;
+    if controller_args.batch {
+        path_structs.push(format_ident!("__path___eywa_batch"));
+        inherited_tags.push(tag.clone());
+        path_item_fields.push(format_ident!("post"));
+        operation_extensions.push(quote! {});
+        generated.schemas.push(format_ident!("__BatchRequest"));
+        generated.schemas.push(format_ident!("__BatchResponse"));
+    }
+    if let Some(graphql) = &controller_args.graphql {
+        let schema = graphql
+            .schema
+            .to_token_stream()
+            .to_string()
+            .replace(' ', "");
+        let playground = graphql.playground;
+        path_structs.push(format_ident!("__path___eywa_graphql"));
+        inherited_tags.push(tag.clone());
+        path_item_fields.push(format_ident!("post"));
+        operation_extensions.push(operation_extension(
+            "x-graphql",
+            quote! {
+                eywa_axum::serde_json::json!({ "schema": #schema, "playground": #playground })
+            },
+        ));
+        generated.schemas.push(format_ident!("__GraphqlRequest"));
+    }
 
-            let auto_500 = if !user_token_str.contains("500")
-                && !user_token_str.contains("Internal server error")
-            {
-                quote! { (status = 500, description = "Internal server error"), }
-            } else {
-                quote! {}
-            };
+    // Gateway configuration export, covering ANY routes as well
+    let controller_name = self_ty.to_token_stream().to_string().replace(' ', "");
+    let manifest_routes: Vec<_> = routes
+        .iter()
+        .map(|(fn_name, route_info, _)| (fn_name, route_info))
+        .chain(
+            any_routes
+                .iter()
+                .map(|(fn_name, route_info)| (fn_name, route_info)),
+        )
+        .collect();
+    let gateway_manifest = gateway_manifest(
+        &controller_name,
+        full_prefix,
+        tag,
+        &controller_args,
+        &manifest_routes,
+    );
+
+    // `api_key`, `cookie` and `oauth2` schemes live with the controller's schemas; `bearer` is left
+    // to the app
+    if let Some(scheme) = &controller_args.security_scheme
+        && let Err(e) = check_security_scheme(scheme)
+    {
+        errors.push(e);
+    }
+    let mut register_security_schemes = Vec::new();
+    let requires_scheme = |name: &str| {
+        routes.iter().any(|(_, route_info, _)| {
+            route_info.security_requirement(&controller_args).as_deref() == Some(name)
+        })
+    };
+    if requires_scheme("api_key") {
+        register_security_schemes.push(quote! {
+            components.add_security_scheme(
+                "api_key",
+                utoipa::openapi::security::SecurityScheme::ApiKey(
+                    utoipa::openapi::security::ApiKey::Header(
+                        utoipa::openapi::security::ApiKeyValue::new("X-API-Key"),
+                    ),
+                ),
+            );
+        });
+    }
+    // One `cookie` scheme per controller, so its routes have to agree on the cookie
+    let controller_cookie = controller_security.and_then(|security| security.cookie.as_ref());
+    if let (Some(scheme), Some(_)) = (&controller_args.security_scheme, controller_cookie) {
+        errors.push(syn::Error::new_spanned(
+            scheme,
+            "`security_scheme` cannot be combined with `security = cookie(...)`",
+        ));
+    }
+    let mut cookie: Option<&syn::LitStr> = None;
+    for name in controller_cookie.into_iter().chain(
+        routes
+            .iter()
+            .filter_map(|(_, route_info, _)| route_info.security_cookie.as_ref()),
+    ) {
+        match cookie {
+            Some(first) if first.value() != name.value() => errors.push(syn::Error::new_spanned(
+                name,
+                format!(
+                    "routes of a controller share one session cookie, `{}` is already used",
+                    first.value()
+                ),
+            )),
+            Some(_) => {}
+            None => cookie = Some(name),
+        }
+    }
+    if let Some(cookie) = cookie
+        && requires_scheme("cookie")
+    {
+        register_security_schemes.push(quote! {
+            components.add_security_scheme(
+                "cookie",
+                utoipa::openapi::security::SecurityScheme::ApiKey(
+                    utoipa::openapi::security::ApiKey::Cookie(
+                        utoipa::openapi::security::ApiKeyValue::new(#cookie),
+                    ),
+                ),
+            );
+        });
+    }
+    match &controller_args.oauth2 {
+        Some(oauth2) => match oauth2.validate() {
+            Ok(()) => register_security_schemes.push(register_oauth2_scheme(oauth2)),
+            Err(e) => errors.push(e),
+        },
+        None if requires_scheme("oauth2") => {
+            let scheme = routes
+                .iter()
+                .find_map(|(_, route_info, _)| {
+                    route_info
+                        .security_scheme
+                        .as_ref()
+                        .filter(|scheme| scheme.value() == "oauth2")
+                })
+                .or(controller_args.security_scheme.as_ref())
+                .or(controller_args.default_security_scheme.as_ref());
+            if let Some(scheme) = scheme {
+                errors.push(syn::Error::new_spanned(
+                    scheme,
+                    "`security_scheme = \"oauth2\"` requires `oauth2(...)` on the controller",
+                ));
+            }
+        }
+        None => {}
+    }
 
-            let combined_responses = if let Some(tokens) = user_resp {
-                 quote! { #tokens, #final_success #auto_401 #auto_500 }
+    // compact_codegen: one call into `eywa_axum::__private` per schema and operation
+    let generated_schemas = &generated.schemas;
+    let (register_schemas_body, register_paths_body) = if controller_args.compact_codegen {
+        let customize = operation_extensions.iter().map(|stmts| {
+            if stmts.is_empty() {
+                quote! { |_| {} }
             } else {
-                 quote! { #final_success #auto_401 #auto_500 }
-            };
-
-            utoipa_body = quote! {
-               #utoipa_body
-               responses(
-                   #combined_responses
-               ),
-            };
-
-            // Use original function signature for stub to allow Utoipa auto-discovery
-            // Filter out 'self'
-            let stub_inputs = method_sig.inputs.iter().filter(|arg| match arg {
-                syn::FnArg::Receiver(_) => false,
-                _ => true,
-            });
-            let stub_output = override_stub_output.as_ref().unwrap_or(&method_sig.output);
-
+                quote! { |operation: &mut utoipa::openapi::path::Operation| { #stmts } }
+            }
+        });
+        (
             quote! {
-                #[utoipa::path(
-                    #utoipa_body
-                )]
-                #[allow(dead_code, unused_variables)]
-                pub async fn #fn_name(
-                    #(#stub_inputs),*
-                ) #stub_output {
-                    unreachable!("This is a stub for utoipa - use controller method instead");
-                }
+                #(eywa_axum::__private::register_schema::<#schema_types>(components);)*
+                #(eywa_axum::__private::register_schema::<#route_schemas>(components);)*
+                #(
+                    eywa_axum::__private::register_schema::<__UTOIPA_PATHS__::#generated_schemas>(
+                        components,
+                    );
+                )*
+            },
+            quote! {
+                #(
+                    eywa_axum::__private::add_operation::<__UTOIPA_PATHS__::#path_structs>(
+                        openapi,
+                        #inherited_tags,
+                        #customize,
+                    );
+                )*
+            },
+        )
+    } else {
+        (
+            quote! {
+                #(
+                    {
+                        use utoipa::{ToSchema, PartialSchema};
+                        let name = <#schema_types as ToSchema>::name().to_string();
+                        let schema = <#schema_types as PartialSchema>::schema();
+                        components.schemas.insert(name, schema);
+                    }
+                )*
+                #(
+                    {
+                        use utoipa::{ToSchema, PartialSchema};
+                        let name = <#route_schemas as ToSchema>::name().to_string();
+                        let schema = <#route_schemas as PartialSchema>::schema();
+                        components.schemas.insert(name, schema);
+                    }
+                )*
+                #(
+                    {
+                        use utoipa::{ToSchema, PartialSchema};
+                        let name = <__UTOIPA_PATHS__::#generated_schemas as ToSchema>::name().to_string();
+                        let schema = <__UTOIPA_PATHS__::#generated_schemas as PartialSchema>::schema();
+                        components.schemas.insert(name, schema);
+                    }
+                )*
+            },
+            quote! {
+                #(
+                    {
+                        // Utoipa generates a struct __path_FnName for each path
+                        use __UTOIPA_PATHS__::*;
 
-                #extra_structs
-            }
-        })
-        .collect();
+                        // Use pre-calculated struct name and fully qualified Path trait calls
+                        let path = <#path_structs as utoipa::Path>::path();
+                        let mut operation = <#path_structs as utoipa::Path>::operation();
 
-    // Generate OpenAPI paths for utoipa
-    let openapi_paths: Vec<_> = routes
-        .iter()
-        .map(|(_fn_name, route_info, _method_sig)| {
-            let full_path = format!("{}{}", full_prefix, route_info.path);
-            let method_str = route_info.method.to_axum_method().to_uppercase();
-            let summary = route_info.summary.as_deref().unwrap_or("");
-            let description = route_info.description.as_deref().unwrap_or("");
-            let tag = &tag;
+                        // Controller tag, unless the route opted out
+                        let tag = #inherited_tags;
+                        if !tag.is_empty() {
+                           operation.tags.get_or_insert_with(Vec::new).push(tag.to_string());
+                        }
 
-            quote! {
-                eywa_axum::OpenApiPath {
-                    path: #full_path.to_string(),
-                    method: #method_str.to_string(),
-                    summary: #summary.to_string(),
-                    description: #description.to_string(),
-                    tag: #tag.to_string(),
-                }
-            }
-        })
-        .collect();
+                        #operation_extensions
 
-    // Prepare generated struct names for register_paths
-    // Utoipa generates structs like __path_functionName
-    let path_structs: Vec<_> = routes
-        .iter()
-        .map(|(ident, _, _)| quote::format_ident!("__path_{}", ident))
-        .collect();
+                        // Each route has exactly one method, known at expansion time, so the
+                        // operation is moved straight into the matching PathItem slot
+                        let item = openapi.paths.paths.entry(path).or_default();
+                        item.#path_item_fields = Some(operation);
+                    }
+                )*
+            },
+        )
+    };
 
     // Generate the into_router implementation
     let into_router_impl = quote! {
@@ -432,14 +878,13 @@ pub fn controller_impl(args: TokenStream, input: TokenStream) -> TokenStream {
             ///
             /// The router includes all routes defined with `#[route(...)]`.
             fn into_router(state: #state_ty) -> eywa_axum::axum::Router<#state_ty> {
-                eywa_axum::axum::Router::new()
-                    #(#route_registrations)*
-                    #(#middleware_layers)*
-                    .with_state(state)
+                Self::__eywa_router(Self::__eywa_routes(), state)
             }
 
             /// Returns the URL prefix for this controller.
             /// Includes version prefix if specified (e.g., "/v1").
+            ///
+            /// Legacy alias of the inherent `full_prefix()`.
             fn prefix() -> &'static str {
                 #full_prefix
             }
@@ -450,81 +895,133 @@ pub fn controller_impl(args: TokenStream, input: TokenStream) -> TokenStream {
             }
 
             /// Returns route metadata for OpenAPI generation.
-            fn openapi_routes() -> Vec<eywa_axum::OpenApiPath> {
-                vec![
-                    #(#openapi_paths),*
-                ]
+            fn openapi_routes() -> &'static [eywa_axum::OpenApiPath] {
+                static ROUTES: [eywa_axum::OpenApiPath; #route_count] = [#(#openapi_paths),*];
+                &ROUTES
             }
 
             /// Register schemas used by this controller.
             fn register_schemas(components: &mut utoipa::openapi::Components) {
-                #(
-                    {
-                        use utoipa::{ToSchema, PartialSchema};
-                        let name = <#schema_types as ToSchema>::name().to_string();
-                        let schema = <#schema_types as PartialSchema>::schema();
-                        components.schemas.insert(name, schema);
-                    }
-                )*
+                #register_schemas_body
+                #(#register_security_schemes)*
             }
 
             /// Register paths in the OpenAPI spec.
             fn register_paths(openapi: &mut utoipa::openapi::OpenApi) {
-                #(
-                    {
-                        // Utoipa generates a struct __path_FnName for each path
-                        use __UTOIPA_PATHS__::*;
+                __UTOIPA_PATHS__::register_tag(openapi);
 
-                        // Use pre-calculated struct name and fully qualified Path trait calls
-                        let path = <#path_structs as utoipa::Path>::path();
-                        let methods = <#path_structs as utoipa::Path>::methods();
-                        let mut operation = <#path_structs as utoipa::Path>::operation();
+                #register_paths_body
 
-                        // Add tag if not present
-                        let tag = #tag;
-                        if !tag.is_empty() {
-                           operation.tags.get_or_insert_with(Vec::new).push(tag.to_string());
-                        }
+                #register_webhooks
+            }
+        }
+    };
 
-                        // Construct PathItem
-                        // In Utoipa 5, PathItem::new takes (method, operation)
-                        let mut methods_iter = methods.into_iter();
-                        let first_method = methods_iter.next().expect("At least one method required");
-
-                        let mut item = utoipa::openapi::path::PathItem::new(
-                            first_method.clone(),
-                            operation.clone()
-                        );
-
-                        // Add remaining methods if any
-                        for method in methods_iter {
-                             match method {
-                                utoipa::openapi::path::HttpMethod::Get => item.get = Some(operation.clone()),
-                                utoipa::openapi::path::HttpMethod::Post => item.post = Some(operation.clone()),
-                                utoipa::openapi::path::HttpMethod::Put => item.put = Some(operation.clone()),
-                                utoipa::openapi::path::HttpMethod::Delete => item.delete = Some(operation.clone()),
-                                utoipa::openapi::path::HttpMethod::Options => item.options = Some(operation.clone()),
-                                utoipa::openapi::path::HttpMethod::Head => item.head = Some(operation.clone()),
-                                utoipa::openapi::path::HttpMethod::Patch => item.patch = Some(operation.clone()),
-                                utoipa::openapi::path::HttpMethod::Trace => item.trace = Some(operation.clone()),
-                            }
-                        }
+    // Inherent helpers that don't belong on the IntoRouter trait
+    let inherent_impl = quote! {
+        impl #self_ty {
+            /// Route table without layers or state.
+            #[doc(hidden)]
+            fn __eywa_routes() -> eywa_axum::axum::Router<#state_ty> {
+                eywa_axum::axum::Router::new()
+                    #(#route_registrations)*
+                    #batch_registration
+                    #graphql_registration
+                    #(#options_registrations)*
+            }
 
-                        // Merge or insert
-                        if let Some(existing) = openapi.paths.paths.get_mut(&path) {
-                             if let Some(op) = item.get { existing.get = Some(op); }
-                             if let Some(op) = item.post { existing.post = Some(op); }
-                             if let Some(op) = item.put { existing.put = Some(op); }
-                             if let Some(op) = item.delete { existing.delete = Some(op); }
-                             if let Some(op) = item.options { existing.options = Some(op); }
-                             if let Some(op) = item.head { existing.head = Some(op); }
-                             if let Some(op) = item.patch { existing.patch = Some(op); }
-                             if let Some(op) = item.trace { existing.trace = Some(op); }
-                        } else {
-                             openapi.paths.paths.insert(path, item);
-                        }
+            /// Applies the controller's middleware layers to `router`.
+            #[doc(hidden)]
+            fn __eywa_layers(
+                router: eywa_axum::axum::Router<#state_ty>,
+                state: &#state_ty,
+            ) -> eywa_axum::axum::Router<#state_ty> {
+                let _ = state;
+                #(#middleware_layers)*
+                router
+            }
+
+            /// Applies the controller's middleware layers and `state` to `routes`.
+            #[doc(hidden)]
+            fn __eywa_router(
+                routes: eywa_axum::axum::Router<#state_ty>,
+                state: #state_ty,
+            ) -> eywa_axum::axum::Router<#state_ty> {
+                #batch_router
+                Self::__eywa_layers(routes, &state).with_state(state)
+            }
+
+            #batch_handler
+
+            #graphql_handlers
+
+            #asyncapi_json
+
+            /// Normalized mount prefix of this controller (e.g., "/api/v1/projects").
+            pub const fn full_prefix() -> &'static str {
+                #full_prefix
+            }
+
+            /// JSON description of the routes, authentication, rate limits and latency
+            /// settings, for deriving API gateway configuration.
+            pub const GATEWAY_MANIFEST: &'static str = #gateway_manifest;
+
+            /// Like `into_router`, but the route table is built once per process and
+            /// cloned afterwards. Useful for tests that build the app many times.
+            pub fn into_router_cached(state: #state_ty) -> eywa_axum::axum::Router<#state_ty> {
+                static ROUTES: std::sync::OnceLock<eywa_axum::axum::Router<#state_ty>> =
+                    std::sync::OnceLock::new();
+                let routes = ROUTES.get_or_init(Self::__eywa_routes).clone();
+                Self::__eywa_router(routes, state)
+            }
+
+            /// Builds this controller's standalone OpenAPI fragment (paths and schemas).
+            ///
+            /// Does not touch any shared spec, so fragments can be built lazily or in
+            /// parallel and merged afterwards.
+            pub fn openapi_fragment() -> utoipa::openapi::OpenApi {
+                let mut openapi = utoipa::openapi::OpenApiBuilder::new().build();
+                <Self as eywa_axum::IntoRouter<#state_ty>>::register_paths(&mut openapi);
+                <Self as eywa_axum::IntoRouter<#state_ty>>::register_schemas(
+                    openapi.components.get_or_insert_with(Default::default),
+                );
+                openapi
+            }
+
+            /// Registers this controller's fragment with an incremental spec builder.
+            pub fn contribute(openapi_builder: &mut eywa_axum::SpecBuilder) {
+                openapi_builder.add(#tag, Self::openapi_fragment);
+            }
+        }
+    };
+
+    // Tag object carrying the controller's description and display name
+    let tag_metadata = {
+        let description = controller_args.description.as_ref().map(|description| {
+            quote! { tag.description.get_or_insert_with(|| #description.to_string()); }
+        });
+        let display_name = controller_args.display_name.as_ref().map(|display_name| {
+            quote! {
+                tag.extensions
+                    .get_or_insert_with(Default::default)
+                    .insert("x-displayName".to_string(), #display_name.into());
+            }
+        });
+        if tag.is_empty() || (description.is_none() && display_name.is_none()) {
+            quote! {}
+        } else {
+            quote! {
+                let tags = openapi.tags.get_or_insert_with(Vec::new);
+                let index = match tags.iter().position(|tag| tag.name == #tag) {
+                    Some(index) => index,
+                    None => {
+                        tags.push(utoipa::openapi::tag::Tag::new(#tag));
+                        tags.len() - 1
                     }
-                )*
+                };
+                let tag = &mut tags[index];
+                #description
+                #display_name
             }
         }
     };
@@ -538,6 +1035,41 @@ pub fn controller_impl(args: TokenStream, input: TokenStream) -> TokenStream {
             .collect();
         let fn_count = fn_names.len();
 
+        // (method, path) of everything this controller serves, for ingress rules and
+        // conflict checks across controllers
+        let served_routes = {
+            let mut served: Vec<(&str, String)> = routes
+                .iter()
+                .map(|(_, route_info, _)| route_info)
+                .chain(any_routes.iter().map(|(_, route_info)| route_info))
+                .map(|route_info| {
+                    (
+                        route_info.method.as_str(),
+                        join_paths(full_prefix, &route_info.path),
+                    )
+                })
+                .collect();
+            if controller_args.batch {
+                served.push(("POST", batch_path.clone()));
+            }
+            if let (Some(graphql), Some(path)) = (&controller_args.graphql, &graphql_path) {
+                served.push(("POST", path.clone()));
+                if graphql.playground {
+                    served.push(("GET", path.clone()));
+                }
+            }
+            let served = served
+                .iter()
+                .map(|(method, path)| quote! { (#method, #path) });
+            quote! {
+                /// Name of the controller type
+                pub const CONTROLLER: &str = #controller_name;
+
+                /// Method and full path of every route served by this controller
+                pub const ROUTES: &[(&str, &str)] = &[#(#served),*];
+            }
+        };
+
         quote! {
             /// Module containing utoipa-compatible wrapper functions for OpenAPI documentation.
             ///
@@ -566,87 +1098,79 @@ pub fn controller_impl(args: TokenStream, input: TokenStream) -> TokenStream {
                 /// Number of paths in this controller
                 pub const PATH_COUNT: usize = #fn_count;
 
+                #served_routes
+
+                /// Adds the controller's tag description and display name to `openapi.tags`,
+                /// keeping a description the tag already has
+                #[allow(unused_variables)]
+                pub fn register_tag(openapi: &mut utoipa::openapi::OpenApi) {
+                    #tag_metadata
+                }
+
                 #(#utoipa_wrappers)*
+
+                #batch_docs
+
+                #graphql_docs
+
+                #problem_details
+
+                #json_patch_schema
             }
         }
     };
 
-    quote! {
+    let compile_errors = errors.iter().map(syn::Error::to_compile_error);
+    let config_tracking = config.track();
+
+    let output = quote! {
+        #(#compile_errors)*
+
+        #config_tracking
+
+        #prefix_deprecation
+
         #impl_block
 
         #into_router_impl
 
-        #utoipa_module
-    }
-}
+        #inherent_impl
 
-/// Parse a #[route(...)] attribute into RouteInfo
-fn parse_route_attr(attr: &Attribute) -> syn::Result<RouteInfo> {
-    let tokens = attr.meta.require_list()?.tokens.clone();
-    RouteInfo::parse(tokens)
-}
+        #error_response_impl
 
-/// Helper to extract T from Result<Json<T>> or Json<T> return types
-fn extract_inner_type(ty: &syn::Type) -> Option<syn::Type> {
-    if let syn::Type::Path(tp) = ty {
-        // Check if it matches Result<...>
-        if let Some(seg) = tp.path.segments.last() {
-            if seg.ident == "Result" {
-                if let syn::PathArguments::AngleBracketed(args) = &seg.arguments {
-                    if let Some(syn::GenericArgument::Type(inner)) = args.args.first() {
-                        // We found the Success type of Result. Check if it's Json<T>
-                        return extract_json_type(inner);
-                    }
-                }
-            }
-            // Check if it matches Json<...>
-            if seg.ident == "Json" {
-                return extract_json_type(ty);
-            }
-        }
-    }
-    None
+        #utoipa_module
+    };
+    config.resolve_crate_path(output)
 }
 
-fn extract_json_type(ty: &syn::Type) -> Option<syn::Type> {
-    if let syn::Type::Path(tp) = ty {
-        if let Some(seg) = tp.path.segments.last() {
-            if seg.ident == "Json" {
-                if let syn::PathArguments::AngleBracketed(args) = &seg.arguments {
-                    if let Some(syn::GenericArgument::Type(inner)) = args.args.first() {
-                        return Some(inner.clone());
-                    }
-                }
-            }
-        }
+/// Record a handler's signature for documentation before an outer wrapper replaces it
+fn remember_signature(sigs: &mut Vec<(syn::Ident, syn::Signature)>, sig: &syn::Signature) {
+    if !sigs.iter().any(|(name, _)| *name == sig.ident) {
+        sigs.push((sig.ident.clone(), sig.clone()));
     }
-    // If not Json<...>, return None as we only support wrapping Json responses for now
-    None
 }
 
-fn extract_hateoas_inner_type(ty: &syn::Type) -> Option<&syn::Type> {
-    if let syn::Type::Path(tp) = ty {
-        if let Some(seg) = tp.path.segments.last() {
-            if seg.ident == "HateoasResponse" {
-                if let syn::PathArguments::AngleBracketed(args) = &seg.arguments {
-                    if let Some(syn::GenericArgument::Type(inner)) = args.args.first() {
-                        return Some(inner);
-                    }
-                }
-            }
-        }
-    }
-    None
+/// Parse a #[route(...)] attribute into RouteInfo, deriving `path = auto` paths
+fn parse_route_attr(
+    attr: &Attribute,
+    fn_name: &syn::Ident,
+    path_case: Case,
+) -> syn::Result<RouteInfo> {
+    let tokens = attr.meta.require_list()?.tokens.clone();
+    let mut route_info = RouteInfo::parse(tokens)?;
+    route_info.apply_auto_path(fn_name, path_case);
+    Ok(route_info)
 }
 
-/// Helper to extract variable identifiers from a pattern (e.g., extract 'id' from 'Path(id)')
-fn collect_pat_idents(pat: &syn::Pat) -> Vec<syn::Ident> {
-    match pat {
-        syn::Pat::Ident(p) => vec![p.ident.clone()],
-        syn::Pat::TupleStruct(p) => p.elems.iter().flat_map(collect_pat_idents).collect(),
-        syn::Pat::Type(p) => collect_pat_idents(&p.pat),
-        syn::Pat::Tuple(p) => p.elems.iter().flat_map(collect_pat_idents).collect(),
-        syn::Pat::Reference(p) => collect_pat_idents(&p.pat),
-        _ => Vec::new(),
+/// Strip the outer parentheses captured around an `other_attrs` value
+fn strip_parens(tokens: &TokenStream) -> TokenStream {
+    let mut iter = tokens.clone().into_iter();
+    match (iter.next(), iter.next()) {
+        (Some(proc_macro2::TokenTree::Group(g)), None)
+            if g.delimiter() == proc_macro2::Delimiter::Parenthesis =>
+        {
+            g.stream()
+        }
+        _ => tokens.clone(),
     }
 }
@@ -0,0 +1,867 @@
+//! utoipa stubs documenting the controller's routes
+
+use convert_case::{Case, Casing};
+use proc_macro2::TokenStream;
+use quote::{ToTokens, format_ident, quote};
+
+use super::handlers::HandlerDocs;
+use super::registration::{ControllerLayers, Route};
+use super::{Controller, strip_parens};
+use crate::codegen::schema_type_name;
+use crate::extractors::{has_path_extractor, has_streaming_body, query_params_types};
+use crate::parse::{HttpMethod, LinkInfo, TenantStrategy, UploadInfo, documented_statuses};
+use crate::wrappers::{
+    extract_generic_inner, extract_hateoas_inner_type, extract_inner_type, negotiated_media_type,
+};
+
+/// Items the route stubs generate for `register_schemas` and `register_paths`
+#[derive(Default)]
+pub(super) struct GeneratedDocs {
+    /// Schema structs generated for wrapped responses, registered alongside `schemas(...)`
+    pub schemas: Vec<syn::Ident>,
+    /// HATEOAS/envelope schema structs by wrapped type, reused across routes
+    wrapper_schemas: Vec<(String, syn::Ident)>,
+    /// Responses compact_codegen leaves to `eywa_axum::__private::default_responses`
+    pub deferred_responses: Vec<(syn::Ident, Vec<(u16, String)>)>,
+    /// Set when a route documents a JSON Patch body
+    pub uses_json_patch: bool,
+}
+
+/// The `#[utoipa::path]` stub documenting a route, with the schema structs it needs
+///
+/// The stub takes the handler's documented signature so utoipa infers parameters and
+/// bodies from its extractors; what the route's attributes and the controller's layers
+/// add to the operation (auth failures, rate limits, validator headers, ...) is
+/// documented here as parameters and responses
+pub(super) fn utoipa_stub(
+    ctx: &Controller,
+    handlers: &HandlerDocs,
+    layers: &ControllerLayers,
+    generated: &mut GeneratedDocs,
+    (fn_name, route_info, method_sig): &Route,
+    errors: &mut Vec<syn::Error>,
+) -> TokenStream {
+    let controller_tag = &ctx.tag;
+    let method_sig = handlers.signature(method_sig);
+    let full_path = ctx.full_path(route_info);
+    let method_ident = syn::Ident::new(
+        route_info.method.to_axum_method(),
+        proc_macro2::Span::call_site(),
+    );
+    let summary = route_info.summary.as_deref().unwrap_or("");
+    // Async jobs link to their status route
+    let mut doc_links = route_info.links.clone();
+    let cursor_paginated = route_info
+        .paginated
+        .as_ref()
+        .is_some_and(|mode| mode.value() == "cursor");
+    if cursor_paginated {
+        doc_links.push(LinkInfo {
+            rel: "next".to_string(),
+            href: format!("{}?cursor={{next_cursor}}", full_path),
+            method: Some("GET".to_string()),
+        });
+    }
+    if route_info.async_job.is_some()
+        && let Some(status_path) = route_info
+            .status_route
+            .as_ref()
+            .and_then(|name| ctx.resolve_route(name))
+    {
+        doc_links.push(LinkInfo {
+            rel: "status".to_string(),
+            href: status_path,
+            method: Some("GET".to_string()),
+        });
+    }
+
+    // Append HATEOAS links to description
+    let mut desc_string = route_info.description.as_deref().unwrap_or("").to_string();
+    if !doc_links.is_empty() {
+        if !desc_string.is_empty() {
+            desc_string.push_str("\n\n");
+        }
+        desc_string.push_str("**Available Links:**\n");
+        for link in &doc_links {
+            let method = link.method.as_deref().unwrap_or("GET");
+            desc_string.push_str(&format!("- `{}`: `{} {}`\n", link.rel, method, link.href));
+        }
+    }
+    if let Some(canary) = &route_info.canary {
+        if !desc_string.is_empty() {
+            desc_string.push_str("\n\n");
+        }
+        desc_string.push_str(&format!(
+            "**Canary:** {}% of `{}` values are served by `{}`, the rest by `{}`.",
+            canary.percent, canary.key, canary.canary, canary.stable
+        ));
+    }
+    let description = desc_string.as_str();
+    let deprecated = route_info.deprecated;
+
+    // Build utoipa::path attribute body
+    let mut utoipa_body = quote! {
+        #method_ident,
+        path = #full_path,
+    };
+
+    if let Some(operation_id) = &route_info.operation_id {
+        utoipa_body = quote! {
+            #utoipa_body
+            operation_id = #operation_id,
+        };
+    }
+
+    if !summary.is_empty() {
+        utoipa_body = quote! {
+            #utoipa_body
+            summary = #summary,
+        };
+    }
+
+    if !description.is_empty() {
+        utoipa_body = quote! {
+            #utoipa_body
+            description = #description,
+        };
+    }
+
+    // Handle tags with priority: route tags array > route single tag > controller tag
+    if let Some(ref tags_array) = route_info.tags {
+        // Multiple tags from route
+        utoipa_body = quote! {
+            #utoipa_body
+            tags = [#(#tags_array),*],
+        };
+    } else if let Some(ref single_tag) = route_info.tag {
+        // Single tag from route (legacy support)
+        utoipa_body = quote! {
+            #utoipa_body
+            tag = #single_tag,
+        };
+    } else {
+        // Fallback to controller tag
+        utoipa_body = quote! {
+            #utoipa_body
+            tag = #controller_tag,
+        };
+    }
+
+    // Add security if specified at route OR controller level
+    // Route security takes precedence, but if controller has security, all routes get it
+    if let Some(scheme) = route_info.security_requirement(ctx.args) {
+        // The route's scopes replace the controller's, which only hold for the controller's
+        // scheme; required permissions are scopes too
+        let controller_scheme = ctx.args.security_scheme_name();
+        let mut scopes: Vec<&String> =
+            if route_info.security_scopes.is_empty() && scheme == controller_scheme {
+                ctx.args
+                    .security
+                    .as_ref()
+                    .map(|security| security.scopes.iter().collect())
+                    .unwrap_or_default()
+            } else {
+                route_info.security_scopes.iter().collect()
+            };
+        for permission in &route_info.require {
+            if !scopes.contains(&permission) {
+                scopes.push(permission);
+            }
+        }
+        // An empty requirement first makes authentication optional
+        let anonymous = route_info.security_optional.then(|| quote! { (), });
+        utoipa_body = quote! {
+            #utoipa_body
+            security(#anonymous (#scheme = [#(#scopes),*])),
+        };
+    }
+
+    // Inject other attributes (like responses(...)); params are merged below
+    let other_tokens = route_info
+        .other_attrs
+        .iter()
+        .filter(|(id, _)| id != "params")
+        .map(|(id, toks)| {
+            quote! { #id #toks, }
+        });
+
+    utoipa_body = quote! {
+        #utoipa_body
+        #(#other_tokens)*
+    };
+
+    // PATCH bodies documented under their patch media type
+    if let Some(format) = &route_info.patch_format {
+        let json_body = method_sig.inputs.iter().find_map(|arg| match arg {
+            syn::FnArg::Typed(pt) => extract_generic_inner(&pt.ty, "Json"),
+            syn::FnArg::Receiver(_) => None,
+        });
+        let body = match (format.value().as_str(), json_body) {
+            _ if route_info.method != HttpMethod::Patch => {
+                Err("`patch_format` only applies to PATCH routes")
+            }
+            // Both would be documented, utoipa keeping only the last
+            _ if route_info
+                .other_attrs
+                .iter()
+                .any(|(id, _)| id == "request_body") =>
+            {
+                Err(
+                    "`patch_format` documents the request body, it cannot be combined with `request_body`",
+                )
+            }
+            ("merge", Some(body)) => Ok(quote! {
+                request_body(content = #body, content_type = "application/merge-patch+json"),
+            }),
+            ("merge", None) => Err("`patch_format = \"merge\"` needs a `Json<T>` body argument"),
+            ("json-patch", _) => {
+                generated.uses_json_patch = true;
+                Ok(quote! {
+                    request_body(content = Vec<__JsonPatchOperation>, content_type = "application/json-patch+json"),
+                })
+            }
+            _ => Err("expected `patch_format = \"merge\"` or `patch_format = \"json-patch\"`"),
+        };
+        match body {
+            Ok(body) => utoipa_body = quote! { #utoipa_body #body },
+            Err(message) => errors.push(syn::Error::new_spanned(format, message)),
+        }
+    }
+
+    // Bulk routes document their item array as the body, unless given explicitly
+    if let Some(bulk) = &route_info.bulk
+        && !route_info
+            .other_attrs
+            .iter()
+            .any(|(id, _)| id == "request_body")
+    {
+        let body = &bulk.body;
+        utoipa_body = quote! {
+            #utoipa_body
+            request_body = #body,
+        };
+    }
+
+    // Multipart uploads document their form schema, `Multipart` itself has none
+    if let Some(schema) = &route_info.multipart
+        && !route_info
+            .other_attrs
+            .iter()
+            .any(|(id, _)| id == "request_body")
+    {
+        utoipa_body = quote! {
+            #utoipa_body
+            request_body(content = #schema, content_type = "multipart/form-data"),
+        };
+    }
+
+    // Streamed bodies have no schema to infer, `upload` names their media type
+    let upload = route_info
+        .upload
+        .clone()
+        .or_else(|| has_streaming_body(method_sig).then(UploadInfo::default));
+    if let Some(upload) = &upload
+        && !route_info
+            .other_attrs
+            .iter()
+            .any(|(id, _)| id == "request_body")
+    {
+        let content_type = upload.content_type();
+        utoipa_body = quote! {
+            #utoipa_body
+            request_body(content = String, content_type = #content_type),
+        };
+    }
+
+    // utoipa keeps only the last params(...), so collect every source into one
+    let mut params: Vec<TokenStream> = route_info
+        .other_attrs
+        .iter()
+        .filter(|(id, _)| id == "params")
+        .map(|(_, toks)| strip_parens(toks))
+        .collect();
+
+    // Custom extractors mapped via param_from(...)
+    for mapping in &ctx.args.param_from.0 {
+        let mapped = mapping.ty.to_token_stream().to_string();
+        let used = method_sig.inputs.iter().any(|arg| {
+            matches!(arg, syn::FnArg::Typed(pat) if pat.ty.to_token_stream().to_string() == mapped)
+        });
+        if used {
+            let name = &mapping.name;
+            let location = mapping.parameter_in();
+            params.push(quote! { (#name = String, #location) });
+        }
+    }
+
+    if cursor_paginated {
+        params.push(quote! {
+            ("cursor" = Option<String>, Query, description = "Opaque cursor from the previous page's `next_cursor`")
+        });
+        params.push(quote! {
+            ("limit" = Option<u32>, Query, description = "Maximum number of items to return")
+        });
+    }
+
+    if ctx.args.request_id {
+        params.push(quote! {
+            ("X-Request-Id" = Option<String>, Header, description = "Correlation id, generated when absent")
+        });
+    }
+
+    if let Some(TenantStrategy::Header(header)) = &layers.tenant_strategy {
+        params.push(quote! { (#header = String, Header, description = "Tenant identifier") });
+    }
+
+    if let Some(signed) = &route_info.signed {
+        let query = &signed.query;
+        params.push(quote! {
+            (#query = String, Query, description = "Hex HMAC-SHA256 signature of the URL")
+        });
+        params.push(quote! {
+            ("expires" = u64, Query, description = "Unix time after which the link is rejected")
+        });
+    }
+
+    if let Some(verify) = &route_info.verify_signature {
+        let header = &verify.header;
+        params.push(quote! {
+            (#header = String, Header, description = "Hex HMAC-SHA256 of the raw request body")
+        });
+    }
+
+    // Filters are documented through their IntoParams impl
+    if let Some(filters) = &route_info.filterable {
+        params.push(quote! { #filters });
+    }
+
+    // Query structs are documented through their IntoParams impl
+    let ignored: Vec<syn::Path> = ctx
+        .args
+        .ignore_params
+        .0
+        .iter()
+        .chain(&route_info.ignore_params)
+        .cloned()
+        .collect();
+    for query in query_params_types(method_sig, &ignored) {
+        let query = query.to_token_stream();
+        if !params.iter().any(|p| p.to_string() == query.to_string()) {
+            params.push(query);
+        }
+    }
+
+    // Sortable fields are documented as an enum
+    let mut sort_schema = quote! {};
+    if !route_info.sortable.is_empty() {
+        let enum_name = format_ident!("__SortSchema_{}", fn_name);
+        let variants = route_info.sortable.iter().enumerate().map(|(i, field)| {
+            let variant = format_ident!("Field{}", i);
+            quote! { #[serde(rename = #field)] #variant }
+        });
+        sort_schema = quote! {
+            #[derive(eywa_axum::Serialize, eywa_axum::utoipa::ToSchema)]
+            #[allow(non_camel_case_types)]
+            pub enum #enum_name {
+                #(#variants),*
+            }
+        };
+        params.push(quote! {
+            ("sort" = inline(Option<#enum_name>), Query, description = "Field to sort by")
+        });
+    }
+
+    // Conditional GET: document the validator headers
+    let mut success_headers = Vec::new();
+    if route_info.last_modified.is_some() {
+        params.push(quote! {
+            ("If-Modified-Since" = Option<String>, Header, description = "Answer 304 if not modified since this HTTP date")
+        });
+        success_headers.push(quote! { ("Last-Modified" = String, description = "Time the resource was last modified") });
+    }
+    if route_info.location.is_some() {
+        success_headers
+            .push(quote! { ("Location" = String, description = "URL of the created resource") });
+    }
+    if route_info.requires_if_match {
+        params.push(quote! {
+            ("If-Match" = String, Header, description = "ETag of the representation being replaced")
+        });
+    }
+    if route_info.ranges.is_some() {
+        params.push(quote! {
+            ("Range" = Option<String>, Header, description = "Single byte range, e.g. `bytes=0-1023`")
+        });
+        success_headers.push(quote! { ("Accept-Ranges" = String, description = "`bytes`") });
+    }
+    success_headers.extend(
+        route_info
+            .response_headers
+            .iter()
+            .map(|header| header.to_token_stream()),
+    );
+    let success_header_attr = if success_headers.is_empty() {
+        quote! {}
+    } else {
+        quote! { headers(#(#success_headers),*), }
+    };
+    let method_output = &method_sig.output;
+
+    if !params.is_empty() {
+        utoipa_body = quote! {
+            #utoipa_body
+            params(#(#params),*),
+        };
+    }
+
+    let success_status = route_info
+        .success_status(ctx.args.rest_conventions())
+        .unwrap_or(200);
+
+    let mut extra_structs = quote! {};
+    let mut override_stub_output: Option<syn::ReturnType> = None;
+
+    // Generated wrappers (envelope, HATEOAS) get concrete schema structs for utoipa
+    let auto_success = if let syn::ReturnType::Type(_, ty) = method_output
+        && let Some(inner) = extract_inner_type(ty)
+    {
+        let envelope_inner = ctx
+            .args
+            .envelope
+            .as_ref()
+            .and_then(|e| e.segments.last())
+            .and_then(|e| extract_generic_inner(&inner, &e.ident.to_string()));
+        let mut body_type = match envelope_inner {
+            Some(t) => quote! { #t },
+            None => quote! { #inner },
+        };
+        let mut structs = Vec::new();
+        let mut wrapped = false;
+        // Readable name of the HATEOAS schema, for the envelope's
+        let mut schema_name: Option<String> = None;
+
+        // Check if inner is HateoasResponse<T>
+        if let Some(hateoas_inner) = extract_hateoas_inner_type(envelope_inner.unwrap_or(&inner)) {
+            let readable = format!(
+                "{}Hateoas",
+                schema_type_name(hateoas_inner)
+                    .unwrap_or_else(|| fn_name.to_string().to_case(Case::Pascal))
+            );
+            // One schema struct per data type, shared by the controller's routes
+            let key = format!("hateoas {}", hateoas_inner.to_token_stream());
+            let struct_name = match generated.wrapper_schemas.iter().find(|(k, _)| *k == key) {
+                Some((_, struct_name)) => struct_name.clone(),
+                None => {
+                    let struct_name = quote::format_ident!("__HateoasSchema_{}", fn_name);
+                    let schema_as = ctx.short_schema_names.then(|| {
+                        let readable = format_ident!("{}", readable);
+                        quote! { #[schema(as = #readable)] }
+                    });
+                    structs.push(quote! {
+                        #[derive(eywa_axum::Serialize, eywa_axum::Deserialize, eywa_axum::utoipa::ToSchema)]
+                        #schema_as
+                        #[allow(non_camel_case_types)]
+                        pub struct #struct_name {
+                            pub data: #hateoas_inner,
+                            pub links: std::collections::HashMap<String, eywa_axum::Link>,
+                        }
+                    });
+                    generated.schemas.push(struct_name.clone());
+                    generated.wrapper_schemas.push((key, struct_name.clone()));
+                    struct_name
+                }
+            };
+            schema_name = Some(readable);
+            body_type = quote! { #struct_name };
+            wrapped = true;
+        }
+
+        if envelope_inner.is_some() {
+            let key = format!("envelope {}", body_type);
+            let struct_name = match generated.wrapper_schemas.iter().find(|(k, _)| *k == key) {
+                Some((_, struct_name)) => struct_name.clone(),
+                None => {
+                    let struct_name = quote::format_ident!("__EnvelopeSchema_{}", fn_name);
+                    let schema_as = ctx.short_schema_names.then(|| {
+                        let data = schema_name
+                            .clone()
+                            .or_else(|| envelope_inner.and_then(schema_type_name))
+                            .unwrap_or_else(|| fn_name.to_string().to_case(Case::Pascal));
+                        let readable = format_ident!("{}Envelope", data);
+                        quote! { #[schema(as = #readable)] }
+                    });
+                    structs.push(quote! {
+                        #[derive(eywa_axum::Serialize, eywa_axum::Deserialize, eywa_axum::utoipa::ToSchema)]
+                        #schema_as
+                        #[allow(non_camel_case_types)]
+                        pub struct #struct_name {
+                            pub data: #body_type,
+                            #[schema(value_type = Option<Object>)]
+                            pub meta: Option<eywa_axum::serde_json::Value>,
+                            #[schema(value_type = Vec<Object>)]
+                            pub errors: Vec<eywa_axum::serde_json::Value>,
+                        }
+                    });
+                    generated.schemas.push(struct_name.clone());
+                    generated.wrapper_schemas.push((key, struct_name.clone()));
+                    struct_name
+                }
+            };
+            body_type = quote! { #struct_name };
+            wrapped = true;
+        }
+
+        if wrapped {
+            extra_structs = quote! { #(#structs)* };
+            override_stub_output = Some(syn::parse_quote! { -> eywa_axum::Json<#body_type> });
+        }
+        if let Some(versions) = &route_info.media_versions {
+            // One content entry per version, under its vendor media type
+            let content = versions.versions.iter().map(|(name, ty)| {
+                let media_type = versions.media_type(name);
+                quote! { (#ty = #media_type) }
+            });
+            quote! { (status = #success_status, content(#(#content),*), #success_header_attr), }
+        } else if route_info.negotiate.is_empty() {
+            quote! { (status = #success_status, body = #body_type, #success_header_attr), }
+        } else {
+            // One content entry per negotiated format; CSV is documented as text
+            let content = route_info.negotiate.iter().filter_map(|format| {
+                let format = format.to_string();
+                let media_type = negotiated_media_type(&format)?;
+                Some(if format == "csv" {
+                    quote! { (String = #media_type) }
+                } else {
+                    quote! { (#body_type = #media_type) }
+                })
+            });
+            quote! { (status = #success_status, content(#(#content),*), #success_header_attr), }
+        }
+    } else if let Some(job_type) = &route_info.async_job {
+        let location = if route_info.status_route.is_some() {
+            quote! { headers(("Location" = String, description = "URL of the job status resource")), }
+        } else {
+            quote! {}
+        };
+        quote! { (status = 202, description = "Job accepted", body = #job_type, #location), }
+    } else if handlers.csv_rows.iter().any(|(name, _)| name == fn_name) {
+        quote! { (status = #success_status, description = "CSV export", content_type = "text/csv", body = String, #success_header_attr), }
+    } else {
+        quote! {}
+    };
+
+    // 204 answers carry no body; other success statuses are documented even without one
+    let auto_success = match success_status {
+        200 => auto_success,
+        204 => quote! { (status = 204, description = "No content", #success_header_attr), },
+        status if auto_success.is_empty() => {
+            quote! { (status = #status, description = "Success", #success_header_attr), }
+        }
+        _ => auto_success,
+    };
+
+    // Override auto_success if user provided 200 manually... (logic below)
+    let user_resp = &route_info.responses;
+    // Statuses the route documents itself, which the generated ones don't repeat
+    let documented = user_resp
+        .as_ref()
+        .map(documented_statuses)
+        .unwrap_or_default();
+    let documents = |status: u16| documented.contains(&status);
+
+    // A shorthand success (`created = T`, `no_content`, ...) replaces the inferred one
+    let shorthand_success = route_info
+        .shorthand_statuses
+        .iter()
+        .any(|status| (200..300).contains(status));
+    let final_success = if !shorthand_success && !documents(200) && !documents(success_status) {
+        auto_success
+    } else {
+        quote! {}
+    };
+
+    // Description-only responses; compact_codegen adds them in register_paths instead
+    let mut deferred: Vec<(u16, String)> = Vec::new();
+    let mut plain_response = |status: u16, description: &str| {
+        if ctx.args.compact_codegen {
+            deferred.push((status, description.to_string()));
+            quote! {}
+        } else {
+            quote! { (status = #status, description = #description), }
+        }
+    };
+
+    // Statuses from error_map for routes returning the mapped error type
+    let mut error_statuses: Vec<(String, Vec<String>)> = Vec::new();
+    if let Some(error) = &ctx.mapped_error
+        && let Some((_, route_error)) = ctx.route_errors.iter().find(|(name, _)| name == fn_name)
+        && let syn::Type::Path(tp) = route_error
+        && tp.path.segments.last().map(|s| &s.ident) == error.segments.last().map(|s| &s.ident)
+    {
+        for m in &ctx.args.error_map.mappings {
+            let status = m.status.base10_digits().to_string();
+            let name = m
+                .variant
+                .segments
+                .last()
+                .map(|s| s.ident.to_string())
+                .unwrap_or_default();
+            match error_statuses.iter_mut().find(|(s, _)| *s == status) {
+                Some((_, names)) => names.push(name),
+                None => error_statuses.push((status, vec![name])),
+            }
+        }
+        error_statuses.retain(|(status, _)| !documents(status.parse().unwrap_or_default()));
+    }
+    let maps_status = |code: &str| error_statuses.iter().any(|(s, _)| s == code);
+    let error_responses: Vec<_> = error_statuses
+        .iter()
+        .map(|(status, names)| plain_response(status.parse().unwrap_or(500), &names.join(", ")))
+        .collect();
+    let error_responses = quote! { #(#error_responses)* };
+
+    let auto_429 = match &ctx.args.throttle {
+        Some(throttle) if !documents(429) => {
+            let description = format!(
+                "Rate limit of {} requests per minute exceeded",
+                throttle.per_minute
+            );
+            let retry_after = match throttle.retry_after_secs() {
+                Ok(Some(secs)) => format!("Seconds to wait before retrying (always {})", secs),
+                _ => "Seconds until the limit resets".to_string(),
+            };
+            quote! {
+                (status = 429, description = #description, headers(
+                    ("Retry-After" = u64, description = #retry_after),
+                    ("X-RateLimit-Limit" = u32, description = "Requests allowed per minute"),
+                )),
+            }
+        }
+        _ => quote! {},
+    };
+
+    let auto_503 = match &route_info.circuit_breaker {
+        Some(breaker) if !documents(503) => {
+            let retry_after = match breaker.settings().map(|s| s.retry_after) {
+                Ok(Some(secs)) => format!("Seconds to wait before retrying (always {})", secs),
+                _ => "Seconds until requests are let through again".to_string(),
+            };
+            quote! {
+                (status = 503, description = "Circuit open after repeated failures", headers(
+                    ("Retry-After" = u64, description = #retry_after),
+                )),
+            }
+        }
+        _ => quote! {},
+    };
+
+    let guard_status = route_info.guard.as_ref().map(|_| route_info.guard_status);
+    let auto_403 = if route_info.signed.is_some() && !documents(403) {
+        plain_response(403, "Invalid or expired signature")
+    } else if !route_info.require.is_empty() && !documents(403) {
+        plain_response(403, "Missing permission")
+    } else if route_info.policy.is_some() && !documents(403) {
+        plain_response(403, "Forbidden by policy")
+    } else if guard_status == Some(403) && !documents(403) {
+        plain_response(403, "Rejected by guard")
+    } else {
+        quote! {}
+    };
+    // 401 and 403 guards are documented with the other auth failures
+    let auto_guard = match guard_status {
+        Some(status) if status != 401 && status != 403 && !documents(status) => {
+            plain_response(status, "Rejected by guard")
+        }
+        _ => quote! {},
+    };
+
+    let auto_401 = if route_info.verify_signature.is_some() && !documents(401) {
+        plain_response(401, "Missing or invalid request signature")
+    } else if guard_status == Some(401) && !documents(401) {
+        plain_response(401, "Rejected by guard")
+    } else if route_info.security_optional || ctx.minimal_responses {
+        quote! {}
+    } else if !maps_status("401") && !documents(401) {
+        plain_response(401, "Unauthorized")
+    } else {
+        quote! {}
+    };
+
+    let auto_500 = if !maps_status("500") && !documents(500) {
+        if ctx.args.catch_panic {
+            quote! {
+                (status = 500, description = "Internal server error",
+                    content_type = "application/problem+json", body = __ProblemDetails),
+            }
+        } else if ctx.minimal_responses {
+            quote! {}
+        } else {
+            plain_response(500, "Internal server error")
+        }
+    } else {
+        quote! {}
+    };
+
+    // By-id routes that can fail usually fail with 404
+    let returns_result = matches!(
+        &method_sig.output,
+        syn::ReturnType::Type(_, ty) if matches!(&**ty, syn::Type::Path(tp)
+            if tp.path.segments.last().is_some_and(|s| s.ident == "Result" || s.ident == "ApiResult"))
+    );
+    let auto_404 = if returns_result
+        && !ctx.minimal_responses
+        && has_path_extractor(method_sig)
+        && !maps_status("404")
+        && !documents(404)
+    {
+        plain_response(404, "Not found")
+    } else {
+        quote! {}
+    };
+
+    let auto_400 = if route_info.param_constraints.enforce && !maps_status("400") && !documents(400)
+    {
+        plain_response(400, "Parameter out of bounds")
+    } else {
+        quote! {}
+    };
+    let auto_413 = match upload.as_ref().and_then(|upload| upload.max.as_ref()) {
+        Some(max) if !documents(413) => {
+            plain_response(413, &format!("Upload larger than {}", max.value()))
+        }
+        _ => quote! {},
+    };
+
+    let auto_304 = if route_info.last_modified.is_some() && !documents(304) {
+        plain_response(304, "Not modified since `If-Modified-Since`")
+    } else {
+        quote! {}
+    };
+    let auto_206 = if route_info.ranges.is_some() && !documents(206) {
+        quote! {
+            (status = 206, description = "Requested byte range", headers(
+                ("Content-Range" = String, description = "Range served, e.g. `bytes 0-1023/4096`"),
+                ("Accept-Ranges" = String, description = "`bytes`"),
+            )),
+            (status = 416, description = "Range not satisfiable", headers(
+                ("Content-Range" = String, description = "Representation length, e.g. `bytes */4096`"),
+            )),
+        }
+    } else {
+        quote! {}
+    };
+
+    let auto_406 = if (!route_info.negotiate.is_empty() || route_info.media_versions.is_some())
+        && !documents(406)
+    {
+        plain_response(406, "None of the negotiated media types is acceptable")
+    } else {
+        quote! {}
+    };
+    let auto_412 = if route_info.requires_if_match && !documents(412) {
+        let precondition_failed =
+            plain_response(412, "`If-Match` does not match the current representation");
+        let precondition_required = plain_response(428, "Missing `If-Match` header");
+        quote! { #precondition_failed #precondition_required }
+    } else {
+        quote! {}
+    };
+
+    // Per-item results of a bulk call
+    let mut bulk_schema = quote! {};
+    let auto_bulk = match &route_info.bulk {
+        Some(bulk) => {
+            let status = bulk
+                .partial_status
+                .as_ref()
+                .map_or("207".to_string(), |s| s.base10_digits().to_string());
+            if documents(status.parse().unwrap_or(207)) {
+                quote! {}
+            } else {
+                let status: u16 = status.parse().unwrap_or(207);
+                let struct_name = format_ident!("__BulkItemResult_{}", fn_name);
+                // Same shape for every bulk route
+                let schema_as = ctx
+                    .short_schema_names
+                    .then(|| quote! { #[schema(as = BulkItemResult)] });
+                bulk_schema = quote! {
+                    /// Outcome of one item of a bulk request
+                    #[derive(eywa_axum::Serialize, eywa_axum::Deserialize, eywa_axum::utoipa::ToSchema)]
+                    #schema_as
+                    #[allow(non_camel_case_types)]
+                    pub struct #struct_name {
+                        /// Position of the item in the request
+                        pub index: usize,
+                        /// HTTP status of this item
+                        pub status: u16,
+                        /// Result of a successful item
+                        #[schema(value_type = Option<Object>)]
+                        pub data: Option<eywa_axum::serde_json::Value>,
+                        /// Error of a failed item
+                        pub error: Option<String>,
+                    }
+                };
+                generated.schemas.push(struct_name.clone());
+                quote! {
+                    (status = #status, description = "Some items failed; per-item results in request order", body = Vec<#struct_name>),
+                }
+            }
+        }
+        None => quote! {},
+    };
+
+    if !deferred.is_empty() {
+        generated
+            .deferred_responses
+            .push((fn_name.clone(), deferred));
+    }
+
+    let combined_responses = if let Some(tokens) = user_resp {
+        quote! { #tokens, #final_success #auto_bulk #auto_206 #auto_304 #auto_406 #auto_412 #auto_400 #error_responses #auto_401 #auto_403 #auto_guard #auto_404 #auto_413 #auto_429 #auto_500 #auto_503 }
+    } else {
+        quote! { #final_success #auto_bulk #auto_206 #auto_304 #auto_406 #auto_412 #auto_400 #error_responses #auto_401 #auto_403 #auto_guard #auto_404 #auto_413 #auto_429 #auto_500 #auto_503 }
+    };
+
+    utoipa_body = quote! {
+       #utoipa_body
+       responses(
+           #combined_responses
+       ),
+    };
+
+    // openapi(append = { ... }) goes last, verbatim
+    if let Some(append) = &route_info.openapi_append {
+        utoipa_body = quote! { #utoipa_body #append };
+    }
+
+    // Use original function signature for stub to allow Utoipa auto-discovery
+    // Filter out 'self'
+    let stub_inputs = method_sig
+        .inputs
+        .iter()
+        .filter(|arg| !matches!(arg, syn::FnArg::Receiver(_)));
+    let filter_input = route_info.filterable.as_ref().map(|filters| {
+        quote! { __filters: eywa_axum::axum::extract::Query<#filters>, }
+    });
+    let stub_output = override_stub_output.as_ref().unwrap_or(method_output);
+    // utoipa reads the deprecation from the function's own attribute
+    let deprecated_attr = deprecated.then(|| quote! { #[deprecated] });
+
+    quote! {
+        #[utoipa::path(
+            #utoipa_body
+        )]
+        #deprecated_attr
+        #[allow(dead_code, unused_variables)]
+        pub async fn #fn_name(
+            #filter_input
+            #(#stub_inputs),*
+        ) #stub_output {
+            unreachable!("This is a stub for utoipa - use controller method instead");
+        }
+
+        #extra_structs
+        #sort_schema
+        #bulk_schema
+    }
+}
@@ -0,0 +1,298 @@
+//! Vendor extensions and other additions to the operations utoipa generates
+
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use super::Controller;
+use super::docs::GeneratedDocs;
+use super::handlers::HandlerDocs;
+use super::registration::{ControllerLayers, Route};
+use crate::codegen::{
+    generate_error_responses, operation_binary_body, operation_example, operation_extension,
+    operation_param_constraints, operation_param_formats,
+};
+use crate::extractors::{has_streaming_body, path_param_formats};
+use crate::parse::{
+    CircuitBreakerInfo, MirrorTarget, OPENAPI_APPEND_KEYS, Policy, TenantStrategy, UploadInfo,
+    appended_item, parse_duration_ms,
+};
+
+/// Statements completing a route's `operation` in `register_paths`: responses left
+/// out of the stub, the route's own tags, parameter formats and bounds, examples,
+/// error responses and the `x-...` extensions describing its behavior
+pub(super) fn operation_extensions(
+    ctx: &Controller,
+    handlers: &HandlerDocs,
+    layers: &ControllerLayers,
+    generated: &GeneratedDocs,
+    (fn_name, route_info, sig): &Route,
+    errors: &mut Vec<syn::Error>,
+) -> TokenStream {
+    // The handler as written, not its generated wrapper
+    let documented = handlers.signature(sig);
+    let mut stmts = Vec::new();
+    // Kept apart to reject an appended `extensions` list they would overwrite
+    let mut extensions = Vec::new();
+    // First, so the statements below see these responses too
+    if let Some((_, deferred)) = generated
+        .deferred_responses
+        .iter()
+        .find(|(name, _)| name == fn_name)
+    {
+        let deferred = deferred
+            .iter()
+            .map(|(status, description)| quote! { (#status, #description) });
+        stmts.push(quote! {
+            eywa_axum::__private::default_responses(operation, &[#(#deferred),*]);
+        });
+    }
+    // The route's own tags, after the controller tag unless it opted out
+    let own_tags = route_info
+        .tags
+        .clone()
+        .or_else(|| route_info.tag.clone().map(|tag| vec![tag]));
+    if let Some(own_tags) = own_tags {
+        let inherits = route_info
+            .inherit_tag
+            .as_ref()
+            .is_none_or(|inherit| inherit.value);
+        let own_tags = own_tags.iter().filter(|own| !inherits || **own != ctx.tag);
+        stmts.push(quote! {
+            let tags = operation.tags.get_or_insert_with(Vec::new);
+            #(tags.push(#own_tags.to_string());)*
+        });
+    }
+    let full_path = ctx.full_path(route_info);
+    // Formats of well-known path types, then the route's own
+    let mut formats: Vec<(String, String)> = path_param_formats(documented, &full_path)
+        .into_iter()
+        .map(|(name, format)| (name, format.to_string()))
+        .collect();
+    for (name, format) in &route_info.param_formats {
+        let name = name.to_string();
+        formats.retain(|(detected, _)| *detected != name);
+        formats.push((name, format.value()));
+    }
+    if !formats.is_empty() {
+        stmts.push(operation_param_formats(&formats, &full_path));
+    }
+    if !route_info.param_constraints.items.is_empty() {
+        stmts.push(operation_param_constraints(
+            &route_info.param_constraints.items,
+            &full_path,
+        ));
+    }
+    if !route_info.sensitive_params.is_empty() {
+        let sensitive = &route_info.sensitive_params;
+        extensions.push(operation_extension(
+            "x-sensitive",
+            quote! { eywa_axum::serde_json::json!([#(#sensitive),*]) },
+        ));
+        // Matching parameters are marked individually as well
+        stmts.push(quote! {
+            for parameter in operation.parameters.iter_mut().flatten() {
+                if [#(#sensitive),*].contains(&parameter.name.as_str()) {
+                    parameter
+                        .extensions
+                        .get_or_insert_with(Default::default)
+                        .insert("x-sensitive".to_string(), true.into());
+                }
+            }
+        });
+    }
+    if let Some(canary) = &route_info.canary {
+        let stable = canary.stable.to_string();
+        let canary_handler = canary.canary.to_string();
+        let percent = canary.percent;
+        let key = &canary.key;
+        extensions.push(operation_extension(
+            "x-canary",
+            quote! {
+                eywa_axum::serde_json::json!({
+                    "stable": #stable,
+                    "canary": #canary_handler,
+                    "percent": #percent,
+                    "key": #key,
+                })
+            },
+        ));
+    }
+    if let Some(target) = &route_info.mirror_to {
+        let target = match target {
+            MirrorTarget::Handler(handler) => handler.to_string(),
+            MirrorTarget::Url(url) => url.value(),
+        };
+        let percent = route_info
+            .mirror_percent
+            .as_ref()
+            .and_then(|p| p.base10_parse::<u8>().ok())
+            .unwrap_or(100);
+        extensions.push(operation_extension(
+            "x-mirror",
+            quote! { eywa_axum::serde_json::json!({ "target": #target, "percent": #percent }) },
+        ));
+    }
+    if let Some(headers) = &layers.security_headers {
+        let names = headers.iter().map(|(name, _)| name);
+        let values = headers.iter().map(|(_, value)| value);
+        extensions.push(operation_extension(
+            "x-security-headers",
+            quote! { eywa_axum::serde_json::json!({ #(#names: #values),* }) },
+        ));
+    }
+    let upload = route_info
+        .upload
+        .clone()
+        .or_else(|| has_streaming_body(documented).then(UploadInfo::default));
+    if let Some(upload) = upload
+        && !route_info
+            .other_attrs
+            .iter()
+            .any(|(id, _)| id == "request_body")
+    {
+        stmts.push(operation_binary_body(&upload.content_type()));
+    }
+    let examples = [
+        (&route_info.example_request, true),
+        (&route_info.example_response, false),
+    ];
+    for (example, request) in examples {
+        if let Some(example) = example {
+            match operation_example(example, request) {
+                Ok(example) => stmts.push(example),
+                Err(e) => errors.push(e),
+            }
+        }
+    }
+    if let Some((cors, methods, max_age)) = &layers.cors
+        && cors.document
+    {
+        let origins = match cors.origins() {
+            origins if origins.is_empty() => vec!["*".to_string()],
+            origins => origins,
+        };
+        let headers = cors.headers();
+        let max_age = match max_age {
+            Some(secs) => quote! { Some(#secs) },
+            None => quote! { None::<u64> },
+        };
+        extensions.push(operation_extension(
+            "x-cors",
+            quote! {
+                eywa_axum::serde_json::json!({
+                    "origins": [#(#origins),*],
+                    "methods": [#(#methods),*],
+                    "headers": [#(#headers),*],
+                    "max_age": #max_age,
+                })
+            },
+        ));
+    }
+    // Echoed on every response
+    if ctx.args.request_id {
+        stmts.push(quote! {
+            for response in operation.responses.responses.values_mut() {
+                if let utoipa::openapi::RefOr::T(response) = response {
+                    response.headers.entry("X-Request-Id".to_string()).or_insert_with(|| {
+                        utoipa::openapi::header::HeaderBuilder::new()
+                            .description(Some("Correlation id of the request"))
+                            .build()
+                    });
+                }
+            }
+        });
+    }
+    match &layers.tenant_strategy {
+        Some(TenantStrategy::Header(header)) => extensions.push(operation_extension(
+            "x-tenant",
+            quote! { eywa_axum::serde_json::json!({ "strategy": "header", "header": #header }) },
+        )),
+        Some(TenantStrategy::Subdomain) => extensions.push(operation_extension(
+            "x-tenant",
+            quote! { eywa_axum::serde_json::json!({ "strategy": "subdomain" }) },
+        )),
+        None => {}
+    }
+    if !route_info.emits.is_empty() {
+        let events = route_info.emits.iter().map(|emit| {
+            let event = &emit.event;
+            let payload = &emit.payload;
+            quote! {
+                eywa_axum::serde_json::json!({
+                    "event": #event,
+                    "payload": <#payload as utoipa::ToSchema>::name(),
+                })
+            }
+        });
+        extensions.push(operation_extension(
+            "x-emits",
+            quote! { eywa_axum::serde_json::Value::Array(vec![#(#events),*]) },
+        ));
+    }
+    if let Some(max) = ctx.args.max_request_size {
+        extensions.push(operation_extension("x-max-request-size", quote! { #max }));
+    }
+    if route_info.read_only {
+        extensions.push(operation_extension("x-read-only", quote! { true }));
+    }
+    if let Some(Policy::Named(name)) = &route_info.policy {
+        extensions.push(operation_extension("x-policy", quote! { #name }));
+    }
+    if !layers.error_codes.is_empty() {
+        let error_codes = &layers.error_codes;
+        extensions.push(operation_extension(
+            "x-error-codes",
+            quote! { eywa_axum::serde_json::json!([#(#error_codes),*]) },
+        ));
+    }
+    // Errors are reported on the route registration
+    if let Some(Ok(ms)) = route_info.slow_warn.as_ref().map(parse_duration_ms) {
+        extensions.push(operation_extension("x-latency-budget-ms", quote! { #ms }));
+    }
+    // Whole seconds, rounded up; errors are reported on the route registration
+    if let Some(Ok(ms)) = route_info.sdk_cache_ttl.as_ref().map(parse_duration_ms) {
+        let secs = ms.div_ceil(1000);
+        extensions.push(operation_extension("x-cache-ttl", quote! { #secs }));
+    }
+    if let Some(Ok(settings)) = route_info
+        .circuit_breaker
+        .as_ref()
+        .map(CircuitBreakerInfo::settings)
+    {
+        let ratio = settings.failure_ratio;
+        let window_ms = settings.window_ms;
+        let min_requests = settings.min_requests;
+        extensions.push(operation_extension(
+            "x-circuit-breaker",
+            quote! {
+                eywa_axum::serde_json::json!({
+                    "failure_ratio": #ratio,
+                    "window_ms": #window_ms,
+                    "min_requests": #min_requests,
+                })
+            },
+        ));
+    }
+    // CSV column names come from the row type's schema properties
+    if let Some((_, row)) = handlers.csv_rows.iter().find(|(name, _)| name == fn_name) {
+        let insert = operation_extension("x-csv-columns", quote! { columns });
+        extensions.push(quote! {
+            if let utoipa::openapi::RefOr::T(utoipa::openapi::schema::Schema::Object(obj)) =
+                <#row as utoipa::PartialSchema>::schema()
+            {
+                let columns: Vec<String> = obj.properties.keys().cloned().collect();
+                #insert
+            }
+        });
+    }
+    if let Some((_, error)) = ctx.route_errors.iter().find(|(name, _)| name == fn_name) {
+        stmts.push(generate_error_responses(error, ctx.args.compact_codegen));
+    }
+    if !extensions.is_empty()
+        && let Some(append) = &route_info.openapi_append
+        && let Some(item) = appended_item(append, "extensions")
+    {
+        errors.push(syn::Error::new_spanned(item, OPENAPI_APPEND_KEYS));
+    }
+    quote! { #(#stmts)* #(#extensions)* }
+}
@@ -0,0 +1,375 @@
+//! Phase 1: rewriting route handlers into their generated wrappers
+
+use syn::ImplItem;
+
+use super::{Controller, parse_route_attr, remember_signature};
+use crate::parse::{HttpMethod, MirrorTarget};
+use crate::wrappers::{
+    canary_dispatcher, extract_generic_inner, extract_inner_type, negotiated_media_type,
+    proxy_handler, wrap_async_job, wrap_csv, wrap_cursor_page, wrap_envelope, wrap_guard,
+    wrap_hateoas, wrap_last_modified, wrap_location, wrap_media_versions, wrap_mirror,
+    wrap_negotiate, wrap_policy, wrap_publish, wrap_signed, wrap_transactional,
+    wrap_verify_signature,
+};
+
+/// What documenting the routes needs to know about the handlers as written, before
+/// phase 1 replaced them
+#[derive(Default)]
+pub(super) struct HandlerDocs {
+    /// Row type of each CSV route, for documenting its columns
+    pub csv_rows: Vec<(syn::Ident, syn::Type)>,
+    /// Signature to document for routes whose outer wrappers return raw responses
+    /// or take extra extractors (last_modified, signed, ...)
+    pub documented_sigs: Vec<(syn::Ident, syn::Signature)>,
+}
+
+impl HandlerDocs {
+    /// The signature to document for the route handled by `sig`
+    pub fn signature<'a>(&'a self, sig: &'a syn::Signature) -> &'a syn::Signature {
+        self.documented_sigs
+            .iter()
+            .find(|(name, _)| *name == sig.ident)
+            .map_or(sig, |(_, documented)| documented)
+    }
+}
+
+/// Wraps every route handler in the wrappers its `#[route]` attribute asks for,
+/// returning the impl block's new items
+///
+/// Each wrapper is a generated method calling the previous one, applied innermost
+/// first, so a request runs through them in this order:
+///
+/// 1. `verify_signature`, which sees the raw body before any extractor consumes it
+/// 2. `mirror_to`
+/// 3. `signed`, so nothing below runs for unsigned links
+/// 4. `guard`
+/// 5. `policy`
+/// 6. `transactional`, inside the checks so rejected requests never begin one
+/// 7. `location`, `last_modified`, `media_versions` and `negotiate`, shaping the response
+/// 8. `csv`, `async_job` or the controller's `envelope`
+/// 9. HATEOAS links (`links`, `collection`, `paginated = "cursor"`)
+/// 10. `publish`, innermost so the event sees the handler's own data
+///
+/// `canary` and `proxy` routes replace the handler outright and get none of them.
+pub(super) fn wrap_handlers(
+    ctx: &Controller,
+    items: Vec<ImplItem>,
+    errors: &mut Vec<syn::Error>,
+) -> (Vec<ImplItem>, HandlerDocs) {
+    let state_ty = &ctx.args.state;
+    let mut new_items = Vec::new();
+    let mut csv_rows: Vec<(syn::Ident, syn::Type)> = Vec::new();
+    let mut documented_sigs: Vec<(syn::Ident, syn::Signature)> = Vec::new();
+
+    // Every method's signature, so routes can dispatch to plain methods (canary)
+    let method_sigs: Vec<syn::Signature> = items
+        .iter()
+        .filter_map(|item| match item {
+            ImplItem::Fn(method) => Some(method.sig.clone()),
+            _ => None,
+        })
+        .collect();
+
+    for item in items {
+        let syn::ImplItem::Fn(mut method) = item else {
+            new_items.push(item);
+            continue;
+        };
+
+        // Find route attr
+        let route_info = method
+            .attrs
+            .iter()
+            .find(|a| a.path().is_ident("route"))
+            .and_then(|a| parse_route_attr(a, &method.sig.ident, ctx.path_case).ok());
+
+        if let Some(info) = &route_info
+            && let Some(canary) = &info.canary
+        {
+            let find = |name: &syn::Ident| method_sigs.iter().find(|sig| sig.ident == *name);
+            match (find(&canary.stable), find(&canary.canary)) {
+                (Some(stable), Some(_)) => {
+                    // Documented with the stable handler's signature
+                    let mut documented = stable.clone();
+                    documented.ident = method.sig.ident.clone();
+                    documented_sigs.push((method.sig.ident.clone(), documented));
+                    method = canary_dispatcher(method, canary, state_ty);
+                }
+                (stable, _) => {
+                    let missing = if stable.is_none() {
+                        &canary.stable
+                    } else {
+                        &canary.canary
+                    };
+                    errors.push(syn::Error::new_spanned(
+                        missing,
+                        format!("`canary`: no method named `{}` in this controller", missing),
+                    ));
+                }
+            }
+            new_items.push(syn::ImplItem::Fn(method));
+            continue;
+        }
+
+        if let Some(info) = &route_info
+            && let Some(upstream) = &info.proxy
+        {
+            let full_path = ctx.full_path(info);
+            new_items.push(syn::ImplItem::Fn(proxy_handler(
+                method, upstream, &full_path,
+            )));
+            continue;
+        }
+
+        if let Some(info) = route_info {
+            let returns_json = matches!(
+                &method.sig.output,
+                syn::ReturnType::Type(_, ty) if extract_inner_type(ty).is_some()
+            );
+            let cursor_paginated = match &info.paginated {
+                Some(mode) if mode.value() == "cursor" => true,
+                Some(mode) => {
+                    errors.push(syn::Error::new_spanned(
+                        mode,
+                        "unsupported pagination mode, expected `paginated = \"cursor\"`",
+                    ));
+                    false
+                }
+                None => false,
+            };
+            // `collection` gives list endpoints the `{ data, links }` shape even without links
+            let returns_vec = matches!(
+                &method.sig.output,
+                syn::ReturnType::Type(_, ty) if extract_inner_type(ty)
+                    .is_some_and(|inner| extract_generic_inner(&inner, "Vec").is_some())
+            );
+            if info.collection && (!returns_vec || cursor_paginated) {
+                errors.push(syn::Error::new_spanned(
+                    &method.sig.output,
+                    "`collection` routes must return `Json<Vec<T>>` or `Result<Json<Vec<T>>>`",
+                ));
+            }
+            // Innermost, so the event sees the handler's own data
+            if let Some(publish) = &info.publish {
+                if matches!(
+                    info.method,
+                    HttpMethod::Post | HttpMethod::Put | HttpMethod::Patch | HttpMethod::Delete
+                ) {
+                    remember_signature(&mut documented_sigs, &method.sig);
+                    match wrap_publish(method.clone(), publish, &mut new_items) {
+                        Ok(wrapped) => method = wrapped,
+                        Err(e) => errors.push(e),
+                    }
+                } else {
+                    errors.push(syn::Error::new_spanned(
+                        &publish.event,
+                        "`publish` only applies to POST, PUT, PATCH and DELETE routes",
+                    ));
+                }
+            }
+            let hateoas_wrapped =
+                cursor_paginated || !info.links.is_empty() || (info.collection && returns_vec);
+            if cursor_paginated {
+                method = wrap_cursor_page(method, &info.links, &mut new_items);
+            } else if hateoas_wrapped {
+                method = wrap_hateoas(method, &info.links, &mut new_items);
+            }
+            if let Some(filename) = &info.csv {
+                // Only Vec<T> responses can be exported as rows
+                let row = match &method.sig.output {
+                    syn::ReturnType::Type(_, ty) => extract_inner_type(ty)
+                        .and_then(|inner| extract_generic_inner(&inner, "Vec").cloned()),
+                    _ => None,
+                };
+                match row {
+                    Some(row) => {
+                        let filename = if filename.is_empty() {
+                            format!("{}.csv", method.sig.ident)
+                        } else {
+                            filename.clone()
+                        };
+                        csv_rows.push((method.sig.ident.clone(), row));
+                        method = wrap_csv(method, &filename, &mut new_items);
+                    }
+                    None => errors.push(syn::Error::new_spanned(
+                        &method.sig.output,
+                        "`csv` routes must return `Json<Vec<T>>` or `Result<Json<Vec<T>>>`",
+                    )),
+                }
+            } else if info.async_job.is_some() {
+                let status_path = match &info.status_route {
+                    Some(name) => match ctx.resolve_route(name) {
+                        Some(path) => Some(path),
+                        None => {
+                            errors.push(syn::Error::new_spanned(
+                                name,
+                                format!(
+                                    "`status_route`: no route named `{}` in this controller",
+                                    name
+                                ),
+                            ));
+                            None
+                        }
+                    },
+                    None => None,
+                };
+                method = wrap_async_job(method, status_path.as_deref(), &mut new_items);
+            } else if let Some(envelope) = &ctx.args.envelope {
+                method = wrap_envelope(method, envelope, &mut new_items);
+            }
+            if !info.negotiate.is_empty() {
+                for format in &info.negotiate {
+                    if negotiated_media_type(&format.to_string()).is_none() {
+                        errors.push(syn::Error::new_spanned(
+                            format,
+                            "unknown `negotiate` format, expected `json`, `csv` or `xml`",
+                        ));
+                    }
+                }
+                if info.csv.is_some() || info.async_job.is_some() || info.last_modified.is_some() {
+                    errors.push(syn::Error::new_spanned(
+                        &info.negotiate[0],
+                        "`negotiate` cannot be combined with `csv`, `async_job` or `last_modified`",
+                    ));
+                } else if !returns_json {
+                    errors.push(syn::Error::new_spanned(
+                        &method.sig.output,
+                        "`negotiate` routes must return `Json<T>` or `Result<Json<T>>`",
+                    ));
+                } else {
+                    let formats: Vec<String> =
+                        info.negotiate.iter().map(ToString::to_string).collect();
+                    let rows = match &method.sig.output {
+                        syn::ReturnType::Type(_, ty) => extract_inner_type(ty)
+                            .is_some_and(|inner| extract_generic_inner(&inner, "Vec").is_some()),
+                        _ => false,
+                    };
+                    remember_signature(&mut documented_sigs, &method.sig);
+                    method = wrap_negotiate(method, &formats, rows, &mut new_items);
+                }
+            }
+            if let Some(versions) = &info.media_versions {
+                let first = &versions.versions[0].0;
+                if !info.negotiate.is_empty()
+                    || info.csv.is_some()
+                    || info.async_job.is_some()
+                    || info.last_modified.is_some()
+                    || info.location.is_some()
+                {
+                    errors.push(syn::Error::new_spanned(
+                        first,
+                        "`media_versions` cannot be combined with `negotiate`, `csv`, `async_job`, `last_modified` or `location`",
+                    ));
+                } else if hateoas_wrapped || ctx.args.envelope.is_some() {
+                    errors.push(syn::Error::new_spanned(
+                        first,
+                        "`media_versions` cannot be combined with links, `collection` or an `envelope`",
+                    ));
+                } else if !returns_json {
+                    errors.push(syn::Error::new_spanned(
+                        &method.sig.output,
+                        "`media_versions` routes must return `Json<T>` or `Result<Json<T>>`",
+                    ));
+                } else {
+                    remember_signature(&mut documented_sigs, &method.sig);
+                    method = wrap_media_versions(method, versions, &mut new_items);
+                }
+            }
+            if let Some(source) = &info.last_modified {
+                if info.csv.is_some() || info.async_job.is_some() {
+                    errors.push(syn::Error::new_spanned(
+                        source,
+                        "`last_modified` cannot be combined with `csv` or `async_job`",
+                    ));
+                } else if !returns_json {
+                    errors.push(syn::Error::new_spanned(
+                        &method.sig.output,
+                        "`last_modified` routes must return `Json<T>` or `Result<Json<T>>`",
+                    ));
+                } else {
+                    // HATEOAS and envelope wrappers each nest the data under `data`
+                    let layers =
+                        usize::from(hateoas_wrapped) + usize::from(ctx.args.envelope.is_some());
+                    remember_signature(&mut documented_sigs, &method.sig);
+                    method = wrap_last_modified(method, source, layers, &mut new_items);
+                }
+            }
+            if let Some(template) = &info.location {
+                if info.method != HttpMethod::Post {
+                    errors.push(syn::Error::new_spanned(
+                        template,
+                        "`location` only applies to POST routes",
+                    ));
+                } else if info.csv.is_some() || info.async_job.is_some() {
+                    errors.push(syn::Error::new_spanned(
+                        template,
+                        "`location` cannot be combined with `csv` or `async_job`",
+                    ));
+                } else if !returns_json {
+                    errors.push(syn::Error::new_spanned(
+                        &method.sig.output,
+                        "`location` routes must return `Json<T>` or `Result<Json<T>>`",
+                    ));
+                } else {
+                    let layers =
+                        usize::from(hateoas_wrapped) + usize::from(ctx.args.envelope.is_some());
+                    remember_signature(&mut documented_sigs, &method.sig);
+                    match wrap_location(method.clone(), template, layers, &mut new_items) {
+                        Ok(wrapped) => method = wrapped,
+                        Err(e) => errors.push(e),
+                    }
+                }
+            }
+            // Inside the guard, so rejected requests never begin one
+            if info.transactional || ctx.args.transactional {
+                remember_signature(&mut documented_sigs, &method.sig);
+                method = wrap_transactional(method, state_ty, &mut new_items);
+            }
+            if let Some(policy) = &info.policy {
+                remember_signature(&mut documented_sigs, &method.sig);
+                method = wrap_policy(method, policy, state_ty, &mut new_items);
+            }
+            if let Some(guard) = &info.guard {
+                remember_signature(&mut documented_sigs, &method.sig);
+                method = wrap_guard(method, guard, state_ty, &mut new_items);
+            }
+            // Outermost, so nothing runs for unsigned requests
+            if let Some(signed) = &info.signed {
+                remember_signature(&mut documented_sigs, &method.sig);
+                method = wrap_signed(method, signed, state_ty, &mut new_items);
+            }
+            if let Some(target) = &info.mirror_to {
+                if let MirrorTarget::Handler(handler) = target
+                    && ctx.resolve_route(handler).is_none()
+                {
+                    errors.push(syn::Error::new_spanned(
+                        handler,
+                        format!(
+                            "`mirror_to`: no route named `{}` in this controller",
+                            handler
+                        ),
+                    ));
+                } else {
+                    let percent = info
+                        .mirror_percent
+                        .as_ref()
+                        .and_then(|p| p.base10_parse().ok())
+                        .unwrap_or(100);
+                    remember_signature(&mut documented_sigs, &method.sig);
+                    method = wrap_mirror(method, target, percent, state_ty, &mut new_items);
+                }
+            }
+            // Sees the raw body before any extractor consumes it
+            if let Some(verify) = &info.verify_signature {
+                remember_signature(&mut documented_sigs, &method.sig);
+                method = wrap_verify_signature(method, verify, state_ty, &mut new_items);
+            }
+        }
+        new_items.push(syn::ImplItem::Fn(method));
+    }
+    let docs = HandlerDocs {
+        csv_rows,
+        documented_sigs,
+    };
+    (new_items, docs)
+}
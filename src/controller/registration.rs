@@ -0,0 +1,526 @@
+//! Route table and middleware layers of the controller's router
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::ImplItem;
+
+use super::handlers::HandlerDocs;
+use super::{Controller, parse_route_attr};
+use crate::codegen::{
+    generate_audit_layer, generate_body_size_layer, generate_catch_panic_layer,
+    generate_circuit_breaker_layer, generate_compression_layer, generate_cors_layer,
+    generate_deprecation_layer, generate_filter_layer, generate_if_match_layer, generate_log_layer,
+    generate_no_compress_layer, generate_param_constraints_layer, generate_range_layer,
+    generate_read_only_layer, generate_request_id_layer, generate_require_layer,
+    generate_security_headers_layer, generate_slow_warn_layer, generate_sort_layer,
+    generate_success_status_layer, generate_tenant_layer, generate_throttle_layer,
+    generate_trace_sample_layer, generate_upload_limit_layer,
+};
+use crate::extractors::{
+    check_method_body, check_param_names, check_path_params, check_signature, check_upload_body,
+};
+use crate::messages::{error_codes, generate_messages_layer, load_catalogs};
+use crate::parse::{
+    CorsArgs, HttpMethod, RouteInfo, SecurityHeaders, SummaryTemplate, TenantArgs, TenantStrategy,
+    parse_duration_ms,
+};
+use crate::paths::axum_path;
+
+/// Handler name, parsed `#[route]` and signature of a documented route
+pub(super) type Route = (syn::Ident, RouteInfo, syn::Signature);
+
+/// Takes the `#[route]` attribute off every handler and checks it against the handler
+/// as written. ANY routes come back apart: they are registered but left out of the
+/// OpenAPI document, which has no catch-all method
+pub(super) fn collect_routes(
+    ctx: &Controller,
+    items: &mut [ImplItem],
+    handlers: &HandlerDocs,
+    errors: &mut Vec<syn::Error>,
+) -> (Vec<Route>, Vec<(syn::Ident, RouteInfo)>) {
+    let summary_template = match ctx
+        .args
+        .summary_template
+        .as_ref()
+        .map(SummaryTemplate::parse)
+        .transpose()
+    {
+        Ok(template) => template,
+        Err(e) => {
+            errors.push(e);
+            None
+        }
+    };
+    let mut routes = Vec::new();
+    let mut any_routes = Vec::new();
+
+    for item in items {
+        if let ImplItem::Fn(method) = item {
+            // Look for #[route(...)] attribute
+            let route_attr_idx = method.attrs.iter().position(|a| a.path().is_ident("route"));
+
+            if let Some(idx) = route_attr_idx {
+                let attr = method.attrs.remove(idx);
+
+                // Parse route info; the method is still emitted when it fails so
+                // the error is the only one reported
+                let route_info = match parse_route_attr(&attr, &method.sig.ident, ctx.path_case) {
+                    Ok(mut route_info) => {
+                        route_info.apply_doc_comments(&method.attrs);
+                        if route_info.summary.is_none()
+                            && let Some(template) = &summary_template
+                        {
+                            route_info.summary = Some(template.render(
+                                route_info.method,
+                                &ctx.full_path(&route_info),
+                                &method.sig.ident.to_string(),
+                                &ctx.resource,
+                            ));
+                        }
+                        Some(route_info)
+                    }
+                    Err(e) => {
+                        errors.push(e);
+                        None
+                    }
+                };
+                if let Some(route_info) = route_info {
+                    let ignored: Vec<_> = ctx
+                        .args
+                        .ignore_params
+                        .0
+                        .iter()
+                        .chain(&route_info.ignore_params)
+                        .cloned()
+                        .chain(ctx.args.param_from.0.iter().filter_map(|m| match &m.ty {
+                            syn::Type::Path(tp) => Some(tp.path.clone()),
+                            _ => None,
+                        }))
+                        .collect();
+                    errors.extend(check_signature(&method.sig, &ignored));
+                    // Against the handler as written, not its generated wrapper; proxies
+                    // forward the whole path
+                    if route_info.proxy.is_none() {
+                        let written = handlers.signature(&method.sig);
+                        let full_path = ctx.full_path(&route_info);
+                        errors.extend(check_path_params(written, &full_path, &ignored));
+                        if !route_info.allow_get_body {
+                            errors.extend(check_method_body(written, route_info.method));
+                        }
+                        if route_info.upload.is_some() {
+                            errors.extend(check_upload_body(written));
+                        }
+                        errors.extend(check_param_names(
+                            written,
+                            &full_path,
+                            route_info
+                                .param_constraints
+                                .items
+                                .iter()
+                                .map(|constraint| &constraint.name)
+                                .chain(route_info.param_formats.iter().map(|(name, _)| name)),
+                        ));
+                    }
+
+                    let fn_name = &method.sig.ident;
+                    if route_info.method == HttpMethod::Any {
+                        any_routes.push((fn_name.clone(), route_info));
+                    } else {
+                        routes.push((fn_name.clone(), route_info, method.sig.clone()));
+                    }
+                }
+            }
+        }
+    }
+    (routes, any_routes)
+}
+
+/// Registers a route on the route table, wrapped in the layers its attribute asks for
+///
+/// Later layers wrap earlier ones, so a request passes them in this order:
+/// `trace_sample`, `deprecated`, `audit`, `log`, `require` (outside the request checks,
+/// so unauthorized callers learn nothing about them), `requires_if_match`, `slow_warn`,
+/// `circuit_breaker`, `compress`/`no_compress`, `ranges`, `sortable`, `filterable`,
+/// enforced `param_constraints`, `read_only`, the `upload` limit and finally `status`,
+/// innermost so the other layers see the final status. The handler wrappers of
+/// [`super::handlers::wrap_handlers`] run inside all of them
+pub(super) fn route_registration(
+    ctx: &Controller,
+    fn_name: &syn::Ident,
+    route_info: &RouteInfo,
+    errors: &mut Vec<syn::Error>,
+) -> TokenStream {
+    let method = format_ident!("{}", route_info.method.to_axum_method());
+    let full_path = ctx.full_path(route_info);
+    let route_path = axum_path(&full_path);
+    let filter_layer = route_info.filterable.as_ref().map(generate_filter_layer);
+    let sort_layer =
+        (!route_info.sortable.is_empty()).then(|| generate_sort_layer(&route_info.sortable));
+    let compression_layer = match (&route_info.compress, route_info.no_compress) {
+        (Some(encodings), true) => {
+            errors.push(syn::Error::new_spanned(
+                encodings,
+                "`compress` and `no_compress` are mutually exclusive",
+            ));
+            None
+        }
+        (Some(encodings), false) => generate_compression_layer(encodings)
+            .map_err(|e| errors.push(e))
+            .ok(),
+        (None, true) => Some(generate_no_compress_layer()),
+        (None, false) => None,
+    };
+    let range_layer = route_info.ranges.as_ref().map(generate_range_layer);
+    if route_info.requires_if_match
+        && !matches!(route_info.method, HttpMethod::Put | HttpMethod::Patch)
+    {
+        errors.push(syn::Error::new_spanned(
+            fn_name,
+            "`requires_if_match` only applies to PUT and PATCH routes",
+        ));
+    }
+    if let Some(inherit_tag) = &route_info.inherit_tag
+        && !inherit_tag.value
+        && route_info.tags.is_none()
+        && route_info.tag.is_none()
+    {
+        errors.push(syn::Error::new_spanned(
+            inherit_tag,
+            "`inherit_tag = false` needs the route's own `tag` or `tags`",
+        ));
+    }
+    let if_match_layer = route_info.requires_if_match.then(generate_if_match_layer);
+    let route_body_limit = route_info
+        .upload
+        .as_ref()
+        .and_then(|upload| upload.max_bytes().ok().flatten());
+    let log_layer = route_info
+        .log
+        .as_ref()
+        .or(ctx.args.log.as_ref())
+        .map(|log| {
+            generate_log_layer(
+                &full_path,
+                log,
+                &route_info.sensitive_params,
+                &ctx.args.log_fields.0,
+                route_body_limit,
+            )
+        });
+    let audit_layer = (route_info.audit || ctx.args.audit)
+        .then(|| generate_audit_layer(&full_path, &route_info.sensitive_params, route_body_limit));
+    let circuit_breaker_layer = route_info.circuit_breaker.as_ref().and_then(|breaker| {
+        breaker
+            .settings()
+            .map(|settings| generate_circuit_breaker_layer(&full_path, &settings))
+            .map_err(|e| errors.push(e))
+            .ok()
+    });
+    let slow_warn_layer = route_info.slow_warn.as_ref().and_then(|threshold| {
+        parse_duration_ms(threshold)
+            .map(|ms| generate_slow_warn_layer(&full_path, ms))
+            .map_err(|e| errors.push(e))
+            .ok()
+    });
+    // Remaining traffic of routes marked for removal
+    let deprecation_layer = route_info
+        .deprecated
+        .then(|| generate_deprecation_layer(&full_path, &route_info.operation_id(fn_name)));
+    // Outermost route layer, so the span covers the route's other layers
+    let trace_sample_layer =
+        route_info
+            .trace_sample
+            .as_ref()
+            .and_then(|rate| match rate.base10_parse::<f64>() {
+                Ok(value) if value > 0.0 && value <= 1.0 => {
+                    Some(generate_trace_sample_layer(&full_path, value))
+                }
+                Ok(_) => {
+                    errors.push(syn::Error::new_spanned(
+                        rate,
+                        "`trace_sample` must be in (0, 1]",
+                    ));
+                    None
+                }
+                Err(e) => {
+                    errors.push(e);
+                    None
+                }
+            });
+    if let Some(ttl) = &route_info.sdk_cache_ttl {
+        if route_info.method != HttpMethod::Get {
+            errors.push(syn::Error::new_spanned(
+                ttl,
+                "`sdk_cache_ttl` only applies to GET routes",
+            ));
+        } else if let Err(e) = parse_duration_ms(ttl) {
+            errors.push(e);
+        }
+    }
+
+    // Limits are validated when parsing the route
+    let upload_layer = route_info
+        .upload
+        .as_ref()
+        .and_then(|upload| upload.max_bytes().ok().flatten())
+        .map(generate_upload_limit_layer);
+
+    // Innermost, so the route's other layers see the final status
+    let success_status_layer = route_info
+        .success_status(ctx.args.rest_conventions())
+        .filter(|status| *status != 200)
+        .map(generate_success_status_layer);
+    let constraints_layer = route_info
+        .param_constraints
+        .enforce
+        .then(|| generate_param_constraints_layer(&route_info.param_constraints.items));
+    if route_info.read_only && route_info.method != HttpMethod::Get {
+        errors.push(syn::Error::new_spanned(
+            fn_name,
+            "`read_only` only applies to GET routes",
+        ));
+    }
+    let read_only_layer = route_info.read_only.then(generate_read_only_layer);
+    // Outside the request checks, so unauthorized callers learn nothing about them
+    let require_layer =
+        (!route_info.require.is_empty()).then(|| generate_require_layer(&route_info.require));
+
+    quote! {
+        .route(#route_path, eywa_axum::axum::routing::#method(Self::#fn_name) #success_status_layer #upload_layer #read_only_layer #constraints_layer #filter_layer #sort_layer #range_layer #compression_layer #circuit_breaker_layer #slow_warn_layer #if_match_layer #require_layer #log_layer #audit_layer #deprecation_layer #trace_sample_layer)
+    }
+}
+
+/// OPTIONS capability handlers, one per distinct path, with `auto_options`
+pub(super) fn options_registrations(ctx: &Controller, routes: &[Route]) -> Vec<TokenStream> {
+    if ctx.args.auto_options {
+        let mut by_path: Vec<(String, Vec<&'static str>, Vec<String>)> = Vec::new();
+        for (_, route_info, _) in routes {
+            let full_path = ctx.full_path(route_info);
+            let idx = match by_path.iter().position(|(p, _, _)| *p == full_path) {
+                Some(idx) => idx,
+                None => {
+                    by_path.push((full_path, Vec::new(), Vec::new()));
+                    by_path.len() - 1
+                }
+            };
+            let entry = &mut by_path[idx];
+            entry.1.push(route_info.method.as_str());
+            for link in &route_info.links {
+                if !entry.2.contains(&link.rel) {
+                    entry.2.push(link.rel.clone());
+                }
+            }
+        }
+
+        by_path
+            .into_iter()
+            // Respect handlers that already answer OPTIONS themselves
+            .filter(|(_, methods, _)| !methods.contains(&"OPTIONS"))
+            .map(|(full_path, mut methods, rels)| {
+                methods.push("OPTIONS");
+                let allow = methods.join(", ");
+                let mut body = serde_json::json!({ "methods": methods });
+                if !rels.is_empty() {
+                    body["links"] = rels.into();
+                }
+                let body = body.to_string();
+
+                let route_path = axum_path(&full_path);
+                quote! {
+                    .route(#route_path, eywa_axum::axum::routing::options(|| async {
+                        (
+                            [
+                                (eywa_axum::axum::http::header::ALLOW, #allow),
+                                (eywa_axum::axum::http::header::CONTENT_TYPE, "application/json"),
+                            ],
+                            #body,
+                        )
+                    }))
+                }
+            })
+            .collect()
+    } else {
+        Vec::new()
+    }
+}
+
+/// The controller's own layers, with what documenting its routes needs from them
+pub(super) struct ControllerLayers<'a> {
+    /// Statements rebinding `router`, innermost layer first, so layers can be gated on
+    /// cfg!(...)
+    pub statements: Vec<TokenStream>,
+    /// Codes of the `error_messages` catalogs
+    pub error_codes: Vec<String>,
+    pub tenant_strategy: Option<TenantStrategy>,
+    pub security_headers: Option<Vec<(&'static str, String)>>,
+    /// `cors(...)` with its methods and max age in seconds
+    pub cors: Option<(&'a CorsArgs, Vec<String>, Option<u64>)>,
+}
+
+/// Layers applied to the controller's whole router, around the route layers
+///
+/// A request passes them in this order:
+///
+/// 1. `request_id`, outermost so every response carries the id
+/// 2. `cors`, outside authentication and throttling, which preflights must not hit
+/// 3. `measure_bodies`, so rejected and short-circuited requests are measured too
+/// 4. `catch_panic`, covering the controller's middleware as well as handlers
+/// 5. `max_request_size`
+/// 6. `security_headers`, also applied to responses short-circuited further in
+/// 7. `tenant`, so the controller's middleware can read the tenant
+/// 8. the controller's `middleware`, the last one listed first
+/// 9. `error_messages`, which sees a locale the middleware set
+/// 10. `throttle`, which knows the principal authentication set when counting
+pub(super) fn controller_layers<'a>(
+    ctx: &Controller<'a>,
+    routes: &[Route],
+    errors: &mut Vec<syn::Error>,
+) -> ControllerLayers<'a> {
+    let controller_args = ctx.args;
+    let mut middleware_layers: Vec<_> = controller_args
+        .middleware
+        .iter()
+        .map(|m| {
+            let path = &m.path;
+            let layer = quote! {
+                router.layer(eywa_axum::axum::middleware::from_fn_with_state(state.clone(), #path))
+            };
+            match &m.condition {
+                Some(cond) => quote! {
+                    let router = if cfg!(#cond) { #layer } else { router };
+                },
+                None => quote! { let router = #layer; },
+            }
+        })
+        .collect();
+
+    // Inside the controller's middleware, so it sees a locale they set
+    let catalogs = controller_args
+        .error_messages
+        .as_ref()
+        .and_then(|messages| load_catalogs(messages).map_err(|e| errors.push(e)).ok())
+        .unwrap_or_default();
+    let error_codes = error_codes(&catalogs)
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+    if !catalogs.is_empty() {
+        let layer = generate_messages_layer(&catalogs);
+        middleware_layers.insert(0, quote! { let router = router #layer; });
+    }
+
+    // Outside the controller's middleware, so it can read the tenant
+    let tenant_strategy = match controller_args.tenant.as_ref().map(TenantArgs::strategy) {
+        Some(Ok(strategy)) => Some(strategy),
+        Some(Err(e)) => {
+            errors.push(e);
+            None
+        }
+        None => None,
+    };
+    if let Some(strategy) = &tenant_strategy {
+        let layer = generate_tenant_layer(strategy);
+        middleware_layers.push(quote! { let router = router #layer; });
+    }
+
+    // Also applied to responses short-circuited by the controller's middleware
+    let security_headers = controller_args
+        .security_headers
+        .as_ref()
+        .map(SecurityHeaders::headers);
+    if let Some(headers) = &security_headers {
+        // HeaderValue::from_static panics on anything but visible ASCII
+        if let Some((_, value)) = headers.iter().find(|(_, value)| {
+            value
+                .chars()
+                .any(|c| c != '\t' && !(' '..='~').contains(&c))
+        }) {
+            errors.push(syn::Error::new(
+                proc_macro2::Span::call_site(),
+                format!("`security_headers`: invalid header value `{}`", value),
+            ));
+        }
+        let layer = generate_security_headers_layer(headers);
+        middleware_layers.push(quote! { let router = router #layer; });
+    }
+
+    // Inside the controller's middleware, so the principal set by authentication is known
+    // when counting
+    if let Some(throttle) = &controller_args.throttle {
+        match (throttle.by_principal(), throttle.retry_after_secs()) {
+            (Ok(by_principal), Ok(retry_after)) => {
+                let layer = generate_throttle_layer(throttle.per_minute, by_principal, retry_after);
+                middleware_layers.insert(0, quote! { let router = router #layer; });
+            }
+            (Err(e), _) | (_, Err(e)) => errors.push(e),
+        }
+    }
+
+    if let Some(max) = controller_args.max_request_size {
+        middleware_layers.push(quote! {
+            let router = router.layer(eywa_axum::axum::extract::DefaultBodyLimit::max(#max));
+        });
+    }
+
+    // Covers panics in the controller's middleware as well as in handlers
+    if controller_args.catch_panic {
+        let layer = generate_catch_panic_layer();
+        middleware_layers.push(quote! { let router = router #layer; });
+    }
+
+    // Outside the other layers, so rejected and short-circuited requests are measured too
+    if controller_args.measure_bodies {
+        let layer = generate_body_size_layer();
+        middleware_layers.push(quote! { let router = router #layer; });
+    }
+
+    // Outside authentication and throttling, which preflights must not hit
+    let cors = controller_args.cors.as_ref().and_then(|cors| {
+        let max_age = cors
+            .validate()
+            .and_then(|()| cors.max_age_secs())
+            .map_err(|e| errors.push(e))
+            .ok()?;
+        let methods: Vec<String> = if cors.methods.is_empty() {
+            routes
+                .iter()
+                .map(|(_, route_info, _)| route_info.method)
+                .filter(|method| *method != HttpMethod::Any)
+                .fold(Vec::new(), |mut methods, method| {
+                    if !methods.iter().any(|m| m == method.as_str()) {
+                        methods.push(method.as_str().to_string());
+                    }
+                    methods
+                })
+        } else {
+            cors.methods
+                .iter()
+                .map(|m| m.value().to_uppercase())
+                .collect()
+        };
+        Some((cors, methods, max_age))
+    });
+    if let Some((cors, methods, max_age)) = &cors {
+        let layer = generate_cors_layer(
+            &cors.origins(),
+            &methods.join(", "),
+            &cors.headers().join(", "),
+            *max_age,
+        );
+        middleware_layers.push(quote! { let router = router #layer; });
+    }
+
+    // Outermost, so every response carries the id
+    if controller_args.request_id {
+        let layer = generate_request_id_layer();
+        middleware_layers.push(quote! { let router = router #layer; });
+    }
+
+    ControllerLayers {
+        statements: middleware_layers,
+        error_codes,
+        tenant_strategy,
+        security_headers,
+        cors,
+    }
+}
@@ -0,0 +1,381 @@
+//! Handler signature analysis.
+//!
+//! Classifies handler arguments by their extractor type so the macros can
+//! tell request bodies and parameters apart from request context, and report
+//! extractors they don't know about instead of guessing.
+
+use syn::{FnArg, GenericArgument, Ident, Path, PathArguments, Signature, Type};
+
+use crate::parse::HttpMethod;
+use crate::paths::path_params;
+
+/// Extractors that read the request body
+const BODY_EXTRACTORS: &[&str] = &[
+    "Json",
+    "Form",
+    "Bytes",
+    "String",
+    "Body",
+    "BodyStream",
+    "Multipart",
+    "RawForm",
+    "Request",
+];
+
+/// Extractors that are documented as OpenAPI parameters
+const PARAM_EXTRACTORS: &[&str] = &["Path", "Query"];
+
+/// Extractors that never contribute to the documented request
+const CONTEXT_EXTRACTORS: &[&str] = &[
+    "State",
+    "Extension",
+    "Extensions",
+    "HeaderMap",
+    "ConnectInfo",
+    "CookieJar",
+    "PrivateCookieJar",
+    "SignedCookieJar",
+    "MatchedPath",
+    "NestedPath",
+    "OriginalUri",
+    "Uri",
+    "Method",
+    "Version",
+    "TypedHeader",
+    "RawQuery",
+    "RawPathParams",
+    "Host",
+    "WebSocketUpgrade",
+    "Parts",
+];
+
+/// Wrappers whose first generic argument is the real extractor
+const TRANSPARENT_WRAPPERS: &[&str] = &["Option", "Result"];
+
+/// How a handler argument contributes to the request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExtractorKind {
+    Body,
+    Params,
+    Context,
+    /// Listed in `ignore_params(...)`
+    Ignored,
+}
+
+/// Classify a single argument type, or `None` if the extractor is unknown
+pub fn classify(ty: &Type, ignored: &[Path]) -> Option<ExtractorKind> {
+    let Type::Path(tp) = ty else {
+        return Some(ExtractorKind::Context);
+    };
+    let seg = tp.path.segments.last()?;
+    let name = seg.ident.to_string();
+
+    if ignored
+        .iter()
+        .any(|p| p.segments.last().is_some_and(|s| s.ident == seg.ident))
+    {
+        return Some(ExtractorKind::Ignored);
+    }
+    if TRANSPARENT_WRAPPERS.contains(&name.as_str())
+        && let PathArguments::AngleBracketed(args) = &seg.arguments
+        && let Some(GenericArgument::Type(inner)) = args.args.first()
+    {
+        return classify(inner, ignored);
+    }
+
+    if BODY_EXTRACTORS.contains(&name.as_str()) {
+        Some(ExtractorKind::Body)
+    } else if PARAM_EXTRACTORS.contains(&name.as_str()) {
+        Some(ExtractorKind::Params)
+    } else if CONTEXT_EXTRACTORS.contains(&name.as_str()) {
+        Some(ExtractorKind::Context)
+    } else {
+        None
+    }
+}
+
+/// Check every argument of a handler, returning a spanned error per unknown extractor
+pub fn check_signature(sig: &Signature, ignored: &[Path]) -> Vec<syn::Error> {
+    sig.inputs
+        .iter()
+        .filter_map(|arg| match arg {
+            FnArg::Typed(pat) => Some(&pat.ty),
+            FnArg::Receiver(_) => None,
+        })
+        .filter(|ty| classify(ty, ignored).is_none())
+        .map(|ty| {
+            let name = match &**ty {
+                Type::Path(tp) => tp
+                    .path
+                    .segments
+                    .last()
+                    .map(|s| s.ident.to_string())
+                    .unwrap_or_default(),
+                _ => String::new(),
+            };
+            syn::Error::new_spanned(
+                ty,
+                format!(
+                    "unsupported extractor `{name}` in `{}`: add `ignore_params({name})` to the route or controller if it does not read the request body or parameters",
+                    sig.ident
+                ),
+            )
+        })
+        .collect()
+}
+
+/// Reject a `Json`/`Form` body on GET and DELETE handlers, which many clients and
+/// proxies drop, unless the route sets `allow_get_body`
+pub fn check_method_body(sig: &Signature, method: HttpMethod) -> Vec<syn::Error> {
+    if !matches!(method, HttpMethod::Get | HttpMethod::Delete) {
+        return Vec::new();
+    }
+    sig.inputs
+        .iter()
+        .filter_map(|arg| match arg {
+            FnArg::Typed(pat) => Some(&pat.ty),
+            FnArg::Receiver(_) => None,
+        })
+        .filter_map(|ty| {
+            ["Json", "Form"]
+                .into_iter()
+                .find(|extractor| extractor_target(ty, extractor).is_some())
+                .map(|extractor| (ty, extractor))
+        })
+        .map(|(ty, extractor)| {
+            syn::Error::new_spanned(
+                ty,
+                format!(
+                    "`{}` reads a `{}` body on a {} route, which many clients and proxies drop; add `allow_get_body` to the route to keep it",
+                    sig.ident,
+                    extractor,
+                    method.as_str()
+                ),
+            )
+        })
+        .collect()
+}
+
+/// Require the `Body`/`BodyStream` argument an `upload(...)` route streams
+pub fn check_upload_body(sig: &Signature) -> Vec<syn::Error> {
+    if has_streaming_body(sig) {
+        return Vec::new();
+    }
+    vec![syn::Error::new_spanned(
+        &sig.ident,
+        format!(
+            "`upload` on `{}`, which has no `Body` or `BodyStream` argument to stream",
+            sig.ident
+        ),
+    )]
+}
+
+/// Types extracted from a single path segment
+const SCALAR_PARAMS: &[&str] = &[
+    "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64", "i128", "isize", "f32",
+    "f64", "bool", "char", "String", "Uuid", "Ulid",
+];
+
+/// Check that a handler extracts the parameters of its route path: a `Path<T>`
+/// argument whenever the path has `{param}` segments, none otherwise, and a
+/// tuple (or scalar) `T` matching the number of parameters.
+///
+/// Struct and map targets are not checked, their fields are matched by name.
+pub fn check_path_params(sig: &Signature, path: &str, ignored: &[Path]) -> Vec<syn::Error> {
+    let params = path_params(path);
+
+    let mut extractors = Vec::new();
+    let mut opaque = false;
+    for arg in &sig.inputs {
+        let FnArg::Typed(pat) = arg else { continue };
+        match extractor_target(&pat.ty, "Path") {
+            Some(target) => extractors.push((&pat.ty, target)),
+            None => {
+                // Custom extractors may read the path themselves
+                opaque |= matches!(
+                    classify(&pat.ty, ignored),
+                    Some(ExtractorKind::Ignored) | None
+                ) || last_ident(&pat.ty).is_some_and(|name| name == "RawPathParams");
+            }
+        }
+    }
+
+    match extractors.as_slice() {
+        [] if !params.is_empty() && !opaque => vec![syn::Error::new_spanned(
+            &sig.ident,
+            format!(
+                "`{}` has no `Path<...>` argument for the path parameter{} {} of `{}`",
+                sig.ident,
+                if params.len() == 1 { "" } else { "s" },
+                params
+                    .iter()
+                    .map(|name| format!("`{}`", name))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                path
+            ),
+        )],
+        [(ty, _)] if params.is_empty() => vec![syn::Error::new_spanned(
+            ty,
+            format!(
+                "`Path` extractor on `{}`, which has no path parameters",
+                path
+            ),
+        )],
+        [(ty, target)] => {
+            let arity = match target {
+                Type::Tuple(tuple) => Some(tuple.elems.len()),
+                ty => last_ident(ty)
+                    .filter(|name| SCALAR_PARAMS.contains(&name.as_str()))
+                    .map(|_| 1),
+            };
+            match arity {
+                Some(arity) if arity != params.len() => vec![syn::Error::new_spanned(
+                    ty,
+                    format!(
+                        "`{}` has {} path parameter{} but the `Path` extractor takes {}",
+                        path,
+                        params.len(),
+                        if params.len() == 1 { "" } else { "s" },
+                        arity
+                    ),
+                )],
+                _ => Vec::new(),
+            }
+        }
+        // Several `Path` extractors each see every parameter
+        _ => Vec::new(),
+    }
+}
+
+/// Check that every parameter named by `param_constraints` or `param_formats` is a
+/// parameter of the path or read from the query
+pub fn check_param_names<'a>(
+    sig: &Signature,
+    path: &str,
+    names: impl IntoIterator<Item = &'a Ident>,
+) -> Vec<syn::Error> {
+    let params = path_params(path);
+    let reads_query = sig.inputs.iter().any(|arg| match arg {
+        FnArg::Typed(pat) => extractor_target(&pat.ty, "Query").is_some(),
+        FnArg::Receiver(_) => false,
+    });
+    names
+        .into_iter()
+        .filter(|name| !reads_query && !params.contains(&name.to_string().as_str()))
+        .map(|name| {
+            syn::Error::new_spanned(
+                name,
+                format!(
+                    "`{}` is not a parameter of `{}` and `{}` reads no query",
+                    name, path, sig.ident
+                ),
+            )
+        })
+        .collect()
+}
+
+/// String formats of well-known parameter types
+const PARAM_FORMATS: &[(&str, &str)] = &[
+    ("Uuid", "uuid"),
+    ("DateTime", "date-time"),
+    ("NaiveDateTime", "date-time"),
+    ("OffsetDateTime", "date-time"),
+    ("PrimitiveDateTime", "date-time"),
+    ("NaiveDate", "date"),
+    ("Date", "date"),
+];
+
+/// Formats of the path parameters extracted as a well-known type (`Path<Uuid>`,
+/// `Path<(Uuid, NaiveDate)>`), by parameter name
+pub fn path_param_formats(sig: &Signature, path: &str) -> Vec<(String, &'static str)> {
+    let params = path_params(path);
+    let Some(target) = sig.inputs.iter().find_map(|arg| match arg {
+        FnArg::Typed(pat) => extractor_target(&pat.ty, "Path"),
+        FnArg::Receiver(_) => None,
+    }) else {
+        return Vec::new();
+    };
+    let types: Vec<&Type> = match target {
+        Type::Tuple(tuple) => tuple.elems.iter().collect(),
+        ty => vec![ty],
+    };
+    if types.len() != params.len() {
+        return Vec::new();
+    }
+    params
+        .into_iter()
+        .zip(types)
+        .filter_map(|(name, ty)| {
+            let ident = last_ident(ty)?;
+            let (_, format) = PARAM_FORMATS.iter().find(|(ty, _)| *ty == ident)?;
+            Some((name.to_string(), *format))
+        })
+        .collect()
+}
+
+/// Query targets that carry no field list for `IntoParams`
+const DYNAMIC_QUERIES: &[&str] = &["HashMap", "BTreeMap", "IndexMap", "Vec", "Value"];
+
+/// `T` of every `Query<T>` argument that can be documented through its
+/// `IntoParams` impl. Maps, tuples and queries listed in `ignore_params(...)`
+/// are left out.
+pub fn query_params_types<'a>(sig: &'a Signature, ignored: &[Path]) -> Vec<&'a Type> {
+    sig.inputs
+        .iter()
+        .filter_map(|arg| match arg {
+            FnArg::Typed(pat) => Some(&*pat.ty),
+            FnArg::Receiver(_) => None,
+        })
+        .filter(|ty| classify(ty, ignored) == Some(ExtractorKind::Params))
+        .filter_map(|ty| extractor_target(ty, "Query"))
+        .filter(|target| {
+            matches!(target, Type::Path(_))
+                && last_ident(target).is_some_and(|name| !DYNAMIC_QUERIES.contains(&name.as_str()))
+        })
+        .collect()
+}
+
+/// Whether the handler takes a `Path<T>` argument
+pub fn has_path_extractor(sig: &Signature) -> bool {
+    sig.inputs.iter().any(|arg| match arg {
+        FnArg::Typed(pat) => extractor_target(&pat.ty, "Path").is_some(),
+        FnArg::Receiver(_) => false,
+    })
+}
+
+/// Whether the handler streams its body through a `Body` or `BodyStream` argument
+pub fn has_streaming_body(sig: &Signature) -> bool {
+    sig.inputs.iter().any(|arg| match arg {
+        FnArg::Typed(pat) => {
+            last_ident(&pat.ty).is_some_and(|name| name == "Body" || name == "BodyStream")
+        }
+        FnArg::Receiver(_) => false,
+    })
+}
+
+/// `T` of a `Path<T>`/`Query<T>` argument, looking through `Option`/`Result`
+fn extractor_target<'a>(ty: &'a Type, extractor: &str) -> Option<&'a Type> {
+    let Type::Path(tp) = ty else { return None };
+    let seg = tp.path.segments.last()?;
+    let PathArguments::AngleBracketed(args) = &seg.arguments else {
+        return None;
+    };
+    let Some(GenericArgument::Type(inner)) = args.args.first() else {
+        return None;
+    };
+    if seg.ident == extractor {
+        Some(inner)
+    } else if TRANSPARENT_WRAPPERS.contains(&seg.ident.to_string().as_str()) {
+        extractor_target(inner, extractor)
+    } else {
+        None
+    }
+}
+
+fn last_ident(ty: &Type) -> Option<String> {
+    match ty {
+        Type::Path(tp) => tp.path.segments.last().map(|s| s.ident.to_string()),
+        _ => None,
+    }
+}
@@ -0,0 +1,77 @@
+//! GraphQL endpoint generation.
+//!
+//! `#[controller(graphql(schema = AppSchema))]` mounts `POST {prefix}/graphql`
+//! executing requests against the schema taken from state, and with
+//! `playground` a `GET` on the same path serving GraphQL Playground.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// Generates the GraphQL handlers for the controller's inherent impl
+pub fn generate_graphql_handlers(schema: &syn::Path, path: &str, playground: bool) -> TokenStream {
+    let playground_handler = playground.then(|| {
+        quote! {
+            /// Serves GraphQL Playground pointed at this endpoint.
+            #[doc(hidden)]
+            async fn __eywa_graphql_playground() -> eywa_axum::axum::response::Html<String> {
+                eywa_axum::axum::response::Html(eywa_axum::async_graphql::http::playground_source(
+                    eywa_axum::async_graphql::http::GraphQLPlaygroundConfig::new(#path),
+                ))
+            }
+        }
+    });
+
+    quote! {
+        /// Executes a GraphQL request against the schema from state.
+        #[doc(hidden)]
+        async fn __eywa_graphql(
+            eywa_axum::axum::extract::State(schema): eywa_axum::axum::extract::State<#schema>,
+            request: eywa_axum::async_graphql_axum::GraphQLRequest,
+        ) -> eywa_axum::async_graphql_axum::GraphQLResponse {
+            schema.execute(request.into_inner()).await.into()
+        }
+
+        #playground_handler
+    }
+}
+
+/// Generates the route registration for the GraphQL endpoint
+pub fn generate_graphql_registration(path: &str, playground: bool) -> TokenStream {
+    let playground_route = playground.then(|| quote! { .get(Self::__eywa_graphql_playground) });
+    quote! {
+        .route(#path, eywa_axum::axum::routing::post(Self::__eywa_graphql) #playground_route)
+    }
+}
+
+/// Generates the documentation stub and schema structs for `__UTOIPA_PATHS__`
+pub fn generate_graphql_docs(path: &str, tag: &str) -> TokenStream {
+    quote! {
+        /// A GraphQL request
+        #[derive(eywa_axum::Serialize, eywa_axum::Deserialize, eywa_axum::utoipa::ToSchema)]
+        #[serde(rename_all = "camelCase")]
+        pub struct __GraphqlRequest {
+            /// Query document
+            pub query: String,
+            /// Operation to run when the document holds several
+            #[serde(default)]
+            pub operation_name: Option<String>,
+            /// Variables by name
+            #[serde(default)]
+            #[schema(value_type = Option<Object>)]
+            pub variables: Option<eywa_axum::serde_json::Value>,
+        }
+
+        #[utoipa::path(
+            post,
+            path = #path,
+            tag = #tag,
+            summary = "Execute a GraphQL query",
+            request_body = __GraphqlRequest,
+            responses(
+                (status = 200, description = "GraphQL response (`data` and `errors`)", body = Object),
+            ),
+        )]
+        #[allow(dead_code)]
+        pub async fn __eywa_graphql() {}
+    }
+}
@@ -6,6 +6,7 @@
 //! - `#[controller]` - Define a controller with routes, middleware, and OpenAPI metadata
 //! - `#[route]` - Define individual routes with HTTP method, path, and documentation
 //! - `openapi_for!` - Generate OpenAPI documentation struct (experimental)
+//! - `#[derive(ApiError)]` - Status mapping, problem responses and docs for error enums
 //!
 //! ## Features
 //!
@@ -15,13 +16,23 @@
 //! - `summary = "..."` - Route summary
 //! - `description = "..."` - Route description
 //! - `deprecated` - Mark as deprecated
-//! - `security` - Require bearer authentication
+//! - `security` - Require bearer authentication, `security = ["scope", ...]` with scopes
 
+mod api_error;
+mod asyncapi;
+mod batch;
 mod codegen;
+mod config;
 mod controller;
+mod extractors;
+mod graphql;
+mod manifest;
+mod messages;
 mod openapi;
 mod parse;
+mod paths;
 mod route;
+mod wrappers;
 
 use proc_macro::TokenStream;
 
@@ -29,10 +40,107 @@ use proc_macro::TokenStream;
 ///
 /// # Attributes
 /// - `path` - URL prefix for all routes (preferred)
-/// - `prefix` - URL prefix for all routes (legacy, use `path` instead)
+/// - `prefix` - URL prefix for all routes (deprecated, use `path` instead; the two
+///   are mutually exclusive)
 /// - `state` - The application state type (required)
 /// - `tag` - OpenAPI tag for grouping (default: controller name)
-/// - `middleware` - Middleware function to apply
+/// - `description` - Prose for the tag object, shown per resource group in the docs sidebar
+/// - `display_name` - Human-readable tag name for the docs sidebar (`x-displayName`)
+/// - `path_case` - Casing of `path = auto` route paths: `"kebab"` (default), `"snake"` or
+///   `"camel"`
+/// - `summary_template` - Summary of routes without their own `summary` or doc comment:
+///   `summary_template = "{method} {resource}"`; placeholders are `{method}`, `{path}`,
+///   `{fn}` (handler name) and `{resource}` (controller name without `Controller`)
+/// - `middleware` - Middleware function(s) to apply: `middleware = auth` or
+///   `middleware = [auth if not(test), request_logger if debug]`. Conditions are
+///   `cfg` predicates, with `debug`/`release` as shorthands for `debug_assertions`
+/// - `security` - Every route requires bearer authentication; `security = ["projects:read"]`
+///   lists the scopes of the requirement, unless a route names its own.
+///   `security = cookie("session_id")` requires a session cookie instead, registered as the
+///   `cookie` security scheme (`apiKey` in `cookie`)
+/// - `security_scheme` - Scheme of the requirement on every route: `"bearer"` (default),
+///   `"api_key"` (an `X-API-Key` header registered as the `api_key` security scheme) or `"oauth2"`
+/// - `oauth2` - Register an `oauth2` security scheme routes require with `security_scheme =
+///   "oauth2"`: `oauth2(flow = "authorization_code", auth_url = "...", token_url = "...",
+///   scopes(read = "Read access", "projects:write"))`. Flows are `authorization_code`, `implicit`
+///   (`auth_url` only), `password` and `client_credentials` (`token_url` only); `refresh_url` is
+///   optional
+/// - `ignore_params` - Custom extractor types to skip in signature analysis:
+///   `ignore_params(MyExtractor)`. Unknown extractors are a compile error
+/// - `param_from` - Document custom extractors as parameters:
+///   `param_from(TypedHeader<Authorization<Bearer>> => header "Authorization")`
+/// - `envelope` - Wrap every `Json<T>` response in an envelope type, e.g.
+///   `envelope = ApiResponse` (constructed with `ApiResponse::new(data)`)
+/// - `batch` - Generate `POST {path}/_batch` running an array of sub-requests
+///   (`operation_id`, `params`, `query`, `body`) through this controller; response bodies over
+///   `max_request_size` (2 MiB by default) come back as a 500 with a `null` body
+/// - `request_id` - Take the request's `X-Request-Id` (generating one when absent), store it as an
+///   `eywa_axum::RequestId` extension and echo it on the response; documented on every operation
+/// - `log` - Structured request logging for every route without its own `log(...)`
+///   (see the route attribute)
+/// - `audit` - Audit record of every request (see the route attribute)
+/// - `log_fields` - Request extensions added (with `Debug`) to every log line of the controller,
+///   `log_fields(tenant = "extensions.TenantId", user = "extensions.UserId")`; `-` when missing
+/// - `graphql` - Mount a GraphQL endpoint, `graphql(schema = AppSchema, path = "/graphql", playground)`:
+///   `POST` executes against the schema taken from state (`AppSchema: FromRef<State>`), `playground`
+///   adds GraphQL Playground on `GET`. Documented as a `POST` operation with `x-graphql`
+/// - `auto_options` - Generate an OPTIONS handler per path returning the allowed
+///   methods (and HATEOAS link relations, if any) as JSON
+/// - `max_request_size` - Request body limit in bytes, documented as `x-max-request-size`
+/// - `measure_bodies` - Record request/response body sizes per route via `tracing`
+/// - `tenant` - Resolve the tenant of every request into an `eywa_axum::Tenant` extension
+///   (400 when absent): `tenant(strategy = "header", header = "X-Tenant")` (documented as a
+///   header parameter) or `tenant(strategy = "subdomain")` (first label of a 3+ label host)
+/// - `throttle` - Per-client limit, `throttle(per_minute = 600, key = "user")`, keyed by the
+///   `eywa_axum::Principal` extension (falling back to the peer IP) or `key = "ip"`. Runs inside
+///   the controller's `middleware`, so authentication there sets the principal first. Answers
+///   429 with `Retry-After` (time left in the window, or fixed with `retry_after = "30s"`),
+///   documented on every route
+/// - `security_headers` - Set HSTS, `X-Content-Type-Options`, `X-Frame-Options` and a CSP on
+///   responses (unless already set), documented as `x-security-headers`;
+///   `security_headers(csp = "...")` replaces the default `default-src 'self'` policy
+/// - `cors` - Answer CORS preflights ahead of the controller's middleware and allow cross-origin
+///   requests, `cors(origins = ["https://app.example.com"], headers = ["authorization"],
+///   max_age = "10m")`; any origin when `origins` is omitted, the routes' methods unless `methods`
+///   is given. Add `document` to describe the policy as an `x-cors` extension on every operation
+/// - `catch_panic` - Answer handler panics with a 500 `application/problem+json` body
+///   (via `CatchPanicLayer`) instead of dropping the connection
+/// - `error_map` - Status per error variant, `error_map((NotFound => 404), (Conflict => 409))`
+///   (or `error_map(DomainError, ...)` when routes use several error types); generates
+///   `From<DomainError> for Response` and documents the statuses on routes returning it
+/// - `error_messages` - Localized error messages from flat `NotFound = "..."` catalogs (relative
+///   to the crate root): `error_messages = "errors/en.toml"` for one locale, named after the
+///   file, or `error_messages(path = "errors/{locale}.toml", locales = ["en", "de"])`. JSON
+///   error bodies with an `error` code get the `message` of the request's locale (the
+///   `eywa_axum::Locale` extension, else `Accept-Language`; the first locale is the fallback).
+///   The codes are documented as `x-error-codes`
+/// - `auto_responses` - `"all"` (default) documents a 401 and 500 on every route and a 404 on
+///   routes with a `Path` returning `Result`; `"minimal"` only documents declared responses and
+///   those generated layers answer
+/// - `schema_naming` - Names of the generated wrapper schemas in the spec: `"prefixed"`
+///   (default, `__HateoasSchema_get`, `__EnvelopeSchema_get`) or `"short"`, named after the
+///   wrapped data type (`ProjectHateoas`, `ProjectListEnvelope`, `BulkItemResult`)
+/// - `rest_conventions` - POST routes document and answer 201, DELETE routes 204 without a
+///   body, instead of 200; routes with `status`, a 2xx shorthand response or `async_job` keep
+///   their own. `rest_conventions = false` turns off a default from `eywa-controllers.toml`
+/// - `transactional` - Run every route in a transaction, see the route attribute
+/// - `compact_codegen` - For large controllers: schema and path registration, description-only
+///   auto responses and error-type response merging go through generic helpers in
+///   `eywa_axum::__private` instead of per-route token blocks. The generated spec is unchanged
+///
+/// Every controller also gets a `GATEWAY_MANIFEST: &str` constant, a JSON description of its
+/// routes (method, path, operation id, auth, permissions, upstream, latency budget, circuit
+/// breaker), rate limit and body limit. A small binary printing it is enough to derive
+/// Kong/Envoy routes. `IntoRouter::openapi_routes()` returns every route as a static
+/// `eywa_axum::OpenApiPath` table (`&'static str` fields), built at compile time.
+///
+/// Project-wide defaults live in an optional `eywa-controllers.toml` next to `Cargo.toml`,
+/// flat `key = value` lines read at expansion time: `security_scheme` (the scheme of
+/// requirements that don't name one, without requiring authentication by itself),
+/// `auto_responses`, `path_case`, `schema_naming` and `rest_conventions = true`. A controller's
+/// own arguments win, `rest_conventions = false` included. `crate = "acme_web::eywa_axum"`
+/// makes the code generated by every macro of this crate (and `eywa_axum::...` paths in
+/// controllers) use that path for the runtime crate.
 ///
 /// # Example
 /// ```ignore
@@ -56,17 +164,181 @@ pub fn controller(args: TokenStream, input: TokenStream) -> TokenStream {
 /// Marks a function as a route handler.
 ///
 /// # Syntax
-/// `#[route(METHOD "/path")]`
+/// `#[route(METHOD "/path")]`, or `#[route(METHOD, path = auto)]` to derive the path from the
+/// handler name (`list_archived` is served at `/list-archived`; see the controller's `path_case`)
+///
+/// Path parameters may be written `/{id}` (axum 0.8) or `/:id` (axum 0.7); catch-alls
+/// `/{*rest}` or `/*rest`. OpenAPI paths always use the `{id}` form and routes are registered
+/// in axum 0.8 syntax, or in axum 0.7 syntax with the `axum-07` feature.
+///
+/// `Query<T>` arguments are documented as query parameters through `T`'s `IntoParams` impl;
+/// map targets such as `Query<HashMap<String, String>>` are skipped, and `ignore_params(Query)`
+/// opts a route out. Handlers taking a `Path<T>` and returning a `Result` also document a 404,
+/// unless the route or the controller's `error_map` declares one.
 ///
 /// # Attributes
-/// - `summary` - OpenAPI summary
-/// - `description` - OpenAPI description
+/// - `operation_id` - OpenAPI operationId, `operation_id = "listProjects"` (default: the
+///   handler name); also used by the batch endpoint and the gateway manifest
+/// - `summary` - OpenAPI summary (default: first line of the handler's `///` comment)
+/// - `description` - OpenAPI description (default: the rest of the `///` comment)
 /// - `tag` - Single OpenAPI tag (legacy)
 /// - `tags` - Multiple OpenAPI tags: `tags = ["Tag1", "Tag2"]`
-/// - `security` - Require bearer authentication
-/// - `deprecated` - Mark as deprecated
-/// - `collection` - Wrap response in CollectionResponse (future)
+/// - `inherit_tag = false` - Don't add the controller tag to a route with its own `tag`/`tags`
+/// - `security` - Require bearer authentication; `security = ["projects:read", "projects:write"]`
+///   lists the scopes of the requirement; `security = cookie("session_id")` requires a session
+///   cookie, which has to match the other cookies of the controller
+/// - `security_scheme` - `security_scheme = "api_key"` requires an `X-API-Key` header instead of
+///   a bearer token (implies `security`); the scheme is registered with the controller's schemas.
+///   `"oauth2"` refers to the controller's `oauth2(...)` scheme
+/// - `security_optional` - Authentication is optional: the operation lists an empty security
+///   requirement next to the route's scheme and gets no automatic 401. Marked `auth_optional`
+///   in the gateway manifest; can't be combined with `require`
+/// - `require` - Permissions the caller must hold, `require = ["admin", "billing:read"]`: a route
+///   layer answers 401 without an `eywa_axum::Permissions` request extension and 403 when one is
+///   missing. Implies `security`, with the permissions as the requirement's scopes
+/// - `deprecated` - Mark as deprecated; in a controller every call is also logged (target
+///   `eywa_axum::deprecated`) with the operation id and a running hit count
+/// - `ignore_params` - Custom extractor types to skip in signature analysis
+/// - `ok`, `created`, `accepted`, `no_content`, `bad_request`, `forbidden`, `not_found`,
+///   `conflict`, `unprocessable` - Shorthand responses merged into `responses(...)` and the
+///   automatic 401/500: `created = Project` (body), `conflict = "Duplicate name"` (description)
+///   or bare `not_found`. A shorthand 2xx replaces the success response inferred from the handler
+/// - `csv` - Serve a `Json<Vec<T>>` response as a CSV download: `csv` or
+///   `csv = "projects.csv"` (ASCII, without quotes, backslashes or control characters)
+/// - `async_job` - Respond `202 Accepted` with a job status body (`async_job` uses
+///   `eywa_axum::JobStatus`, or `async_job = MyJobStatus`); the job needs an `id` field
+/// - `status_route` - Handler serving the job status (`status_route = get_job`), used
+///   for the `Location` header and the `status` link
+/// - `filterable` - Parse the query string into a filter type (`filterable(ProjectFilter)`,
+///   which derives `Deserialize` and `IntoParams`) handed to the handler as
+///   `Extension<ProjectFilter>`
+/// - `sortable` - Allowed values of the `sort` query parameter, documented as an enum and
+///   validated with 400 for unknown fields: `sortable = ["created_at", "name"]`
+/// - `paginated = "cursor"` - Cursor pagination: documents `cursor`/`limit` query params; the
+///   handler returns `Json<CursorPage<T>>` and the response gets a `next` link from `next_cursor`
+/// - `emits` - Outbound event triggered by the route (repeatable):
+///   `emits(event = "project.created", payload = ProjectCreated)`; registers the payload schema,
+///   adds an `x-emits` extension and a `webhooks` entry
+/// - `publish` - On POST/PUT/PATCH/DELETE, push an event to the `eywa_axum::EventPublisher` in
+///   state once the handler succeeded, `publish(event = ProjectCreated, from = "response")`. The
+///   event is built with `From<&T>` of the response data (`from = "request"`: of the `Json<T>`
+///   body); `name` defaults to the type name. Documented like `emits`; failures are logged
+/// - `guard` - Run `guard = auth::owns_project` before the handler's extractors: an async fn taking
+///   `&mut Parts` and `&State` and returning `Result<(), Response>`; an `Err` is answered as is.
+///   The rejection is documented as 403, or as `guard_status = 404`
+/// - `transactional` - Begin a transaction from the `eywa_axum::TransactionFactory` in state before
+///   the handler, which reads it as `Extension<eywa_axum::Transaction>`; it is committed on a 2xx
+///   response (a failed commit answers 500) and rolled back otherwise
+/// - `read_only` - On GET routes, insert an `eywa_axum::DbRouting::Replica` request extension so
+///   the data layer reads from a replica; documented as `x-read-only`
+/// - `policy` - Authorization policy, 403 when denied: `policy = "project:read"` is checked by
+///   `eywa_axum::Authorizer::authorize(name, params, extensions)` (state via `FromRef`) and
+///   documented as `x-policy`; `policy = policies::owns` calls `owns(&state, params, extensions)`.
+///   `params` are the raw path parameters, `extensions` those left by authentication
+/// - `signed` - Only serve expiring signed links, `signed(query = "sig", secret = AppState::url_secret)`;
+///   `sig` is the hex HMAC-SHA256 (keyed by `secret(&state)`) of the path and remaining query,
+///   which must include an `expires` Unix timestamp; anything else gets 403
+/// - `verify_signature` - Check an inbound webhook's body against an HMAC-SHA256 header before the
+///   handler runs (401 otherwise): `verify_signature(header = "X-Hub-Signature-256",
+///   algo = "hmac-sha256", secret = AppState::webhook_secret)`
+/// - `mirror_to` - Copy requests, fire-and-forget, to another handler (`mirror_to = list_v2`)
+///   or upstream (`mirror_to = "http://shadow:8080"`, via `eywa_axum::mirror::forward`);
+///   `mirror_percent = 5` samples a share of them. Documented as `x-mirror`
+/// - `canary` - Serve the route from two methods of the controller, split per value of a key
+///   header: `canary(stable = list_v1, canary = list_v2, percent = 5, key = "X-User-Id")`
+///   (`key` defaults to `X-User-Id`; requests without it get `stable`). The annotated
+///   handler's body is unused; the route is documented with `stable`'s signature and `x-canary`
+/// - `proxy` - Forward the request, streaming, to an upstream using the `eywa_axum::ProxyClient`
+///   from state: `#[route(ANY "/legacy/{*rest}", proxy = "http://legacy:8080")]`. The path before
+///   the first parameter is stripped and hop-by-hop headers are dropped; client errors give 502.
+///   `ANY` routes (controllers only) are registered with `axum::routing::any` but not documented
+/// - `compress` - Compress this route's responses with its own `CompressionLayer` limited to the
+///   listed encodings: `compress = "gzip,br"` (`gzip`, `br`, `deflate`, `zstd`)
+/// - `no_compress` - Keep responses (downloads, SSE) out of compression by outer layers, by marking
+///   them `Content-Encoding: identity` unless already encoded
+/// - `ranges` - Answer single `Range: bytes=...` GET requests with 206 (416 when unsatisfiable)
+///   and advertise `Accept-Ranges`. Bodies of a known size up to `ranges(max = "64MB")` (8MB by
+///   default) are buffered to slice them; streams and larger bodies are served whole with 200, as
+///   are requests whose `If-Range` is a weak or stale entity tag or a date other than `Last-Modified`
+/// - `log` - Log one line per request (target `eywa_axum::request`) with the route template, method,
+///   status and latency: `log(level = "info", body = false, headers = ["x-request-id"])`; `body`
+///   buffers the request body into the line, so keep it off for sensitive endpoints
+/// - `audit` - Record every request (target `eywa_axum::audit`) with the route template, method,
+///   status, path parameters and body, once the handler answered; the body is read within the
+///   body limit
+/// - `sensitive_params` - Fields to keep out of logs, `sensitive_params = ["password", "ssn"]`: `log`
+///   and `audit` redact them in JSON/form bodies (form keys are percent-decoded first), logged
+///   headers and audited path parameters; documented as `x-sensitive` on the operation and on
+///   matching parameters
+/// - `slow_warn` - Log a warning (target `eywa_axum::slow_request`) for requests slower than the
+///   budget, `slow_warn = "500ms"` (`ms`, `s` or `m`); documented as `x-latency-budget-ms`
+/// - `trace_sample` - Run a fraction of the route's requests inside a `request` span (target
+///   `eywa_axum::trace`, with the route, method and status), `trace_sample = 0.1`
+/// - `sdk_cache_ttl` - How long generated clients may cache a GET response,
+///   `sdk_cache_ttl = "60s"`; documented as `x-cache-ttl` in seconds
+/// - `circuit_breaker` - Answer 503 (with `Retry-After`) for a `window` once `failure_ratio` of the
+///   route's responses in a window were 5xx: `circuit_breaker(failure_ratio = 0.5, window = "30s")`
+///   (`min_requests = 10` by default; `retry_after = "10s"` fixes the `Retry-After` otherwise set to
+///   the time until the circuit closes); documented as `x-circuit-breaker`
+/// - `bulk` - Document a bulk endpoint, `bulk(body = Vec<CreateProject>, partial_status = 207)`: the
+///   item array as request body and a Multi-Status response listing a generated per-item result
+///   (`index`, `status`, `data`, `error`)
+/// - `patch_format` - Document a PATCH body as `application/merge-patch+json` (`"merge"`, the
+///   `Json<T>` argument's schema) or `application/json-patch+json` (`"json-patch"`, an array of
+///   the generated `JsonPatchOperation` schema); not combinable with `request_body`
+/// - `allow_get_body` - Accept a `Json`/`Form` body on a GET or DELETE handler, otherwise a
+///   compile error as many clients and proxies drop such bodies
+/// - `multipart` - Document a `Multipart` upload as `multipart/form-data` with the fields of a
+///   `ToSchema` type, `multipart(schema = UploadForm)`; file fields use
+///   `#[schema(value_type = String, format = Binary)]`
+/// - `status` - Success status replacing the inferred 200, `status = 201`; 200 answers of the
+///   handler are rewritten to it (204 also drops the body)
+/// - `param_constraints` - Numeric bounds of path and query parameters, `param_constraints((id,
+///   minimum = 1), (page, maximum = 1000))`, documented on the parameters; with `enforce` in the
+///   list, out-of-bounds values are answered with 400 before the handler runs
+/// - `param_formats` - String formats of path and query parameters, `param_formats(id = "uuid",
+///   since = "date-time")`. Path parameters extracted as `Uuid`, `DateTime`, `OffsetDateTime`,
+///   `NaiveDate`, ... get theirs without it; `Query` struct fields are documented by their
+///   `IntoParams` impl
+/// - `upload` - Document a streamed `Body`/`BodyStream` upload, `upload(content_type =
+///   "application/zip", max = "1GB")`: a binary body of `content_type` (`application/octet-stream`
+///   by default, also used for streaming handlers without `upload`) and, with `max`, a 413 response
+///   and a layer cutting off larger bodies
+/// - `headers` - Headers of the success response, `headers((name = "X-Request-Id", description =
+///   "Id of the request", schema = String))`; `schema` defaults to `String`
+/// - `example_request` / `example_response` - Example of the request body and of the success
+///   response body: a JSON string literal (validated at compile time) or a path to a `Serialize`
+///   const, `example_response = SAMPLE_PROJECT`; controller routes only
+/// - `requires_if_match` - On PUT/PATCH, answer 428 to requests without `If-Match`; documents the
+///   header plus 412/428 (comparing the validator and answering 412 is up to the handler)
+/// - `negotiate` - Serve a `Json<T>` route as `negotiate(json, csv, xml)` by `Accept` (first listed
+///   format for `*/*` or no header, 406 when nothing matches; `Vec<T>` becomes one CSV record per
+///   item). Every format is documented under the 200 response
+/// - `media_versions` - Serve versioned representations of a `Json<T>` route by vendor media
+///   type, `media_versions((v1 = ProjectV1, v2 = ProjectV2))`: `Accept:
+///   application/vnd.app.v2+json` gets `ProjectV2::from(data)`, plain JSON requests the first
+///   version, unknown versions 406. `vendor = "acme"` replaces `app`; every version is documented
+///   under the success response
+/// - `messages` - Payloads of a WebSocket (`WebSocketUpgrade` argument) or SSE (`Sse<_>` return)
+///   route, `messages(send = Event, receive = Command)`. Such routes become channels of the
+///   controller's generated `asyncapi_json()` AsyncAPI 2.6 document
+/// - `last_modified` - Set `Last-Modified` from a response field (`last_modified = updated_at`)
+///   or function (`last_modified = my::modified_at`) and answer `If-Modified-Since` with 304
+/// - `location` - On POST, answer 201 with the `Location` of the created resource,
+///   `location = "/projects/{id}"`; each `{field}` is read from the returned data and
+///   percent-encoded
+/// - `links` - HATEOAS links, `links((rel = "self", href = "/projects/1"), (rel = "delete",
+///   href = "/projects/1", method = "DELETE"))`; the `Json<T>` response becomes
+///   `HateoasResponse<T>` (`{ data, links }`), list endpoints returning `Json<Vec<T>>` get
+///   `HateoasResponse<Vec<T>>` with the links at the top level
+/// - `collection` - Give a `Json<Vec<T>>` list endpoint the `HateoasResponse<Vec<T>>` shape
+///   even without `links`
 /// - `hateoas` - Wrap response in HateoasResponse (future)
+/// - `openapi` - Raw `utoipa::path` content appended after the generated attributes,
+///   `openapi(append = { extensions(("x-team" = json!("billing"))), operation_id = "..." })`.
+///   The block must parse as attribute items and may only set `operation_id`, `summary`,
+///   `description`, `servers` and, on routes without generated `x-` extensions, `extensions`;
+///   other keys would replace generated output
 ///
 /// # Example
 /// ```ignore
@@ -80,7 +352,7 @@ pub fn controller(args: TokenStream, input: TokenStream) -> TokenStream {
 /// ```
 #[proc_macro_attribute]
 pub fn route(args: TokenStream, input: TokenStream) -> TokenStream {
-    route::route_impl(args.into(), input.into()).into()
+    config::with_crate_path(|| route::route_impl(args.into(), input.into())).into()
 }
 
 /// Generate OpenAPI documentation struct (experimental).
@@ -99,15 +371,67 @@ pub fn route(args: TokenStream, input: TokenStream) -> TokenStream {
 ///     info = (
 ///         title = "My API",
 ///         version = "1.0.0",
-///     )
+///     ),
+///     schema_examples(ToggleTimerRequest = toggle_fixture()),
+///     apply(middleware = admin_auth, to_tags = ["Admin"]),
 /// }
 /// ```
 ///
+/// `schema_examples(Type = expr, ...)` serializes each value when
+/// `ApiDoc::build_openapi()` runs and attaches it to the schema's `examples`
+/// and, as an Example Object named after the schema, to the components'
+/// `x-examples` (utoipa's `Components` has no `examples` map, and drops other
+/// keys when a document is read back).
+///
+/// `ApiDoc::route_conflicts()` lists routes of different controllers that
+/// register the same method and path, comparing parameters regardless of
+/// their names, and catch-all routes (`/files/{*rest}`) shadowing another
+/// controller's more specific routes below their prefix (`/files/uploads/{id}`).
+/// `ApiDoc::assert_no_route_conflicts()` panics on any of them;
+/// `route_conflict_test` also generates a unit test calling it.
+///
+/// `apply(middleware = path, to_tags = ["Tag", ...])` attaches a
+/// `from_fn` middleware to every controller whose tag is listed, instead of
+/// repeating `middleware = ...` on each of them. Build the routers with
+/// `ApiDoc::controller_router::<Controller, State>(state)`; the layers wrap
+/// the controller's own middleware and run in the order they are listed.
+///
+/// With the `export-ingress` feature, `ApiDoc::ingress_rules()` returns an
+/// `IngressRule { prefix, methods }` per static path prefix served by the
+/// listed controllers (the modules containing them), e.g. for writing
+/// ingress annotations in CI.
+///
 /// # Note
 /// Due to proc macro limitations, individual paths still need to be listed
 /// manually in the `#[openapi(paths(...))]` attribute. This macro primarily
 /// helps with organization and provides a consistent pattern.
 #[proc_macro]
 pub fn openapi_for(input: TokenStream) -> TokenStream {
-    openapi::openapi_for_impl(input.into()).into()
+    config::with_crate_path(|| openapi::openapi_for_impl(input.into())).into()
+}
+
+/// Derives HTTP handling for an error enum.
+///
+/// Each variant maps to a status with `#[api_error(status = 404)]` (default
+/// 500; `title = "..."` overrides the problem title). Generates
+/// `status_code()`, an `IntoResponse` impl answering `application/problem+json`
+/// and a `utoipa::IntoResponses` impl documenting every status with the
+/// variants' doc comments. Controllers add these responses to routes
+/// returning `Result<_, ThisError>`.
+///
+/// # Example
+/// ```ignore
+/// #[derive(ApiError)]
+/// enum ProjectError {
+///     /// Project not found
+///     #[api_error(status = 404)]
+///     NotFound,
+///     #[api_error(status = 409, title = "Name already taken")]
+///     Conflict { name: String },
+///     Internal(anyhow::Error),
+/// }
+/// ```
+#[proc_macro_derive(ApiError, attributes(api_error))]
+pub fn derive_api_error(input: TokenStream) -> TokenStream {
+    config::with_crate_path(|| api_error::derive_api_error_impl(input.into())).into()
 }
@@ -0,0 +1,93 @@
+//! Gateway manifest generation.
+//!
+//! Every controller gets a `GATEWAY_MANIFEST` constant: a JSON description of
+//! its routes, authentication, rate limits and latency settings that
+//! deployment tooling turns into API gateway (Kong, Envoy, ...) configuration.
+
+use serde_json::{Map, Value, json};
+
+use crate::parse::{CircuitBreakerInfo, ControllerArgs, RouteInfo, parse_duration_ms};
+use crate::paths::join_paths;
+
+/// Builds the manifest JSON at expansion time.
+///
+/// Invalid settings are skipped here; they are reported where the
+/// corresponding layer is generated.
+pub fn gateway_manifest(
+    controller: &str,
+    prefix: &str,
+    tag: &str,
+    controller_args: &ControllerArgs,
+    routes: &[(&syn::Ident, &RouteInfo)],
+) -> String {
+    let rate_limit = controller_args.throttle.as_ref().map(|throttle| {
+        let key = if throttle.by_principal().unwrap_or(true) {
+            "user"
+        } else {
+            "ip"
+        };
+        json!({
+            "per_minute": throttle.per_minute,
+            "key": key,
+            "retry_after_secs": throttle.retry_after_secs().ok().flatten(),
+        })
+    });
+
+    let routes: Vec<Value> = routes
+        .iter()
+        .map(|(fn_name, route_info)| {
+            let mut route = Map::new();
+            route.insert(
+                "operation_id".into(),
+                route_info.operation_id(fn_name).into(),
+            );
+            route.insert("method".into(), route_info.method.as_str().into());
+            route.insert("path".into(), join_paths(prefix, &route_info.path).into());
+            let auth: Vec<String> = route_info
+                .security_requirement(controller_args)
+                .into_iter()
+                .collect();
+            route.insert("auth".into(), auth.into());
+            if route_info.security_optional {
+                route.insert("auth_optional".into(), true.into());
+            }
+            if !route_info.require.is_empty() {
+                route.insert("permissions".into(), route_info.require.clone().into());
+            }
+            if route_info.deprecated {
+                route.insert("deprecated".into(), true.into());
+            }
+            if let Some(upstream) = &route_info.proxy {
+                route.insert("upstream".into(), upstream.value().into());
+            }
+            if let Some(Ok(ms)) = route_info.slow_warn.as_ref().map(parse_duration_ms) {
+                route.insert("latency_budget_ms".into(), ms.into());
+            }
+            if let Some(Ok(settings)) = route_info
+                .circuit_breaker
+                .as_ref()
+                .map(CircuitBreakerInfo::settings)
+            {
+                route.insert(
+                    "circuit_breaker".into(),
+                    json!({
+                        "failure_ratio": settings.failure_ratio,
+                        "window_ms": settings.window_ms,
+                        "min_requests": settings.min_requests,
+                    }),
+                );
+            }
+            Value::Object(route)
+        })
+        .collect();
+
+    json!({
+        "controller": controller,
+        "prefix": prefix,
+        "tag": tag,
+        "rate_limit": rate_limit,
+        "max_body_bytes": controller_args.max_request_size,
+        "routes": routes,
+    })
+    .to_string()
+}
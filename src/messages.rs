@@ -0,0 +1,270 @@
+//! Localized error message catalogs.
+//!
+//! `error_messages = "errors/en.toml"` names a single catalog, for the locale in its file name;
+//! `error_messages(path = "errors/{locale}.toml", locales = ["en", "de"])` names one per locale,
+//! the first being the fallback. Catalogs are flat `Code = "Message"` tables, read at expansion
+//! time and embedded into a layer that adds a `message` to JSON error bodies carrying an `error`
+//! code.
+
+use std::path::{Path, PathBuf};
+
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use crate::parse::ErrorMessages;
+
+/// Messages of one locale, in file order
+pub struct Catalog {
+    pub locale: String,
+    pub file: PathBuf,
+    pub messages: Vec<(String, String)>,
+}
+
+/// Loads the catalogs named by `error_messages`; the fallback locale comes first
+pub fn load_catalogs(error_messages: &ErrorMessages) -> syn::Result<Vec<Catalog>> {
+    let path = &error_messages.path;
+    let error = |message: String| syn::Error::new_spanned(path, message);
+
+    let root = std::env::var("CARGO_MANIFEST_DIR").unwrap_or_default();
+    let files: Vec<(String, PathBuf)> = if error_messages.locales.is_empty() {
+        let file = Path::new(&root).join(path.value());
+        let locale = file
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        vec![(locale, file)]
+    } else {
+        error_messages
+            .locales
+            .iter()
+            .map(|locale| {
+                let file = path.value().replace("{locale}", &locale.value());
+                (locale.value(), Path::new(&root).join(file))
+            })
+            .collect()
+    };
+
+    files
+        .into_iter()
+        .map(|(locale, file)| {
+            let source = std::fs::read_to_string(&file)
+                .map_err(|e| error(format!("cannot read `{}`: {}", file.display(), e)))?;
+            let messages = parse_catalog(&source)
+                .map_err(|e| error(format!("`{}`: {}", file.display(), e)))?;
+            Ok(Catalog {
+                locale,
+                file,
+                messages,
+            })
+        })
+        .collect()
+}
+
+/// Parses the `Code = "Message"` lines of a catalog; comments and blank lines are skipped
+fn parse_catalog(source: &str) -> Result<Vec<(String, String)>, String> {
+    let mut messages: Vec<(String, String)> = Vec::new();
+    for (number, line) in source.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let invalid = || format!("line {}: expected `Code = \"Message\"`", number + 1);
+        let (key, value) = line.split_once('=').ok_or_else(invalid)?;
+        let key = key.trim();
+        let key = key
+            .strip_prefix('"')
+            .and_then(|key| key.strip_suffix('"'))
+            .unwrap_or(key);
+        if key.is_empty() {
+            return Err(invalid());
+        }
+        let value = parse_string(value.trim()).ok_or_else(invalid)?;
+        if messages.iter().any(|(existing, _)| existing == key) {
+            return Err(format!("line {}: duplicate code `{}`", number + 1, key));
+        }
+        messages.push((key.to_string(), value));
+    }
+    Ok(messages)
+}
+
+/// A basic TOML string, optionally followed by a comment
+pub fn parse_string(value: &str) -> Option<String> {
+    let mut chars = value.strip_prefix('"')?.chars();
+    let mut out = String::new();
+    loop {
+        match chars.next()? {
+            '"' => break,
+            '\\' => out.push(match chars.next()? {
+                'n' => '\n',
+                't' => '\t',
+                '"' => '"',
+                '\\' => '\\',
+                _ => return None,
+            }),
+            c => out.push(c),
+        }
+    }
+    let rest = chars.as_str().trim();
+    (rest.is_empty() || rest.starts_with('#')).then_some(out)
+}
+
+/// Codes of all catalogs, in the order they first appear
+pub fn error_codes(catalogs: &[Catalog]) -> Vec<&str> {
+    let mut codes: Vec<&str> = Vec::new();
+    for (code, _) in catalogs.iter().flat_map(|catalog| &catalog.messages) {
+        if !codes.contains(&code.as_str()) {
+            codes.push(code);
+        }
+    }
+    codes
+}
+
+/// Generates a layer adding the localized `message` of the `error` code to JSON error bodies.
+///
+/// The locale is the `eywa_axum::Locale` request extension, or the first `Accept-Language`
+/// entry; `de-AT` falls back to `de`, unknown locales and codes to the first catalog.
+pub fn generate_messages_layer(catalogs: &[Catalog]) -> TokenStream {
+    let default_locale = catalogs
+        .first()
+        .map(|catalog| catalog.locale.as_str())
+        .unwrap_or_default();
+    // Recompile when a catalog changes
+    let files = catalogs
+        .iter()
+        .map(|catalog| catalog.file.to_string_lossy().into_owned());
+    let tables = catalogs.iter().map(|catalog| {
+        let locale = &catalog.locale;
+        let codes = catalog.messages.iter().map(|(code, _)| code);
+        let messages = catalog.messages.iter().map(|(_, message)| message);
+        quote! { (#locale, &[#((#codes, #messages)),*]) }
+    });
+    quote! {
+        .layer(eywa_axum::axum::middleware::from_fn(
+            |req: eywa_axum::axum::extract::Request, next: eywa_axum::axum::middleware::Next| async move {
+                use eywa_axum::axum::response::IntoResponse;
+
+                #(const _: &[u8] = include_bytes!(#files);)*
+                const CATALOGS: &[(&str, &[(&str, &str)])] = &[#(#tables),*];
+
+                let requested = req
+                    .extensions()
+                    .get::<eywa_axum::Locale>()
+                    .map(|locale| locale.0.clone())
+                    .or_else(|| {
+                        let header = req
+                            .headers()
+                            .get(eywa_axum::axum::http::header::ACCEPT_LANGUAGE)?
+                            .to_str()
+                            .ok()?;
+                        let first = header.split(',').next()?;
+                        Some(first.split(';').next().unwrap_or(first).trim().to_string())
+                    });
+
+                let response = next.run(req).await;
+                let is_json = response
+                    .headers()
+                    .get(eywa_axum::axum::http::header::CONTENT_TYPE)
+                    .and_then(|v| v.to_str().ok())
+                    .is_some_and(|v| v.starts_with("application/json"));
+                if !(response.status().is_client_error() || response.status().is_server_error())
+                    || !is_json
+                {
+                    return response;
+                }
+
+                let (mut parts, body) = response.into_parts();
+                let Ok(bytes) = eywa_axum::axum::body::to_bytes(body, usize::MAX).await else {
+                    return eywa_axum::axum::http::StatusCode::INTERNAL_SERVER_ERROR.into_response();
+                };
+                let mut value: eywa_axum::serde_json::Value =
+                    match eywa_axum::serde_json::from_slice(&bytes) {
+                        Ok(value) => value,
+                        Err(_) => {
+                            return eywa_axum::axum::response::Response::from_parts(
+                                parts,
+                                eywa_axum::axum::body::Body::from(bytes),
+                            );
+                        }
+                    };
+
+                let lookup = |locale: &str, code: &str| {
+                    CATALOGS
+                        .iter()
+                        .find(|(candidate, _)| candidate.eq_ignore_ascii_case(locale))
+                        .and_then(|(_, messages)| messages.iter().find(|(c, _)| *c == code))
+                        .map(|(_, message)| *message)
+                };
+                let message = value
+                    .get("error")
+                    .and_then(|code| code.as_str())
+                    .and_then(|code| {
+                        requested
+                            .as_deref()
+                            .and_then(|locale| {
+                                lookup(locale, code).or_else(|| {
+                                    lookup(locale.split('-').next().unwrap_or(locale), code)
+                                })
+                            })
+                            .or_else(|| lookup(#default_locale, code))
+                    });
+                match (message, value.as_object_mut()) {
+                    (Some(message), Some(object)) => {
+                        object.insert("message".to_string(), message.into());
+                        parts
+                            .headers
+                            .remove(eywa_axum::axum::http::header::CONTENT_LENGTH);
+                        eywa_axum::axum::response::Response::from_parts(
+                            parts,
+                            eywa_axum::axum::body::Body::from(value.to_string()),
+                        )
+                    }
+                    _ => eywa_axum::axum::response::Response::from_parts(
+                        parts,
+                        eywa_axum::axum::body::Body::from(bytes),
+                    ),
+                }
+            }
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_string_reads_escapes_and_trailing_comments() {
+        assert_eq!(parse_string(r#""Not found""#).as_deref(), Some("Not found"));
+        assert_eq!(
+            parse_string(r#""a \"b\"\n" # note"#).as_deref(),
+            Some("a \"b\"\n")
+        );
+        assert_eq!(parse_string(r#""unclosed"#), None);
+        assert_eq!(parse_string(r#""bad \x""#), None);
+        assert_eq!(parse_string(r#""text" trailing"#), None);
+        assert_eq!(parse_string("bare"), None);
+    }
+
+    #[test]
+    fn parse_catalog_reads_codes_in_order() {
+        let source = "# Errors\n\nNotFound = \"Not found\"\n\"Gone\" = \"Gone for good\" # 410\n";
+        let messages = parse_catalog(source).unwrap();
+        assert_eq!(
+            messages,
+            [
+                ("NotFound".to_string(), "Not found".to_string()),
+                ("Gone".to_string(), "Gone for good".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_catalog_reports_invalid_and_duplicate_lines() {
+        let error = parse_catalog("NotFound \"Not found\"").unwrap_err();
+        assert_eq!(error, "line 1: expected `Code = \"Message\"`");
+        let error = parse_catalog("= \"Nothing\"").unwrap_err();
+        assert_eq!(error, "line 1: expected `Code = \"Message\"`");
+        let error = parse_catalog("A = \"a\"\nA = \"b\"").unwrap_err();
+        assert_eq!(error, "line 2: duplicate code `A`");
+    }
+}
@@ -3,8 +3,10 @@
 //! This module provides the `openapi_for!` procedural macro
 //! that generates OpenAPI documentation structs automatically.
 
+use std::hash::{Hash, Hasher};
+
 use proc_macro2::TokenStream;
-use quote::quote;
+use quote::{format_ident, quote};
 use syn::parse::{Parse, ParseStream};
 use syn::{Ident, LitStr, Path, Token, bracketed, punctuated::Punctuated};
 
@@ -18,6 +20,24 @@ pub struct OpenApiForArgs {
     pub tags: Vec<TagDef>,
     /// API info
     pub info: Option<ApiInfo>,
+    /// Sample values attached to schemas as component-level examples
+    pub schema_examples: Vec<SchemaExample>,
+    /// Middleware layered onto controllers by tag
+    pub applies: Vec<TagMiddleware>,
+    /// Generate a unit test running `ApiDoc::assert_no_route_conflicts()`
+    pub route_conflict_test: bool,
+}
+
+/// Middleware for the controllers of some tags: `apply(middleware = auth, to_tags = ["Admin"])`
+pub struct TagMiddleware {
+    pub middleware: Path,
+    pub tags: Vec<LitStr>,
+}
+
+/// Example value for a schema: `Project = project_fixture()`
+pub struct SchemaExample {
+    pub schema: Path,
+    pub value: syn::Expr,
 }
 
 /// Tag definition for OpenAPI
@@ -39,6 +59,9 @@ impl Parse for OpenApiForArgs {
         let mut schemas = Vec::new();
         let mut tags = Vec::new();
         let mut info = None;
+        let mut schema_examples = Vec::new();
+        let mut applies = Vec::new();
+        let mut route_conflict_test = false;
 
         while !input.is_empty() {
             let key: Ident = input.parse()?;
@@ -128,6 +151,70 @@ impl Parse for OpenApiForArgs {
                         description,
                     });
                 }
+                "schema_examples" => {
+                    let content;
+                    syn::parenthesized!(content in input);
+                    while !content.is_empty() {
+                        let schema: Path = content.parse()?;
+                        let _: Token![=] = content.parse()?;
+                        let value: syn::Expr = content.parse()?;
+                        schema_examples.push(SchemaExample { schema, value });
+
+                        if !content.is_empty() {
+                            let _: Token![,] = content.parse()?;
+                        }
+                    }
+                }
+                "route_conflict_test" => {
+                    route_conflict_test = true;
+                }
+                "apply" => {
+                    let content;
+                    syn::parenthesized!(content in input);
+
+                    let mut middleware = None;
+                    let mut tags = None;
+
+                    while !content.is_empty() {
+                        let field_key: Ident = content.parse()?;
+                        let _: Token![=] = content.parse()?;
+
+                        match field_key.to_string().as_str() {
+                            "middleware" => middleware = Some(content.parse::<Path>()?),
+                            "to_tags" => {
+                                let tag_content;
+                                bracketed!(tag_content in content);
+                                let names: Punctuated<LitStr, Token![,]> = tag_content
+                                    .parse_terminated(<LitStr as Parse>::parse, Token![,])?;
+                                tags = Some(names.into_iter().collect::<Vec<_>>());
+                            }
+                            _ => {
+                                return Err(syn::Error::new_spanned(
+                                    &field_key,
+                                    format!("Unknown apply argument: {}", field_key),
+                                ));
+                            }
+                        }
+
+                        if !content.is_empty() {
+                            let _: Token![,] = content.parse()?;
+                        }
+                    }
+
+                    let middleware = middleware.ok_or_else(|| {
+                        syn::Error::new_spanned(&key, "`apply` requires `middleware = path`")
+                    })?;
+                    let tags = match tags {
+                        Some(tags) if !tags.is_empty() => tags,
+                        _ => {
+                            return Err(syn::Error::new_spanned(
+                                &key,
+                                "`apply` requires `to_tags = [\"Tag\", ...]`",
+                            ));
+                        }
+                    };
+                    applies.push(TagMiddleware { middleware, tags });
+                }
                 _ => {
                     return Err(syn::Error::new_spanned(
                         key,
@@ -147,6 +234,9 @@ impl Parse for OpenApiForArgs {
             schemas,
             tags,
             info,
+            schema_examples,
+            applies,
+            route_conflict_test,
         })
     }
 }
@@ -242,6 +332,253 @@ pub fn openapi_for_impl(input: TokenStream) -> TokenStream {
         }
     };
 
+    // Serialized when building and attached to the schema itself and to the components' named
+    // examples. utoipa's `Components` has no `examples` map and only keeps `x-` extensions when
+    // a document is read back, so those go under `x-examples`
+    let examples = args.schema_examples.iter().map(|example| {
+        let schema = &example.schema;
+        let value = &example.value;
+        quote! {
+            if let Ok(value) = eywa_axum::serde_json::to_value(#value) {
+                let name = <#schema as utoipa::ToSchema>::name().to_string();
+                let components = openapi.components.get_or_insert_with(Default::default);
+                if let Some(utoipa::openapi::RefOr::T(utoipa::openapi::schema::Schema::Object(object))) =
+                    components.schemas.get_mut(&name)
+                {
+                    object.examples = vec![value.clone()];
+                }
+                let example = utoipa::openapi::example::ExampleBuilder::new()
+                    .value(Some(value))
+                    .build();
+                let examples = components
+                    .extensions
+                    .get_or_insert_with(Default::default)
+                    .entry("x-examples".to_string())
+                    .or_insert_with(|| eywa_axum::serde_json::json!({}));
+                if let (Some(examples), Ok(example)) =
+                    (examples.as_object_mut(), eywa_axum::serde_json::to_value(example))
+                {
+                    examples.insert(name, example);
+                }
+            }
+        }
+    });
+
+    // Listed first runs first, so the first `apply` is layered last
+    let tag_layers = args.applies.iter().rev().map(|apply| {
+        let middleware = &apply.middleware;
+        let tags = &apply.tags;
+        quote! {
+            let router = if [#(#tags),*].contains(&tag) {
+                router.layer(eywa_axum::axum::middleware::from_fn(#middleware))
+            } else {
+                router
+            };
+        }
+    });
+
+    let tag_binding = if args.applies.is_empty() {
+        quote! {}
+    } else {
+        quote! { let tag = C::tag(); }
+    };
+
+    // Ingress rules from the routes each controller serves
+    let ingress = if cfg!(feature = "export-ingress") {
+        quote! {
+            /// Public path prefix and the methods served below it.
+            #[derive(Debug, Clone, PartialEq, Eq)]
+            pub struct IngressRule {
+                /// Static part of the route paths, up to the first parameter
+                pub prefix: String,
+                /// Upper-case methods (`ANY` for catch-all routes)
+                pub methods: Vec<&'static str>,
+            }
+
+            impl ApiDoc {
+                /// Path prefixes and methods of every controller route, sorted by prefix,
+                /// for dumping into ingress annotations.
+                pub fn ingress_rules() -> Vec<IngressRule> {
+                    let mut rules: Vec<IngressRule> = Vec::new();
+                    let routes: &[&[(&'static str, &'static str)]] =
+                        &[#(#controller_paths::ROUTES),*];
+                    for (method, path) in routes.iter().copied().flatten().copied() {
+                        let prefix = path.split('{').next().unwrap_or(path).to_string();
+                        match rules.iter_mut().find(|rule| rule.prefix == prefix) {
+                            Some(rule) => {
+                                if !rule.methods.contains(&method) {
+                                    rule.methods.push(method);
+                                }
+                            }
+                            None => rules.push(IngressRule {
+                                prefix,
+                                methods: vec![method],
+                            }),
+                        }
+                    }
+                    rules.sort_by(|a, b| a.prefix.cmp(&b.prefix));
+                    rules
+                }
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    // Opt-in test; named after the controller list so several invocations don't clash
+    let conflict_test = if args.route_conflict_test {
+        let mut hasher = std::hash::DefaultHasher::new();
+        quote!(#(#controller_paths),*).to_string().hash(&mut hasher);
+        let test_name = format_ident!("eywa_route_conflicts_{:016x}", hasher.finish());
+        quote! {
+            #[cfg(test)]
+            #[test]
+            fn #test_name() {
+                ApiDoc::assert_no_route_conflicts();
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    // Routes of different controllers that axum would reject when merging their routers, or
+    // that take requests meant for the other controller
+    let conflicts = quote! {
+        /// How two routes of different controllers collide.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum ConflictKind {
+            /// Both register the same method and path
+            Identical,
+            /// The first route's catch-all covers the second, more specific path: requests
+            /// to it go to the second controller, which answers 405 for methods it lacks
+            Shadowed,
+        }
+
+        /// Two routes of different controllers matching the same requests.
+        #[derive(Debug, Clone, PartialEq, Eq)]
+        pub struct RouteConflict {
+            /// Upper-case method of the first route (`ANY` for catch-all routes)
+            pub method: &'static str,
+            /// Controller and full path of the first route
+            pub first: (&'static str, &'static str),
+            /// Controller and full path of the second route
+            pub second: (&'static str, &'static str),
+            /// Whether the paths are identical or the first shadows the second
+            pub kind: ConflictKind,
+        }
+
+        impl std::fmt::Display for RouteConflict {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                let relation = match self.kind {
+                    ConflictKind::Identical => "is also registered by",
+                    ConflictKind::Shadowed => "is shadowed by",
+                };
+                write!(
+                    f,
+                    "{} {} ({}) {} {} ({})",
+                    self.method, self.first.1, self.first.0, relation, self.second.1, self.second.0
+                )
+            }
+        }
+
+        impl ApiDoc {
+            /// Pairs of routes from different controllers matching the same requests:
+            ///
+            /// - the same method (or `ANY`) and the same path up to parameter names, e.g.
+            ///   two `GET /api/items/{id}`, or `/api/items/{id}` and `/api/items/{item_id}`
+            /// - a catch-all route of one controller (`/api/files/{*rest}`) and a route of
+            ///   another below its prefix (`/api/files/uploads/{id}`), whatever the methods
+            ///
+            /// Static segments next to parameters (`/api/items/export`) are routed
+            /// by axum and not reported.
+            pub fn route_conflicts() -> Vec<RouteConflict> {
+                // Path segments with parameters reduced to `{}` and catch-alls to `{*}`
+                fn segments(path: &str) -> Vec<&str> {
+                    path.split('/')
+                        .filter(|segment| !segment.is_empty())
+                        .map(|segment| {
+                            if segment.starts_with("{*") || segment.starts_with('*') {
+                                "{*}"
+                            } else if segment.starts_with('{') || segment.starts_with(':') {
+                                "{}"
+                            } else {
+                                segment
+                            }
+                        })
+                        .collect()
+                }
+                // Whether catch-all route `outer` also matches the longer path `inner`
+                fn shadows(outer: &[&str], inner: &[&str]) -> bool {
+                    match outer.split_last() {
+                        Some((&"{*}", prefix)) => {
+                            inner.len() > prefix.len() && inner.starts_with(prefix) && outer != inner
+                        }
+                        _ => false,
+                    }
+                }
+
+                let controllers: &[(&'static str, &'static [(&'static str, &'static str)])] =
+                    &[#((#controller_paths::CONTROLLER, #controller_paths::ROUTES)),*];
+                let mut conflicts = Vec::new();
+                for (i, (first_controller, first_routes)) in controllers.iter().enumerate() {
+                    for (second_controller, second_routes) in &controllers[i + 1..] {
+                        for (first_method, first_path) in first_routes.iter().copied() {
+                            for (second_method, second_path) in second_routes.iter().copied() {
+                                let (a, b) = (segments(first_path), segments(second_path));
+                                let first = (*first_controller, first_path);
+                                let second = (*second_controller, second_path);
+                                let same_method = first_method == second_method
+                                    || first_method == "ANY"
+                                    || second_method == "ANY";
+                                if same_method && a == b {
+                                    conflicts.push(RouteConflict {
+                                        method: first_method,
+                                        first,
+                                        second,
+                                        kind: ConflictKind::Identical,
+                                    });
+                                    continue;
+                                }
+                                let shadow = if shadows(&a, &b) {
+                                    (first_method, first, second)
+                                } else if shadows(&b, &a) {
+                                    (second_method, second, first)
+                                } else {
+                                    continue;
+                                };
+                                // Reported once per path, not per method of the shadowed route
+                                let conflict = RouteConflict {
+                                    method: shadow.0,
+                                    first: shadow.1,
+                                    second: shadow.2,
+                                    kind: ConflictKind::Shadowed,
+                                };
+                                if !conflicts.contains(&conflict) {
+                                    conflicts.push(conflict);
+                                }
+                            }
+                        }
+                    }
+                }
+                conflicts
+            }
+
+            /// Panics listing every [`RouteConflict`] between the controllers.
+            ///
+            /// Call this at startup, or generate a unit test running it with
+            /// `route_conflict_test`.
+            pub fn assert_no_route_conflicts() {
+                let conflicts = Self::route_conflicts();
+                if !conflicts.is_empty() {
+                    let conflicts: Vec<String> = conflicts.iter().map(ToString::to_string).collect();
+                    panic!("conflicting routes:\n  {}", conflicts.join("\n  "));
+                }
+            }
+        }
+
+        #conflict_test
+    };
+
     quote! {
         #[derive(utoipa::OpenApi)]
         #[openapi(
@@ -251,5 +588,33 @@ pub fn openapi_for_impl(input: TokenStream) -> TokenStream {
             #tags_section
         )]
         pub struct ApiDoc;
+
+        impl ApiDoc {
+            /// Builds the OpenAPI document, with controller tag metadata and schema
+            /// examples attached.
+            pub fn build_openapi() -> utoipa::openapi::OpenApi {
+                let mut openapi = <Self as utoipa::OpenApi>::openapi();
+                #(#controller_paths::register_tag(&mut openapi);)*
+                #(#examples)*
+                openapi
+            }
+
+            /// Builds the router of controller `C` with the `apply(...)` middleware
+            /// listed for its tag layered on, outside the controller's own middleware.
+            pub fn controller_router<C, S>(state: S) -> eywa_axum::axum::Router<S>
+            where
+                C: eywa_axum::IntoRouter<S>,
+                S: Clone + Send + Sync + 'static,
+            {
+                #tag_binding
+                let router = C::into_router(state);
+                #(#tag_layers)*
+                router
+            }
+        }
+
+        #conflicts
+
+        #ingress
     }
 }
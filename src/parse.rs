@@ -1,11 +1,15 @@
 //! Parsing utilities for macro attributes.
 
+use convert_case::{Case, Casing};
 use darling::FromMeta;
+use darling::ast::NestedMeta;
 use proc_macro2::TokenStream;
 use quote::ToTokens;
 use syn::parse::{Parse, ParseStream};
 use syn::{Expr, Ident, LitStr, Path, Token};
 
+use crate::paths::{normalize_params, validate_path};
+
 /// Parsed controller attributes
 #[derive(Debug, FromMeta)]
 pub struct ControllerArgs {
@@ -28,86 +32,1540 @@ pub struct ControllerArgs {
     #[darling(default)]
     pub tag: Option<String>,
 
-    /// Middleware function to apply (use `middleware = path::to::fn` syntax)
+    /// Description of the tag, shown as prose for the resource group in the docs sidebar
     #[darling(default)]
-    pub middleware: Option<Path>,
+    pub description: Option<String>,
 
-    /// All routes require bearer authentication (applies to all routes in controller)
+    /// Human-readable tag name for the docs sidebar (`x-displayName`)
     #[darling(default)]
-    pub security: bool,
+    pub display_name: Option<String>,
+
+    /// Summary of routes that don't set one: `summary_template = "{method} {resource}"`,
+    /// with `{method}`, `{path}`, `{fn}` (handler name) and `{resource}` placeholders
+    #[darling(default)]
+    pub summary_template: Option<LitStr>,
+
+    /// Middleware functions to apply (use `middleware = path::to::fn` or
+    /// `middleware = [auth if not(test), logger]` syntax).
+    /// Extracted before darling runs, see [`split_middleware_arg`].
+    #[darling(skip)]
+    pub middleware: Vec<MiddlewareSpec>,
+
+    /// All routes require bearer authentication (applies to all routes in controller):
+    /// `security`, or `security = ["projects:read"]` with the scopes of the requirement;
+    /// `security = cookie("session_id")` requires a session cookie instead
+    #[darling(default)]
+    pub security: Option<Security>,
+
+    /// Scheme of the security requirement: `"bearer"` (default) or `"api_key"` (an
+    /// `X-API-Key` header); setting it requires authentication on every route
+    #[darling(default)]
+    pub security_scheme: Option<LitStr>,
+
+    /// Scheme of requirements that name none, from `eywa-controllers.toml`; unlike
+    /// `security_scheme` it doesn't require authentication by itself
+    #[darling(skip)]
+    pub default_security_scheme: Option<LitStr>,
+
+    /// OAuth2 security scheme routes can require with `security_scheme = "oauth2"`:
+    /// `oauth2(flow = "authorization_code", auth_url = "...", token_url = "...", scopes(...))`
+    #[darling(default)]
+    pub oauth2: Option<OAuth2Args>,
 
     /// Schema types to register for OpenAPI
     /// usage: schemas(Type1, Type2)
     #[darling(default)]
     pub schemas: PathList,
+
+    /// Generate an OPTIONS handler per path listing allowed methods and link relations
+    #[darling(default)]
+    pub auto_options: bool,
+
+    /// Generate `POST {path}/_batch` dispatching sub-requests by operation id
+    #[darling(default)]
+    pub batch: bool,
+
+    /// Extractor types to skip in signature analysis for every route
+    /// usage: ignore_params(MyExtractor, Other)
+    #[darling(default)]
+    pub ignore_params: PathList,
+
+    /// Document custom extractors as OpenAPI parameters
+    /// usage: param_from(TypedHeader<Authorization<Bearer>> => header "Authorization")
+    #[darling(default)]
+    pub param_from: ParamMappings,
+
+    /// Response envelope wrapping every `Json<T>` handler (e.g., `envelope = ApiResponse`)
+    /// The type must provide `new(data: T)`
+    #[darling(default)]
+    pub envelope: Option<Path>,
+
+    /// Maximum accepted request body size in bytes (documented as `x-max-request-size`)
+    #[darling(default)]
+    pub max_request_size: Option<usize>,
+
+    /// Record request/response body sizes for every route
+    #[darling(default)]
+    pub measure_bodies: bool,
+
+    /// Catalogs of localized error messages: `error_messages = "errors/en.toml"` for a single
+    /// locale, or `error_messages(path = "errors/{locale}.toml", locales = ["en", "de"])`
+    #[darling(default)]
+    pub error_messages: Option<ErrorMessages>,
+
+    /// HTTP status per error variant: `error_map((NotFound => 404), (Conflict => 409))`,
+    /// optionally naming the error type first (`error_map(DomainError, ...)`)
+    #[darling(default)]
+    pub error_map: ErrorMap,
+
+    /// Tenant resolution for every route:
+    /// `tenant(strategy = "header", header = "X-Tenant")` or `tenant(strategy = "subdomain")`
+    #[darling(default)]
+    pub tenant: Option<TenantArgs>,
+
+    /// Request limit per client and minute: `throttle(per_minute = 600, key = "user")`
+    #[darling(default)]
+    pub throttle: Option<ThrottleArgs>,
+
+    /// Standard security response headers: `security_headers` or
+    /// `security_headers(csp = "default-src 'self'; img-src *")`
+    #[darling(default)]
+    pub security_headers: Option<SecurityHeaders>,
+
+    /// Answer CORS preflights and allow cross-origin requests:
+    /// `cors(origins = ["https://app.example.com"], headers = ["authorization"], max_age = "10m")`
+    #[darling(default)]
+    pub cors: Option<CorsArgs>,
+
+    /// Turn handler panics into a 500 ProblemDetails response
+    #[darling(default)]
+    pub catch_panic: bool,
+
+    /// Accept or generate an `X-Request-Id` per request and echo it on the response
+    #[darling(default)]
+    pub request_id: bool,
+
+    /// Structured request logging for every route without its own `log(...)`
+    #[darling(default)]
+    pub log: Option<LogOptions>,
+
+    /// Audit record of every request, as with the route's `audit`
+    #[darling(default)]
+    pub audit: bool,
+
+    /// Request extensions added to every access log line:
+    /// `log_fields(tenant = "extensions.TenantId", user = "extensions.UserId")`
+    #[darling(default)]
+    pub log_fields: LogFields,
+
+    /// GraphQL endpoint: `graphql(schema = AppSchema, path = "/graphql", playground)`
+    #[darling(default)]
+    pub graphql: Option<GraphqlArgs>,
+
+    /// Route documentation through shared helpers in `eywa_axum::__private` instead of
+    /// per-route token blocks, for large controllers
+    #[darling(default)]
+    pub compact_codegen: bool,
+
+    /// Casing of paths derived with `path = auto`: `"kebab"` (default), `"snake"` or `"camel"`
+    #[darling(default)]
+    pub path_case: Option<LitStr>,
+
+    /// Names of the generated wrapper schemas: `"prefixed"` (`__HateoasSchema_get`, default)
+    /// or `"short"` (`ProjectHateoas`)
+    #[darling(default)]
+    pub schema_naming: Option<LitStr>,
+
+    /// Document and answer 201 on POST and 204 (without body) on DELETE routes instead of 200,
+    /// unless the route sets its own success status; `rest_conventions = false` opts out of a
+    /// configured default
+    #[darling(default)]
+    pub rest_conventions: Option<bool>,
+
+    /// Which error responses are documented without being declared: `"all"` (default) or
+    /// `"minimal"`, leaving out the generic 401, 404 and 500 that no generated layer answers
+    #[darling(default)]
+    pub auto_responses: Option<LitStr>,
+
+    /// Run every route in a transaction, see the route attribute `transactional`
+    #[darling(default)]
+    pub transactional: bool,
+}
+
+impl ControllerArgs {
+    /// Scheme of the controller's security requirement: `security_scheme`, `"cookie"` for
+    /// `security = cookie(...)`, otherwise the configured default or `"bearer"`
+    pub fn security_scheme_name(&self) -> String {
+        match (&self.security_scheme, &self.security) {
+            (Some(scheme), _) => scheme.value(),
+            (
+                None,
+                Some(Security {
+                    cookie: Some(_), ..
+                }),
+            ) => "cookie".to_string(),
+            _ => self
+                .default_security_scheme
+                .as_ref()
+                .map_or_else(|| "bearer".to_string(), LitStr::value),
+        }
+    }
+
+    /// Case of the paths derived from handler names
+    pub fn path_case(&self) -> syn::Result<Case> {
+        match self.path_case.as_ref().map(LitStr::value).as_deref() {
+            None | Some("kebab") => Ok(Case::Kebab),
+            Some("snake") => Ok(Case::Snake),
+            Some("camel") => Ok(Case::Camel),
+            Some(_) => Err(syn::Error::new_spanned(
+                &self.path_case,
+                "expected `path_case = \"kebab\"`, `\"snake\"` or `\"camel\"`",
+            )),
+        }
+    }
+
+    /// Whether `rest_conventions` is on
+    pub fn rest_conventions(&self) -> bool {
+        self.rest_conventions.unwrap_or(false)
+    }
+
+    /// Whether `auto_responses = "minimal"` drops the generic 401, 404 and 500 responses
+    pub fn minimal_auto_responses(&self) -> syn::Result<bool> {
+        match self.auto_responses.as_ref().map(LitStr::value).as_deref() {
+            None | Some("all") => Ok(false),
+            Some("minimal") => Ok(true),
+            Some(_) => Err(syn::Error::new_spanned(
+                &self.auto_responses,
+                "expected `auto_responses = \"all\"` or `auto_responses = \"minimal\"`",
+            )),
+        }
+    }
+
+    /// Whether generated wrapper schemas get readable names derived from their data type
+    pub fn short_schema_names(&self) -> syn::Result<bool> {
+        match self.schema_naming.as_ref().map(LitStr::value).as_deref() {
+            None | Some("prefixed") => Ok(false),
+            Some("short") => Ok(true),
+            Some(_) => Err(syn::Error::new_spanned(
+                &self.schema_naming,
+                "expected `schema_naming = \"prefixed\"` or `schema_naming = \"short\"`",
+            )),
+        }
+    }
+}
+
+/// Controller `summary_template`, split at its placeholders
+#[derive(Debug)]
+pub struct SummaryTemplate {
+    parts: Vec<TemplatePart>,
+}
+
+#[derive(Debug)]
+enum TemplatePart {
+    Text(String),
+    Method,
+    Path,
+    Fn,
+    Resource,
+}
+
+impl SummaryTemplate {
+    pub fn parse(lit: &LitStr) -> syn::Result<Self> {
+        let template = lit.value();
+        let mut parts = Vec::new();
+        let mut rest = template.as_str();
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                parts.push(TemplatePart::Text(rest[..start].to_string()));
+            }
+            let Some(len) = rest[start..].find('}') else {
+                return Err(syn::Error::new_spanned(
+                    lit,
+                    "unclosed `{` in summary_template",
+                ));
+            };
+            parts.push(match &rest[start + 1..start + len] {
+                "method" => TemplatePart::Method,
+                "path" => TemplatePart::Path,
+                "fn" => TemplatePart::Fn,
+                "resource" => TemplatePart::Resource,
+                other => {
+                    return Err(syn::Error::new_spanned(
+                        lit,
+                        format!(
+                            "unknown placeholder `{{{}}}` in summary_template, expected `{{method}}`, `{{path}}`, `{{fn}}` or `{{resource}}`",
+                            other
+                        ),
+                    ));
+                }
+            });
+            rest = &rest[start + len + 1..];
+        }
+        if !rest.is_empty() {
+            parts.push(TemplatePart::Text(rest.to_string()));
+        }
+        Ok(Self { parts })
+    }
+
+    /// Summary of one route
+    pub fn render(&self, method: HttpMethod, path: &str, fn_name: &str, resource: &str) -> String {
+        self.parts
+            .iter()
+            .map(|part| match part {
+                TemplatePart::Text(text) => text.as_str(),
+                TemplatePart::Method => method.as_str(),
+                TemplatePart::Path => path,
+                TemplatePart::Fn => fn_name,
+                TemplatePart::Resource => resource,
+            })
+            .collect()
+    }
+}
+
+/// Authentication requirement of a controller: bearer with scopes, or a session cookie
+#[derive(Debug, Default)]
+pub struct Security {
+    pub scopes: Vec<String>,
+    /// Name of the session cookie, `security = cookie("session_id")`
+    pub cookie: Option<LitStr>,
+}
+
+impl FromMeta for Security {
+    fn from_word() -> darling::Result<Self> {
+        Ok(Security::default())
+    }
+
+    fn from_expr(expr: &Expr) -> darling::Result<Self> {
+        if let Expr::Call(call) = expr
+            && let Expr::Path(func) = &*call.func
+            && func.path.is_ident("cookie")
+        {
+            let cookie = match call.args.iter().collect::<Vec<_>>().as_slice() {
+                [
+                    Expr::Lit(syn::ExprLit {
+                        lit: syn::Lit::Str(name),
+                        ..
+                    }),
+                ] => name.clone(),
+                _ => {
+                    return Err(
+                        darling::Error::custom("expected `cookie(\"name\")`").with_span(call)
+                    );
+                }
+            };
+            return Ok(Security {
+                scopes: Vec::new(),
+                cookie: Some(cookie),
+            });
+        }
+        let Expr::Array(array) = expr else {
+            return Err(darling::Error::unexpected_expr_type(expr));
+        };
+        let scopes = array
+            .elems
+            .iter()
+            .map(|elem| match elem {
+                Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Str(scope),
+                    ..
+                }) => Ok(scope.value()),
+                other => Err(darling::Error::custom("expected a scope string").with_span(other)),
+            })
+            .collect::<darling::Result<_>>()?;
+        Ok(Security {
+            scopes,
+            cookie: None,
+        })
+    }
+}
+
+/// Keys `openapi(append = { ... })` may set, none of which the macros generate
+const OPENAPI_APPEND_ALLOWED: &[&str] = &[
+    "operation_id",
+    "summary",
+    "description",
+    "servers",
+    "extensions",
+];
+
+/// Error for an `openapi(append)` key that would replace generated output
+pub const OPENAPI_APPEND_KEYS: &str = "`openapi(append)` may only set `operation_id`, `summary`, \
+     `description`, `servers` and `extensions` when none are generated";
+
+/// Finds a top-level `key` among the items of an `openapi(append = { ... })` block
+pub fn appended_item(append: &TokenStream, key: &str) -> Option<Ident> {
+    append.clone().into_iter().find_map(|tree| match tree {
+        proc_macro2::TokenTree::Ident(ident) if ident == key => Some(ident),
+        _ => None,
+    })
+}
+
+/// Checks a `security_scheme` value, `"bearer"`, `"api_key"` or `"oauth2"`
+pub fn check_security_scheme(scheme: &LitStr) -> syn::Result<()> {
+    match scheme.value().as_str() {
+        "bearer" | "api_key" | "oauth2" => Ok(()),
+        _ => Err(syn::Error::new_spanned(
+            scheme,
+            "unknown `security_scheme`, expected \"bearer\", \"api_key\" or \"oauth2\"",
+        )),
+    }
+}
+
+/// Security headers set on every response of a controller
+#[derive(Debug)]
+pub struct SecurityHeaders {
+    pub csp: String,
+}
+
+impl SecurityHeaders {
+    /// Header names and values, in the order they are set
+    pub fn headers(&self) -> Vec<(&'static str, String)> {
+        vec![
+            (
+                "strict-transport-security",
+                "max-age=31536000; includeSubDomains".to_string(),
+            ),
+            ("x-content-type-options", "nosniff".to_string()),
+            ("x-frame-options", "DENY".to_string()),
+            ("content-security-policy", self.csp.clone()),
+        ]
+    }
+}
+
+impl FromMeta for SecurityHeaders {
+    fn from_word() -> darling::Result<Self> {
+        Ok(SecurityHeaders {
+            csp: "default-src 'self'".to_string(),
+        })
+    }
+
+    fn from_list(items: &[darling::ast::NestedMeta]) -> darling::Result<Self> {
+        #[derive(FromMeta)]
+        struct Options {
+            #[darling(default)]
+            csp: Option<String>,
+        }
+        let options = Options::from_list(items)?;
+        let mut headers = Self::from_word()?;
+        if let Some(csp) = options.csp {
+            headers.csp = csp;
+        }
+        Ok(headers)
+    }
+}
+
+/// Structured request logging: `log(level = "info", body = false, headers = ["x-request-id"])`
+#[derive(Debug, Clone)]
+pub struct LogOptions {
+    /// `tracing` level macro (`trace`, `debug`, `info`, `warn` or `error`)
+    pub level: Ident,
+    /// Include the request body (lossy UTF-8)
+    pub body: bool,
+    /// Request headers included in the line
+    pub headers: Vec<String>,
+}
+
+impl FromMeta for LogOptions {
+    fn from_word() -> darling::Result<Self> {
+        Ok(LogOptions {
+            level: Ident::new("info", proc_macro2::Span::call_site()),
+            body: false,
+            headers: Vec::new(),
+        })
+    }
+
+    fn from_list(items: &[darling::ast::NestedMeta]) -> darling::Result<Self> {
+        #[derive(FromMeta)]
+        struct Options {
+            #[darling(default)]
+            level: Option<LitStr>,
+            #[darling(default)]
+            body: bool,
+            #[darling(default)]
+            headers: Vec<LitStr>,
+        }
+        let options = Options::from_list(items)?;
+        let mut log = Self::from_word()?;
+        if let Some(level) = options.level {
+            match level.value().as_str() {
+                "trace" | "debug" | "info" | "warn" | "error" => {
+                    log.level = Ident::new(&level.value(), level.span());
+                }
+                _ => {
+                    return Err(darling::Error::custom(
+                        "expected one of `trace`, `debug`, `info`, `warn`, `error`",
+                    )
+                    .with_span(&level));
+                }
+            }
+        }
+        log.body = options.body;
+        log.headers = options.headers.iter().map(LitStr::value).collect();
+        Ok(log)
+    }
+}
+
+/// Byte-range serving: `ranges` or `ranges(max = "64MB")`
+#[derive(Debug, Clone)]
+pub struct RangeOptions {
+    /// Largest response body buffered to cut a range out of; bigger or unsized
+    /// bodies are served whole with 200
+    pub max: usize,
+}
+
+impl FromMeta for RangeOptions {
+    fn from_word() -> darling::Result<Self> {
+        Ok(RangeOptions { max: 8 << 20 })
+    }
+
+    fn from_list(items: &[darling::ast::NestedMeta]) -> darling::Result<Self> {
+        #[derive(FromMeta)]
+        struct Options {
+            #[darling(default)]
+            max: Option<LitStr>,
+        }
+        let options = Options::from_list(items)?;
+        let mut ranges = Self::from_word()?;
+        if let Some(max) = options.max {
+            ranges.max = parse_size_bytes(&max).map_err(darling::Error::from)?;
+        }
+        Ok(ranges)
+    }
+}
+
+/// Arguments of `throttle(...)`
+#[derive(Debug, FromMeta)]
+pub struct ThrottleArgs {
+    pub per_minute: u32,
+    /// `"user"` (authenticated principal, falling back to the client IP) or `"ip"`
+    #[darling(default)]
+    pub key: Option<LitStr>,
+    /// Fixed `Retry-After` (`"30s"`) instead of the time left in the window
+    #[darling(default)]
+    pub retry_after: Option<LitStr>,
+}
+
+impl ThrottleArgs {
+    /// Configured `Retry-After` in seconds
+    pub fn retry_after_secs(&self) -> syn::Result<Option<u64>> {
+        self.retry_after.as_ref().map(parse_retry_after).transpose()
+    }
+
+    /// Whether requests are keyed by the authenticated principal
+    pub fn by_principal(&self) -> syn::Result<bool> {
+        match self.key.as_ref().map(LitStr::value).as_deref() {
+            None | Some("user") => Ok(true),
+            Some("ip") => Ok(false),
+            Some(_) => Err(syn::Error::new_spanned(
+                &self.key,
+                "expected `key = \"user\"` or `key = \"ip\"`",
+            )),
+        }
+    }
+}
+
+/// Catalog files of `error_messages`
+#[derive(Debug)]
+pub struct ErrorMessages {
+    /// Catalog path relative to the crate root, with a `{locale}` placeholder when `locales` is set
+    pub path: LitStr,
+    /// Locales with a catalog, the first being the fallback; empty for a single catalog named
+    /// after its file
+    pub locales: Vec<LitStr>,
+}
+
+impl FromMeta for ErrorMessages {
+    fn from_value(value: &syn::Lit) -> darling::Result<Self> {
+        match value {
+            syn::Lit::Str(path) => Ok(ErrorMessages {
+                path: path.clone(),
+                locales: Vec::new(),
+            }),
+            _ => Err(darling::Error::unexpected_lit_type(value)),
+        }
+    }
+
+    fn from_list(items: &[darling::ast::NestedMeta]) -> darling::Result<Self> {
+        #[derive(FromMeta)]
+        struct Options {
+            path: LitStr,
+            locales: Vec<LitStr>,
+        }
+        let options = Options::from_list(items)?;
+        if !options.path.value().contains("{locale}") {
+            return Err(darling::Error::custom(
+                "expected a `{locale}` placeholder in the catalog path",
+            )
+            .with_span(&options.path));
+        }
+        if options.locales.is_empty() {
+            return Err(darling::Error::custom(
+                "expected at least one locale, `locales = [\"en\"]`",
+            ));
+        }
+        Ok(ErrorMessages {
+            path: options.path,
+            locales: options.locales,
+        })
+    }
+}
+
+/// Arguments of `cors(...)`
+#[derive(Debug, FromMeta)]
+pub struct CorsArgs {
+    /// Allowed origins (default: any origin)
+    #[darling(default)]
+    pub origins: Vec<LitStr>,
+    /// Allowed methods (default: the methods of the controller's routes)
+    #[darling(default)]
+    pub methods: Vec<LitStr>,
+    /// Allowed request headers besides the CORS-safelisted ones
+    #[darling(default)]
+    pub headers: Vec<LitStr>,
+    /// How long browsers may cache a preflight response (`"10m"`)
+    #[darling(default)]
+    pub max_age: Option<LitStr>,
+    /// Document the policy as an `x-cors` extension on every operation
+    #[darling(default)]
+    pub document: bool,
+}
+
+impl CorsArgs {
+    /// Configured `Access-Control-Max-Age` in seconds
+    pub fn max_age_secs(&self) -> syn::Result<Option<u64>> {
+        self.max_age
+            .as_ref()
+            .map(|lit| parse_duration_ms(lit).map(|ms| ms.div_ceil(1000)))
+            .transpose()
+    }
+
+    pub fn origins(&self) -> Vec<String> {
+        self.origins.iter().map(LitStr::value).collect()
+    }
+
+    pub fn headers(&self) -> Vec<String> {
+        self.headers.iter().map(LitStr::value).collect()
+    }
+
+    /// Reject values `HeaderValue::from_static` would panic on
+    pub fn validate(&self) -> syn::Result<()> {
+        let invalid = self
+            .origins
+            .iter()
+            .chain(&self.methods)
+            .chain(&self.headers)
+            .find(|lit| {
+                let value = lit.value();
+                value.is_empty() || value.chars().any(|c| !('!'..='~').contains(&c))
+            });
+        match invalid {
+            Some(lit) => Err(syn::Error::new_spanned(
+                lit,
+                "`cors`: expected a non-empty value without whitespace",
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Arguments of `oauth2(...)`, registered as the `oauth2` security scheme
+#[derive(Debug, FromMeta)]
+pub struct OAuth2Args {
+    /// `"authorization_code"`, `"implicit"`, `"password"` or `"client_credentials"`
+    pub flow: LitStr,
+    #[darling(default)]
+    pub auth_url: Option<String>,
+    #[darling(default)]
+    pub token_url: Option<String>,
+    #[darling(default)]
+    pub refresh_url: Option<String>,
+    /// `scopes(read = "Read access", "projects:write")`; bare strings have no description
+    #[darling(default)]
+    pub scopes: OAuth2Scopes,
+}
+
+impl OAuth2Args {
+    /// Checks that the flow is known and has the URLs it needs
+    pub fn validate(&self) -> syn::Result<()> {
+        let (auth_url, token_url) = match self.flow.value().as_str() {
+            "authorization_code" => (true, true),
+            "implicit" => (true, false),
+            "password" | "client_credentials" => (false, true),
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &self.flow,
+                    "unknown `flow`, expected \"authorization_code\", \"implicit\", \"password\" or \"client_credentials\"",
+                ));
+            }
+        };
+        let missing = match (
+            auth_url && self.auth_url.is_none(),
+            token_url && self.token_url.is_none(),
+        ) {
+            (true, _) => "auth_url",
+            (_, true) => "token_url",
+            _ => return Ok(()),
+        };
+        Err(syn::Error::new_spanned(
+            &self.flow,
+            format!(
+                "`oauth2`: the `{}` flow requires `{}`",
+                self.flow.value(),
+                missing
+            ),
+        ))
+    }
+}
+
+/// Scopes of an OAuth2 flow with their descriptions
+#[derive(Debug, Default)]
+pub struct OAuth2Scopes(pub Vec<(String, String)>);
+
+impl FromMeta for OAuth2Scopes {
+    fn from_list(items: &[NestedMeta]) -> darling::Result<Self> {
+        items
+            .iter()
+            .map(|item| match item {
+                NestedMeta::Lit(syn::Lit::Str(scope)) => Ok((scope.value(), String::new())),
+                NestedMeta::Meta(syn::Meta::NameValue(nv)) => {
+                    let name = nv
+                        .path
+                        .get_ident()
+                        .map(ToString::to_string)
+                        .ok_or_else(|| {
+                            darling::Error::custom("expected a scope name").with_span(&nv.path)
+                        })?;
+                    Ok((name, String::from_expr(&nv.value)?))
+                }
+                other => Err(darling::Error::custom(
+                    "expected `name = \"description\"` or a scope string",
+                )
+                .with_span(other)),
+            })
+            .collect::<darling::Result<_>>()
+            .map(OAuth2Scopes)
+    }
+}
+
+/// Arguments of `graphql(...)`
+#[derive(Debug, FromMeta)]
+pub struct GraphqlArgs {
+    /// Schema type, extracted from state (`State<AppSchema>`)
+    pub schema: Path,
+    /// Endpoint path relative to the controller (default: `/graphql`)
+    #[darling(default)]
+    pub path: Option<LitStr>,
+    /// Serve the GraphQL Playground on `GET`
+    #[darling(default)]
+    pub playground: bool,
+}
+
+/// Arguments of `tenant(...)`
+#[derive(Debug, FromMeta)]
+pub struct TenantArgs {
+    /// `"header"` or `"subdomain"`
+    pub strategy: LitStr,
+    /// Header carrying the tenant for the header strategy (defaults to `X-Tenant`)
+    #[darling(default)]
+    pub header: Option<String>,
+}
+
+/// How a controller resolves the tenant of a request
+#[derive(Debug, Clone)]
+pub enum TenantStrategy {
+    Header(String),
+    Subdomain,
+}
+
+impl TenantArgs {
+    pub fn strategy(&self) -> syn::Result<TenantStrategy> {
+        match self.strategy.value().as_str() {
+            "header" => Ok(TenantStrategy::Header(
+                self.header
+                    .clone()
+                    .unwrap_or_else(|| "X-Tenant".to_string()),
+            )),
+            "subdomain" if self.header.is_none() => Ok(TenantStrategy::Subdomain),
+            "subdomain" => Err(syn::Error::new_spanned(
+                &self.strategy,
+                "`header` only applies to `strategy = \"header\"`",
+            )),
+            _ => Err(syn::Error::new_spanned(
+                &self.strategy,
+                "expected `strategy = \"header\"` or `strategy = \"subdomain\"`",
+            )),
+        }
+    }
+}
+
+/// Wrapper for a list of paths to support list syntax schemas(A, B)
+#[derive(Debug, Default)]
+pub struct PathList(pub Vec<Path>);
+
+impl FromMeta for PathList {
+    fn from_list(items: &[darling::ast::NestedMeta]) -> darling::Result<Self> {
+        let mut paths = Vec::new();
+        for item in items {
+            if let darling::ast::NestedMeta::Meta(syn::Meta::Path(path)) = item {
+                paths.push(path.clone());
+            } else {
+                return Err(darling::Error::custom("expected path").with_span(item));
+            }
+        }
+        Ok(PathList(paths))
+    }
+}
+
+/// Mapping from an extractor type to the parameter it reads
+#[derive(Debug, Clone)]
+pub struct ParamMapping {
+    /// Extractor type as written in handler signatures
+    pub ty: syn::Type,
+    /// Parameter location: `header`, `query`, `path` or `cookie`
+    pub location: Ident,
+    /// Parameter name
+    pub name: LitStr,
+}
+
+impl ParamMapping {
+    /// utoipa `ParameterIn` variant for this mapping
+    pub fn parameter_in(&self) -> Ident {
+        let variant = match self.location.to_string().as_str() {
+            "header" => "Header",
+            "query" => "Query",
+            "path" => "Path",
+            _ => "Cookie",
+        };
+        Ident::new(variant, self.location.span())
+    }
+}
+
+impl Parse for ParamMapping {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let ty: syn::Type = input.parse()?;
+        let _: Token![=>] = input.parse()?;
+        let location: Ident = input.parse()?;
+        if !matches!(
+            location.to_string().as_str(),
+            "header" | "query" | "path" | "cookie"
+        ) {
+            return Err(syn::Error::new_spanned(
+                &location,
+                "expected one of `header`, `query`, `path`, `cookie`",
+            ));
+        }
+        let name: LitStr = input.parse()?;
+        Ok(ParamMapping { ty, location, name })
+    }
+}
+
+/// Wrapper for `param_from(...)` lists
+#[derive(Debug, Default)]
+pub struct ParamMappings(pub Vec<ParamMapping>);
+
+impl FromMeta for ParamMappings {
+    fn from_meta(item: &syn::Meta) -> darling::Result<Self> {
+        let list = item.require_list()?;
+        let mappings = list.parse_args_with(
+            syn::punctuated::Punctuated::<ParamMapping, Token![,]>::parse_terminated,
+        )?;
+        Ok(ParamMappings(mappings.into_iter().collect()))
+    }
+}
+
+/// Request extension included in access log lines: `tenant = "extensions.TenantId"`
+#[derive(Debug, Clone)]
+pub struct LogField {
+    pub name: Ident,
+    /// Extension type, formatted with `Debug`
+    pub extension: Path,
+}
+
+impl Parse for LogField {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name: Ident = input.parse()?;
+        if ["route", "method", "status", "latency_ms", "headers", "body"]
+            .iter()
+            .any(|field| name == field)
+        {
+            return Err(syn::Error::new_spanned(
+                &name,
+                format!("`{}` is already part of every log line", name),
+            ));
+        }
+        let _: Token![=] = input.parse()?;
+        let source: LitStr = input.parse()?;
+        let extension = source
+            .value()
+            .strip_prefix("extensions.")
+            .and_then(|ty| LitStr::new(ty, source.span()).parse::<Path>().ok())
+            .ok_or_else(|| {
+                syn::Error::new_spanned(&source, "expected `\"extensions.TypeName\"`")
+            })?;
+        Ok(LogField { name, extension })
+    }
+}
+
+/// Wrapper for `log_fields(...)` lists
+#[derive(Debug, Default)]
+pub struct LogFields(pub Vec<LogField>);
+
+impl FromMeta for LogFields {
+    fn from_meta(item: &syn::Meta) -> darling::Result<Self> {
+        let list = item.require_list()?;
+        let fields = list.parse_args_with(
+            syn::punctuated::Punctuated::<LogField, Token![,]>::parse_terminated,
+        )?;
+        Ok(LogFields(fields.into_iter().collect()))
+    }
+}
+
+/// HTTP status for an error variant: `(NotFound => 404)`
+#[derive(Debug, Clone)]
+pub struct ErrorMapping {
+    /// Variant, bare (`NotFound`) or qualified (`DomainError::NotFound`)
+    pub variant: Path,
+    pub status: syn::LitInt,
+}
+
+impl Parse for ErrorMapping {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let content;
+        syn::parenthesized!(content in input);
+        let variant: Path = content.parse()?;
+        let _: Token![=>] = content.parse()?;
+        let status: syn::LitInt = content.parse()?;
+        let code = status.base10_parse::<u16>()?;
+        if !(100..=599).contains(&code) {
+            return Err(syn::Error::new_spanned(
+                &status,
+                "expected an HTTP status code",
+            ));
+        }
+        Ok(ErrorMapping { variant, status })
+    }
+}
+
+/// Error variant to status mapping table
+#[derive(Debug, Default)]
+pub struct ErrorMap {
+    /// Error type, when given explicitly
+    pub error: Option<Path>,
+    pub mappings: Vec<ErrorMapping>,
+}
+
+impl ErrorMap {
+    /// The mapped error type: explicit, or the prefix of qualified variants
+    pub fn error_type(&self) -> Option<Path> {
+        if let Some(error) = &self.error {
+            return Some(error.clone());
+        }
+        self.mappings.iter().find_map(|m| {
+            let mut prefix = m.variant.clone();
+            prefix.segments.pop()?;
+            prefix.segments.pop_punct();
+            (!prefix.segments.is_empty()).then_some(prefix)
+        })
+    }
+}
+
+impl FromMeta for ErrorMap {
+    fn from_meta(item: &syn::Meta) -> darling::Result<Self> {
+        let list = item.require_list()?;
+        let map = list.parse_args_with(|input: ParseStream| {
+            let mut map = ErrorMap::default();
+            if !input.peek(syn::token::Paren) {
+                map.error = Some(input.parse()?);
+                if !input.is_empty() {
+                    let _: Token![,] = input.parse()?;
+                }
+            }
+            let mappings =
+                syn::punctuated::Punctuated::<ErrorMapping, Token![,]>::parse_terminated(input)?;
+            map.mappings = mappings.into_iter().collect();
+            Ok(map)
+        })?;
+        Ok(map)
+    }
+}
+
+/// A middleware function with an optional build-environment condition
+#[derive(Debug, Clone)]
+pub struct MiddlewareSpec {
+    /// Path to the middleware function
+    pub path: Path,
+    /// `cfg` predicate (e.g., `not(test)`) gating the layer
+    pub condition: Option<TokenStream>,
+}
+
+impl Parse for MiddlewareSpec {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let path: Path = input.parse()?;
+        let condition = if input.peek(Token![if]) {
+            let _: Token![if] = input.parse()?;
+            let meta: syn::Meta = input.parse()?;
+            Some(cfg_predicate(&meta)?)
+        } else {
+            None
+        };
+        Ok(MiddlewareSpec { path, condition })
+    }
+}
+
+/// Translate a middleware condition into a `cfg` predicate.
+///
+/// `debug` and `release` are shorthands for `debug_assertions` and
+/// `not(debug_assertions)`; `not`/`any`/`all` nest, everything else
+/// (`test`, `feature = "..."`) is passed through as-is.
+fn cfg_predicate(meta: &syn::Meta) -> syn::Result<TokenStream> {
+    match meta {
+        syn::Meta::Path(path) if path.is_ident("debug") => Ok(quote::quote! { debug_assertions }),
+        syn::Meta::Path(path) if path.is_ident("release") => {
+            Ok(quote::quote! { not(debug_assertions) })
+        }
+        syn::Meta::List(list)
+            if list.path.is_ident("not")
+                || list.path.is_ident("any")
+                || list.path.is_ident("all") =>
+        {
+            let nested = list.parse_args_with(
+                syn::punctuated::Punctuated::<syn::Meta, Token![,]>::parse_terminated,
+            )?;
+            let preds = nested
+                .iter()
+                .map(cfg_predicate)
+                .collect::<syn::Result<Vec<_>>>()?;
+            let op = &list.path;
+            Ok(quote::quote! { #op(#(#preds),*) })
+        }
+        syn::Meta::List(list) => Err(syn::Error::new_spanned(
+            &list.path,
+            "expected `not(...)`, `any(...)` or `all(...)`",
+        )),
+        other => Ok(other.to_token_stream()),
+    }
+}
+
+/// Shorthand response attributes: key, status and default description
+const SHORTHAND_RESPONSES: &[(&str, u16, &str)] = &[
+    ("ok", 200, "OK"),
+    ("created", 201, "Created"),
+    ("accepted", 202, "Accepted"),
+    ("no_content", 204, "No content"),
+    ("bad_request", 400, "Bad request"),
+    ("forbidden", 403, "Forbidden"),
+    ("not_found", 404, "Not found"),
+    ("conflict", 409, "Conflict"),
+    ("unprocessable", 422, "Unprocessable entity"),
+];
+
+/// Check the parenthesized entries of `responses(...)`: each needs a `status`
+/// and entries are separated by commas. Other entries (`IntoResponses` types)
+/// are left to utoipa.
+fn check_responses(tokens: &TokenStream) -> syn::Result<()> {
+    use proc_macro2::{Delimiter, TokenTree};
+
+    let mut segment: Vec<TokenTree> = Vec::new();
+    let tokens = tokens.clone().into_iter().map(Some).chain([None]);
+    for tt in tokens {
+        match tt {
+            Some(TokenTree::Punct(p)) if p.as_char() == ',' => {}
+            Some(tt) => {
+                segment.push(tt);
+                continue;
+            }
+            None => {}
+        }
+        if let Some(TokenTree::Group(group)) = segment.first()
+            && group.delimiter() == Delimiter::Parenthesis
+        {
+            if let Some(extra) = segment.get(1) {
+                return Err(syn::Error::new_spanned(
+                    extra,
+                    "expected `,` between responses",
+                ));
+            }
+            let has_status = group
+                .stream()
+                .into_iter()
+                .any(|tt| matches!(&tt, TokenTree::Ident(ident) if ident == "status"));
+            if !has_status {
+                return Err(syn::Error::new(
+                    group.span(),
+                    "response is missing `status = ...`",
+                ));
+            }
+        }
+        segment.clear();
+    }
+    Ok(())
+}
+
+/// `StatusCode` constants accepted by utoipa as `status = NOT_FOUND`
+const STATUS_NAMES: &[(u16, &str)] = &[
+    (100, "CONTINUE"),
+    (101, "SWITCHING_PROTOCOLS"),
+    (102, "PROCESSING"),
+    (200, "OK"),
+    (201, "CREATED"),
+    (202, "ACCEPTED"),
+    (203, "NON_AUTHORITATIVE_INFORMATION"),
+    (204, "NO_CONTENT"),
+    (205, "RESET_CONTENT"),
+    (206, "PARTIAL_CONTENT"),
+    (207, "MULTI_STATUS"),
+    (208, "ALREADY_REPORTED"),
+    (226, "IM_USED"),
+    (300, "MULTIPLE_CHOICES"),
+    (301, "MOVED_PERMANENTLY"),
+    (302, "FOUND"),
+    (303, "SEE_OTHER"),
+    (304, "NOT_MODIFIED"),
+    (305, "USE_PROXY"),
+    (307, "TEMPORARY_REDIRECT"),
+    (308, "PERMANENT_REDIRECT"),
+    (400, "BAD_REQUEST"),
+    (401, "UNAUTHORIZED"),
+    (402, "PAYMENT_REQUIRED"),
+    (403, "FORBIDDEN"),
+    (404, "NOT_FOUND"),
+    (405, "METHOD_NOT_ALLOWED"),
+    (406, "NOT_ACCEPTABLE"),
+    (407, "PROXY_AUTHENTICATION_REQUIRED"),
+    (408, "REQUEST_TIMEOUT"),
+    (409, "CONFLICT"),
+    (410, "GONE"),
+    (411, "LENGTH_REQUIRED"),
+    (412, "PRECONDITION_FAILED"),
+    (413, "PAYLOAD_TOO_LARGE"),
+    (414, "URI_TOO_LONG"),
+    (415, "UNSUPPORTED_MEDIA_TYPE"),
+    (416, "RANGE_NOT_SATISFIABLE"),
+    (417, "EXPECTATION_FAILED"),
+    (418, "IM_A_TEAPOT"),
+    (421, "MISDIRECTED_REQUEST"),
+    (422, "UNPROCESSABLE_ENTITY"),
+    (423, "LOCKED"),
+    (424, "FAILED_DEPENDENCY"),
+    (426, "UPGRADE_REQUIRED"),
+    (428, "PRECONDITION_REQUIRED"),
+    (429, "TOO_MANY_REQUESTS"),
+    (431, "REQUEST_HEADER_FIELDS_TOO_LARGE"),
+    (451, "UNAVAILABLE_FOR_LEGAL_REASONS"),
+    (500, "INTERNAL_SERVER_ERROR"),
+    (501, "NOT_IMPLEMENTED"),
+    (502, "BAD_GATEWAY"),
+    (503, "SERVICE_UNAVAILABLE"),
+    (504, "GATEWAY_TIMEOUT"),
+    (505, "HTTP_VERSION_NOT_SUPPORTED"),
+    (506, "VARIANT_ALSO_NEGOTIATES"),
+    (507, "INSUFFICIENT_STORAGE"),
+    (508, "LOOP_DETECTED"),
+    (510, "NOT_EXTENDED"),
+    (511, "NETWORK_AUTHENTICATION_REQUIRED"),
+];
+
+/// Name of the `StatusCode` constant for `status`, if http defines one
+pub fn status_name(status: u16) -> Option<&'static str> {
+    STATUS_NAMES
+        .iter()
+        .find(|(code, _)| *code == status)
+        .map(|(_, name)| *name)
+}
+
+/// Statuses documented by the parenthesized entries of `responses(...)`, given as a number or a
+/// `StatusCode` constant; ranges (`"4XX"`) and `IntoResponses` types are left out
+pub fn documented_statuses(tokens: &TokenStream) -> Vec<u16> {
+    use proc_macro2::{Delimiter, TokenTree};
+
+    tokens
+        .clone()
+        .into_iter()
+        .filter_map(|tt| match tt {
+            TokenTree::Group(group) if group.delimiter() == Delimiter::Parenthesis => {
+                Some(group.stream().into_iter().collect::<Vec<_>>())
+            }
+            _ => None,
+        })
+        .filter_map(|entry| {
+            // The value follows `status =` and runs to the next comma
+            let start = entry.windows(2).position(|pair| {
+                matches!(&pair[0], TokenTree::Ident(ident) if ident == "status")
+                    && matches!(&pair[1], TokenTree::Punct(p) if p.as_char() == '=')
+            })? + 2;
+            let value: Vec<_> = entry[start..]
+                .iter()
+                .take_while(|tt| !matches!(tt, TokenTree::Punct(p) if p.as_char() == ','))
+                .collect();
+            match value.as_slice() {
+                [TokenTree::Literal(lit)] => lit.to_string().parse().ok(),
+                // `StatusCode::NOT_FOUND` or `NOT_FOUND`
+                [.., TokenTree::Ident(ident)] => STATUS_NAMES
+                    .iter()
+                    .find(|(_, name)| ident == name)
+                    .map(|(status, _)| *status),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Remove the `middleware = ...` argument from the controller args.
+///
+/// Conditional entries (`auth if not(test)`) are not valid expressions, so
+/// they cannot go through darling and are parsed here instead.
+pub fn split_middleware_arg(args: TokenStream) -> syn::Result<(TokenStream, Vec<MiddlewareSpec>)> {
+    use proc_macro2::TokenTree;
+
+    let mut segments: Vec<Vec<TokenTree>> = vec![Vec::new()];
+    for tt in args {
+        match &tt {
+            TokenTree::Punct(p) if p.as_char() == ',' => segments.push(Vec::new()),
+            _ => {
+                if let Some(segment) = segments.last_mut() {
+                    segment.push(tt);
+                }
+            }
+        }
+    }
+
+    let mut rest = TokenStream::new();
+    let mut middleware = Vec::new();
+    for segment in segments {
+        let is_middleware = matches!(
+            (segment.first(), segment.get(1)),
+            (Some(TokenTree::Ident(id)), Some(TokenTree::Punct(eq)))
+                if id == "middleware" && eq.as_char() == '='
+        );
+        if !is_middleware {
+            if !segment.is_empty() {
+                rest.extend(segment);
+                rest.extend(quote::quote! { , });
+            }
+            continue;
+        }
+
+        let value: TokenStream = segment.into_iter().skip(2).collect();
+        let parsed = match value.clone().into_iter().next() {
+            Some(TokenTree::Group(g)) if g.delimiter() == proc_macro2::Delimiter::Bracket => {
+                syn::parse::Parser::parse2(
+                    syn::punctuated::Punctuated::<MiddlewareSpec, Token![,]>::parse_terminated,
+                    g.stream(),
+                )?
+                .into_iter()
+                .collect()
+            }
+            _ => vec![syn::parse2::<MiddlewareSpec>(value)?],
+        };
+        middleware.extend(parsed);
+    }
+
+    Ok((rest, middleware))
+}
+
+/// HTTP method for a route
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HttpMethod {
+    Get,
+    Post,
+    Put,
+    Patch,
+    Delete,
+    Head,
+    Options,
+    Trace,
+    /// Any method (`axum::routing::any`); not representable in OpenAPI
+    Any,
+}
+
+impl HttpMethod {
+    pub fn from_ident(ident: &Ident) -> Option<Self> {
+        let s = ident.to_string().to_uppercase();
+        match s.as_str() {
+            "GET" => Some(Self::Get),
+            "POST" => Some(Self::Post),
+            "PUT" => Some(Self::Put),
+            "PATCH" => Some(Self::Patch),
+            "DELETE" => Some(Self::Delete),
+            "HEAD" => Some(Self::Head),
+            "OPTIONS" => Some(Self::Options),
+            "TRACE" => Some(Self::Trace),
+            "ANY" => Some(Self::Any),
+            _ => None,
+        }
+    }
+
+    /// Upper-case method name as it appears on the wire (e.g., "GET")
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Get => "GET",
+            Self::Post => "POST",
+            Self::Put => "PUT",
+            Self::Patch => "PATCH",
+            Self::Delete => "DELETE",
+            Self::Head => "HEAD",
+            Self::Options => "OPTIONS",
+            Self::Trace => "TRACE",
+            Self::Any => "ANY",
+        }
+    }
+
+    pub fn to_axum_method(self) -> &'static str {
+        match self {
+            Self::Get => "get",
+            Self::Post => "post",
+            Self::Put => "put",
+            Self::Patch => "patch",
+            Self::Delete => "delete",
+            Self::Head => "head",
+            Self::Options => "options",
+            Self::Trace => "trace",
+            Self::Any => "any",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LinkInfo {
+    pub rel: String,
+    pub href: String,
+    pub method: Option<String>,
+}
+
+/// A header documented on the success response of a route
+#[derive(Debug, Clone)]
+pub struct ResponseHeader {
+    pub name: LitStr,
+    pub description: Option<LitStr>,
+    /// `String` unless given
+    pub schema: syn::Type,
+}
+
+/// Renders the `utoipa` `headers(...)` entry, `("X-Request-Id" = String, description = "...")`
+impl ToTokens for ResponseHeader {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let name = &self.name;
+        let schema = &self.schema;
+        let description = self
+            .description
+            .as_ref()
+            .map(|description| quote::quote! { , description = #description });
+        tokens.extend(quote::quote! { (#name = #schema #description) });
+    }
+}
+
+/// An outbound event (webhook) triggered by a route
+#[derive(Debug, Clone)]
+pub struct EmitInfo {
+    pub event: String,
+    pub payload: syn::Type,
+}
+
+/// A domain event published to the state's `EventPublisher` after a successful mutation
+#[derive(Debug, Clone)]
+pub struct PublishInfo {
+    /// Event type, built with `From<&T>` of the response data or request body
+    pub event: syn::Type,
+    /// `"response"` or `"request"`
+    pub from: LitStr,
+    /// Name passed to the publisher, the event type's name unless given
+    pub name: String,
+}
+
+impl PublishInfo {
+    pub fn uses_request(&self) -> bool {
+        self.from.value() == "request"
+    }
+}
+
+/// Signed link verification settings of a route
+#[derive(Debug, Clone)]
+pub struct SignedInfo {
+    /// Query parameter carrying the hex HMAC-SHA256 signature
+    pub query: String,
+    /// Function taking `&State` and returning the key bytes
+    pub secret: Path,
+}
+
+/// Inbound request signature settings of a route
+#[derive(Debug, Clone)]
+pub struct VerifySignatureInfo {
+    /// Header carrying the hex HMAC-SHA256 of the body
+    pub header: String,
+    /// Function taking `&State` and returning the key bytes
+    pub secret: Path,
+}
+
+/// Where `mirror_to` sends request copies
+#[derive(Debug, Clone)]
+pub enum MirrorTarget {
+    /// Another handler of the same controller
+    Handler(Ident),
+    /// Base URL of an upstream service
+    Url(LitStr),
+}
+
+/// Authorization policy a request must satisfy
+#[derive(Debug, Clone)]
+pub enum Policy {
+    /// Checked by the `eywa_axum::Authorizer` in state
+    Named(LitStr),
+    /// A policy function, `async fn(&State, &[(&str, &str)], &Extensions) -> bool`
+    Fn(Path),
+}
+
+/// Bulk endpoint documentation of a route
+#[derive(Debug, Clone)]
+pub struct BulkInfo {
+    /// Request body, e.g. `Vec<CreateProject>`
+    pub body: syn::Type,
+    /// Status of responses mixing successes and failures (default 207)
+    pub partial_status: Option<syn::LitInt>,
+}
+
+/// Message payloads of a WebSocket or SSE route
+#[derive(Debug, Clone)]
+pub struct MessagesInfo {
+    /// Messages the server sends to the client
+    pub send: Option<syn::Type>,
+    /// Messages the client sends to the server (WebSocket only)
+    pub receive: Option<syn::Type>,
+}
+
+/// Streaming upload settings of a route
+#[derive(Debug, Clone, Default)]
+pub struct UploadInfo {
+    /// Media type of the body, `application/octet-stream` unless given
+    pub content_type: Option<LitStr>,
+    /// Size limit (`"1GB"`), enforced while the body streams in
+    pub max: Option<LitStr>,
+}
+
+impl UploadInfo {
+    pub fn content_type(&self) -> String {
+        self.content_type
+            .as_ref()
+            .map(LitStr::value)
+            .unwrap_or_else(|| "application/octet-stream".to_string())
+    }
+
+    /// The size limit in bytes
+    pub fn max_bytes(&self) -> syn::Result<Option<usize>> {
+        self.max.as_ref().map(parse_size_bytes).transpose()
+    }
 }
 
-/// Wrapper for a list of paths to support list syntax schemas(A, B)
-#[derive(Debug, Default)]
-pub struct PathList(pub Vec<Path>);
+/// Versioned representations a route serves by vendor media type
+#[derive(Debug, Clone)]
+pub struct MediaVersions {
+    /// `app` in `application/vnd.app.v2+json`
+    pub vendor: Option<LitStr>,
+    /// Version names and their types; the first is served to plain JSON requests
+    pub versions: Vec<(Ident, syn::Type)>,
+}
 
-impl FromMeta for PathList {
-    fn from_list(items: &[darling::ast::NestedMeta]) -> darling::Result<Self> {
-        let mut paths = Vec::new();
-        for item in items {
-            if let darling::ast::NestedMeta::Meta(syn::Meta::Path(path)) = item {
-                paths.push(path.clone());
-            } else {
-                return Err(darling::Error::custom("expected path").with_span(item));
-            }
-        }
-        Ok(PathList(paths))
+impl MediaVersions {
+    /// The media type of a version, `application/vnd.app.v2+json`
+    pub fn media_type(&self, version: &Ident) -> String {
+        let vendor = self
+            .vendor
+            .as_ref()
+            .map(LitStr::value)
+            .unwrap_or_else(|| "app".to_string());
+        format!("application/vnd.{}.{}+json", vendor, version)
     }
 }
 
-/// HTTP method for a route
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum HttpMethod {
-    Get,
-    Post,
-    Put,
-    Patch,
-    Delete,
-    Head,
-    Options,
-    Trace,
+/// Numeric bounds of a route's parameters
+#[derive(Debug, Clone, Default)]
+pub struct ParamConstraints {
+    pub items: Vec<ParamConstraint>,
+    /// Answer 400 before the handler when a bound is violated
+    pub enforce: bool,
 }
 
-impl HttpMethod {
-    pub fn from_ident(ident: &Ident) -> Option<Self> {
-        let s = ident.to_string().to_uppercase();
-        match s.as_str() {
-            "GET" => Some(Self::Get),
-            "POST" => Some(Self::Post),
-            "PUT" => Some(Self::Put),
-            "PATCH" => Some(Self::Patch),
-            "DELETE" => Some(Self::Delete),
-            "HEAD" => Some(Self::Head),
-            "OPTIONS" => Some(Self::Options),
-            "TRACE" => Some(Self::Trace),
-            _ => None,
-        }
-    }
+/// Inclusive bounds of a single path or query parameter
+#[derive(Debug, Clone)]
+pub struct ParamConstraint {
+    pub name: Ident,
+    pub minimum: Option<f64>,
+    pub maximum: Option<f64>,
+}
 
-    pub fn to_axum_method(&self) -> &'static str {
-        match self {
-            Self::Get => "get",
-            Self::Post => "post",
-            Self::Put => "put",
-            Self::Patch => "patch",
-            Self::Delete => "delete",
-            Self::Head => "head",
-            Self::Options => "options",
-            Self::Trace => "trace",
+/// Parses a possibly negative number literal
+fn parse_bound(input: ParseStream) -> syn::Result<f64> {
+    let negative = input.parse::<Option<Token![-]>>()?.is_some();
+    let value: f64 = match input.parse::<syn::Lit>()? {
+        syn::Lit::Int(lit) => lit.base10_parse()?,
+        syn::Lit::Float(lit) => lit.base10_parse()?,
+        other => return Err(syn::Error::new_spanned(other, "expected a number")),
+    };
+    Ok(if negative { -value } else { value })
+}
+
+/// Circuit breaker settings of a route
+#[derive(Debug, Clone)]
+pub struct CircuitBreakerInfo {
+    /// Share of 5xx responses within the window that opens the circuit
+    pub failure_ratio: syn::LitFloat,
+    /// Counting window, also the time the circuit stays open (`"30s"`)
+    pub window: LitStr,
+    /// Requests needed in the window before the ratio is considered
+    pub min_requests: Option<syn::LitInt>,
+    /// Fixed `Retry-After` (`"10s"`) instead of the time left until the circuit closes
+    pub retry_after: Option<LitStr>,
+}
+
+/// Validated circuit breaker settings
+pub struct CircuitBreakerSettings {
+    /// Share of 5xx responses opening the circuit, in (0, 1]
+    pub failure_ratio: f64,
+    /// Counting window and open duration, in milliseconds
+    pub window_ms: u64,
+    /// Requests needed in the window before the ratio is considered (default 10)
+    pub min_requests: u64,
+    /// Fixed `Retry-After` in whole seconds (rounded up), `None` for the time left until
+    /// the circuit closes
+    pub retry_after: Option<u64>,
+}
+
+impl CircuitBreakerInfo {
+    /// Validates the settings
+    pub fn settings(&self) -> syn::Result<CircuitBreakerSettings> {
+        let ratio: f64 = self.failure_ratio.base10_parse()?;
+        if !(ratio > 0.0 && ratio <= 1.0) {
+            return Err(syn::Error::new_spanned(
+                &self.failure_ratio,
+                "`failure_ratio` must be in (0, 1]",
+            ));
         }
+        let window_ms = parse_duration_ms(&self.window)?;
+        let min_requests = match &self.min_requests {
+            Some(min) => min.base10_parse()?,
+            None => 10,
+        };
+        Ok(CircuitBreakerSettings {
+            failure_ratio: ratio,
+            window_ms,
+            min_requests,
+            retry_after: self
+                .retry_after
+                .as_ref()
+                .map(parse_retry_after)
+                .transpose()?,
+        })
     }
 }
 
+/// Canary split of a route between two handlers
 #[derive(Debug, Clone)]
-pub struct LinkInfo {
-    pub rel: String,
-    pub href: String,
-    pub method: Option<String>,
+pub struct CanaryInfo {
+    pub stable: Ident,
+    pub canary: Ident,
+    /// Share of keys served by the canary
+    pub percent: u8,
+    /// Header whose value selects the variant (stable when absent)
+    pub key: String,
 }
 
 /// Parsed route information
@@ -132,7 +1590,7 @@ pub struct RouteInfo {
     /// Whether this route requires authentication (Extension<UserId>)
     pub security: bool,
 
-    /// Wrap in CollectionResponse
+    /// Wrap a `Json<Vec<T>>` response in `HateoasResponse<Vec<T>>`, with or without links
     pub collection: bool,
 
     /// Wrap in HateoasResponse
@@ -153,9 +1611,303 @@ pub struct RouteInfo {
 
     /// Raw content of responses(...) attribute, enabling merging
     pub responses: Option<TokenStream>,
+
+    /// Extractor types to skip in signature analysis
+    /// usage: ignore_params(MyExtractor, Other)
+    pub ignore_params: Vec<Path>,
+
+    /// Serve the `Vec<T>` response as a CSV download
+    /// usage: `csv` or `csv = "projects.csv"` (empty string means `{fn_name}.csv`)
+    pub csv: Option<String>,
+
+    /// Long-running job: respond 202 with a job status body
+    /// usage: `async_job` (uses `eywa_axum::JobStatus`) or `async_job = MyJobStatus`
+    pub async_job: Option<Path>,
+
+    /// Handler in the same controller serving the job status (`status_route = get_job`)
+    pub status_route: Option<Ident>,
+
+    /// Response field (`last_modified = updated_at`) or function of the response data
+    /// (`last_modified = my::modified_at`) providing the `Last-Modified` time
+    pub last_modified: Option<Path>,
+
+    /// Filter parameters parsed from the query string (`filterable(ProjectFilter)`);
+    /// the handler reads them as `Extension<ProjectFilter>`
+    pub filterable: Option<syn::Type>,
+
+    /// Fields accepted by the `sort` query parameter (`sortable = ["created_at", "name"]`)
+    pub sortable: Vec<String>,
+
+    /// Pagination mode; only `paginated = "cursor"` (handler returns `Json<CursorPage<T>>`)
+    pub paginated: Option<LitStr>,
+
+    /// Outbound events triggered by this route (repeatable):
+    /// `emits(event = "project.created", payload = ProjectCreated)`
+    pub emits: Vec<EmitInfo>,
+
+    /// Expiring signed links: `signed(query = "sig", secret = AppState::url_secret)`
+    pub signed: Option<SignedInfo>,
+
+    /// Inbound webhook signature check:
+    /// `verify_signature(header = "X-Hub-Signature-256", algo = "hmac-sha256", secret = AppState::webhook_secret)`
+    pub verify_signature: Option<VerifySignatureInfo>,
+
+    /// Fire-and-forget copy of each request to another handler of the controller
+    /// (`mirror_to = list_v2`) or an upstream (`mirror_to = "http://shadow:8080"`)
+    pub mirror_to: Option<MirrorTarget>,
+
+    /// Percentage of requests mirrored by `mirror_to` (default 100)
+    pub mirror_percent: Option<syn::LitInt>,
+
+    /// Traffic split between two handlers of the controller:
+    /// `canary(stable = list_v1, canary = list_v2, percent = 5, key = "X-User-Id")`
+    pub canary: Option<CanaryInfo>,
+
+    /// Upstream base URL the route forwards to (`proxy = "http://legacy:8080"`)
+    pub proxy: Option<LitStr>,
+
+    /// Encodings offered for this route's responses (`compress = "gzip,br"`)
+    pub compress: Option<LitStr>,
+
+    /// Keep responses of this route out of compression (`no_compress`)
+    pub no_compress: bool,
+
+    /// Serve single byte-range requests (`Range: bytes=...`) with 206 responses
+    pub ranges: Option<RangeOptions>,
+
+    /// Structured request logging, overriding the controller's `log(...)`
+    pub log: Option<LogOptions>,
+
+    /// Audit record of the request on the `eywa_axum::audit` target, with `sensitive_params`
+    /// redacted from its path parameters and body
+    pub audit: bool,
+
+    /// Fields redacted from request logs and audit records and marked `x-sensitive`
+    /// (`sensitive_params = ["password"]`)
+    pub sensitive_params: Vec<String>,
+
+    /// Latency above which a warning is logged (`slow_warn = "500ms"`)
+    pub slow_warn: Option<LitStr>,
+
+    /// Fail fast with 503 while the route's failure rate is high
+    pub circuit_breaker: Option<CircuitBreakerInfo>,
+
+    /// Bulk endpoint documentation (`bulk(body = Vec<CreateProject>, partial_status = 207)`)
+    pub bulk: Option<BulkInfo>,
+
+    /// PATCH body format, `"merge"` (RFC 7396) or `"json-patch"` (RFC 6902)
+    pub patch_format: Option<LitStr>,
+
+    /// Reject PUT/PATCH requests without `If-Match` (428), documenting 412
+    pub requires_if_match: bool,
+
+    /// Message payloads of a WebSocket/SSE route (`messages(send = Event, receive = Command)`)
+    pub messages: Option<MessagesInfo>,
+
+    /// Formats served by `Accept` (`negotiate(json, csv, xml)`)
+    pub negotiate: Vec<Ident>,
+
+    /// Whether routes with their own `tag`/`tags` also get the controller tag
+    /// (`inherit_tag = false` opts out; default true)
+    pub inherit_tag: Option<syn::LitBool>,
+
+    /// How long generated clients may cache GET responses (`sdk_cache_ttl = "60s"`)
+    pub sdk_cache_ttl: Option<LitStr>,
+
+    /// OpenAPI operationId (`operation_id = "listProjects"`, default: the handler name)
+    pub operation_id: Option<LitStr>,
+
+    /// Path derived from the handler name (`path = auto`), filled in by the macros
+    pub auto_path: bool,
+
+    /// Schema of a `multipart/form-data` request body: `multipart(schema = UploadForm)`
+    pub multipart: Option<syn::Type>,
+
+    /// Accept a `Json`/`Form` body on a GET or DELETE route
+    pub allow_get_body: bool,
+
+    /// Fraction of requests traced with a `request` span: `trace_sample = 0.1`
+    pub trace_sample: Option<syn::LitFloat>,
+
+    /// Statuses documented by shorthand response attributes (`created = Project`, `not_found`, ...),
+    /// already merged into `responses`
+    pub shorthand_statuses: Vec<u16>,
+
+    /// Example request body: a JSON string literal or a path to a `Serialize` const
+    pub example_request: Option<Expr>,
+
+    /// Example success response body: a JSON string literal or a path to a `Serialize` const
+    pub example_response: Option<Expr>,
+
+    /// Headers of the success response
+    /// usage: headers( (name = "X-Request-Id", description = "...", schema = String), ... )
+    pub response_headers: Vec<ResponseHeader>,
+
+    /// Streaming upload: `upload(content_type = "application/octet-stream", max = "1GB")`
+    pub upload: Option<UploadInfo>,
+
+    /// Numeric bounds of path and query parameters, enforced with a 400 when `enforce` is listed
+    /// usage: param_constraints( (id, minimum = 1), (page, maximum = 1000), enforce )
+    pub param_constraints: ParamConstraints,
+
+    /// Success status replacing the inferred 200, `status = 201`
+    pub status: Option<u16>,
+
+    /// `Location` of the resource a POST creates, `location = "/projects/{id}"`: placeholders are
+    /// fields of the returned data
+    pub location: Option<LitStr>,
+
+    /// String formats of path and query parameters, overriding the detected ones
+    /// usage: param_formats(id = "uuid", since = "date-time")
+    pub param_formats: Vec<(Ident, LitStr)>,
+
+    /// Representations chosen by `Accept`, converted from the handler data with `From`
+    /// usage: media_versions((v1 = ProjectV1, v2 = ProjectV2), vendor = "app")
+    pub media_versions: Option<MediaVersions>,
+
+    /// Event published after the handler succeeds; also documented like `emits`
+    /// usage: publish(event = ProjectCreated, from = "response", name = "project.created")
+    pub publish: Option<PublishInfo>,
+
+    /// Guard run before the handler, `guard = auth::owns_project`: an async fn taking the request
+    /// parts and the state, returning `Result<(), Response>`
+    pub guard: Option<syn::Path>,
+
+    /// Status the guard rejects with, documented on the route (403 unless given)
+    pub guard_status: u16,
+
+    /// Permissions the caller must hold, `require = ["admin", "billing:read"]`; implies `security`
+    pub require: Vec<String>,
+
+    /// Run the handler in a transaction begun from the `eywa_axum::TransactionFactory` in state,
+    /// committed on 2xx and rolled back otherwise
+    pub transactional: bool,
+
+    /// Scopes of the bearer requirement, `security = ["projects:read"]`
+    pub security_scopes: Vec<String>,
+
+    /// On GET routes, hand the data layer an `eywa_axum::DbRouting::Replica` extension so it reads
+    /// from a replica; documented as `x-read-only`
+    pub read_only: bool,
+
+    /// Scheme of the security requirement, `security_scheme = "api_key"`; implies `security`
+    pub security_scheme: Option<LitStr>,
+
+    /// Authorization policy checked with the path parameters and request extensions, answering
+    /// 403 when denied: `policy = "project:read"` or `policy = policies::owns_project`
+    pub policy: Option<Policy>,
+
+    /// Session cookie authenticating the route, `security = cookie("session_id")`; implies `security`
+    pub security_cookie: Option<LitStr>,
+
+    /// Authentication is optional: the operation also lists an empty security requirement and no
+    /// automatic 401, `security_optional`; implies `security`
+    pub security_optional: bool,
+
+    /// Raw `utoipa::path` content appended after the generated attributes,
+    /// `openapi(append = { extensions(...), ... })`; checked to be a list of attribute items
+    pub openapi_append: Option<TokenStream>,
 }
 
 impl RouteInfo {
+    /// Scheme of the route's security requirement (`"bearer"` unless set on the route or
+    /// controller, `"cookie"` for session cookies), or `None` when the route is public
+    pub fn security_requirement(&self, controller_args: &ControllerArgs) -> Option<String> {
+        let required = self.security
+            || self.security_optional
+            || controller_args.security.is_some()
+            || self.security_scheme.is_some()
+            || controller_args.security_scheme.is_some()
+            || !self.require.is_empty();
+        required.then(|| match (&self.security_scheme, &self.security_cookie) {
+            (Some(scheme), _) => scheme.value(),
+            (None, Some(_)) => "cookie".to_string(),
+            (None, None) => controller_args.security_scheme_name(),
+        })
+    }
+
+    /// Success status replacing the inferred 200: the route's `status`, 201 with `location`,
+    /// otherwise 201 for POST and 204 for DELETE with `rest_conventions`. Shorthand 2xx responses and async jobs keep
+    /// their own status.
+    pub fn success_status(&self, rest_conventions: bool) -> Option<u16> {
+        if self.status.is_some() {
+            return self.status;
+        }
+        if self.location.is_some() {
+            return Some(201);
+        }
+        if !rest_conventions
+            || self.async_job.is_some()
+            || self
+                .shorthand_statuses
+                .iter()
+                .any(|status| (200..300).contains(status))
+        {
+            return None;
+        }
+        match self.method {
+            HttpMethod::Post => Some(201),
+            HttpMethod::Delete => Some(204),
+            _ => None,
+        }
+    }
+
+    /// OpenAPI operationId: the `operation_id` attribute or the handler name
+    pub fn operation_id(&self, fn_name: &Ident) -> String {
+        self.operation_id
+            .as_ref()
+            .map_or_else(|| fn_name.to_string(), LitStr::value)
+    }
+
+    /// Derive the path of a `path = auto` route from its handler name
+    /// (`list_archived` becomes `/list-archived` in kebab case)
+    pub fn apply_auto_path(&mut self, fn_name: &Ident, case: Case) {
+        if self.auto_path {
+            self.path = format!("/{}", fn_name.to_string().to_case(case));
+        }
+    }
+
+    /// Fill in a missing `summary` (first line) and `description` (remaining
+    /// lines) from the handler's `///` comments, as `#[utoipa::path]` does
+    pub fn apply_doc_comments(&mut self, attrs: &[syn::Attribute]) {
+        let lines: Vec<String> = attrs
+            .iter()
+            .filter_map(|attr| match &attr.meta {
+                syn::Meta::NameValue(nv) if nv.path.is_ident("doc") => match &nv.value {
+                    Expr::Lit(syn::ExprLit {
+                        lit: syn::Lit::Str(s),
+                        ..
+                    }) => Some(s.value()),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .flat_map(|doc| {
+                doc.lines()
+                    .map(|line| {
+                        line.strip_prefix(' ')
+                            .unwrap_or(line)
+                            .trim_end()
+                            .to_string()
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .skip_while(|line| line.trim().is_empty())
+            .collect();
+        let Some((first, rest)) = lines.split_first() else {
+            return;
+        };
+
+        if self.summary.is_none() {
+            self.summary = Some(first.trim().to_string());
+        }
+        let rest = rest.join("\n");
+        let rest = rest.trim();
+        if self.description.is_none() && !rest.is_empty() {
+            self.description = Some(rest.to_string());
+        }
+    }
+
     /// Parse route attributes from tokens
     pub fn parse(tokens: TokenStream) -> syn::Result<Self> {
         struct RouteAttr {
@@ -172,6 +1924,63 @@ impl RouteInfo {
             other_attrs: Vec<(Ident, TokenStream)>,
             links: Vec<LinkInfo>,
             responses: Option<TokenStream>,
+            ignore_params: Vec<Path>,
+            csv: Option<String>,
+            async_job: Option<Path>,
+            status_route: Option<Ident>,
+            last_modified: Option<Path>,
+            filterable: Option<syn::Type>,
+            sortable: Vec<String>,
+            paginated: Option<LitStr>,
+            emits: Vec<EmitInfo>,
+            signed: Option<SignedInfo>,
+            verify_signature: Option<VerifySignatureInfo>,
+            mirror_to: Option<MirrorTarget>,
+            mirror_percent: Option<syn::LitInt>,
+            canary: Option<CanaryInfo>,
+            proxy: Option<LitStr>,
+            compress: Option<LitStr>,
+            no_compress: bool,
+            ranges: Option<RangeOptions>,
+            log: Option<LogOptions>,
+            sensitive_params: Vec<String>,
+            slow_warn: Option<LitStr>,
+            circuit_breaker: Option<CircuitBreakerInfo>,
+            bulk: Option<BulkInfo>,
+            patch_format: Option<LitStr>,
+            requires_if_match: bool,
+            messages: Option<MessagesInfo>,
+            negotiate: Vec<Ident>,
+            inherit_tag: Option<syn::LitBool>,
+            sdk_cache_ttl: Option<LitStr>,
+            operation_id: Option<LitStr>,
+            auto_path: bool,
+            multipart: Option<syn::Type>,
+            allow_get_body: bool,
+            trace_sample: Option<syn::LitFloat>,
+            shorthand_statuses: Vec<u16>,
+            example_request: Option<Expr>,
+            example_response: Option<Expr>,
+            response_headers: Vec<ResponseHeader>,
+            upload: Option<UploadInfo>,
+            param_constraints: ParamConstraints,
+            status: Option<u16>,
+            location: Option<LitStr>,
+            param_formats: Vec<(Ident, LitStr)>,
+            media_versions: Option<MediaVersions>,
+            publish: Option<PublishInfo>,
+            guard: Option<syn::Path>,
+            guard_status: u16,
+            require: Vec<String>,
+            transactional: bool,
+            security_scopes: Vec<String>,
+            read_only: bool,
+            security_scheme: Option<LitStr>,
+            policy: Option<Policy>,
+            security_cookie: Option<LitStr>,
+            security_optional: bool,
+            openapi_append: Option<TokenStream>,
+            audit: bool,
         }
 
         impl Parse for RouteAttr {
@@ -181,9 +1990,21 @@ impl RouteInfo {
                 let method = HttpMethod::from_ident(&method_ident)
                     .ok_or_else(|| syn::Error::new_spanned(&method_ident, "Invalid HTTP method"))?;
 
-                // Parse path string
-                let path_lit: LitStr = input.parse()?;
-                let path = path_lit.value();
+                // Parse path string, unless derived from the handler name (`path = auto`)
+                let path_lit: Option<LitStr> = if input.peek(LitStr) {
+                    Some(input.parse()?)
+                } else {
+                    None
+                };
+                let path = match &path_lit {
+                    Some(lit) => {
+                        validate_path("route path", &lit.value())
+                            .map_err(|msg| syn::Error::new_spanned(lit, msg))?;
+                        // `:id` (axum 0.7) and `{id}` (axum 0.8) are both accepted
+                        normalize_params(&lit.value())
+                    }
+                    None => String::new(),
+                };
 
                 let mut summary = None;
                 let mut description = None;
@@ -196,6 +2017,64 @@ impl RouteInfo {
                 let mut other_attrs = Vec::new();
                 let mut links = Vec::new();
                 let mut responses = None;
+                let mut ignore_params: Vec<Path> = Vec::new();
+                let mut csv: Option<String> = None;
+                let mut async_job: Option<Path> = None;
+                let mut status_route: Option<Ident> = None;
+                let mut last_modified: Option<Path> = None;
+                let mut filterable: Option<syn::Type> = None;
+                let mut sortable: Vec<String> = Vec::new();
+                let mut paginated: Option<LitStr> = None;
+                let mut emits: Vec<EmitInfo> = Vec::new();
+                let mut signed: Option<SignedInfo> = None;
+                let mut verify_signature: Option<VerifySignatureInfo> = None;
+                let mut mirror_to: Option<MirrorTarget> = None;
+                let mut mirror_percent: Option<syn::LitInt> = None;
+                let mut canary: Option<CanaryInfo> = None;
+                let mut proxy: Option<LitStr> = None;
+                let mut compress: Option<LitStr> = None;
+                let mut no_compress: bool = false;
+                let mut ranges: Option<RangeOptions> = None;
+                let mut log: Option<LogOptions> = None;
+                let mut sensitive_params: Vec<String> = Vec::new();
+                let mut slow_warn: Option<LitStr> = None;
+                let mut circuit_breaker: Option<CircuitBreakerInfo> = None;
+                let mut bulk: Option<BulkInfo> = None;
+                let mut patch_format: Option<LitStr> = None;
+                let mut requires_if_match: bool = false;
+                let mut messages: Option<MessagesInfo> = None;
+                let mut negotiate: Vec<Ident> = Vec::new();
+                let mut inherit_tag: Option<syn::LitBool> = None;
+                let mut sdk_cache_ttl: Option<LitStr> = None;
+                let mut operation_id: Option<LitStr> = None;
+                let mut auto_path: bool = false;
+                let mut multipart: Option<syn::Type> = None;
+                let mut allow_get_body: bool = false;
+                let mut trace_sample: Option<syn::LitFloat> = None;
+                let mut shorthand_statuses: Vec<u16> = Vec::new();
+                let mut shorthand: Vec<TokenStream> = Vec::new();
+                let mut example_request: Option<Expr> = None;
+                let mut example_response: Option<Expr> = None;
+                let mut response_headers: Vec<ResponseHeader> = Vec::new();
+                let mut upload: Option<UploadInfo> = None;
+                let mut param_constraints: ParamConstraints = ParamConstraints::default();
+                let mut status: Option<u16> = None;
+                let mut location: Option<LitStr> = None;
+                let mut param_formats: Vec<(Ident, LitStr)> = Vec::new();
+                let mut media_versions: Option<MediaVersions> = None;
+                let mut publish: Option<PublishInfo> = None;
+                let mut guard: Option<syn::Path> = None;
+                let mut guard_status: u16 = 403;
+                let mut require: Vec<String> = Vec::new();
+                let mut transactional: bool = false;
+                let mut security_scopes: Vec<String> = Vec::new();
+                let mut read_only: bool = false;
+                let mut security_scheme: Option<LitStr> = None;
+                let mut policy: Option<Policy> = None;
+                let mut security_cookie: Option<LitStr> = None;
+                let mut security_optional: bool = false;
+                let mut openapi_append: Option<TokenStream> = None;
+                let mut audit: bool = false;
 
                 // Parse optional key=value pairs
                 while input.peek(Token![,]) {
@@ -207,86 +2086,1000 @@ impl RouteInfo {
 
                     let key: Ident = input.parse()?;
                     let key_str = key.to_string();
+                    // Errors about a whole argument point at its key
+                    let key_span = key.span();
 
                     match key_str.as_str() {
                         "summary" => {
                             let _: Token![=] = input.parse()?;
-                            let val: LitStr = input.parse()?;
-                            summary = Some(val.value());
+                            let val: LitStr = input.parse()?;
+                            summary = Some(val.value());
+                        }
+                        "description" => {
+                            let _: Token![=] = input.parse()?;
+                            let val: LitStr = input.parse()?;
+                            description = Some(val.value());
+                        }
+                        "deprecated" => {
+                            deprecated = true;
+                        }
+                        "security" => {
+                            security = true;
+                            if input.peek(Token![=]) {
+                                let _: Token![=] = input.parse()?;
+                                if input.peek(Ident) {
+                                    let form: Ident = input.parse()?;
+                                    if form != "cookie" {
+                                        return Err(syn::Error::new_spanned(
+                                            form,
+                                            "expected `cookie(\"name\")` or a list of scopes",
+                                        ));
+                                    }
+                                    let content;
+                                    syn::parenthesized!(content in input);
+                                    security_cookie = Some(content.parse()?);
+                                } else {
+                                    let content;
+                                    syn::bracketed!(content in input);
+                                    let scopes = content.parse_terminated(
+                                        |scope| scope.parse::<LitStr>(),
+                                        Token![,],
+                                    )?;
+                                    security_scopes = scopes.iter().map(LitStr::value).collect();
+                                }
+                            }
+                        }
+                        "collection" => {
+                            collection = true;
+                        }
+                        "hateoas" => {
+                            hateoas = true;
+                        }
+                        "tag" => {
+                            let _: Token![=] = input.parse()?;
+                            let val: LitStr = input.parse()?;
+                            tag = Some(val.value());
+                        }
+                        "tags" => {
+                            let _: Token![=] = input.parse()?;
+                            let content;
+                            syn::bracketed!(content in input);
+                            let mut tag_list = Vec::new();
+                            while !content.is_empty() {
+                                let tag_val: LitStr = content.parse()?;
+                                if tag_val.value().is_empty() {
+                                    return Err(syn::Error::new_spanned(
+                                        &tag_val,
+                                        "tag names cannot be empty",
+                                    ));
+                                }
+                                tag_list.push(tag_val.value());
+                                if !content.is_empty() {
+                                    let _: Token![,] = content.parse()?;
+                                }
+                            }
+                            tags = Some(tag_list);
+                        }
+                        "links" => {
+                            let content;
+                            syn::parenthesized!(content in input);
+                            let mut link_list = Vec::new();
+                            while !content.is_empty() {
+                                let inner;
+                                let link = syn::parenthesized!(inner in content);
+                                let mut rel = None;
+                                let mut href = None;
+                                let mut method = None;
+
+                                while !inner.is_empty() {
+                                    let key: Ident = inner.parse()?;
+                                    let _: Token![=] = inner.parse()?;
+                                    let val: LitStr = inner.parse()?;
+                                    match key.to_string().as_str() {
+                                        "rel" => rel = Some(val.value()),
+                                        "href" => href = Some(val.value()),
+                                        "method" => method = Some(val.value()),
+                                        other => {
+                                            return Err(syn::Error::new_spanned(
+                                                &key,
+                                                format!(
+                                                    "unknown link key `{}`, expected `rel`, `href` or `method`",
+                                                    other
+                                                ),
+                                            ));
+                                        }
+                                    }
+                                    if !inner.is_empty() {
+                                        let _: Token![,] = inner.parse()?;
+                                    }
+                                }
+                                let (Some(rel), Some(href)) = (rel, href) else {
+                                    return Err(syn::Error::new(
+                                        link.span.join(),
+                                        "a link requires `rel = \"...\"` and `href = \"...\"`",
+                                    ));
+                                };
+                                link_list.push(LinkInfo { rel, href, method });
+                                if !content.is_empty() {
+                                    let _: Token![,] = content.parse()?;
+                                }
+                            }
+                            links = link_list;
+                        }
+                        "responses" => {
+                            let content;
+                            syn::parenthesized!(content in input);
+                            let val: TokenStream = content.parse()?;
+                            check_responses(&val)?;
+                            responses = Some(val);
+                        }
+                        "ignore_params" => {
+                            let content;
+                            syn::parenthesized!(content in input);
+                            let paths: syn::punctuated::Punctuated<Path, Token![,]> =
+                                content.parse_terminated(Path::parse, Token![,])?;
+                            ignore_params = paths.into_iter().collect();
+                        }
+                        "csv" => {
+                            if input.peek(Token![=]) {
+                                let _: Token![=] = input.parse()?;
+                                let val: LitStr = input.parse()?;
+                                // Quoted as is in `Content-Disposition`, which only takes ASCII
+                                if val.value().chars().any(|c| {
+                                    matches!(c, '"' | '\\') || c.is_control() || !c.is_ascii()
+                                }) {
+                                    return Err(syn::Error::new_spanned(
+                                        &val,
+                                        "the CSV filename can't contain `\"`, `\\`, control or \
+                                         non-ASCII characters",
+                                    ));
+                                }
+                                csv = Some(val.value());
+                            } else {
+                                csv = Some(String::new());
+                            }
+                        }
+                        "async_job" => {
+                            if input.peek(Token![=]) {
+                                let _: Token![=] = input.parse()?;
+                                async_job = Some(input.parse()?);
+                            } else {
+                                async_job = Some(syn::parse_quote!(eywa_axum::JobStatus));
+                            }
+                        }
+                        "status_route" => {
+                            let _: Token![=] = input.parse()?;
+                            status_route = Some(input.parse()?);
+                        }
+                        "last_modified" => {
+                            let _: Token![=] = input.parse()?;
+                            last_modified = Some(input.parse()?);
+                        }
+                        "filterable" => {
+                            let content;
+                            syn::parenthesized!(content in input);
+                            filterable = Some(content.parse()?);
+                        }
+                        "sortable" => {
+                            let _: Token![=] = input.parse()?;
+                            let content;
+                            syn::bracketed!(content in input);
+                            let fields: syn::punctuated::Punctuated<LitStr, Token![,]> =
+                                content.parse_terminated(|p| p.parse::<LitStr>(), Token![,])?;
+                            sortable = fields.iter().map(LitStr::value).collect();
+                        }
+                        "paginated" => {
+                            let _: Token![=] = input.parse()?;
+                            paginated = Some(input.parse()?);
+                        }
+                        "emits" => {
+                            let content;
+                            syn::parenthesized!(content in input);
+                            let mut event = None;
+                            let mut payload = None;
+                            while !content.is_empty() {
+                                let key: Ident = content.parse()?;
+                                let _: Token![=] = content.parse()?;
+                                match key.to_string().as_str() {
+                                    "event" => event = Some(content.parse::<LitStr>()?.value()),
+                                    "payload" => payload = Some(content.parse::<syn::Type>()?),
+                                    other => {
+                                        return Err(syn::Error::new_spanned(
+                                            &key,
+                                            format!("unknown `emits` key `{}`", other),
+                                        ));
+                                    }
+                                }
+                                if !content.is_empty() {
+                                    let _: Token![,] = content.parse()?;
+                                }
+                            }
+                            match (event, payload) {
+                                (Some(event), Some(payload)) => {
+                                    emits.push(EmitInfo { event, payload })
+                                }
+                                _ => {
+                                    return Err(syn::Error::new(
+                                        key_span,
+                                        "`emits` requires both `event = \"...\"` and `payload = Type`",
+                                    ));
+                                }
+                            }
+                        }
+                        "signed" => {
+                            let content;
+                            syn::parenthesized!(content in input);
+                            let mut query = "sig".to_string();
+                            let mut secret = None;
+                            while !content.is_empty() {
+                                let key: Ident = content.parse()?;
+                                let _: Token![=] = content.parse()?;
+                                match key.to_string().as_str() {
+                                    "query" => query = content.parse::<LitStr>()?.value(),
+                                    "secret" => secret = Some(content.parse::<Path>()?),
+                                    other => {
+                                        return Err(syn::Error::new_spanned(
+                                            &key,
+                                            format!("unknown `signed` key `{}`", other),
+                                        ));
+                                    }
+                                }
+                                if !content.is_empty() {
+                                    let _: Token![,] = content.parse()?;
+                                }
+                            }
+                            let Some(secret) = secret else {
+                                return Err(syn::Error::new(
+                                    key_span,
+                                    "`signed` requires `secret = path::to_fn`",
+                                ));
+                            };
+                            signed = Some(SignedInfo { query, secret });
+                        }
+                        "verify_signature" => {
+                            let content;
+                            syn::parenthesized!(content in input);
+                            let mut header = None;
+                            let mut secret = None;
+                            while !content.is_empty() {
+                                let key: Ident = content.parse()?;
+                                let _: Token![=] = content.parse()?;
+                                match key.to_string().as_str() {
+                                    "header" => header = Some(content.parse::<LitStr>()?.value()),
+                                    "algo" => {
+                                        let algo: LitStr = content.parse()?;
+                                        if algo.value() != "hmac-sha256" {
+                                            return Err(syn::Error::new_spanned(
+                                                &algo,
+                                                "only `algo = \"hmac-sha256\"` is supported",
+                                            ));
+                                        }
+                                    }
+                                    "secret" => secret = Some(content.parse::<Path>()?),
+                                    other => {
+                                        return Err(syn::Error::new_spanned(
+                                            &key,
+                                            format!("unknown `verify_signature` key `{}`", other),
+                                        ));
+                                    }
+                                }
+                                if !content.is_empty() {
+                                    let _: Token![,] = content.parse()?;
+                                }
+                            }
+                            let (Some(header), Some(secret)) = (header, secret) else {
+                                return Err(syn::Error::new(
+                                    key_span,
+                                    "`verify_signature` requires `header = \"...\"` and `secret = path::to_fn`",
+                                ));
+                            };
+                            verify_signature = Some(VerifySignatureInfo { header, secret });
+                        }
+                        "mirror_to" => {
+                            let _: Token![=] = input.parse()?;
+                            mirror_to = Some(if input.peek(LitStr) {
+                                MirrorTarget::Url(input.parse()?)
+                            } else {
+                                MirrorTarget::Handler(input.parse()?)
+                            });
                         }
-                        "description" => {
+                        "mirror_percent" => {
                             let _: Token![=] = input.parse()?;
-                            let val: LitStr = input.parse()?;
-                            description = Some(val.value());
+                            let percent: syn::LitInt = input.parse()?;
+                            if percent.base10_parse::<u8>().map_or(true, |p| p > 100) {
+                                return Err(syn::Error::new_spanned(
+                                    &percent,
+                                    "expected a percentage (0-100)",
+                                ));
+                            }
+                            mirror_percent = Some(percent);
                         }
-                        "deprecated" => {
-                            deprecated = true;
+                        "canary" => {
+                            let content;
+                            syn::parenthesized!(content in input);
+                            let mut stable = None;
+                            let mut canary_handler = None;
+                            let mut percent = None;
+                            let mut key = None;
+                            while !content.is_empty() {
+                                let name: Ident = content.parse()?;
+                                let _: Token![=] = content.parse()?;
+                                match name.to_string().as_str() {
+                                    "stable" => stable = Some(content.parse::<Ident>()?),
+                                    "canary" => canary_handler = Some(content.parse::<Ident>()?),
+                                    "percent" => {
+                                        let lit: syn::LitInt = content.parse()?;
+                                        match lit.base10_parse::<u8>() {
+                                            Ok(p) if p <= 100 => percent = Some(p),
+                                            _ => {
+                                                return Err(syn::Error::new_spanned(
+                                                    &lit,
+                                                    "expected a percentage (0-100)",
+                                                ));
+                                            }
+                                        }
+                                    }
+                                    "key" => key = Some(content.parse::<LitStr>()?.value()),
+                                    other => {
+                                        return Err(syn::Error::new_spanned(
+                                            &name,
+                                            format!("unknown `canary` key `{}`", other),
+                                        ));
+                                    }
+                                }
+                                if !content.is_empty() {
+                                    let _: Token![,] = content.parse()?;
+                                }
+                            }
+                            let (Some(stable), Some(canary_handler), Some(percent)) =
+                                (stable, canary_handler, percent)
+                            else {
+                                return Err(syn::Error::new(
+                                    key_span,
+                                    "`canary` requires `stable = handler`, `canary = handler` and `percent = N`",
+                                ));
+                            };
+                            canary = Some(CanaryInfo {
+                                stable,
+                                canary: canary_handler,
+                                percent,
+                                key: key.unwrap_or_else(|| "X-User-Id".to_string()),
+                            });
                         }
-                        "security" => {
-                            security = true;
+                        "proxy" => {
+                            let _: Token![=] = input.parse()?;
+                            proxy = Some(input.parse()?);
                         }
-                        "collection" => {
-                            collection = true;
+                        "compress" => {
+                            let _: Token![=] = input.parse()?;
+                            compress = Some(input.parse()?);
                         }
-                        "hateoas" => {
-                            hateoas = true;
+                        "no_compress" => {
+                            no_compress = true;
                         }
-                        "tag" => {
-                            let _: Token![=] = input.parse()?;
-                            let val: LitStr = input.parse()?;
-                            tag = Some(val.value());
+                        "ranges" => {
+                            ranges = Some(if input.peek(syn::token::Paren) {
+                                let content;
+                                syn::parenthesized!(content in input);
+                                let items = NestedMeta::parse_meta_list(content.parse()?)?;
+                                RangeOptions::from_list(&items)?
+                            } else {
+                                RangeOptions::from_word()?
+                            });
                         }
-                        "tags" => {
+                        "log" => {
+                            log = Some(if input.peek(syn::token::Paren) {
+                                let content;
+                                syn::parenthesized!(content in input);
+                                let items = NestedMeta::parse_meta_list(content.parse()?)?;
+                                LogOptions::from_list(&items)?
+                            } else {
+                                LogOptions::from_word()?
+                            });
+                        }
+                        "sensitive_params" => {
                             let _: Token![=] = input.parse()?;
                             let content;
                             syn::bracketed!(content in input);
-                            let mut tag_list = Vec::new();
+                            let fields: syn::punctuated::Punctuated<LitStr, Token![,]> =
+                                content.parse_terminated(|p| p.parse::<LitStr>(), Token![,])?;
+                            sensitive_params = fields.iter().map(LitStr::value).collect();
+                        }
+                        "slow_warn" => {
+                            let _: Token![=] = input.parse()?;
+                            slow_warn = Some(input.parse()?);
+                        }
+                        "circuit_breaker" => {
+                            let content;
+                            syn::parenthesized!(content in input);
+                            let mut failure_ratio = None;
+                            let mut window = None;
+                            let mut min_requests = None;
+                            let mut retry_after = None;
                             while !content.is_empty() {
-                                let tag_val: LitStr = content.parse()?;
-                                tag_list.push(tag_val.value());
+                                let key: Ident = content.parse()?;
+                                let _: Token![=] = content.parse()?;
+                                match key.to_string().as_str() {
+                                    "failure_ratio" => failure_ratio = Some(content.parse()?),
+                                    "window" => window = Some(content.parse()?),
+                                    "min_requests" => min_requests = Some(content.parse()?),
+                                    "retry_after" => retry_after = Some(content.parse()?),
+                                    other => {
+                                        return Err(syn::Error::new_spanned(
+                                            &key,
+                                            format!("unknown `circuit_breaker` key `{}`", other),
+                                        ));
+                                    }
+                                }
                                 if !content.is_empty() {
                                     let _: Token![,] = content.parse()?;
                                 }
                             }
-                            tags = Some(tag_list);
+                            let (Some(failure_ratio), Some(window)) = (failure_ratio, window)
+                            else {
+                                return Err(syn::Error::new(
+                                    key_span,
+                                    "`circuit_breaker` requires `failure_ratio = 0.5` and `window = \"30s\"`",
+                                ));
+                            };
+                            circuit_breaker = Some(CircuitBreakerInfo {
+                                failure_ratio,
+                                window,
+                                min_requests,
+                                retry_after,
+                            });
                         }
-                        "links" => {
+                        "bulk" => {
                             let content;
                             syn::parenthesized!(content in input);
-                            let mut link_list = Vec::new();
+                            let mut body = None;
+                            let mut partial_status = None;
+                            while !content.is_empty() {
+                                let key: Ident = content.parse()?;
+                                let _: Token![=] = content.parse()?;
+                                match key.to_string().as_str() {
+                                    "body" => body = Some(content.parse()?),
+                                    "partial_status" => partial_status = Some(content.parse()?),
+                                    other => {
+                                        return Err(syn::Error::new_spanned(
+                                            &key,
+                                            format!("unknown `bulk` key `{}`", other),
+                                        ));
+                                    }
+                                }
+                                if !content.is_empty() {
+                                    let _: Token![,] = content.parse()?;
+                                }
+                            }
+                            let Some(body) = body else {
+                                return Err(syn::Error::new(
+                                    key_span,
+                                    "`bulk` requires `body = Vec<Item>`",
+                                ));
+                            };
+                            bulk = Some(BulkInfo {
+                                body,
+                                partial_status,
+                            });
+                        }
+                        "patch_format" => {
+                            let _: Token![=] = input.parse()?;
+                            patch_format = Some(input.parse()?);
+                        }
+                        "requires_if_match" => {
+                            requires_if_match = true;
+                        }
+                        "messages" => {
+                            let content;
+                            syn::parenthesized!(content in input);
+                            let mut info = MessagesInfo {
+                                send: None,
+                                receive: None,
+                            };
+                            while !content.is_empty() {
+                                let key: Ident = content.parse()?;
+                                let _: Token![=] = content.parse()?;
+                                match key.to_string().as_str() {
+                                    "send" => info.send = Some(content.parse()?),
+                                    "receive" => info.receive = Some(content.parse()?),
+                                    other => {
+                                        return Err(syn::Error::new_spanned(
+                                            &key,
+                                            format!("unknown `messages` key `{}`", other),
+                                        ));
+                                    }
+                                }
+                                if !content.is_empty() {
+                                    let _: Token![,] = content.parse()?;
+                                }
+                            }
+                            messages = Some(info);
+                        }
+                        "negotiate" => {
+                            let content;
+                            syn::parenthesized!(content in input);
+                            let formats: syn::punctuated::Punctuated<Ident, Token![,]> =
+                                content.parse_terminated(Ident::parse, Token![,])?;
+                            negotiate = formats.into_iter().collect();
+                        }
+                        "inherit_tag" => {
+                            let _: Token![=] = input.parse()?;
+                            inherit_tag = Some(input.parse()?);
+                        }
+                        "sdk_cache_ttl" => {
+                            let _: Token![=] = input.parse()?;
+                            sdk_cache_ttl = Some(input.parse()?);
+                        }
+                        "operation_id" => {
+                            let _: Token![=] = input.parse()?;
+                            operation_id = Some(input.parse()?);
+                        }
+                        "path" => {
+                            let _: Token![=] = input.parse()?;
+                            let value: Ident = input.parse()?;
+                            if value != "auto" {
+                                return Err(syn::Error::new_spanned(
+                                    value,
+                                    "expected `path = auto`",
+                                ));
+                            }
+                            if path_lit.is_some() {
+                                return Err(syn::Error::new(
+                                    key_span,
+                                    "`path = auto` on a route with a path string",
+                                ));
+                            }
+                            auto_path = true;
+                        }
+                        "multipart" => {
+                            let content;
+                            syn::parenthesized!(content in input);
+                            while !content.is_empty() {
+                                let key: Ident = content.parse()?;
+                                let _: Token![=] = content.parse()?;
+                                match key.to_string().as_str() {
+                                    "schema" => multipart = Some(content.parse()?),
+                                    other => {
+                                        return Err(syn::Error::new_spanned(
+                                            &key,
+                                            format!("unknown `multipart` key `{}`", other),
+                                        ));
+                                    }
+                                }
+                                if !content.is_empty() {
+                                    let _: Token![,] = content.parse()?;
+                                }
+                            }
+                            if multipart.is_none() {
+                                return Err(syn::Error::new(
+                                    key_span,
+                                    "`multipart` requires `schema = UploadForm`",
+                                ));
+                            }
+                        }
+                        "allow_get_body" => {
+                            allow_get_body = true;
+                        }
+                        "trace_sample" => {
+                            let _: Token![=] = input.parse()?;
+                            trace_sample = Some(input.parse()?);
+                        }
+                        name if SHORTHAND_RESPONSES.iter().any(|(n, _, _)| *n == name) => {
+                            let (_, status, default) = SHORTHAND_RESPONSES
+                                .iter()
+                                .find(|(n, _, _)| *n == name)
+                                .copied()
+                                .unwrap_or_default();
+                            if shorthand_statuses.contains(&status) {
+                                return Err(syn::Error::new(
+                                    key_span,
+                                    format!("duplicate `{}` response", name),
+                                ));
+                            }
+                            // `= "Description"` or `= BodyType`
+                            let mut description = default.to_string();
+                            let mut body = None;
+                            if input.peek(Token![=]) {
+                                let _: Token![=] = input.parse()?;
+                                if input.peek(LitStr) {
+                                    description = input.parse::<LitStr>()?.value();
+                                } else {
+                                    body = Some(input.parse::<syn::Type>()?);
+                                }
+                            }
+                            let body = body.map(|ty| quote::quote! { , body = #ty });
+                            shorthand.push(quote::quote! {
+                                (status = #status, description = #description #body)
+                            });
+                            shorthand_statuses.push(status);
+                        }
+                        "example_request" => {
+                            let _: Token![=] = input.parse()?;
+                            example_request = Some(input.parse()?);
+                        }
+                        "example_response" => {
+                            let _: Token![=] = input.parse()?;
+                            example_response = Some(input.parse()?);
+                        }
+                        "headers" => {
+                            let content;
+                            syn::parenthesized!(content in input);
+                            let mut header_list = Vec::new();
                             while !content.is_empty() {
                                 let inner;
-                                syn::parenthesized!(inner in content);
-                                let mut rel = String::new();
-                                let mut href = String::new();
-                                let mut method = None;
+                                let header = syn::parenthesized!(inner in content);
+                                let mut name = None;
+                                let mut description = None;
+                                let mut schema = None;
 
                                 while !inner.is_empty() {
                                     let key: Ident = inner.parse()?;
                                     let _: Token![=] = inner.parse()?;
-                                    let val: LitStr = inner.parse()?;
                                     match key.to_string().as_str() {
-                                        "rel" => rel = val.value(),
-                                        "href" => href = val.value(),
-                                        "method" => method = Some(val.value()),
-                                        _ => {}
+                                        "name" => name = Some(inner.parse::<LitStr>()?),
+                                        "description" => {
+                                            description = Some(inner.parse::<LitStr>()?)
+                                        }
+                                        "schema" => schema = Some(inner.parse::<syn::Type>()?),
+                                        other => {
+                                            return Err(syn::Error::new_spanned(
+                                                &key,
+                                                format!(
+                                                    "unknown header key `{}`, expected `name`, `description` or `schema`",
+                                                    other
+                                                ),
+                                            ));
+                                        }
                                     }
                                     if !inner.is_empty() {
                                         let _: Token![,] = inner.parse()?;
                                     }
                                 }
-                                link_list.push(LinkInfo { rel, href, method });
+                                let Some(name) = name else {
+                                    return Err(syn::Error::new(
+                                        header.span.join(),
+                                        "a response header requires `name = \"...\"`",
+                                    ));
+                                };
+                                header_list.push(ResponseHeader {
+                                    name,
+                                    description,
+                                    schema: schema.unwrap_or_else(|| syn::parse_quote!(String)),
+                                });
                                 if !content.is_empty() {
                                     let _: Token![,] = content.parse()?;
                                 }
                             }
-                            links = link_list;
+                            response_headers = header_list;
                         }
-                        "responses" => {
+                        "upload" => {
+                            let mut upload_info = UploadInfo::default();
+                            if input.peek(syn::token::Paren) {
+                                let content;
+                                syn::parenthesized!(content in input);
+                                while !content.is_empty() {
+                                    let key: Ident = content.parse()?;
+                                    let _: Token![=] = content.parse()?;
+                                    match key.to_string().as_str() {
+                                        "content_type" => {
+                                            upload_info.content_type = Some(content.parse()?)
+                                        }
+                                        "max" => upload_info.max = Some(content.parse()?),
+                                        other => {
+                                            return Err(syn::Error::new_spanned(
+                                                &key,
+                                                format!(
+                                                    "unknown `upload` key `{}`, expected `content_type` or `max`",
+                                                    other
+                                                ),
+                                            ));
+                                        }
+                                    }
+                                    if !content.is_empty() {
+                                        let _: Token![,] = content.parse()?;
+                                    }
+                                }
+                            }
+                            upload_info.max_bytes()?;
+                            upload = Some(upload_info);
+                        }
+                        "param_constraints" => {
                             let content;
                             syn::parenthesized!(content in input);
-                            let val: TokenStream = content.parse()?;
-                            responses = Some(val);
+                            let mut constraints = ParamConstraints::default();
+                            while !content.is_empty() {
+                                if content.peek(Ident) {
+                                    let word: Ident = content.parse()?;
+                                    if word != "enforce" {
+                                        return Err(syn::Error::new_spanned(
+                                            &word,
+                                            "expected `(param, minimum = ..., maximum = ...)` or `enforce`",
+                                        ));
+                                    }
+                                    constraints.enforce = true;
+                                } else {
+                                    let inner;
+                                    let group = syn::parenthesized!(inner in content);
+                                    let name: Ident = inner.parse()?;
+                                    let mut constraint = ParamConstraint {
+                                        name,
+                                        minimum: None,
+                                        maximum: None,
+                                    };
+                                    while !inner.is_empty() {
+                                        let _: Token![,] = inner.parse()?;
+                                        if inner.is_empty() {
+                                            break;
+                                        }
+                                        let key: Ident = inner.parse()?;
+                                        let _: Token![=] = inner.parse()?;
+                                        let bound = parse_bound(&inner)?;
+                                        match key.to_string().as_str() {
+                                            "minimum" => constraint.minimum = Some(bound),
+                                            "maximum" => constraint.maximum = Some(bound),
+                                            other => {
+                                                return Err(syn::Error::new_spanned(
+                                                    &key,
+                                                    format!(
+                                                        "unknown constraint `{}`, expected `minimum` or `maximum`",
+                                                        other
+                                                    ),
+                                                ));
+                                            }
+                                        }
+                                    }
+                                    match (constraint.minimum, constraint.maximum) {
+                                        (None, None) => {
+                                            return Err(syn::Error::new(
+                                                group.span.join(),
+                                                "a parameter constraint requires `minimum` or `maximum`",
+                                            ));
+                                        }
+                                        (Some(min), Some(max)) if min > max => {
+                                            return Err(syn::Error::new(
+                                                group.span.join(),
+                                                "`minimum` is larger than `maximum`",
+                                            ));
+                                        }
+                                        _ => {}
+                                    }
+                                    constraints.items.push(constraint);
+                                }
+                                if !content.is_empty() {
+                                    let _: Token![,] = content.parse()?;
+                                }
+                            }
+                            param_constraints = constraints;
+                        }
+                        "status" => {
+                            let _: Token![=] = input.parse()?;
+                            let lit: syn::LitInt = input.parse()?;
+                            let value: u16 = lit.base10_parse()?;
+                            if !(200..300).contains(&value) || status_name(value).is_none() {
+                                return Err(syn::Error::new_spanned(
+                                    &lit,
+                                    "`status` must be a registered 2xx status code",
+                                ));
+                            }
+                            status = Some(value);
+                        }
+                        "location" => {
+                            let _: Token![=] = input.parse()?;
+                            location = Some(input.parse()?);
+                        }
+                        "param_formats" => {
+                            let content;
+                            syn::parenthesized!(content in input);
+                            let entries = content.parse_terminated(
+                                |entry| {
+                                    let name: Ident = entry.parse()?;
+                                    let _: Token![=] = entry.parse()?;
+                                    Ok((name, entry.parse::<LitStr>()?))
+                                },
+                                Token![,],
+                            )?;
+                            param_formats = entries.into_iter().collect();
+                        }
+                        "media_versions" => {
+                            let content;
+                            syn::parenthesized!(content in input);
+                            let list;
+                            syn::parenthesized!(list in content);
+                            let versions = list.parse_terminated(
+                                |entry| {
+                                    let name: Ident = entry.parse()?;
+                                    let _: Token![=] = entry.parse()?;
+                                    Ok((name, entry.parse::<syn::Type>()?))
+                                },
+                                Token![,],
+                            )?;
+                            let mut info = MediaVersions {
+                                vendor: None,
+                                versions: versions.into_iter().collect(),
+                            };
+                            if info.versions.is_empty() {
+                                return Err(syn::Error::new(
+                                    list.span(),
+                                    "`media_versions` needs at least one version",
+                                ));
+                            }
+                            while !content.is_empty() {
+                                let _: Token![,] = content.parse()?;
+                                if content.is_empty() {
+                                    break;
+                                }
+                                let key: Ident = content.parse()?;
+                                if key != "vendor" {
+                                    return Err(syn::Error::new_spanned(
+                                        &key,
+                                        format!(
+                                            "unknown `media_versions` key `{}`, expected `vendor`",
+                                            key
+                                        ),
+                                    ));
+                                }
+                                let _: Token![=] = content.parse()?;
+                                info.vendor = Some(content.parse()?);
+                            }
+                            media_versions = Some(info);
+                        }
+                        "publish" => {
+                            let content;
+                            syn::parenthesized!(content in input);
+                            let mut event: Option<syn::Type> = None;
+                            let mut from: Option<LitStr> = None;
+                            let mut name: Option<LitStr> = None;
+                            while !content.is_empty() {
+                                let key: Ident = content.parse()?;
+                                let _: Token![=] = content.parse()?;
+                                match key.to_string().as_str() {
+                                    "event" => event = Some(content.parse()?),
+                                    "from" => {
+                                        let source: LitStr = content.parse()?;
+                                        if !matches!(
+                                            source.value().as_str(),
+                                            "response" | "request"
+                                        ) {
+                                            return Err(syn::Error::new_spanned(
+                                                &source,
+                                                "`from` must be \"response\" or \"request\"",
+                                            ));
+                                        }
+                                        from = Some(source);
+                                    }
+                                    "name" => name = Some(content.parse()?),
+                                    other => {
+                                        return Err(syn::Error::new_spanned(
+                                            &key,
+                                            format!("unknown `publish` key `{}`", other),
+                                        ));
+                                    }
+                                }
+                                if !content.is_empty() {
+                                    let _: Token![,] = content.parse()?;
+                                }
+                            }
+                            let Some(event) = event else {
+                                return Err(syn::Error::new(
+                                    key_span,
+                                    "`publish` requires `event = Type`",
+                                ));
+                            };
+                            let name = match name {
+                                Some(name) => name.value(),
+                                None => match &event {
+                                    syn::Type::Path(tp) => tp
+                                        .path
+                                        .segments
+                                        .last()
+                                        .map(|seg| seg.ident.to_string())
+                                        .unwrap_or_default(),
+                                    other => other.to_token_stream().to_string(),
+                                },
+                            };
+                            emits.push(EmitInfo {
+                                event: name.clone(),
+                                payload: event.clone(),
+                            });
+                            publish = Some(PublishInfo {
+                                event,
+                                from: from.unwrap_or_else(|| LitStr::new("response", key_span)),
+                                name,
+                            });
+                        }
+                        "guard" => {
+                            let _: Token![=] = input.parse()?;
+                            guard = Some(input.parse()?);
+                        }
+                        "guard_status" => {
+                            let _: Token![=] = input.parse()?;
+                            let lit: syn::LitInt = input.parse()?;
+                            let value: u16 = lit.base10_parse()?;
+                            if !(400..600).contains(&value) {
+                                return Err(syn::Error::new_spanned(
+                                    &lit,
+                                    "`guard_status` must be a 4xx or 5xx status code",
+                                ));
+                            }
+                            guard_status = value;
+                        }
+                        "require" => {
+                            let _: Token![=] = input.parse()?;
+                            let content;
+                            syn::bracketed!(content in input);
+                            while !content.is_empty() {
+                                let permission: LitStr = content.parse()?;
+                                if permission.value().is_empty() {
+                                    return Err(syn::Error::new_spanned(
+                                        &permission,
+                                        "permissions cannot be empty",
+                                    ));
+                                }
+                                require.push(permission.value());
+                                if !content.is_empty() {
+                                    let _: Token![,] = content.parse()?;
+                                }
+                            }
+                        }
+                        "transactional" => {
+                            transactional = true;
+                        }
+                        "read_only" => {
+                            read_only = true;
+                        }
+                        "security_scheme" => {
+                            let _: Token![=] = input.parse()?;
+                            let scheme: LitStr = input.parse()?;
+                            check_security_scheme(&scheme)?;
+                            security_scheme = Some(scheme);
+                        }
+                        "policy" => {
+                            let _: Token![=] = input.parse()?;
+                            policy = Some(if input.peek(LitStr) {
+                                Policy::Named(input.parse()?)
+                            } else {
+                                Policy::Fn(input.parse()?)
+                            });
+                        }
+                        "security_optional" => {
+                            security_optional = true;
+                        }
+                        "openapi" => {
+                            let content;
+                            syn::parenthesized!(content in input);
+                            let option: Ident = content.parse()?;
+                            if option != "append" {
+                                return Err(syn::Error::new_spanned(
+                                    option,
+                                    "expected `openapi(append = { ... })`",
+                                ));
+                            }
+                            let _: Token![=] = content.parse()?;
+                            let block;
+                            syn::braced!(block in content);
+                            let tokens: TokenStream = block.parse()?;
+                            // Reported here rather than as an error inside `#[utoipa::path]`
+                            let items = syn::parse::Parser::parse2(
+                                syn::punctuated::Punctuated::<syn::Meta, Token![,]>::parse_terminated,
+                                tokens.clone(),
+                            )?;
+                            // utoipa keeps the last value of a key, which would replace the
+                            // generated one; whether `extensions` are generated is only known
+                            // in the controller
+                            if let Some(item) = items.iter().find(|item| {
+                                !OPENAPI_APPEND_ALLOWED
+                                    .iter()
+                                    .any(|key| item.path().is_ident(key))
+                            }) {
+                                return Err(syn::Error::new_spanned(
+                                    item.path(),
+                                    OPENAPI_APPEND_KEYS,
+                                ));
+                            }
+                            openapi_append = Some(tokens);
+                        }
+                        "audit" => {
+                            audit = true;
                         }
                         _ => {
                             // Capture any other attribute (like responses)
@@ -313,6 +3106,37 @@ impl RouteInfo {
                     }
                 }
 
+                // Shorthand responses are merged with any explicit responses(...)
+                if !shorthand.is_empty() {
+                    responses = Some(match responses {
+                        Some(explicit) => quote::quote! { #explicit, #(#shorthand),* },
+                        None => quote::quote! { #(#shorthand),* },
+                    });
+                }
+
+                if path_lit.is_none() && !auto_path {
+                    return Err(syn::Error::new_spanned(
+                        &method_ident,
+                        format!(
+                            "expected a path: `{} \"/path\"` or `{}, path = auto`",
+                            method_ident, method_ident
+                        ),
+                    ));
+                }
+                if security_optional && !require.is_empty() {
+                    return Err(syn::Error::new_spanned(
+                        &method_ident,
+                        "`security_optional` cannot be combined with `require`, which answers 401 \
+                         without credentials",
+                    ));
+                }
+                if let (Some(scheme), Some(_)) = (&security_scheme, &security_cookie) {
+                    return Err(syn::Error::new_spanned(
+                        scheme,
+                        "`security_scheme` cannot be combined with `security = cookie(...)`",
+                    ));
+                }
+
                 Ok(RouteAttr {
                     method,
                     path,
@@ -327,6 +3151,63 @@ impl RouteInfo {
                     other_attrs,
                     links,
                     responses,
+                    ignore_params,
+                    csv,
+                    async_job,
+                    status_route,
+                    last_modified,
+                    filterable,
+                    sortable,
+                    paginated,
+                    emits,
+                    signed,
+                    verify_signature,
+                    mirror_to,
+                    mirror_percent,
+                    canary,
+                    proxy,
+                    compress,
+                    no_compress,
+                    ranges,
+                    log,
+                    sensitive_params,
+                    slow_warn,
+                    circuit_breaker,
+                    bulk,
+                    patch_format,
+                    requires_if_match,
+                    messages,
+                    negotiate,
+                    inherit_tag,
+                    sdk_cache_ttl,
+                    operation_id,
+                    auto_path,
+                    multipart,
+                    allow_get_body,
+                    trace_sample,
+                    shorthand_statuses,
+                    example_request,
+                    example_response,
+                    response_headers,
+                    upload,
+                    param_constraints,
+                    status,
+                    location,
+                    param_formats,
+                    media_versions,
+                    publish,
+                    guard,
+                    guard_status,
+                    require,
+                    transactional,
+                    security_scopes,
+                    read_only,
+                    security_scheme,
+                    policy,
+                    security_cookie,
+                    security_optional,
+                    openapi_append,
+                    audit,
                 })
             }
         }
@@ -347,6 +3228,187 @@ impl RouteInfo {
             other_attrs: attr.other_attrs,
             links: attr.links,
             responses: attr.responses,
+            ignore_params: attr.ignore_params,
+            csv: attr.csv,
+            async_job: attr.async_job,
+            status_route: attr.status_route,
+            last_modified: attr.last_modified,
+            filterable: attr.filterable,
+            sortable: attr.sortable,
+            paginated: attr.paginated,
+            emits: attr.emits,
+            signed: attr.signed,
+            verify_signature: attr.verify_signature,
+            mirror_to: attr.mirror_to,
+            mirror_percent: attr.mirror_percent,
+            canary: attr.canary,
+            proxy: attr.proxy,
+            compress: attr.compress,
+            no_compress: attr.no_compress,
+            ranges: attr.ranges,
+            log: attr.log,
+            sensitive_params: attr.sensitive_params,
+            slow_warn: attr.slow_warn,
+            circuit_breaker: attr.circuit_breaker,
+            bulk: attr.bulk,
+            patch_format: attr.patch_format,
+            requires_if_match: attr.requires_if_match,
+            messages: attr.messages,
+            negotiate: attr.negotiate,
+            inherit_tag: attr.inherit_tag,
+            sdk_cache_ttl: attr.sdk_cache_ttl,
+            operation_id: attr.operation_id,
+            auto_path: attr.auto_path,
+            multipart: attr.multipart,
+            allow_get_body: attr.allow_get_body,
+            trace_sample: attr.trace_sample,
+            shorthand_statuses: attr.shorthand_statuses,
+            example_request: attr.example_request,
+            example_response: attr.example_response,
+            response_headers: attr.response_headers,
+            upload: attr.upload,
+            param_constraints: attr.param_constraints,
+            status: attr.status,
+            location: attr.location,
+            param_formats: attr.param_formats,
+            media_versions: attr.media_versions,
+            publish: attr.publish,
+            guard: attr.guard,
+            guard_status: attr.guard_status,
+            require: attr.require,
+            transactional: attr.transactional,
+            security_scopes: attr.security_scopes,
+            read_only: attr.read_only,
+            security_scheme: attr.security_scheme,
+            policy: attr.policy,
+            security_cookie: attr.security_cookie,
+            security_optional: attr.security_optional,
+            openapi_append: attr.openapi_append,
+            audit: attr.audit,
         })
     }
 }
+
+/// Parses a duration literal such as `"500ms"`, `"2s"` or `"1m"` into milliseconds
+pub fn parse_duration_ms(lit: &LitStr) -> syn::Result<u64> {
+    let value = lit.value();
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+    let factor = match unit.trim() {
+        "ms" => 1,
+        "s" => 1_000,
+        "m" => 60_000,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                lit,
+                "expected a duration like \"500ms\", \"2s\" or \"1m\"",
+            ));
+        }
+    };
+    amount
+        .parse::<u64>()
+        .ok()
+        .and_then(|amount| amount.checked_mul(factor))
+        .ok_or_else(|| syn::Error::new_spanned(lit, "invalid duration"))
+}
+
+/// Parses a size literal such as `"512KB"`, `"10MB"` or `"1GB"` (powers of 1024) into bytes
+pub fn parse_size_bytes(lit: &LitStr) -> syn::Result<usize> {
+    let value = lit.value();
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+    let factor = match unit.trim() {
+        "B" => 1,
+        "KB" => 1 << 10,
+        "MB" => 1 << 20,
+        "GB" => 1 << 30,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                lit,
+                "expected a size like \"512KB\", \"10MB\" or \"1GB\"",
+            ));
+        }
+    };
+    amount
+        .parse::<usize>()
+        .ok()
+        .and_then(|amount| amount.checked_mul(factor))
+        .ok_or_else(|| syn::Error::new_spanned(lit, "invalid size"))
+}
+
+/// Parses a `retry_after` duration into whole seconds (rounded up)
+fn parse_retry_after(lit: &LitStr) -> syn::Result<u64> {
+    match parse_duration_ms(lit)? {
+        0 => Err(syn::Error::new_spanned(
+            lit,
+            "`retry_after` must be positive",
+        )),
+        ms => Ok(ms.div_ceil(1000)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proc_macro2::Span;
+
+    use super::*;
+
+    fn lit(value: &str) -> LitStr {
+        LitStr::new(value, Span::call_site())
+    }
+
+    #[test]
+    fn parse_duration_ms_reads_units() {
+        assert_eq!(parse_duration_ms(&lit("500ms")).unwrap(), 500);
+        assert_eq!(parse_duration_ms(&lit("2s")).unwrap(), 2_000);
+        assert_eq!(parse_duration_ms(&lit(" 1 m ")).unwrap(), 60_000);
+    }
+
+    #[test]
+    fn parse_duration_ms_rejects_unknown_units_and_overflow() {
+        for invalid in ["10", "10h", "ms", "-1s", "1.5s", "99999999999999999999m"] {
+            assert!(parse_duration_ms(&lit(invalid)).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn summary_template_renders_placeholders() {
+        let template =
+            SummaryTemplate::parse(&lit("{method} {resource} ({fn} at {path})")).unwrap();
+        let summary = template.render(HttpMethod::Get, "/projects/{id}", "get_project", "Project");
+        assert_eq!(summary, "GET Project (get_project at /projects/{id})");
+    }
+
+    #[test]
+    fn summary_template_rejects_unknown_and_unclosed_placeholders() {
+        let error = SummaryTemplate::parse(&lit("{verb} {path}")).unwrap_err();
+        assert!(
+            error.to_string().contains("unknown placeholder `{verb}`"),
+            "{}",
+            error
+        );
+        let error = SummaryTemplate::parse(&lit("{method")).unwrap_err();
+        assert_eq!(error.to_string(), "unclosed `{` in summary_template");
+    }
+
+    #[test]
+    fn parse_size_bytes_uses_powers_of_1024() {
+        assert_eq!(parse_size_bytes(&lit("512B")).unwrap(), 512);
+        assert_eq!(parse_size_bytes(&lit("512KB")).unwrap(), 512 * 1024);
+        assert_eq!(parse_size_bytes(&lit("10MB")).unwrap(), 10 * 1024 * 1024);
+        assert_eq!(parse_size_bytes(&lit("1GB")).unwrap(), 1 << 30);
+    }
+
+    #[test]
+    fn parse_size_bytes_rejects_unknown_units() {
+        for invalid in ["512", "1TB", "1mb", "MB", "1.5MB"] {
+            assert!(parse_size_bytes(&lit(invalid)).is_err(), "{}", invalid);
+        }
+    }
+}
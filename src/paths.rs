@@ -0,0 +1,191 @@
+//! Route path normalization.
+
+/// Build the controller mount prefix (e.g., "/api/v1/projects") from its
+/// version and path.
+///
+/// The root controller (`path = "/"`) mounts at "/api" so routes never end up
+/// with `//` in them.
+pub fn controller_prefix(version: Option<&str>, path: &str) -> Result<String, String> {
+    if !path.is_empty() && !path.starts_with('/') {
+        return Err(format!("controller path `{}` must start with `/`", path));
+    }
+
+    let mut prefix = String::from("/api");
+    if let Some(version) = version {
+        let version = version.trim_matches('/');
+        if !version.is_empty() {
+            prefix.push('/');
+            prefix.push_str(version);
+        }
+    }
+    prefix.push_str(path);
+
+    let mut prefix = collapse_slashes(&normalize_params(&prefix));
+    if prefix.len() > 1 && prefix.ends_with('/') {
+        prefix.pop();
+    }
+    Ok(prefix)
+}
+
+/// Check that a path literal is plain ASCII (or percent-encoded) and carries
+/// no query string or fragment.
+///
+/// `what` names the path in error messages (e.g., "route path").
+pub fn validate_path(what: &str, path: &str) -> Result<(), String> {
+    let bytes = path.as_bytes();
+    for (i, c) in path.char_indices() {
+        match c {
+            '?' => {
+                return Err(format!(
+                    "{} `{}` contains a query string, declare query parameters with `Query<T>` instead",
+                    what, path
+                ));
+            }
+            '#' => return Err(format!("{} `{}` contains a fragment", what, path)),
+            '%' => {
+                let encoded = bytes.get(i + 1..i + 3);
+                if !encoded.is_some_and(|hex| hex.iter().all(u8::is_ascii_hexdigit)) {
+                    return Err(format!(
+                        "{} `{}` contains an invalid percent-encoding, `%` must be followed by two hex digits",
+                        what, path
+                    ));
+                }
+            }
+            c if !c.is_ascii() => {
+                let mut buf = [0; 4];
+                let encoded: String = c
+                    .encode_utf8(&mut buf)
+                    .bytes()
+                    .map(|b| format!("%{:02X}", b))
+                    .collect();
+                return Err(format!(
+                    "{} `{}` contains the non-ASCII character `{}`, percent-encode it as `{}`",
+                    what, path, c, encoded
+                ));
+            }
+            c if c.is_ascii_whitespace() || c.is_ascii_control() => {
+                return Err(format!(
+                    "{} `{}` contains whitespace or a control character",
+                    what, path
+                ));
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Rewrite axum 0.7 style parameters (`:id`, `*rest`) to the `{id}` /
+/// `{*rest}` form used in OpenAPI paths and by axum 0.8.
+pub fn normalize_params(path: &str) -> String {
+    path.split('/')
+        .map(|segment| {
+            if let Some(name) = segment.strip_prefix(':') {
+                format!("{{{}}}", name)
+            } else if let Some(name) = segment.strip_prefix('*') {
+                format!("{{*{}}}", name)
+            } else {
+                segment.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Route path in the syntax of the targeted axum version: `{id}` for axum 0.8,
+/// `:id` / `*rest` with the `axum-07` feature.
+pub fn axum_path(path: &str) -> String {
+    if !cfg!(feature = "axum-07") {
+        return path.to_string();
+    }
+    path.split('/')
+        .map(|segment| {
+            match segment
+                .strip_prefix('{')
+                .and_then(|segment| segment.strip_suffix('}'))
+            {
+                Some(name) => match name.strip_prefix('*') {
+                    Some(rest) => format!("*{}", rest),
+                    None => format!(":{}", name),
+                },
+                None => segment.to_string(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Names of the `{param}` / `{*rest}` parameters of a path.
+pub fn path_params(path: &str) -> Vec<&str> {
+    path.split('/')
+        .filter_map(|segment| segment.strip_prefix('{')?.strip_suffix('}'))
+        .map(|name| name.trim_start_matches('*'))
+        .collect()
+}
+
+/// Join a controller prefix and a route path, collapsing duplicate slashes.
+pub fn join_paths(prefix: &str, path: &str) -> String {
+    collapse_slashes(&format!("{}{}", prefix, path))
+}
+
+fn collapse_slashes(path: &str) -> String {
+    let mut out = String::with_capacity(path.len());
+    for c in path.chars() {
+        if c == '/' && out.ends_with('/') {
+            continue;
+        }
+        out.push(c);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_path_accepts_plain_and_encoded_paths() {
+        assert!(validate_path("route path", "/projects/{id}").is_ok());
+        assert!(validate_path("route path", "/caf%C3%A9").is_ok());
+    }
+
+    #[test]
+    fn validate_path_rejects_query_fragment_and_bad_characters() {
+        let error = validate_path("route path", "/projects?page=1").unwrap_err();
+        assert!(error.contains("query string"), "{}", error);
+        let error = validate_path("route path", "/projects#top").unwrap_err();
+        assert!(error.contains("fragment"), "{}", error);
+        let error = validate_path("route path", "/50%").unwrap_err();
+        assert!(error.contains("percent-encoding"), "{}", error);
+        let error = validate_path("route path", "/café").unwrap_err();
+        assert!(error.contains("`%C3%A9`"), "{}", error);
+        let error = validate_path("route path", "/my projects").unwrap_err();
+        assert!(error.contains("whitespace"), "{}", error);
+    }
+
+    #[test]
+    fn normalize_params_rewrites_axum_07_syntax() {
+        assert_eq!(normalize_params("/projects/:id"), "/projects/{id}");
+        assert_eq!(normalize_params("/files/*rest"), "/files/{*rest}");
+        assert_eq!(normalize_params("/projects/{id}"), "/projects/{id}");
+    }
+
+    #[test]
+    fn axum_path_targets_the_axum_version() {
+        if cfg!(feature = "axum-07") {
+            assert_eq!(axum_path("/projects/{id}/{*rest}"), "/projects/:id/*rest");
+        } else {
+            assert_eq!(
+                axum_path("/projects/{id}/{*rest}"),
+                "/projects/{id}/{*rest}"
+            );
+        }
+        assert_eq!(axum_path("/projects"), "/projects");
+    }
+
+    #[test]
+    fn path_params_lists_parameter_names() {
+        assert_eq!(path_params("/projects/{id}/files/{*rest}"), ["id", "rest"]);
+        assert!(path_params("/projects").is_empty());
+    }
+}
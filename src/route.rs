@@ -1,18 +1,35 @@
 //! Route macro implementation
 
+use convert_case::Case;
 use proc_macro2::TokenStream;
 use quote::quote;
 use syn::{FnArg, GenericArgument, ItemFn, PatType, PathArguments, Type, TypePath, parse2};
 
-use crate::parse::RouteInfo;
+use crate::extractors::{
+    check_method_body, check_path_params, check_signature, check_upload_body, has_path_extractor,
+    has_streaming_body, query_params_types,
+};
+use crate::parse::{HttpMethod, RouteInfo, UploadInfo};
 
 /// Process the #[route(...)] attribute macro
 pub fn route_impl(args: TokenStream, input: TokenStream) -> TokenStream {
     // Parse the route info from args
-    let route_info = match RouteInfo::parse(args) {
+    // On errors the function is still emitted, so only the attribute is reported
+    let mut route_info = match RouteInfo::parse(args.clone()) {
         Ok(info) => info,
-        Err(e) => return e.to_compile_error(),
+        Err(e) => {
+            let error = e.to_compile_error();
+            return quote! { #error #input };
+        }
     };
+    if route_info.method == HttpMethod::Any {
+        let error = syn::Error::new_spanned(
+            args.into_iter().next(),
+            "ANY routes can't be documented on their own, declare them inside a #[controller]",
+        )
+        .to_compile_error();
+        return quote! { #error #input };
+    }
 
     // Parse the function
     let func: ItemFn = match parse2(input.clone()) {
@@ -20,12 +37,46 @@ pub fn route_impl(args: TokenStream, input: TokenStream) -> TokenStream {
         Err(e) => return e.to_compile_error(),
     };
 
+    route_info.apply_doc_comments(&func.attrs);
+    route_info.apply_auto_path(&func.sig.ident, Case::Kebab);
+
+    let compile_errors = check_signature(&func.sig, &route_info.ignore_params)
+        .into_iter()
+        .chain(check_path_params(
+            &func.sig,
+            &route_info.path,
+            &route_info.ignore_params,
+        ))
+        .chain(if route_info.allow_get_body {
+            Vec::new()
+        } else {
+            check_method_body(&func.sig, route_info.method)
+        })
+        .chain(if route_info.upload.is_some() {
+            check_upload_body(&func.sig)
+        } else {
+            Vec::new()
+        })
+        // Examples are set when the controller registers its operations
+        .chain(
+            [&route_info.example_request, &route_info.example_response]
+                .into_iter()
+                .flatten()
+                .map(|example| {
+                    syn::Error::new_spanned(
+                        example,
+                        "examples are only documented inside a #[controller]",
+                    )
+                }),
+        )
+        .map(|e| e.to_compile_error());
+
     let fn_name = &func.sig.ident;
     let method = route_info.method.to_axum_method();
     let path = &route_info.path;
 
     // Generate utoipa path annotation with automatic type extraction
-    let utoipa_attr = generate_utoipa_attribute(&func, method, &path, &route_info);
+    let utoipa_attr = generate_utoipa_attribute(&func, method, path, &route_info);
 
     // Store route metadata as a const for the controller to pick up
     let route_const_name = syn::Ident::new(
@@ -33,8 +84,18 @@ pub fn route_impl(args: TokenStream, input: TokenStream) -> TokenStream {
         fn_name.span(),
     );
 
+    // utoipa reads the deprecation from the function's own attribute
+    let deprecated_attr = (route_info.deprecated
+        && !func
+            .attrs
+            .iter()
+            .any(|attr| attr.path().is_ident("deprecated")))
+    .then(|| quote! { #[deprecated] });
+
     quote! {
+        #(#compile_errors)*
         #utoipa_attr
+        #deprecated_attr
         #func
 
         #[doc(hidden)]
@@ -50,7 +111,7 @@ fn generate_utoipa_attribute(
     path: &str,
     route_info: &RouteInfo,
 ) -> TokenStream {
-    let mut request_body_type: Option<TokenStream> = None;
+    let mut request_body: Option<TokenStream> = None;
     let mut response_type: Option<TokenStream> = None;
     let mut security_required = false;
 
@@ -58,67 +119,57 @@ fn generate_utoipa_attribute(
     for arg in &func.sig.inputs {
         if let FnArg::Typed(PatType { ty, .. }) = arg {
             // Check for Json<T> - request body
-            if let Type::Path(TypePath { path, .. }) = &**ty {
-                if let Some(segment) = path.segments.last() {
-                    if segment.ident == "Json" {
-                        if let PathArguments::AngleBracketed(args) = &segment.arguments {
-                            if let Some(GenericArgument::Type(Type::Path(TypePath {
-                                path, ..
-                            }))) = args.args.first()
-                            {
-                                request_body_type = Some(quote! { #path });
-                            }
-                        }
-                    }
-                    // Check for Extension<UserId> - security requirement
-                    if segment.ident == "Extension" {
-                        if let PathArguments::AngleBracketed(args) = &segment.arguments {
-                            if let Some(GenericArgument::Type(Type::Path(TypePath {
-                                path, ..
-                            }))) = args.args.first()
-                            {
-                                if path.segments.last().map(|s| s.ident.to_string()).as_deref()
-                                    == Some("UserId")
-                                {
-                                    security_required = true;
-                                }
-                            }
-                        }
-                    }
+            if let Type::Path(TypePath { path, .. }) = &**ty
+                && let Some(segment) = path.segments.last()
+            {
+                if segment.ident == "Json"
+                    && let PathArguments::AngleBracketed(args) = &segment.arguments
+                    && let Some(GenericArgument::Type(Type::Path(TypePath { path, .. }))) =
+                        args.args.first()
+                {
+                    request_body = Some(quote! { request_body = #path });
+                }
+                // Check for Form<T> - url-encoded request body
+                if segment.ident == "Form"
+                    && let PathArguments::AngleBracketed(args) = &segment.arguments
+                    && let Some(GenericArgument::Type(Type::Path(TypePath { path, .. }))) =
+                        args.args.first()
+                {
+                    request_body = Some(quote! {
+                        request_body(content = #path, content_type = "application/x-www-form-urlencoded")
+                    });
+                }
+                // Check for Extension<UserId> - security requirement
+                if segment.ident == "Extension"
+                    && let PathArguments::AngleBracketed(args) = &segment.arguments
+                    && let Some(GenericArgument::Type(Type::Path(TypePath { path, .. }))) =
+                        args.args.first()
+                    && path.segments.last().map(|s| s.ident.to_string()).as_deref()
+                        == Some("UserId")
+                {
+                    security_required = true;
                 }
             }
         }
     }
 
     // Analyze return type to extract response
-    if let syn::ReturnType::Type(_, return_type) = &func.sig.output {
-        if let Type::Path(TypePath { path, .. }) = &**return_type {
-            if let Some(segment) = path.segments.last() {
-                // Handle Result<Json<T>> or ApiResult<Json<T>>
-                if segment.ident == "Result" || segment.ident == "ApiResult" {
-                    if let PathArguments::AngleBracketed(args) = &segment.arguments {
-                        if let Some(GenericArgument::Type(Type::Path(TypePath { path, .. }))) =
-                            args.args.first()
-                        {
-                            if let Some(inner_segment) = path.segments.last() {
-                                if inner_segment.ident == "Json" {
-                                    if let PathArguments::AngleBracketed(args) =
-                                        &inner_segment.arguments
-                                    {
-                                        if let Some(GenericArgument::Type(Type::Path(TypePath {
-                                            path,
-                                            ..
-                                        }))) = args.args.first()
-                                        {
-                                            response_type = Some(quote! { #path });
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
+    if let syn::ReturnType::Type(_, return_type) = &func.sig.output
+        && let Type::Path(TypePath { path, .. }) = &**return_type
+        && let Some(segment) = path.segments.last()
+    {
+        // Handle Result<Json<T>> or ApiResult<Json<T>>
+        if (segment.ident == "Result" || segment.ident == "ApiResult")
+            && let PathArguments::AngleBracketed(args) = &segment.arguments
+            && let Some(GenericArgument::Type(Type::Path(TypePath { path, .. }))) =
+                args.args.first()
+            && let Some(inner_segment) = path.segments.last()
+            && inner_segment.ident == "Json"
+            && let PathArguments::AngleBracketed(args) = &inner_segment.arguments
+            && let Some(GenericArgument::Type(Type::Path(TypePath { path, .. }))) =
+                args.args.first()
+        {
+            response_type = Some(quote! { #path });
         }
     }
 
@@ -126,9 +177,11 @@ fn generate_utoipa_attribute(
     let method_ident = syn::Ident::new(method, proc_macro2::Span::call_site());
     let summary = route_info.summary.as_deref().unwrap_or("");
     let description = route_info.description.as_deref().unwrap_or("");
-    let deprecated = route_info.deprecated;
     // Combine auto-detected security with explicit security flag
-    let has_security = security_required || route_info.security;
+    let has_security = security_required
+        || route_info.security
+        || route_info.security_optional
+        || route_info.security_scheme.is_some();
 
     // Build the attribute body
     let mut utoipa_body = quote! {
@@ -136,6 +189,13 @@ fn generate_utoipa_attribute(
         path = #path,
     };
 
+    if let Some(operation_id) = &route_info.operation_id {
+        utoipa_body = quote! {
+            #utoipa_body
+            operation_id = #operation_id,
+        };
+    }
+
     // Add summary if present
     if !summary.is_empty() {
         utoipa_body = quote! {
@@ -165,21 +225,70 @@ fn generate_utoipa_attribute(
         };
     }
 
+    // Multipart uploads are documented with their declared form schema
+    if let Some(schema) = &route_info.multipart {
+        request_body = Some(quote! {
+            request_body(content = #schema, content_type = "multipart/form-data")
+        });
+    }
+
+    // Streamed uploads have no schema to infer
+    let upload = route_info
+        .upload
+        .clone()
+        .or_else(|| has_streaming_body(&func.sig).then(UploadInfo::default));
+    if let Some(upload) = &upload {
+        let content_type = upload.content_type();
+        request_body = Some(quote! {
+            request_body(content = String, content_type = #content_type)
+        });
+    }
+
     // Add request body if found
-    if let Some(body_type) = request_body_type {
+    if let Some(request_body) = request_body {
         utoipa_body = quote! {
             #utoipa_body
-            request_body = #body_type,
+            #request_body,
         };
     }
 
-    // Add response if found
+    // Query structs are documented through their IntoParams impl
+    let query_params = query_params_types(&func.sig, &route_info.ignore_params);
+    if !query_params.is_empty() {
+        utoipa_body = quote! {
+            #utoipa_body
+            params(#(#query_params),*),
+        };
+    }
+
+    // Add response if found; by-id routes also document 404
     if let Some(resp_type) = response_type {
+        let not_found = has_path_extractor(&func.sig)
+            .then(|| quote! { (status = 404, description = "Not found"), });
+        let too_large = upload
+            .as_ref()
+            .and_then(|upload| upload.max.as_ref())
+            .map(|max| {
+                let description = format!("Upload larger than {}", max.value());
+                quote! { (status = 413, description = #description), }
+            });
+        let headers = &route_info.response_headers;
+        let headers = (!headers.is_empty()).then(|| quote! { , headers(#(#headers),*) });
+        let success = match route_info.status.unwrap_or(200) {
+            204 => quote! { (status = 204, description = "No content" #headers), },
+            status => {
+                quote! { (status = #status, description = "Success", body = #resp_type #headers), }
+            }
+        };
+        let unauthorized = (!route_info.security_optional)
+            .then(|| quote! { (status = 401, description = "Unauthorized"), });
         utoipa_body = quote! {
             #utoipa_body
             responses(
-                (status = 200, description = "Success", body = #resp_type),
-                (status = 401, description = "Unauthorized"),
+                #success
+                #unauthorized
+                #not_found
+                #too_large
                 (status = 500, description = "Internal server error")
             ),
         };
@@ -187,20 +296,25 @@ fn generate_utoipa_attribute(
 
     // Add security if UserId extension is present or explicit security flag is set
     if has_security {
+        let scheme = match (&route_info.security_scheme, &route_info.security_cookie) {
+            (Some(scheme), _) => scheme.value(),
+            (None, Some(_)) => "cookie".to_string(),
+            (None, None) => "bearer".to_string(),
+        };
+        let scopes = &route_info.security_scopes;
+        let anonymous = route_info.security_optional.then(|| quote! { (), });
         utoipa_body = quote! {
             #utoipa_body
             security(
-                ("bearer" = [])
+                #anonymous
+                (#scheme = [#(#scopes),*])
             ),
         };
     }
 
-    // Add deprecated flag
-    if deprecated {
-        utoipa_body = quote! {
-            #utoipa_body
-            deprecated,
-        };
+    // openapi(append = { ... }) goes last, verbatim
+    if let Some(append) = &route_info.openapi_append {
+        utoipa_body = quote! { #utoipa_body #append };
     }
 
     quote! {
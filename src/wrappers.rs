@@ -0,0 +1,1470 @@
+//! Handler wrapper generation.
+//!
+//! Wrappers split a handler into a hidden implementation method and a
+//! wrapper that keeps the original name and signature, transforming the
+//! response (HATEOAS links, envelopes, CSV export, ...).
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::ImplItem;
+
+use crate::codegen::buffer_request_body;
+use crate::parse::{
+    CanaryInfo, LinkInfo, MediaVersions, MirrorTarget, Policy, PublishInfo, SignedInfo,
+    VerifySignatureInfo,
+};
+
+/// Helper to extract T from Result<Json<T>>, Result<Json<T>, E> or Json<T> return types
+pub fn extract_inner_type(ty: &syn::Type) -> Option<syn::Type> {
+    if let syn::Type::Path(tp) = ty {
+        // Check if it matches Result<...>
+        if let Some(seg) = tp.path.segments.last() {
+            if seg.ident == "Result"
+                && let syn::PathArguments::AngleBracketed(args) = &seg.arguments
+                && let Some(syn::GenericArgument::Type(inner)) = args.args.first()
+            {
+                // We found the Success type of Result. Check if it's Json<T>
+                return extract_json_type(inner);
+            }
+            // Check if it matches Json<...>
+            if seg.ident == "Json" {
+                return extract_json_type(ty);
+            }
+        }
+    }
+    None
+}
+
+fn extract_json_type(ty: &syn::Type) -> Option<syn::Type> {
+    if let syn::Type::Path(tp) = ty
+        && let Some(seg) = tp.path.segments.last()
+        && seg.ident == "Json"
+        && let syn::PathArguments::AngleBracketed(args) = &seg.arguments
+        && let Some(syn::GenericArgument::Type(inner)) = args.args.first()
+    {
+        return Some(inner.clone());
+    }
+    // If not Json<...>, return None as we only support wrapping Json responses for now
+    None
+}
+
+pub fn extract_hateoas_inner_type(ty: &syn::Type) -> Option<&syn::Type> {
+    extract_generic_inner(ty, "HateoasResponse")
+}
+
+/// Helper to extract T from `Name<T>`, matching on the last path segment
+pub fn extract_generic_inner<'a>(ty: &'a syn::Type, name: &str) -> Option<&'a syn::Type> {
+    if let syn::Type::Path(tp) = ty
+        && let Some(seg) = tp.path.segments.last()
+        && seg.ident == name
+        && let syn::PathArguments::AngleBracketed(args) = &seg.arguments
+        && let Some(syn::GenericArgument::Type(inner)) = args.args.first()
+    {
+        return Some(inner);
+    }
+    None
+}
+
+/// Whether the handler returns a `Result`, so wrappers know to propagate with `?`
+fn returns_result(output: &syn::ReturnType) -> bool {
+    matches!(
+        output,
+        syn::ReturnType::Type(_, ty) if matches!(
+            &**ty,
+            syn::Type::Path(tp) if tp.path.segments.last().is_some_and(|s| s.ident == "Result")
+        )
+    )
+}
+
+/// Error type `E` of a handler returning `Result<T, E>` (not the defaulted `Result<T>`)
+pub fn extract_result_error(output: &syn::ReturnType) -> Option<&syn::Type> {
+    let syn::ReturnType::Type(_, ty) = output else {
+        return None;
+    };
+    if let syn::Type::Path(tp) = &**ty
+        && let Some(seg) = tp.path.segments.last()
+        && seg.ident == "Result"
+        && let syn::PathArguments::AngleBracketed(args) = &seg.arguments
+        && let Some(syn::GenericArgument::Type(error)) = args.args.iter().nth(1)
+    {
+        return Some(error);
+    }
+    None
+}
+
+/// `?` for handlers returning a `Result`, nothing otherwise
+fn propagate_error(output: &syn::ReturnType) -> TokenStream {
+    if returns_result(output) {
+        quote! { ? }
+    } else {
+        quote! {}
+    }
+}
+
+/// Wrapper return type `Result<Json<ok>>`, keeping the handler's concrete error
+/// type when it returns `Result<_, E>` so `?` doesn't need a conversion
+fn wrapped_output(output: &syn::ReturnType, ok: TokenStream) -> syn::ReturnType {
+    match extract_result_error(output) {
+        Some(error) => syn::parse_quote! { -> std::result::Result<Json<#ok>, #error> },
+        None => syn::parse_quote! { -> Result<Json<#ok>> },
+    }
+}
+
+/// Split a handler into a hidden implementation method (pushed onto `items`)
+/// and a wrapper that keeps the original name, attributes and argument types.
+///
+/// Returns the wrapper together with the awaited call forwarding to the
+/// implementation (not yet unwrapped); the caller fills in the wrapper's
+/// output and body.
+fn split_handler(
+    method: &syn::ImplItemFn,
+    hidden_prefix: &str,
+    items: &mut Vec<ImplItem>,
+) -> (syn::ImplItemFn, TokenStream) {
+    let impl_ident = format_ident!("{}{}", hidden_prefix, method.sig.ident);
+
+    // 1. Implementation Method (renamed, hidden, no route attr)
+    let mut impl_method = method.clone();
+    impl_method.sig.ident = impl_ident.clone();
+    impl_method.attrs.retain(|a| !a.path().is_ident("route"));
+    impl_method.attrs.push(syn::parse_quote!(#[doc(hidden)]));
+    impl_method
+        .attrs
+        .push(syn::parse_quote!(#[allow(non_snake_case)]));
+    items.push(ImplItem::Fn(impl_method));
+
+    // 2. Wrapper Method (original name, forwards args). Each argument is bound to a
+    // plain name, so any pattern (`Path(id)`, `Query(Filter { .. })`, `_`) is handed
+    // to the implementation as the extracted value and destructured there.
+    let mut wrapper_method = method.clone();
+    let args: Vec<_> = wrapper_method
+        .sig
+        .inputs
+        .iter_mut()
+        .enumerate()
+        .map(|(i, arg)| match arg {
+            syn::FnArg::Typed(pat) => {
+                let ident = format_ident!("__arg{}", i);
+                *pat.pat = syn::parse_quote! { #ident };
+                quote! { #ident }
+            }
+            syn::FnArg::Receiver(_) => quote! { self },
+        })
+        .collect();
+    let call = quote! { Self::#impl_ident( #(#args),* ).await };
+
+    (wrapper_method, call)
+}
+
+/// Wrap a handler returning `Json<T>` so the response carries HATEOAS links
+pub fn wrap_hateoas(
+    method: syn::ImplItemFn,
+    links: &[LinkInfo],
+    items: &mut Vec<ImplItem>,
+) -> syn::ImplItemFn {
+    let Some(inner_type) = (match &method.sig.output {
+        syn::ReturnType::Type(_, ty) => extract_inner_type(ty),
+        _ => None,
+    }) else {
+        return method;
+    };
+
+    let propagate = propagate_error(&method.sig.output);
+    let (mut wrapper_method, call) = split_handler(&method, "__impl_", items);
+
+    // Change return type to Result<Json<HateoasResponse<T>>>
+    // Use short names assuming they are in scope (via prelude) to help Utoipa resolution
+    wrapper_method.sig.output =
+        wrapped_output(&method.sig.output, quote! { HateoasResponse<#inner_type> });
+
+    // Links statements
+    let link_stmts = links.iter().map(|l| {
+        let rel = &l.rel;
+        let href = &l.href;
+        let method = l.method.as_deref().unwrap_or("GET");
+        quote! {
+            h = h.add_link(#rel, Link::new(#href).method(#method));
+        }
+    });
+
+    wrapper_method.block = syn::parse_quote! {
+        {
+            let Json(data) = #call #propagate;
+            let mut h = HateoasResponse::new(data);
+            #(#link_stmts)*
+            Ok(Json(h))
+        }
+    };
+    wrapper_method
+}
+
+/// Wrap a handler returning `Json<T>` in the controller's response envelope
+pub fn wrap_envelope(
+    method: syn::ImplItemFn,
+    envelope: &syn::Path,
+    items: &mut Vec<ImplItem>,
+) -> syn::ImplItemFn {
+    let Some(inner_type) = (match &method.sig.output {
+        syn::ReturnType::Type(_, ty) => extract_inner_type(ty),
+        _ => None,
+    }) else {
+        return method;
+    };
+
+    let propagate = propagate_error(&method.sig.output);
+    let (mut wrapper_method, call) = split_handler(&method, "__envelope_", items);
+
+    wrapper_method.sig.output =
+        wrapped_output(&method.sig.output, quote! { #envelope<#inner_type> });
+    wrapper_method.block = syn::parse_quote! {
+        {
+            let Json(data) = #call #propagate;
+            Ok(Json(#envelope::new(data)))
+        }
+    };
+    wrapper_method
+}
+
+/// Wrap a handler returning `Json<Vec<T>>` so the rows are served as a CSV download
+pub fn wrap_csv(
+    method: syn::ImplItemFn,
+    filename: &str,
+    items: &mut Vec<ImplItem>,
+) -> syn::ImplItemFn {
+    let is_result = returns_result(&method.sig.output);
+    let (mut wrapper_method, call) = split_handler(&method, "__csv_", items);
+
+    let rows = if is_result {
+        quote! {
+            match #call {
+                Ok(eywa_axum::Json(rows)) => rows,
+                Err(e) => return e.into_response(),
+            }
+        }
+    } else {
+        quote! { #call.0 }
+    };
+    let disposition = format!("attachment; filename=\"{}\"", filename);
+
+    wrapper_method.sig.output = syn::parse_quote! {
+        -> eywa_axum::axum::response::Response
+    };
+    wrapper_method.block = syn::parse_quote! {
+        {
+            use eywa_axum::axum::response::IntoResponse;
+
+            let rows = #rows;
+            let mut writer = eywa_axum::csv::Writer::from_writer(Vec::new());
+            for row in &rows {
+                if writer.serialize(row).is_err() {
+                    return eywa_axum::axum::http::StatusCode::INTERNAL_SERVER_ERROR.into_response();
+                }
+            }
+            let Ok(body) = writer.into_inner() else {
+                return eywa_axum::axum::http::StatusCode::INTERNAL_SERVER_ERROR.into_response();
+            };
+            (
+                [
+                    (eywa_axum::axum::http::header::CONTENT_TYPE, "text/csv"),
+                    (eywa_axum::axum::http::header::CONTENT_DISPOSITION, #disposition),
+                ],
+                body,
+            )
+                .into_response()
+        }
+    };
+    wrapper_method
+}
+
+/// Media type served for a `negotiate(...)` format
+pub fn negotiated_media_type(format: &str) -> Option<&'static str> {
+    match format {
+        "json" => Some("application/json"),
+        "csv" => Some("text/csv"),
+        "xml" => Some("application/xml"),
+        _ => None,
+    }
+}
+
+/// Wrap a handler returning `Json<T>` so the body is serialized in the format
+/// preferred by `Accept` among `formats` (the first one for `*/*` or no header),
+/// answering 406 when none is acceptable.
+///
+/// `rows` marks a `Vec<T>` payload, written as one CSV record per element.
+pub fn wrap_negotiate(
+    method: syn::ImplItemFn,
+    formats: &[String],
+    rows: bool,
+    items: &mut Vec<ImplItem>,
+) -> syn::ImplItemFn {
+    let is_result = returns_result(&method.sig.output);
+    let (mut wrapper_method, call) = split_handler(&method, "__negotiate_", items);
+
+    let data = if is_result {
+        quote! {
+            match #call {
+                Ok(eywa_axum::Json(data)) => data,
+                Err(e) => return e.into_response(),
+            }
+        }
+    } else {
+        quote! { #call.0 }
+    };
+    let write_csv = if rows {
+        quote! {
+            for row in &data {
+                writer.serialize(row).map_err(|_| ())?;
+            }
+        }
+    } else {
+        quote! { writer.serialize(&data).map_err(|_| ())?; }
+    };
+    let serialize_xml = if rows {
+        quote! {
+            data.iter()
+                .map(|row| eywa_axum::quick_xml::se::to_string_with_root("item", row))
+                .collect::<Result<String, _>>()
+                .map(|rows| format!("<items>{}</items>", rows))
+        }
+    } else {
+        quote! { eywa_axum::quick_xml::se::to_string(&data) }
+    };
+    let arms = formats.iter().filter_map(|format| {
+        let media_type = negotiated_media_type(format)?;
+        let body = match format.as_str() {
+            "json" => quote! { eywa_axum::serde_json::to_vec(&data).map_err(|_| ()) },
+            "csv" => quote! {
+                (|| {
+                    let mut writer = eywa_axum::csv::Writer::from_writer(Vec::new());
+                    #write_csv
+                    writer.into_inner().map_err(|_| ())
+                })()
+            },
+            _ => quote! { #serialize_xml.map(String::into_bytes).map_err(|_| ()) },
+        };
+        Some(quote! { #media_type => #body, })
+    });
+    let offered: Vec<&str> = formats
+        .iter()
+        .filter_map(|format| negotiated_media_type(format))
+        .collect();
+
+    wrapper_method.sig.inputs.insert(
+        0,
+        syn::parse_quote! { __request_headers: eywa_axum::axum::http::HeaderMap },
+    );
+    wrapper_method.sig.output = syn::parse_quote! {
+        -> eywa_axum::axum::response::Response
+    };
+    wrapper_method.block = syn::parse_quote! {
+        {
+            use eywa_axum::axum::response::IntoResponse;
+
+            const OFFERED: &[&str] = &[#(#offered),*];
+            // Accepted media ranges by descending quality; `text/xml` stands for `application/xml`
+            let mut accepted: Vec<(&str, f32)> = __request_headers
+                .get(eywa_axum::axum::http::header::ACCEPT)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or("*/*")
+                .split(',')
+                .filter_map(|range| {
+                    let mut parts = range.split(';').map(str::trim);
+                    let media = parts.next().filter(|m| !m.is_empty())?;
+                    let q = parts
+                        .find_map(|p| p.strip_prefix("q="))
+                        .and_then(|q| q.parse().ok())
+                        .unwrap_or(1.0);
+                    Some((if media == "text/xml" { "application/xml" } else { media }, q))
+                })
+                .filter(|(_, q)| *q > 0.0)
+                .collect();
+            accepted.sort_by(|a, b| b.1.total_cmp(&a.1));
+            let chosen = accepted.iter().find_map(|(media, _)| {
+                OFFERED.iter().copied().find(|offered| {
+                    *media == "*/*"
+                        || media == offered
+                        || media
+                            .strip_suffix("/*")
+                            .is_some_and(|kind| offered.split('/').next() == Some(kind))
+                })
+            });
+            let Some(media_type) = chosen else {
+                return (
+                    eywa_axum::axum::http::StatusCode::NOT_ACCEPTABLE,
+                    format!("Supported media types: {}", OFFERED.join(", ")),
+                )
+                    .into_response();
+            };
+
+            let data = #data;
+            let body: Result<Vec<u8>, ()> = match media_type {
+                #(#arms)*
+                _ => Err(()),
+            };
+            match body {
+                Ok(body) => (
+                    [
+                        (eywa_axum::axum::http::header::CONTENT_TYPE, media_type),
+                        (eywa_axum::axum::http::header::VARY, "Accept"),
+                    ],
+                    body,
+                )
+                    .into_response(),
+                Err(()) => eywa_axum::axum::http::StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+            }
+        }
+    };
+    wrapper_method
+}
+
+/// Wrap a handler returning `Json<T>` so the data is converted into the version
+/// requested by `Accept: application/vnd.app.v2+json` (`From<T>` for each version type).
+///
+/// Plain JSON requests (`application/json`, `*/*` or no header) get the first
+/// version; a version that is not offered is answered with 406.
+pub fn wrap_media_versions(
+    method: syn::ImplItemFn,
+    versions: &MediaVersions,
+    items: &mut Vec<ImplItem>,
+) -> syn::ImplItemFn {
+    let is_result = returns_result(&method.sig.output);
+    let (mut wrapper_method, call) = split_handler(&method, "__media_versions_", items);
+
+    let data = if is_result {
+        quote! {
+            match #call {
+                Ok(eywa_axum::Json(data)) => data,
+                Err(e) => return e.into_response(),
+            }
+        }
+    } else {
+        quote! { #call.0 }
+    };
+    let offered: Vec<String> = versions
+        .versions
+        .iter()
+        .map(|(name, _)| versions.media_type(name))
+        .collect();
+    let to_body = |ty: &syn::Type| {
+        quote! { eywa_axum::serde_json::to_vec(&<#ty as ::core::convert::From<_>>::from(data)) }
+    };
+    let arms = versions
+        .versions
+        .iter()
+        .zip(&offered)
+        .skip(1)
+        .map(|((_, ty), media_type)| {
+            let body = to_body(ty);
+            quote! { #media_type => #body, }
+        });
+    // The default version takes the fallback arm rather than panicking on a media type
+    // the negotiation picked but the arms don't list
+    let default_body = to_body(&versions.versions[0].1);
+
+    wrapper_method.sig.inputs.insert(
+        0,
+        syn::parse_quote! { __request_headers: eywa_axum::axum::http::HeaderMap },
+    );
+    wrapper_method.sig.output = syn::parse_quote! {
+        -> eywa_axum::axum::response::Response
+    };
+    wrapper_method.block = syn::parse_quote! {
+        {
+            use eywa_axum::axum::response::IntoResponse;
+
+            const OFFERED: &[&str] = &[#(#offered),*];
+            let mut accepted: Vec<(&str, f32)> = __request_headers
+                .get(eywa_axum::axum::http::header::ACCEPT)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or("*/*")
+                .split(',')
+                .filter_map(|range| {
+                    let mut parts = range.split(';').map(str::trim);
+                    let media = parts.next().filter(|m| !m.is_empty())?;
+                    let q = parts
+                        .find_map(|p| p.strip_prefix("q="))
+                        .and_then(|q| q.parse().ok())
+                        .unwrap_or(1.0);
+                    Some((media, q))
+                })
+                .filter(|(_, q)| *q > 0.0)
+                .collect();
+            accepted.sort_by(|a, b| b.1.total_cmp(&a.1));
+            let chosen = accepted.iter().find_map(|(media, _)| match *media {
+                "*/*" | "application/*" | "application/json" => Some(OFFERED[0]),
+                media => OFFERED
+                    .iter()
+                    .copied()
+                    .find(|offered| offered.eq_ignore_ascii_case(media)),
+            });
+            let Some(media_type) = chosen else {
+                return (
+                    eywa_axum::axum::http::StatusCode::NOT_ACCEPTABLE,
+                    format!("Supported media types: {}", OFFERED.join(", ")),
+                )
+                    .into_response();
+            };
+
+            let data = #data;
+            let body = match media_type {
+                #(#arms)*
+                _ => #default_body,
+            };
+            match body {
+                Ok(body) => (
+                    [
+                        (eywa_axum::axum::http::header::CONTENT_TYPE, media_type),
+                        (eywa_axum::axum::http::header::VARY, "Accept"),
+                    ],
+                    body,
+                )
+                    .into_response(),
+                Err(_) => eywa_axum::axum::http::StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+            }
+        }
+    };
+    wrapper_method
+}
+
+/// Wrap a mutation handler so its event is pushed to the `eywa_axum::EventPublisher`
+/// from state once the handler succeeded (`Ok` for `Result` handlers).
+///
+/// The event is built with `From<&T>` of the response data, or of the `Json<T>`
+/// request body for `from = "request"`; publication failures are logged.
+pub fn wrap_publish(
+    method: syn::ImplItemFn,
+    publish: &PublishInfo,
+    items: &mut Vec<ImplItem>,
+) -> syn::Result<syn::ImplItemFn> {
+    let is_result = returns_result(&method.sig.output);
+    let event = &publish.event;
+    let name = &publish.name;
+    let (mut wrapper_method, call) = split_handler(&method, "__publish_", items);
+
+    let publish_event = quote! {
+        if let Err(err) = __publisher.publish(#name, &event).await {
+            eywa_axum::tracing::warn!(
+                target: "eywa_axum::publish",
+                error = %err,
+                event = #name,
+                "event publication failed"
+            );
+        }
+    };
+    let body = if publish.uses_request() {
+        let body = method
+            .sig
+            .inputs
+            .iter()
+            .enumerate()
+            .find_map(|(i, arg)| match arg {
+                syn::FnArg::Typed(pat) => extract_generic_inner(&pat.ty, "Json").map(|ty| (i, ty)),
+                syn::FnArg::Receiver(_) => None,
+            });
+        let Some((index, body_ty)) = body else {
+            return Err(syn::Error::new_spanned(
+                &publish.from,
+                "`from = \"request\"` needs a `Json<T>` body argument",
+            ));
+        };
+        // The event is built before the handler consumes the body
+        let arg = format_ident!("__arg{}", index);
+        let succeeded = if is_result {
+            quote! { result.is_ok() }
+        } else {
+            quote! { true }
+        };
+        quote! {
+            let event = <#event as ::core::convert::From<&#body_ty>>::from(&#arg.0);
+            let result = #call;
+            if #succeeded {
+                #publish_event
+            }
+            result
+        }
+    } else {
+        let data_ty = match &method.sig.output {
+            syn::ReturnType::Type(_, ty) => extract_inner_type(ty),
+            syn::ReturnType::Default => None,
+        };
+        let Some(data_ty) = data_ty else {
+            return Err(syn::Error::new_spanned(
+                &method.sig.output,
+                "`publish` routes must return `Json<T>` or `Result<Json<T>>` unless `from = \"request\"`",
+            ));
+        };
+        let data = if is_result {
+            quote! { Ok(eywa_axum::Json(data)) }
+        } else {
+            quote! { eywa_axum::Json(data) }
+        };
+        quote! {
+            let result = #call;
+            if let #data = &result {
+                let event = <#event as ::core::convert::From<&#data_ty>>::from(data);
+                #publish_event
+            }
+            result
+        }
+    };
+
+    wrapper_method.sig.inputs.insert(
+        0,
+        syn::parse_quote! {
+            eywa_axum::axum::extract::State(__publisher): eywa_axum::axum::extract::State<eywa_axum::EventPublisher>
+        },
+    );
+    wrapper_method.block = syn::parse_quote! {
+        {
+            #body
+        }
+    };
+    Ok(wrapper_method)
+}
+
+/// Wrap a long-running job handler so it answers `202 Accepted`, pointing
+/// `Location` at the job's status route when one is configured.
+///
+/// The returned job must have an `id` field; it fills the status route's path
+/// parameter.
+pub fn wrap_async_job(
+    method: syn::ImplItemFn,
+    status_path: Option<&str>,
+    items: &mut Vec<ImplItem>,
+) -> syn::ImplItemFn {
+    let is_result = returns_result(&method.sig.output);
+    let (mut wrapper_method, call) = split_handler(&method, "__job_", items);
+
+    let job = if is_result {
+        quote! {
+            match #call {
+                Ok(eywa_axum::Json(job)) => job,
+                Err(e) => return e.into_response(),
+            }
+        }
+    } else {
+        quote! { #call.0 }
+    };
+
+    let respond = match status_path {
+        Some(path) => {
+            // Split around the first path parameter, which receives the job id
+            let (before, after) = split_first_param(path);
+            quote! {
+                let location = format!("{}{}{}", #before, job.id, #after);
+                (
+                    eywa_axum::axum::http::StatusCode::ACCEPTED,
+                    [(eywa_axum::axum::http::header::LOCATION, location)],
+                    eywa_axum::Json(job),
+                )
+                    .into_response()
+            }
+        }
+        None => quote! {
+            (eywa_axum::axum::http::StatusCode::ACCEPTED, eywa_axum::Json(job)).into_response()
+        },
+    };
+
+    wrapper_method.sig.output = syn::parse_quote! {
+        -> eywa_axum::axum::response::Response
+    };
+    wrapper_method.block = syn::parse_quote! {
+        {
+            use eywa_axum::axum::response::IntoResponse;
+
+            let job = #job;
+            #respond
+        }
+    };
+    wrapper_method
+}
+
+/// Split a path around its first parameter (`{id}` or `:id`)
+fn split_first_param(path: &str) -> (String, String) {
+    for (start, c) in path.char_indices() {
+        let end = match c {
+            '{' => path[start..].find('}').map(|i| start + i + 1),
+            ':' => Some(path[start..].find('/').map_or(path.len(), |i| start + i)),
+            _ => None,
+        };
+        if let Some(end) = end {
+            return (path[..start].to_string(), path[end..].to_string());
+        }
+    }
+    (path.to_string(), String::new())
+}
+
+/// Wrap a handler so it sets `Last-Modified` from the response data and
+/// answers `If-Modified-Since` with `304 Not Modified`.
+///
+/// `source` is a field of the handler's data (single identifier) or a
+/// function taking it by reference; either must yield something convertible
+/// into `SystemTime`. `layers` counts the wrappers (HATEOAS, envelope) already
+/// nesting the data under a `data` field.
+pub fn wrap_last_modified(
+    method: syn::ImplItemFn,
+    source: &syn::Path,
+    layers: usize,
+    items: &mut Vec<ImplItem>,
+) -> syn::ImplItemFn {
+    let is_result = returns_result(&method.sig.output);
+    let (mut wrapper_method, call) = split_handler(&method, "__last_modified_", items);
+
+    let body = if is_result {
+        quote! {
+            match #call {
+                Ok(body) => body,
+                Err(e) => return e.into_response(),
+            }
+        }
+    } else {
+        call
+    };
+
+    let mut data = quote! { body.0 };
+    for _ in 0..layers {
+        data = quote! { #data.data };
+    }
+    let modified = match source.get_ident() {
+        Some(field) => quote! { #data.#field.clone().into() },
+        None => quote! { #source(&#data).into() },
+    };
+
+    wrapper_method.sig.inputs.insert(
+        0,
+        syn::parse_quote! { __request_headers: eywa_axum::axum::http::HeaderMap },
+    );
+    wrapper_method.sig.output = syn::parse_quote! {
+        -> eywa_axum::axum::response::Response
+    };
+    wrapper_method.block = syn::parse_quote! {
+        {
+            use eywa_axum::axum::response::IntoResponse;
+
+            let body = #body;
+            let modified: std::time::SystemTime = #modified;
+            let last_modified = eywa_axum::httpdate::fmt_http_date(modified);
+
+            // HTTP dates have second precision
+            let not_modified = __request_headers
+                .get(eywa_axum::axum::http::header::IF_MODIFIED_SINCE)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| eywa_axum::httpdate::parse_http_date(v).ok())
+                .zip(eywa_axum::httpdate::parse_http_date(&last_modified).ok())
+                .is_some_and(|(since, modified)| modified <= since);
+            if not_modified {
+                return (
+                    eywa_axum::axum::http::StatusCode::NOT_MODIFIED,
+                    [(eywa_axum::axum::http::header::LAST_MODIFIED, last_modified)],
+                )
+                    .into_response();
+            }
+
+            (
+                [(eywa_axum::axum::http::header::LAST_MODIFIED, last_modified)],
+                body,
+            )
+                .into_response()
+        }
+    };
+    wrapper_method
+}
+
+/// Wrap a handler so its response carries the `Location` of the created resource,
+/// `template` with each `{field}` replaced by that field of the response data,
+/// percent-encoded.
+///
+/// `layers` counts the wrappers (HATEOAS, envelope) already nesting the data
+/// under a `data` field.
+pub fn wrap_location(
+    method: syn::ImplItemFn,
+    template: &syn::LitStr,
+    layers: usize,
+    items: &mut Vec<ImplItem>,
+) -> syn::Result<syn::ImplItemFn> {
+    let (format, fields) = location_format(template)?;
+    let is_result = returns_result(&method.sig.output);
+    let (mut wrapper_method, call) = split_handler(&method, "__location_", items);
+
+    let body = if is_result {
+        quote! {
+            match #call {
+                Ok(body) => body,
+                Err(e) => return e.into_response(),
+            }
+        }
+    } else {
+        call
+    };
+
+    let mut data = quote! { body.0 };
+    for _ in 0..layers {
+        data = quote! { #data.data };
+    }
+    let encode = encode_path_segment();
+    let fields = fields
+        .iter()
+        .map(|field| quote! { encode(&#data.#field.to_string()) });
+
+    wrapper_method.sig.output = syn::parse_quote! {
+        -> eywa_axum::axum::response::Response
+    };
+    wrapper_method.block = syn::parse_quote! {
+        {
+            use eywa_axum::axum::response::IntoResponse;
+
+            let body = #body;
+            let encode = #encode;
+            let location = format!(#format, #(#fields),*);
+            (
+                [(eywa_axum::axum::http::header::LOCATION, location)],
+                body,
+            )
+                .into_response()
+        }
+    };
+    Ok(wrapper_method)
+}
+
+/// Split a `location` template into a format string and the fields filling it
+fn location_format(template: &syn::LitStr) -> syn::Result<(String, Vec<syn::Ident>)> {
+    let value = template.value();
+    let mut format = String::new();
+    let mut fields = Vec::new();
+    let mut rest = value.as_str();
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}').map(|i| start + i) else {
+            return Err(syn::Error::new_spanned(
+                template,
+                "unclosed `{` in `location`",
+            ));
+        };
+        let field = syn::parse_str::<syn::Ident>(&rest[start + 1..end]).map_err(|_| {
+            syn::Error::new_spanned(
+                template,
+                format!("`{{{}}}` is not a field name", &rest[start + 1..end]),
+            )
+        })?;
+        format.push_str(&rest[..start].replace('}', "}}"));
+        format.push_str("{}");
+        fields.push(syn::Ident::new(&field.to_string(), template.span()));
+        rest = &rest[end + 1..];
+    }
+    format.push_str(&rest.replace('}', "}}"));
+    Ok((format, fields))
+}
+
+/// Wrap a handler returning `Json<CursorPage<T>>` in a HATEOAS response whose
+/// `next` link carries the page's `next_cursor`, keeping the other query
+/// parameters (such as `limit`) of the current request
+pub fn wrap_cursor_page(
+    method: syn::ImplItemFn,
+    links: &[LinkInfo],
+    items: &mut Vec<ImplItem>,
+) -> syn::ImplItemFn {
+    let Some(inner_type) = (match &method.sig.output {
+        syn::ReturnType::Type(_, ty) => extract_inner_type(ty),
+        _ => None,
+    }) else {
+        return method;
+    };
+
+    let propagate = propagate_error(&method.sig.output);
+    let (mut wrapper_method, call) = split_handler(&method, "__cursor_", items);
+
+    let link_stmts = links.iter().map(|l| {
+        let rel = &l.rel;
+        let href = &l.href;
+        let method = l.method.as_deref().unwrap_or("GET");
+        quote! {
+            h = h.add_link(#rel, Link::new(#href).method(#method));
+        }
+    });
+
+    wrapper_method.sig.inputs.insert(
+        0,
+        syn::parse_quote! { __request_uri: eywa_axum::axum::extract::OriginalUri },
+    );
+    wrapper_method.sig.output =
+        wrapped_output(&method.sig.output, quote! { HateoasResponse<#inner_type> });
+    wrapper_method.block = syn::parse_quote! {
+        {
+            let Json(page) = #call #propagate;
+            let mut h = HateoasResponse::new(page);
+            #(#link_stmts)*
+            if let Some(cursor) = h.data.next_cursor.clone() {
+                let encode = |value: &str| {
+                    value
+                        .bytes()
+                        .map(|b| match b {
+                            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                                (b as char).to_string()
+                            }
+                            _ => format!("%{:02X}", b),
+                        })
+                        .collect::<String>()
+                };
+                let uri = &__request_uri.0;
+                let mut href = format!("{}?cursor={}", uri.path(), encode(&cursor));
+                if let Ok(eywa_axum::axum::extract::Query(query)) =
+                    eywa_axum::axum::extract::Query::<Vec<(String, String)>>::try_from_uri(uri)
+                {
+                    for (key, value) in query.iter().filter(|(key, _)| key != "cursor") {
+                        href.push_str(&format!("&{}={}", encode(key), encode(value)));
+                    }
+                }
+                h = h.add_link("next", Link::new(&href).method("GET"));
+            }
+            Ok(Json(h))
+        }
+    };
+    wrapper_method
+}
+
+/// Wrap a handler so it only runs for requests whose URL carries a valid,
+/// unexpired signature.
+///
+/// The signature is the hex HMAC-SHA256 of the path and query string without
+/// the signature parameter, which must include an `expires` Unix timestamp.
+pub fn wrap_signed(
+    method: syn::ImplItemFn,
+    signed: &SignedInfo,
+    state_ty: &syn::Path,
+    items: &mut Vec<ImplItem>,
+) -> syn::ImplItemFn {
+    let (mut wrapper_method, call) = split_handler(&method, "__signed_", items);
+    let query_param = &signed.query;
+    let secret = &signed.secret;
+    let decode_hex = decode_hex();
+    let verify_hmac = verify_hmac_sha256();
+
+    wrapper_method.sig.inputs.insert(
+        0,
+        syn::parse_quote! { __signed_state: eywa_axum::axum::extract::State<#state_ty> },
+    );
+    wrapper_method.sig.inputs.insert(
+        1,
+        syn::parse_quote! { __signed_uri: eywa_axum::axum::extract::OriginalUri },
+    );
+    wrapper_method.sig.output = syn::parse_quote! {
+        -> eywa_axum::axum::response::Response
+    };
+    wrapper_method.block = syn::parse_quote! {
+        {
+            use eywa_axum::axum::response::IntoResponse;
+
+            let uri = &__signed_uri.0;
+            let mut signature = None;
+            let mut expires = None;
+            let mut signed_pairs = Vec::new();
+            for pair in uri.query().unwrap_or_default().split('&').filter(|p| !p.is_empty()) {
+                match pair.split_once('=') {
+                    Some((#query_param, value)) => signature = Some(value),
+                    Some(("expires", value)) => {
+                        expires = value.parse::<u64>().ok();
+                        signed_pairs.push(pair);
+                    }
+                    _ => signed_pairs.push(pair),
+                }
+            }
+            let message = if signed_pairs.is_empty() {
+                uri.path().to_string()
+            } else {
+                format!("{}?{}", uri.path(), signed_pairs.join("&"))
+            };
+
+            let signature = signature.and_then(|s| #decode_hex(s));
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs());
+            let valid = match (signature, expires) {
+                (Some(signature), Some(expires)) if expires >= now => {
+                    let secret = #secret(&__signed_state.0);
+                    #verify_hmac(AsRef::<[u8]>::as_ref(&secret), message.as_bytes(), &signature)
+                }
+                _ => false,
+            };
+            if !valid {
+                return (
+                    eywa_axum::axum::http::StatusCode::FORBIDDEN,
+                    "Invalid or expired signature",
+                )
+                    .into_response();
+            }
+
+            #call.into_response()
+        }
+    };
+    wrapper_method
+}
+
+/// Wrap a handler so `guard(&mut parts, &state)` runs before any of its
+/// extractors; an `Err(response)` is answered as is.
+pub fn wrap_guard(
+    method: syn::ImplItemFn,
+    guard: &syn::Path,
+    state_ty: &syn::Path,
+    items: &mut Vec<ImplItem>,
+) -> syn::ImplItemFn {
+    let impl_ident = format_ident!("__guard_{}", method.sig.ident);
+    let (mut wrapper_method, _) = split_handler(&method, "__guard_", items);
+
+    // The wrapper takes the whole request and hands it on once the guard passes
+    wrapper_method.sig.inputs = syn::parse_quote! {
+        eywa_axum::axum::extract::State(__guard_state): eywa_axum::axum::extract::State<#state_ty>,
+        __guard_request: eywa_axum::axum::extract::Request
+    };
+    wrapper_method.sig.output = syn::parse_quote! {
+        -> eywa_axum::axum::response::Response
+    };
+    wrapper_method.block = syn::parse_quote! {
+        {
+            use eywa_axum::axum::handler::Handler;
+
+            let (mut parts, body) = __guard_request.into_parts();
+            if let Err(response) = #guard(&mut parts, &__guard_state).await {
+                return response;
+            }
+            let request = eywa_axum::axum::extract::Request::from_parts(parts, body);
+            Handler::<_, #state_ty>::call(Self::#impl_ident, request, __guard_state).await
+        }
+    };
+    wrapper_method
+}
+
+/// Wrap a handler so it only runs when its authorization policy allows the
+/// request, answering 403 otherwise.
+///
+/// The policy sees the raw path parameters and the request extensions, where
+/// authentication middleware leaves the caller's identity.
+pub fn wrap_policy(
+    method: syn::ImplItemFn,
+    policy: &Policy,
+    state_ty: &syn::Path,
+    items: &mut Vec<ImplItem>,
+) -> syn::ImplItemFn {
+    let impl_ident = format_ident!("__policy_{}", method.sig.ident);
+    let (mut wrapper_method, _) = split_handler(&method, "__policy_", items);
+
+    let allowed = match policy {
+        Policy::Named(name) => quote! {
+            eywa_axum::Authorizer::from_ref(&__policy_state)
+                .authorize(#name, &params, &parts.extensions)
+                .await
+        },
+        Policy::Fn(path) => quote! {
+            #path(&__policy_state, &params, &parts.extensions).await
+        },
+    };
+
+    wrapper_method.sig.inputs = syn::parse_quote! {
+        eywa_axum::axum::extract::State(__policy_state): eywa_axum::axum::extract::State<#state_ty>,
+        __policy_request: eywa_axum::axum::extract::Request
+    };
+    wrapper_method.sig.output = syn::parse_quote! {
+        -> eywa_axum::axum::response::Response
+    };
+    wrapper_method.block = syn::parse_quote! {
+        {
+            use eywa_axum::axum::extract::{FromRef, FromRequestParts};
+            use eywa_axum::axum::handler::Handler;
+            use eywa_axum::axum::response::IntoResponse;
+
+            let (mut parts, body) = __policy_request.into_parts();
+            let raw = match eywa_axum::axum::extract::RawPathParams::from_request_parts(
+                &mut parts,
+                &__policy_state,
+            )
+            .await
+            {
+                Ok(raw) => raw,
+                Err(rejection) => return rejection.into_response(),
+            };
+            let params: Vec<(&str, &str)> = raw.iter().collect();
+            if !#allowed {
+                return (eywa_axum::axum::http::StatusCode::FORBIDDEN, "Forbidden by policy")
+                    .into_response();
+            }
+            let request = eywa_axum::axum::extract::Request::from_parts(parts, body);
+            Handler::<_, #state_ty>::call(Self::#impl_ident, request, __policy_state).await
+        }
+    };
+    wrapper_method
+}
+
+/// Wrap a handler so it runs in a transaction begun from the state's
+/// `eywa_axum::TransactionFactory`, handed to it as an `eywa_axum::Transaction`
+/// extension.
+///
+/// The transaction is committed when the response is 2xx and rolled back
+/// otherwise; a failed commit turns the response into a 500.
+pub fn wrap_transactional(
+    method: syn::ImplItemFn,
+    state_ty: &syn::Path,
+    items: &mut Vec<ImplItem>,
+) -> syn::ImplItemFn {
+    let impl_ident = format_ident!("__transactional_{}", method.sig.ident);
+    let (mut wrapper_method, _) = split_handler(&method, "__transactional_", items);
+
+    wrapper_method.sig.inputs = syn::parse_quote! {
+        eywa_axum::axum::extract::State(__transaction_state): eywa_axum::axum::extract::State<#state_ty>,
+        mut __transaction_request: eywa_axum::axum::extract::Request
+    };
+    wrapper_method.sig.output = syn::parse_quote! {
+        -> eywa_axum::axum::response::Response
+    };
+    wrapper_method.block = syn::parse_quote! {
+        {
+            use eywa_axum::axum::extract::FromRef;
+            use eywa_axum::axum::handler::Handler;
+            use eywa_axum::axum::response::IntoResponse;
+
+            let factory = eywa_axum::TransactionFactory::from_ref(&__transaction_state);
+            let transaction = match factory.begin().await {
+                Ok(transaction) => transaction,
+                Err(err) => {
+                    eywa_axum::tracing::warn!(
+                        target: "eywa_axum::transaction",
+                        error = %err,
+                        "cannot begin transaction"
+                    );
+                    return eywa_axum::axum::http::StatusCode::INTERNAL_SERVER_ERROR.into_response();
+                }
+            };
+            __transaction_request.extensions_mut().insert(transaction.clone());
+
+            let response =
+                Handler::<_, #state_ty>::call(Self::#impl_ident, __transaction_request, __transaction_state)
+                    .await;
+            if response.status().is_success() {
+                if let Err(err) = transaction.commit().await {
+                    eywa_axum::tracing::warn!(
+                        target: "eywa_axum::transaction",
+                        error = %err,
+                        "cannot commit transaction"
+                    );
+                    return eywa_axum::axum::http::StatusCode::INTERNAL_SERVER_ERROR.into_response();
+                }
+            } else if let Err(err) = transaction.rollback().await {
+                eywa_axum::tracing::warn!(
+                    target: "eywa_axum::transaction",
+                    error = %err,
+                    "cannot roll back transaction"
+                );
+            }
+            response
+        }
+    };
+    wrapper_method
+}
+
+/// Wrap a webhook receiver so its body is buffered and checked against an
+/// HMAC-SHA256 signature header before the handler's extractors run.
+///
+/// The header holds the hex digest, optionally prefixed with `sha256=`.
+pub fn wrap_verify_signature(
+    method: syn::ImplItemFn,
+    verify: &VerifySignatureInfo,
+    state_ty: &syn::Path,
+    items: &mut Vec<ImplItem>,
+) -> syn::ImplItemFn {
+    let impl_ident = format_ident!("__verify_signature_{}", method.sig.ident);
+    let (mut wrapper_method, _) = split_handler(&method, "__verify_signature_", items);
+    let header = &verify.header;
+    let secret = &verify.secret;
+    let buffer_body = buffer_request_body();
+    let decode_hex = decode_hex();
+    let verify_hmac = verify_hmac_sha256();
+
+    // The wrapper takes the whole request and replays it into the handler
+    wrapper_method.sig.inputs = syn::parse_quote! {
+        eywa_axum::axum::extract::State(__verify_state): eywa_axum::axum::extract::State<#state_ty>,
+        __verify_request: eywa_axum::axum::extract::Request
+    };
+    wrapper_method.sig.output = syn::parse_quote! {
+        -> eywa_axum::axum::response::Response
+    };
+    wrapper_method.block = syn::parse_quote! {
+        {
+            use eywa_axum::axum::handler::Handler;
+            use eywa_axum::axum::response::IntoResponse;
+
+            // Buffered within the body limit before the signature is checked
+            let req = __verify_request;
+            #buffer_body
+
+            let signature = parts
+                .headers
+                .get(#header)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.trim().strip_prefix("sha256=").unwrap_or(v.trim()))
+                .and_then(|v| #decode_hex(v));
+            let secret = #secret(&__verify_state);
+            let valid = signature.is_some_and(|signature| {
+                #verify_hmac(AsRef::<[u8]>::as_ref(&secret), &bytes, &signature)
+            });
+            if !valid {
+                return (
+                    eywa_axum::axum::http::StatusCode::UNAUTHORIZED,
+                    "Invalid request signature",
+                )
+                    .into_response();
+            }
+
+            let request = eywa_axum::axum::extract::Request::from_parts(
+                parts,
+                eywa_axum::axum::body::Body::from(bytes),
+            );
+            Handler::<_, #state_ty>::call(Self::#impl_ident, request, __verify_state).await
+        }
+    };
+    wrapper_method
+}
+
+/// Closure expression percent-encoding a path segment, keeping only unreserved characters
+pub fn encode_path_segment() -> TokenStream {
+    quote! {
+        (|segment: &str| -> String {
+            segment
+                .bytes()
+                .map(|b| match b {
+                    b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                        (b as char).to_string()
+                    }
+                    _ => format!("%{:02X}", b),
+                })
+                .collect()
+        })
+    }
+}
+
+/// Closure expression decoding a hex string into bytes (`None` if malformed)
+fn decode_hex() -> TokenStream {
+    quote! {
+        (|s: &str| -> Option<Vec<u8>> {
+            if s.len() % 2 != 0 {
+                return None;
+            }
+            (0..s.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+                .collect()
+        })
+    }
+}
+
+/// Closure expression checking an HMAC-SHA256 tag in constant time
+fn verify_hmac_sha256() -> TokenStream {
+    quote! {
+        (|key: &[u8], message: &[u8], tag: &[u8]| -> bool {
+            use eywa_axum::hmac::Mac;
+
+            eywa_axum::hmac::Hmac::<eywa_axum::sha2::Sha256>::new_from_slice(key).is_ok_and(|mut mac| {
+                mac.update(message);
+                mac.verify_slice(tag).is_ok()
+            })
+        })
+    }
+}
+
+/// Wrap a handler so a sample of its requests is copied, fire-and-forget, to
+/// another handler of the controller or to an upstream URL. The shadow's
+/// response and failures never affect the caller.
+pub fn wrap_mirror(
+    method: syn::ImplItemFn,
+    target: &MirrorTarget,
+    percent: u8,
+    state_ty: &syn::Path,
+    items: &mut Vec<ImplItem>,
+) -> syn::ImplItemFn {
+    let impl_ident = format_ident!("__mirror_{}", method.sig.ident);
+    let (mut wrapper_method, _) = split_handler(&method, "__mirror_", items);
+    let buffer_body = buffer_request_body();
+    let percent = u64::from(percent);
+
+    let send = match target {
+        MirrorTarget::Handler(handler) => quote! {
+            let state = __mirror_state.clone();
+            eywa_axum::tokio::spawn(async move {
+                let _ = Handler::<_, #state_ty>::call(Self::#handler, copy, state).await;
+            });
+        },
+        MirrorTarget::Url(url) => quote! {
+            eywa_axum::mirror::forward(#url, copy);
+        },
+    };
+
+    wrapper_method.sig.inputs = syn::parse_quote! {
+        eywa_axum::axum::extract::State(__mirror_state): eywa_axum::axum::extract::State<#state_ty>,
+        __mirror_request: eywa_axum::axum::extract::Request
+    };
+    wrapper_method.sig.output = syn::parse_quote! {
+        -> eywa_axum::axum::response::Response
+    };
+    wrapper_method.block = syn::parse_quote! {
+        {
+            use eywa_axum::axum::handler::Handler;
+
+            static REQUESTS: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+            // Spread evenly: mirror whenever the running share crosses a whole request
+            let n = REQUESTS.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            let sampled = (n + 1) * #percent / 100 > n * #percent / 100;
+            if !sampled {
+                return Handler::<_, #state_ty>::call(Self::#impl_ident, __mirror_request, __mirror_state).await;
+            }
+
+            // Only sampled requests are buffered, within the body limit
+            let req = __mirror_request;
+            #buffer_body
+
+            let mut copy = eywa_axum::axum::extract::Request::new(
+                eywa_axum::axum::body::Body::from(bytes.clone()),
+            );
+            *copy.method_mut() = parts.method.clone();
+            *copy.uri_mut() = parts.uri.clone();
+            *copy.version_mut() = parts.version;
+            *copy.headers_mut() = parts.headers.clone();
+            *copy.extensions_mut() = parts.extensions.clone();
+            #send
+
+            let request = eywa_axum::axum::extract::Request::from_parts(
+                parts,
+                eywa_axum::axum::body::Body::from(bytes),
+            );
+            Handler::<_, #state_ty>::call(Self::#impl_ident, request, __mirror_state).await
+        }
+    };
+    wrapper_method
+}
+
+/// Replace a canary route's handler with one dispatching each request to the
+/// stable or canary handler. The split is deterministic per value of the key
+/// header (FNV-1a hash), so a client keeps seeing the same variant.
+pub fn canary_dispatcher(
+    mut method: syn::ImplItemFn,
+    canary: &CanaryInfo,
+    state_ty: &syn::Path,
+) -> syn::ImplItemFn {
+    let stable = &canary.stable;
+    let canary_handler = &canary.canary;
+    let percent = u64::from(canary.percent);
+    let key = &canary.key;
+
+    method.sig.inputs = syn::parse_quote! {
+        eywa_axum::axum::extract::State(__canary_state): eywa_axum::axum::extract::State<#state_ty>,
+        __canary_request: eywa_axum::axum::extract::Request
+    };
+    method.sig.output = syn::parse_quote! {
+        -> eywa_axum::axum::response::Response
+    };
+    method.block = syn::parse_quote! {
+        {
+            use eywa_axum::axum::handler::Handler;
+
+            let use_canary = __canary_request
+                .headers()
+                .get(#key)
+                .map(|value| {
+                    let hash = value.as_bytes().iter().fold(0xcbf29ce484222325u64, |hash, b| {
+                        (hash ^ u64::from(*b)).wrapping_mul(0x100000001b3)
+                    });
+                    hash % 100 < #percent
+                })
+                .unwrap_or(false);
+            if use_canary {
+                Handler::<_, #state_ty>::call(Self::#canary_handler, __canary_request, __canary_state).await
+            } else {
+                Handler::<_, #state_ty>::call(Self::#stable, __canary_request, __canary_state).await
+            }
+        }
+    };
+    method
+}
+
+/// Replace a proxy route's handler with a streaming pass-through to the
+/// upstream, using the `eywa_axum::ProxyClient` from state. The route's
+/// static prefix is stripped from the request path before it is appended to
+/// the upstream URL; hop-by-hop headers are dropped in both directions.
+pub fn proxy_handler(
+    mut method: syn::ImplItemFn,
+    upstream: &syn::LitStr,
+    full_path: &str,
+) -> syn::ImplItemFn {
+    // Everything up to the first path parameter is rewritten away
+    let static_prefix = full_path
+        .find(['{', '*', ':'])
+        .map_or(full_path, |idx| &full_path[..idx]);
+
+    method.sig.inputs = syn::parse_quote! {
+        eywa_axum::axum::extract::State(__proxy_client): eywa_axum::axum::extract::State<eywa_axum::ProxyClient>,
+        __proxy_request: eywa_axum::axum::extract::Request
+    };
+    method.sig.output = syn::parse_quote! {
+        -> eywa_axum::axum::response::Response
+    };
+    method.block = syn::parse_quote! {
+        {
+            use eywa_axum::axum::http::{HeaderMap, StatusCode, header};
+            use eywa_axum::axum::response::IntoResponse;
+
+            fn strip_hop_by_hop(headers: &mut HeaderMap) {
+                // Headers named in `Connection` are hop-by-hop as well
+                let listed: Vec<String> = headers
+                    .get_all(header::CONNECTION)
+                    .iter()
+                    .filter_map(|value| value.to_str().ok())
+                    .flat_map(|value| value.split(','))
+                    .map(|name| name.trim().to_ascii_lowercase())
+                    .filter(|name| !name.is_empty())
+                    .collect();
+                for name in listed {
+                    headers.remove(name.as_str());
+                }
+                for name in [
+                    "connection",
+                    "keep-alive",
+                    "proxy-authenticate",
+                    "proxy-authorization",
+                    "te",
+                    "trailer",
+                    "transfer-encoding",
+                    "upgrade",
+                ] {
+                    headers.remove(name);
+                }
+            }
+
+            let (mut parts, body) = __proxy_request.into_parts();
+            let path = parts.uri.path();
+            let rest = path.strip_prefix(#static_prefix).unwrap_or(path);
+            let mut target = format!(
+                "{}/{}",
+                #upstream.trim_end_matches('/'),
+                rest.trim_start_matches('/')
+            );
+            if let Some(query) = parts.uri.query() {
+                target.push('?');
+                target.push_str(query);
+            }
+            parts.uri = match target.parse() {
+                Ok(uri) => uri,
+                Err(_) => return StatusCode::BAD_GATEWAY.into_response(),
+            };
+            strip_hop_by_hop(&mut parts.headers);
+            parts.headers.remove(header::HOST);
+
+            let request = eywa_axum::axum::extract::Request::from_parts(parts, body);
+            match __proxy_client.send(request).await {
+                Ok(mut response) => {
+                    strip_hop_by_hop(response.headers_mut());
+                    response
+                }
+                Err(err) => {
+                    eywa_axum::tracing::warn!(error = %err, upstream = #upstream, "proxy request failed");
+                    StatusCode::BAD_GATEWAY.into_response()
+                }
+            }
+        }
+    };
+    method
+}
@@ -0,0 +1,128 @@
+//! `#[derive(ApiError)]` and standalone `#[route]` handlers
+
+mod common;
+
+use common::{TestState, get, json, send};
+use eywa_axum::axum::http::StatusCode;
+use eywa_axum::axum::response::IntoResponse;
+use eywa_axum::prelude::*;
+use eywa_axum::{ApiError, controller, route};
+use utoipa::IntoResponses;
+
+#[derive(Debug, ApiError)]
+pub enum ProjectError {
+    /// Project not found
+    #[api_error(status = 404)]
+    NotFound,
+    #[api_error(status = 409, title = "Name already taken")]
+    Conflict {
+        name: String,
+    },
+    Internal(String),
+}
+
+/// Fetch a project on its own
+#[route(GET "/projects/{id}")]
+async fn standalone(Path(id): Path<u32>) -> Result<Json<u32>> {
+    Ok(Json(id))
+}
+
+pub struct ErrorsController;
+
+#[controller(path = "/errors", state = TestState)]
+impl ErrorsController {
+    #[route(GET "/{id}")]
+    async fn get(Path(id): Path<u32>) -> Result<Json<u32>, ProjectError> {
+        match id {
+            1 => Err(ProjectError::NotFound),
+            2 => Err(ProjectError::Conflict {
+                name: "apollo".to_string(),
+            }),
+            3 => Err(ProjectError::Internal("db down".to_string())),
+            _ => Ok(Json(id)),
+        }
+    }
+}
+
+#[test]
+fn maps_variants_to_statuses() {
+    assert_eq!(ProjectError::NotFound.status_code(), StatusCode::NOT_FOUND);
+    assert_eq!(
+        ProjectError::Conflict {
+            name: String::new()
+        }
+        .status_code(),
+        StatusCode::CONFLICT
+    );
+    assert_eq!(
+        ProjectError::Internal(String::new()).status_code(),
+        StatusCode::INTERNAL_SERVER_ERROR
+    );
+}
+
+#[tokio::test]
+async fn answers_problem_details() {
+    let response = ProjectError::Conflict {
+        name: "apollo".to_string(),
+    }
+    .into_response();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    assert_eq!(
+        response.headers()["content-type"],
+        "application/problem+json"
+    );
+
+    let state = TestState::default();
+    let router = || ErrorsController::into_router(state.clone());
+    let reply = send(router(), state.clone(), get("/api/errors/2")).await;
+    assert_eq!(reply.status, 409);
+    let problem: eywa_axum::serde_json::Value =
+        eywa_axum::serde_json::from_str(&reply.body).unwrap();
+    assert_eq!(problem["title"], "Name already taken");
+    assert_eq!(problem["status"], 409);
+
+    assert_eq!(
+        send(router(), state.clone(), get("/api/errors/1"))
+            .await
+            .status,
+        404
+    );
+    assert_eq!(
+        send(router(), state.clone(), get("/api/errors/3"))
+            .await
+            .status,
+        500
+    );
+    assert_eq!(send(router(), state, get("/api/errors/4")).await.body, "4");
+}
+
+#[test]
+fn documents_every_status() {
+    let responses = ProjectError::responses();
+    let statuses: Vec<&str> = responses.keys().map(String::as_str).collect();
+    assert_eq!(statuses, ["404", "409", "500"]);
+
+    let spec = json(ErrorsController::openapi_fragment());
+    let documented = &spec["paths"]["/api/errors/{id}"]["get"]["responses"];
+    assert!(documented["404"].is_object());
+    assert!(documented["409"].is_object());
+}
+
+#[test]
+fn documents_standalone_routes() {
+    use utoipa::Path as _;
+
+    assert_eq!(__path_standalone::path(), "/projects/{id}");
+    let operation = eywa_axum::serde_json::to_value(__path_standalone::operation()).unwrap();
+    assert_eq!(operation["summary"], "Fetch a project on its own");
+    assert_eq!(operation["responses"]["200"]["description"], "Success");
+    assert_eq!(operation["responses"]["404"]["description"], "Not found");
+}
+
+#[tokio::test]
+async fn serves_standalone_routes() {
+    let router = eywa_axum::axum::Router::new()
+        .route("/projects/{id}", eywa_axum::axum::routing::get(standalone));
+    let reply = send(router, (), get("/projects/8")).await;
+    assert_eq!(reply.body, "8");
+}
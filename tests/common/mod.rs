@@ -0,0 +1,87 @@
+//! Shared state and request helpers of the controller tests
+
+#![allow(dead_code)]
+
+use eywa_axum::axum::Router;
+use eywa_axum::axum::body::{Body, to_bytes};
+use eywa_axum::axum::extract::FromRef;
+use eywa_axum::axum::http::{HeaderMap, Request, StatusCode};
+use eywa_axum::{Authorizer, EventPublisher, ProxyClient, TransactionFactory};
+use tower::ServiceExt;
+
+/// Application state holding every collaborator the generated code takes from state
+#[derive(Clone, Default)]
+pub struct TestState {
+    pub publisher: EventPublisher,
+    pub transactions: TransactionFactory,
+    pub authorizer: Authorizer,
+    pub proxy: ProxyClient,
+}
+
+impl FromRef<TestState> for EventPublisher {
+    fn from_ref(state: &TestState) -> Self {
+        state.publisher.clone()
+    }
+}
+
+impl FromRef<TestState> for TransactionFactory {
+    fn from_ref(state: &TestState) -> Self {
+        state.transactions.clone()
+    }
+}
+
+impl FromRef<TestState> for Authorizer {
+    fn from_ref(state: &TestState) -> Self {
+        state.authorizer.clone()
+    }
+}
+
+impl FromRef<TestState> for ProxyClient {
+    fn from_ref(state: &TestState) -> Self {
+        state.proxy.clone()
+    }
+}
+
+/// A response as seen by the tests
+pub struct Reply {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: String,
+}
+
+impl Reply {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|value| value.to_str().ok())
+    }
+}
+
+/// Runs one request through `router`
+pub async fn send<S>(router: Router<S>, state: S, request: Request<Body>) -> Reply
+where
+    S: Clone + Send + Sync + 'static,
+{
+    let response = router.with_state(state).oneshot(request).await.unwrap();
+    let (parts, body) = response.into_parts();
+    let body = to_bytes(body, usize::MAX).await.unwrap();
+    Reply {
+        status: parts.status,
+        headers: parts.headers,
+        body: String::from_utf8_lossy(&body).into_owned(),
+    }
+}
+
+pub fn get(uri: &str) -> Request<Body> {
+    Request::get(uri).body(Body::empty()).unwrap()
+}
+
+pub fn post_json(uri: &str, body: &str) -> Request<Body> {
+    Request::post(uri)
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+/// `value` as JSON, e.g. a controller's `openapi_fragment()`
+pub fn json<T: eywa_axum::Serialize>(value: T) -> eywa_axum::serde_json::Value {
+    eywa_axum::serde_json::to_value(value).unwrap()
+}
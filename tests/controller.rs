@@ -0,0 +1,299 @@
+//! Routing, response shaping and OpenAPI registration of `#[controller]`
+
+mod common;
+
+use common::{TestState, get, json, post_json, send};
+use eywa_axum::prelude::*;
+use eywa_axum::{Deserialize, Serialize, controller};
+use utoipa::{IntoParams, ToSchema};
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Project {
+    pub id: u32,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ProjectFilter {
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct Paging {
+    pub limit: Option<u32>,
+}
+
+pub struct ProjectsController;
+
+#[controller(
+    path = "/projects",
+    version = "v1",
+    state = TestState,
+    tag = "Projects",
+    schemas(Project)
+)]
+impl ProjectsController {
+    /// List projects
+    ///
+    /// Every project the caller can see.
+    #[route(GET "/", operation_id = "listProjects")]
+    async fn list() -> Json<Vec<Project>> {
+        Json(vec![project(1)])
+    }
+
+    #[route(GET "/{id}", links((rel = "self", href = "/api/v1/projects/1")))]
+    async fn get(Path(id): Path<u32>) -> Result<Json<Project>> {
+        if id == 0 {
+            return Err(eywa_axum::axum::http::StatusCode::NOT_FOUND);
+        }
+        Ok(Json(project(id)))
+    }
+
+    #[route(POST "/", location = "/api/v1/projects/{id}")]
+    async fn create(Json(new): Json<Project>) -> Json<Project> {
+        Json(new)
+    }
+
+    #[route(GET "/search", filterable(ProjectFilter), sortable = ["name"])]
+    async fn search(Extension(filter): Extension<ProjectFilter>) -> Json<Vec<Project>> {
+        let mut found = project(2);
+        found.name = filter.name.unwrap_or_default();
+        Json(vec![found])
+    }
+
+    #[route(GET "/paged", paginated = "cursor")]
+    async fn paged() -> Json<CursorPage<Project>> {
+        Json(CursorPage {
+            items: vec![project(3)],
+            next_cursor: Some("abc".to_string()),
+        })
+    }
+
+    #[route(GET "/export", negotiate(json, csv, xml))]
+    async fn export() -> Json<Vec<Project>> {
+        Json(vec![project(1), project(2)])
+    }
+
+    #[route(DELETE "/{id}", status = 204)]
+    async fn delete(Path(_id): Path<u32>) -> Json<Project> {
+        Json(project(0))
+    }
+
+    #[route(GET "/pages/{page}", param_constraints((page, minimum = 1, maximum = 100), (limit, maximum = 50), enforce))]
+    async fn page(Path(page): Path<u32>, Query(_paging): Query<Paging>) -> Json<u32> {
+        Json(page)
+    }
+
+    #[route(GET "/ranked/{rank}", param_constraints((rank, minimum = 1)))]
+    async fn ranked(Path(rank): Path<u32>) -> Json<u32> {
+        Json(rank)
+    }
+
+    #[route(POST "/jobs", async_job)]
+    async fn start() -> Json<eywa_axum::JobStatus> {
+        Json(eywa_axum::JobStatus {
+            id: "7".to_string(),
+            state: "queued".to_string(),
+        })
+    }
+}
+
+fn project(id: u32) -> Project {
+    Project {
+        id,
+        name: format!("project {id}"),
+    }
+}
+
+async fn call(
+    request: eywa_axum::axum::http::Request<eywa_axum::axum::body::Body>,
+) -> common::Reply {
+    send(
+        ProjectsController::into_router(TestState::default()),
+        TestState::default(),
+        request,
+    )
+    .await
+}
+
+#[test]
+fn prefix_and_tag() {
+    assert_eq!(
+        <ProjectsController as IntoRouter<TestState>>::prefix(),
+        "/api/v1/projects"
+    );
+    assert_eq!(
+        <ProjectsController as IntoRouter<TestState>>::tag(),
+        "Projects"
+    );
+}
+
+#[tokio::test]
+async fn serves_routes_under_the_versioned_prefix() {
+    let reply = call(get("/api/v1/projects/")).await;
+    assert_eq!(reply.status, 200);
+    assert_eq!(reply.body, r#"[{"id":1,"name":"project 1"}]"#);
+
+    assert_eq!(call(get("/projects/")).await.status, 404);
+}
+
+#[tokio::test]
+async fn adds_hateoas_links() {
+    let reply = call(get("/api/v1/projects/4")).await;
+    assert_eq!(reply.status, 200);
+    let body: eywa_axum::serde_json::Value = eywa_axum::serde_json::from_str(&reply.body).unwrap();
+    assert_eq!(body["data"]["id"], 4);
+    assert_eq!(body["links"]["self"]["href"], "/api/v1/projects/1");
+
+    assert_eq!(call(get("/api/v1/projects/0")).await.status, 404);
+}
+
+#[tokio::test]
+async fn answers_created_with_location() {
+    let reply = call(post_json("/api/v1/projects/", r#"{"id":9,"name":"new"}"#)).await;
+    assert_eq!(reply.status, 201);
+    assert_eq!(reply.header("location"), Some("/api/v1/projects/9"));
+}
+
+#[tokio::test]
+async fn filters_and_validates_sort() {
+    let reply = call(get("/api/v1/projects/search?name=apollo&sort=name")).await;
+    assert_eq!(reply.status, 200);
+    assert!(reply.body.contains(r#""name":"apollo""#), "{}", reply.body);
+
+    assert_eq!(
+        call(get("/api/v1/projects/search?sort=owner")).await.status,
+        400
+    );
+}
+
+#[tokio::test]
+async fn links_the_next_page() {
+    let reply = call(get("/api/v1/projects/paged")).await;
+    assert_eq!(reply.status, 200);
+    assert!(reply.body.contains("cursor=abc"), "{}", reply.body);
+}
+
+#[tokio::test]
+async fn negotiates_the_representation() {
+    let accept = |accept: &str| {
+        eywa_axum::axum::http::Request::get("/api/v1/projects/export")
+            .header("accept", accept)
+            .body(eywa_axum::axum::body::Body::empty())
+            .unwrap()
+    };
+
+    let json = call(accept("application/json")).await;
+    assert_eq!(json.header("content-type"), Some("application/json"));
+
+    let csv = call(accept("text/csv")).await;
+    assert_eq!(csv.status, 200);
+    assert_eq!(csv.body, "id,name\n1,project 1\n2,project 2\n");
+
+    let xml = call(accept("application/xml")).await;
+    assert_eq!(xml.status, 200);
+    assert!(xml.body.contains("<name>project 2</name>"), "{}", xml.body);
+
+    assert_eq!(call(accept("image/png")).await.status, 406);
+}
+
+#[tokio::test]
+async fn rewrites_the_success_status() {
+    let reply = call(
+        eywa_axum::axum::http::Request::delete("/api/v1/projects/1")
+            .body(eywa_axum::axum::body::Body::empty())
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(reply.status, 204);
+    assert_eq!(reply.body, "");
+}
+
+#[tokio::test]
+async fn accepts_async_jobs() {
+    let reply = call(post_json("/api/v1/projects/jobs", "")).await;
+    assert_eq!(reply.status, 202);
+    assert!(reply.body.contains(r#""id":"7""#), "{}", reply.body);
+}
+
+#[tokio::test]
+async fn enforces_parameter_bounds() {
+    let reply = call(get("/api/v1/projects/pages/3?limit=50")).await;
+    assert_eq!(reply.status, 200);
+    assert_eq!(reply.body, "3");
+
+    let reply = call(get("/api/v1/projects/pages/0")).await;
+    assert_eq!(reply.status, 400);
+    assert_eq!(reply.body, "`page` must be at least 1");
+    assert_eq!(call(get("/api/v1/projects/pages/101")).await.status, 400);
+    assert_eq!(
+        call(get("/api/v1/projects/pages/3?limit=51")).await.status,
+        400
+    );
+
+    // Without `enforce` the bounds are only documented
+    assert_eq!(call(get("/api/v1/projects/ranked/0")).await.status, 200);
+}
+
+#[test]
+fn documents_parameter_bounds() {
+    let spec = json(ProjectsController::openapi_fragment());
+    let page = &spec["paths"]["/api/v1/projects/pages/{page}"]["get"];
+    let parameter = |name: &str| {
+        page["parameters"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|parameter| parameter["name"] == name)
+            .unwrap()
+            .clone()
+    };
+    let number = parameter("page");
+    assert_eq!(number["in"], "path");
+    assert_eq!(number["schema"]["minimum"], 1);
+    assert_eq!(number["schema"]["maximum"], 100);
+    let limit = parameter("limit");
+    assert_eq!(limit["in"], "query");
+    assert_eq!(limit["schema"]["maximum"], 50);
+    assert!(page["responses"]["400"].is_object());
+
+    let ranked = &spec["paths"]["/api/v1/projects/ranked/{rank}"]["get"];
+    assert_eq!(ranked["parameters"][0]["schema"]["minimum"], 1);
+    assert!(ranked["parameters"][0]["schema"]["maximum"].is_null());
+}
+
+#[test]
+fn documents_every_route() {
+    let spec = json(ProjectsController::openapi_fragment());
+    let list = &spec["paths"]["/api/v1/projects/"]["get"];
+    assert_eq!(list["operationId"], "listProjects");
+    assert_eq!(list["summary"], "List projects");
+    assert_eq!(list["description"], "Every project the caller can see.");
+    assert_eq!(list["tags"][0], "Projects");
+
+    assert!(spec["paths"]["/api/v1/projects/{id}"]["delete"]["responses"]["204"].is_object());
+    assert!(spec["paths"]["/api/v1/projects/jobs"]["post"]["responses"]["202"].is_object());
+    assert!(spec["components"]["schemas"]["Project"].is_object());
+}
+
+#[test]
+fn lists_routes_from_a_static_table() {
+    let routes = <ProjectsController as IntoRouter<TestState>>::openapi_routes();
+    assert_eq!(routes.len(), 10);
+    assert_eq!(
+        routes[0],
+        eywa_axum::OpenApiPath {
+            path: "/api/v1/projects/",
+            method: "GET",
+            summary: "List projects",
+            description: "Every project the caller can see.",
+            tag: "Projects",
+        }
+    );
+    assert!(std::ptr::eq(
+        routes,
+        <ProjectsController as IntoRouter<TestState>>::openapi_routes()
+    ));
+}
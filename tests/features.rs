@@ -0,0 +1,308 @@
+//! A large controller on `compact_codegen` combining the documentation-heavy attributes
+
+mod common;
+
+use common::{TestState, get, json, post_json, send};
+use eywa_axum::axum::body::{Body, Bytes};
+use eywa_axum::axum::http::Request;
+use eywa_axum::axum::response::sse::{Event, Sse};
+use eywa_axum::prelude::*;
+use eywa_axum::{Deserialize, Serialize, Tenant, controller};
+use futures_util::stream;
+use std::convert::Infallible;
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Project {
+    pub id: u32,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ProjectV2 {
+    pub id: u32,
+    pub title: String,
+}
+
+impl From<Project> for ProjectV2 {
+    fn from(project: Project) -> Self {
+        Self {
+            id: project.id,
+            title: project.name,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ProjectCreated {
+    pub id: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Tick {
+    pub n: u32,
+}
+
+#[derive(ToSchema)]
+pub struct UploadForm {
+    pub title: String,
+    #[schema(value_type = String, format = Binary)]
+    pub file: Vec<u8>,
+}
+
+type Ticks = stream::Iter<std::vec::IntoIter<Result<Event, Infallible>>>;
+
+pub enum DomainError {
+    NotFound,
+    Conflict,
+}
+
+// `error_map` provides `From<DomainError> for Response`
+impl eywa_axum::axum::response::IntoResponse for DomainError {
+    fn into_response(self) -> eywa_axum::axum::response::Response {
+        self.into()
+    }
+}
+
+const SAMPLE_PROJECT: Project = Project {
+    id: 1,
+    name: String::new(),
+};
+
+pub struct CatalogController;
+
+#[controller(
+    path = "/catalog",
+    state = TestState,
+    tag = "Catalog",
+    display_name = "Project catalog",
+    compact_codegen,
+    auto_options,
+    batch,
+    measure_bodies,
+    log_fields(tenant = "extensions.Tenant"),
+    rest_conventions,
+    schema_naming = "short",
+    error_map(DomainError, (NotFound => 404), (Conflict => 409)),
+    error_messages(path = "tests/fixtures/errors/{locale}.toml", locales = ["en", "de"]),
+    security_scheme = "api_key",
+    summary_template = "{method} {resource}"
+)]
+impl CatalogController {
+    #[route(GET "/", operation_id = "listProjects", csv = "projects.csv")]
+    async fn list() -> Json<Vec<Project>> {
+        Json(vec![project(1), project(2)])
+    }
+
+    #[route(
+        GET "/{id}",
+        operation_id = "getProject",
+        media_versions((v1 = Project, v2 = ProjectV2)),
+        sdk_cache_ttl = "60s",
+        read_only,
+        slow_warn = "500ms",
+        trace_sample = 0.5,
+        example_response = SAMPLE_PROJECT
+    )]
+    async fn get(Path(id): Path<u32>) -> Result<Json<Project>, DomainError> {
+        match id {
+            0 => Err(DomainError::NotFound),
+            _ => Ok(Json(project(id))),
+        }
+    }
+
+    #[route(
+        POST "/",
+        operation_id = "createProject",
+        emits(event = "project.created", payload = ProjectCreated),
+        example_request = r#"{"id": 1, "name": "Apollo"}"#,
+        log(level = "debug", body = true, headers = ["x-request-id"]),
+        audit,
+        sensitive_params = ["name"]
+    )]
+    async fn create(Json(new): Json<Project>) -> Result<Json<Project>, DomainError> {
+        if new.id == 0 {
+            return Err(DomainError::Conflict);
+        }
+        Ok(Json(new))
+    }
+
+    #[route(PATCH "/{id}", patch_format = "merge", deprecated)]
+    async fn patch(Path(id): Path<u32>, Json(_patch): Json<Project>) -> Json<Project> {
+        Json(project(id))
+    }
+
+    #[route(DELETE "/{id}", security_optional)]
+    async fn remove(Path(_id): Path<u32>) -> Json<Project> {
+        Json(project(0))
+    }
+
+    #[route(POST "/bulk", bulk(body = Vec<Project>, partial_status = 207))]
+    async fn bulk(Json(projects): Json<Vec<Project>>) -> Json<Vec<Project>> {
+        Json(projects)
+    }
+
+    #[route(POST "/{id}/attachments", multipart(schema = UploadForm), created = Project)]
+    async fn attach(Path(id): Path<u32>, body: Bytes) -> Json<Project> {
+        Json(Project {
+            id,
+            name: body.len().to_string(),
+        })
+    }
+
+    #[route(PUT "/{id}/archive", upload(content_type = "application/zip", max = "1KB"))]
+    async fn archive(Path(id): Path<u32>, body: Body) -> Json<u32> {
+        let _ = body;
+        Json(id)
+    }
+
+    #[route(
+        GET "/{id}/flaky",
+        circuit_breaker(failure_ratio = 0.5, window = "30s", min_requests = 2),
+        headers((name = "X-Attempt", description = "Attempt number", schema = u32))
+    )]
+    async fn flaky(Path(_id): Path<u32>) -> eywa_axum::axum::http::StatusCode {
+        eywa_axum::axum::http::StatusCode::BAD_GATEWAY
+    }
+
+    #[route(GET "/{id}/ticks", messages(send = Tick))]
+    async fn ticks(Path(id): Path<u32>) -> Sse<Ticks> {
+        Sse::new(stream::iter(vec![
+            Ok(Event::default().data(id.to_string())),
+        ]))
+    }
+
+    #[route(GET, path = auto, param_formats(since = "date-time"))]
+    async fn recently_archived(
+        Query(_since): Query<std::collections::HashMap<String, String>>,
+    ) -> Json<Vec<Project>> {
+        Json(vec![])
+    }
+}
+
+fn project(id: u32) -> Project {
+    Project {
+        id,
+        name: format!("project {id}"),
+    }
+}
+
+async fn call(request: Request<Body>) -> common::Reply {
+    let state = TestState::default();
+    send(
+        CatalogController::into_router(state.clone()),
+        state,
+        request,
+    )
+    .await
+}
+
+#[test]
+fn documents_the_operations() {
+    let spec = json(CatalogController::openapi_fragment());
+    let paths = &spec["paths"];
+    let get = &paths["/api/catalog/{id}"]["get"];
+    assert_eq!(get["operationId"], "getProject");
+    assert_eq!(get["summary"], "GET Catalog");
+    assert_eq!(get["x-cache-ttl"], 60);
+    assert_eq!(get["x-read-only"], true);
+    assert!(get["responses"]["404"].is_object());
+
+    let create = &paths["/api/catalog/"]["post"];
+    assert!(create["responses"]["201"].is_object());
+    assert!(create["responses"]["409"].is_object());
+    assert_eq!(create["x-emits"][0]["event"], "project.created");
+    assert!(create["x-sensitive"].is_array());
+
+    assert_eq!(paths["/api/catalog/{id}"]["patch"]["deprecated"], true);
+    assert!(paths["/api/catalog/{id}"]["delete"]["responses"]["204"].is_object());
+    assert!(paths["/api/catalog/bulk"]["post"]["responses"]["207"].is_object());
+    assert!(paths["/api/catalog/{id}/archive"]["put"]["responses"]["413"].is_object());
+    assert!(paths["/api/catalog/{id}/flaky"]["get"]["x-circuit-breaker"].is_object());
+    assert!(paths["/api/catalog/recently-archived"]["get"].is_object());
+    assert!(paths["/api/catalog/_batch"]["post"].is_object());
+
+    let schemes = &spec["components"]["securitySchemes"];
+    assert_eq!(schemes["api_key"]["in"], "header");
+    assert!(spec["components"]["schemas"]["ProjectCreated"].is_object());
+}
+
+#[test]
+fn describes_channels_and_gateway_routes() {
+    let asyncapi = CatalogController::asyncapi_json();
+    assert_eq!(asyncapi["asyncapi"], "2.6.0");
+    assert!(asyncapi["channels"]["/api/catalog/{id}/ticks"].is_object());
+
+    let manifest: eywa_axum::serde_json::Value =
+        eywa_axum::serde_json::from_str(CatalogController::GATEWAY_MANIFEST).unwrap();
+    assert!(
+        manifest["routes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|route| route["operation_id"] == "listProjects")
+    );
+}
+
+#[tokio::test]
+async fn serves_versions_downloads_and_localized_errors() {
+    let accept = |uri: &str, accept: &str| {
+        Request::get(uri)
+            .header("accept", accept)
+            .body(Body::empty())
+            .unwrap()
+    };
+    let v2 = call(accept("/api/catalog/3", "application/vnd.app.v2+json")).await;
+    assert_eq!(v2.body, r#"{"id":3,"title":"project 3"}"#);
+
+    let csv = call(get("/api/catalog/")).await;
+    assert_eq!(
+        csv.header("content-disposition"),
+        Some(r#"attachment; filename="projects.csv""#)
+    );
+
+    let missing = call(accept("/api/catalog/0", "application/json")).await;
+    assert_eq!(missing.status, 404);
+    let request = Request::get("/api/catalog/0")
+        .header("accept-language", "de-AT, en")
+        .body(Body::empty())
+        .unwrap();
+    let localized = call(request).await;
+    assert!(
+        localized.body.contains("Projekt nicht gefunden"),
+        "{}",
+        localized.body
+    );
+
+    let conflict = call(post_json("/api/catalog/", r#"{"id":0,"name":"x"}"#)).await;
+    assert_eq!(conflict.status, 409);
+    let created = call(post_json("/api/catalog/", r#"{"id":5,"name":"x"}"#)).await;
+    assert_eq!(created.status, 201);
+}
+
+#[tokio::test]
+async fn lists_allowed_methods_and_runs_batches() {
+    let options = Request::options("/api/catalog/7")
+        .body(Body::empty())
+        .unwrap();
+    let reply = call(options).await;
+    let allow = reply.header("allow").unwrap_or_default().to_string();
+    for method in ["GET", "PATCH", "DELETE"] {
+        assert!(allow.contains(method), "{allow}");
+    }
+
+    let batch = r#"[{"operation_id": "getProject", "params": {"id": "4"}}]"#;
+    let reply = call(post_json("/api/catalog/_batch", batch)).await;
+    assert_eq!(reply.status, 200);
+    assert!(reply.body.contains("project 4"), "{}", reply.body);
+}
+
+#[tokio::test]
+async fn opens_the_circuit() {
+    for _ in 0..2 {
+        assert_eq!(call(get("/api/catalog/1/flaky")).await.status, 502);
+    }
+    let open = call(get("/api/catalog/1/flaky")).await;
+    assert_eq!(open.status, 503);
+    assert!(open.header("retry-after").is_some());
+}
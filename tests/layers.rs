@@ -0,0 +1,386 @@
+//! Controller and route layers generated by `#[controller]`
+
+mod common;
+
+use common::{Reply, TestState, get, post_json, send};
+use eywa_axum::axum::body::Body;
+use eywa_axum::axum::http::{Request, StatusCode, header};
+use eywa_axum::prelude::*;
+use eywa_axum::{Deserialize, Serialize, controller};
+use std::time::{Duration, SystemTime};
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Document {
+    pub id: u32,
+    pub body: String,
+    #[serde(skip)]
+    #[schema(ignore)]
+    pub updated_at: Option<SystemTime>,
+}
+
+fn modified_at(document: &Document) -> SystemTime {
+    document.updated_at.unwrap_or(SystemTime::UNIX_EPOCH)
+}
+
+pub struct ServedController;
+
+#[controller(
+    path = "/served",
+    state = TestState,
+    cors(origins = ["https://app.example.com"], headers = ["content-type"], max_age = "10m"),
+    security_headers(csp = "default-src 'none'"),
+    catch_panic,
+    request_id,
+    max_request_size = 64
+)]
+impl ServedController {
+    #[route(GET "/documents/{id}", last_modified = self::modified_at)]
+    async fn document(Path(id): Path<u32>) -> Json<Document> {
+        Json(Document {
+            id,
+            body: "x".repeat(2048),
+            updated_at: Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000)),
+        })
+    }
+
+    #[route(PUT "/documents/{id}", requires_if_match)]
+    async fn replace(Path(_id): Path<u32>, Json(document): Json<Document>) -> Json<Document> {
+        Json(document)
+    }
+
+    #[route(POST "/documents")]
+    async fn create(Json(document): Json<Document>) -> Json<Document> {
+        Json(document)
+    }
+
+    #[route(GET "/zipped", compress = "gzip")]
+    async fn zipped() -> String {
+        "z".repeat(4096)
+    }
+
+    #[route(GET "/raw", no_compress)]
+    async fn raw() -> &'static str {
+        "raw"
+    }
+
+    #[route(GET "/blob", ranges)]
+    async fn blob() -> ([(header::HeaderName, &'static str); 2], &'static str) {
+        (
+            [
+                (header::ETAG, "\"v1\""),
+                (header::LAST_MODIFIED, "Tue, 14 Nov 2023 22:13:20 GMT"),
+            ],
+            "0123456789",
+        )
+    }
+
+    #[route(GET "/big", ranges(max = "1KB"))]
+    async fn big() -> String {
+        "b".repeat(2048)
+    }
+
+    #[route(
+        GET "/reports/{page}",
+        require = ["reports"],
+        param_constraints((page, minimum = 1), enforce)
+    )]
+    async fn report(Path(page): Path<u32>) -> Json<u32> {
+        Json(page)
+    }
+
+    #[route(GET "/panics")]
+    async fn panics() -> Json<u32> {
+        panic!("handler bug")
+    }
+}
+
+/// Its own module, as every controller's generated docs module is `__UTOIPA_PATHS__`
+mod tenants {
+    use super::TestState;
+    use eywa_axum::controller;
+    use eywa_axum::prelude::*;
+
+    pub struct TenantController;
+
+    #[controller(
+        path = "/tenants",
+        state = TestState,
+        cors(origins = ["https://app.example.com"]),
+        security_headers,
+        request_id,
+        tenant(strategy = "header", header = "X-Org"),
+        throttle(per_minute = 2, key = "user", retry_after = "30s")
+    )]
+    impl TenantController {
+        #[route(GET "/current")]
+        async fn current(Extension(tenant): Extension<eywa_axum::Tenant>) -> String {
+            tenant.0
+        }
+    }
+}
+
+async fn served(request: Request<Body>) -> Reply {
+    let state = TestState::default();
+    send(ServedController::into_router(state.clone()), state, request).await
+}
+
+async fn tenants(request: Request<Body>) -> Reply {
+    let state = TestState::default();
+    send(
+        tenants::TenantController::into_router(state.clone()),
+        state,
+        request,
+    )
+    .await
+}
+
+#[tokio::test]
+async fn answers_cors_preflights() {
+    let preflight = Request::options("/api/served/documents/1")
+        .header("origin", "https://app.example.com")
+        .header("access-control-request-method", "GET")
+        .body(Body::empty())
+        .unwrap();
+    let reply = served(preflight).await;
+    assert!(reply.status.is_success(), "{}", reply.status);
+    assert_eq!(
+        reply.header("access-control-allow-origin"),
+        Some("https://app.example.com")
+    );
+    assert_eq!(reply.header("access-control-max-age"), Some("600"));
+
+    let request = Request::get("/api/served/raw")
+        .header("origin", "https://app.example.com")
+        .body(Body::empty())
+        .unwrap();
+    assert_eq!(
+        served(request).await.header("access-control-allow-origin"),
+        Some("https://app.example.com")
+    );
+}
+
+#[tokio::test]
+async fn sets_security_headers_and_request_ids() {
+    let reply = served(get("/api/served/raw")).await;
+    assert_eq!(
+        reply.header("content-security-policy"),
+        Some("default-src 'none'")
+    );
+    assert_eq!(reply.header("x-content-type-options"), Some("nosniff"));
+    assert!(
+        reply
+            .header("x-request-id")
+            .is_some_and(|id| !id.is_empty())
+    );
+
+    let request = Request::get("/api/served/raw")
+        .header("x-request-id", "req-1")
+        .body(Body::empty())
+        .unwrap();
+    assert_eq!(served(request).await.header("x-request-id"), Some("req-1"));
+}
+
+#[tokio::test]
+async fn turns_panics_into_problems() {
+    let reply = served(get("/api/served/panics")).await;
+    assert_eq!(reply.status, 500);
+    assert_eq!(
+        reply.header("content-type"),
+        Some("application/problem+json")
+    );
+}
+
+#[tokio::test]
+async fn limits_the_request_body() {
+    let small = post_json("/api/served/documents", r#"{"id":1,"body":"b"}"#);
+    assert_eq!(served(small).await.status, 200);
+
+    let large = format!(r#"{{"id":1,"body":"{}"}}"#, "b".repeat(100));
+    assert_eq!(
+        served(post_json("/api/served/documents", &large))
+            .await
+            .status,
+        413
+    );
+}
+
+#[tokio::test]
+async fn answers_conditional_gets() {
+    let reply = served(get("/api/served/documents/1")).await;
+    assert_eq!(reply.status, 200);
+    let last_modified = reply.header("last-modified").unwrap().to_string();
+    assert_eq!(last_modified, "Tue, 14 Nov 2023 22:13:20 GMT");
+
+    let request = Request::get("/api/served/documents/1")
+        .header("if-modified-since", last_modified)
+        .body(Body::empty())
+        .unwrap();
+    assert_eq!(served(request).await.status, 304);
+}
+
+#[tokio::test]
+async fn requires_if_match() {
+    let body = r#"{"id":1,"body":"b"}"#;
+    let put = |if_match: Option<&str>| {
+        let mut request =
+            Request::put("/api/served/documents/1").header("content-type", "application/json");
+        if let Some(tag) = if_match {
+            request = request.header("if-match", tag);
+        }
+        request.body(Body::from(body)).unwrap()
+    };
+    assert_eq!(served(put(None)).await.status, 428);
+    assert_eq!(served(put(Some("\"v1\""))).await.status, 200);
+}
+
+#[tokio::test]
+async fn compresses_per_route() {
+    let request = Request::get("/api/served/zipped")
+        .header("accept-encoding", "br, gzip")
+        .body(Body::empty())
+        .unwrap();
+    assert_eq!(
+        served(request).await.header("content-encoding"),
+        Some("gzip")
+    );
+
+    let request = Request::get("/api/served/raw")
+        .header("accept-encoding", "gzip")
+        .body(Body::empty())
+        .unwrap();
+    assert_eq!(
+        served(request).await.header("content-encoding"),
+        Some("identity")
+    );
+}
+
+#[tokio::test]
+async fn resolves_the_tenant() {
+    assert_eq!(tenants(get("/api/tenants/current")).await.status, 400);
+
+    let request = Request::get("/api/tenants/current")
+        .header("x-org", "acme")
+        .body(Body::empty())
+        .unwrap();
+    let reply = tenants(request).await;
+    assert_eq!(reply.status, 200);
+    assert_eq!(reply.body, "acme");
+}
+
+#[tokio::test]
+async fn throttles_per_principal() {
+    let request = |principal: &str| {
+        let mut request = Request::get("/api/tenants/current")
+            .header("x-org", "acme")
+            .body(Body::empty())
+            .unwrap();
+        request
+            .extensions_mut()
+            .insert(eywa_axum::Principal(principal.to_string()));
+        request
+    };
+    for remaining in ["1", "0"] {
+        let reply = tenants(request("throttled")).await;
+        assert_eq!(reply.status, 200);
+        assert_eq!(reply.header("x-ratelimit-remaining"), Some(remaining));
+    }
+    let reply = tenants(request("throttled")).await;
+    assert_eq!(reply.status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(reply.header("retry-after"), Some("30"));
+
+    assert_eq!(tenants(request("other")).await.status, 200);
+}
+
+fn ranged(path: &str, range: &str, if_range: Option<&str>) -> Request<Body> {
+    let mut request = Request::get(path).header("range", range);
+    if let Some(validator) = if_range {
+        request = request.header("if-range", validator);
+    }
+    request.body(Body::empty()).unwrap()
+}
+
+#[tokio::test]
+async fn serves_byte_ranges() {
+    let reply = served(ranged("/api/served/blob", "bytes=2-5", None)).await;
+    assert_eq!(reply.status, 206);
+    assert_eq!(reply.header("content-range"), Some("bytes 2-5/10"));
+    assert_eq!(reply.body, "2345");
+
+    let reply = served(ranged("/api/served/blob", "bytes=-3", None)).await;
+    assert_eq!(reply.status, 206);
+    assert_eq!(reply.body, "789");
+
+    let reply = served(ranged("/api/served/blob", "bytes=10-", None)).await;
+    assert_eq!(reply.status, 416);
+    assert_eq!(reply.header("content-range"), Some("bytes */10"));
+
+    let reply = served(ranged("/api/served/blob", "bytes=0-1,4-5", None)).await;
+    assert_eq!(reply.status, 200);
+    assert_eq!(reply.body, "0123456789");
+
+    let reply = served(get("/api/served/blob")).await;
+    assert_eq!(reply.status, 200);
+    assert_eq!(reply.header("accept-ranges"), Some("bytes"));
+}
+
+#[tokio::test]
+async fn evaluates_if_range() {
+    for validator in ["\"v1\"", "Tue, 14 Nov 2023 22:13:20 GMT"] {
+        let reply = served(ranged("/api/served/blob", "bytes=0-1", Some(validator))).await;
+        assert_eq!(reply.status, 206, "{validator}");
+        assert_eq!(reply.body, "01");
+    }
+    // Weak tags never match; other tags and dates are stale
+    for validator in [
+        "W/\"v1\"",
+        "\"v2\"",
+        "Wed, 15 Nov 2023 22:13:20 GMT",
+        "yesterday",
+    ] {
+        let reply = served(ranged("/api/served/blob", "bytes=0-1", Some(validator))).await;
+        assert_eq!(reply.status, 200, "{validator}");
+        assert_eq!(reply.body, "0123456789");
+    }
+}
+
+#[tokio::test]
+async fn serves_bodies_over_the_buffer_cap_whole() {
+    let reply = served(ranged("/api/served/big", "bytes=0-9", None)).await;
+    assert_eq!(reply.status, 200);
+    assert_eq!(reply.body.len(), 2048);
+}
+
+#[tokio::test]
+async fn answers_preflights_before_tenant_and_throttle() {
+    // More preflights than the throttle allows, none naming a tenant
+    for _ in 0..3 {
+        let preflight = Request::options("/api/tenants/current")
+            .header("origin", "https://app.example.com")
+            .header("access-control-request-method", "GET")
+            .body(Body::empty())
+            .unwrap();
+        let reply = tenants(preflight).await;
+        assert!(reply.status.is_success(), "{}", reply.status);
+        assert_eq!(reply.header("x-ratelimit-remaining"), None);
+    }
+}
+
+#[tokio::test]
+async fn decorates_responses_short_circuited_by_inner_layers() {
+    let reply = tenants(get("/api/tenants/current")).await;
+    assert_eq!(reply.status, 400);
+    assert!(reply.header("x-request-id").is_some());
+    assert_eq!(reply.header("x-content-type-options"), Some("nosniff"));
+}
+
+#[tokio::test]
+async fn checks_permissions_before_parameters() {
+    assert_eq!(served(get("/api/served/reports/0")).await.status, 401);
+
+    let mut request = get("/api/served/reports/0");
+    request
+        .extensions_mut()
+        .insert(eywa_axum::Permissions(vec!["reports".to_string()]));
+    assert_eq!(served(request).await.status, 400);
+}
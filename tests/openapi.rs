@@ -0,0 +1,243 @@
+//! `openapi_for!` over several controllers
+
+mod common;
+
+use common::{TestState, get, send};
+use eywa_axum::axum::extract::Request;
+use eywa_axum::axum::middleware::Next;
+use eywa_axum::axum::response::Response;
+use eywa_axum::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Timer {
+    pub id: u32,
+    pub running: bool,
+}
+
+fn timer_fixture() -> Timer {
+    Timer {
+        id: 1,
+        running: true,
+    }
+}
+
+async fn admin_marker(req: Request, next: Next) -> Response {
+    let mut response = next.run(req).await;
+    response.headers_mut().insert(
+        "x-admin",
+        eywa_axum::axum::http::HeaderValue::from_static("1"),
+    );
+    response
+}
+
+mod timers {
+    use super::{TestState, Timer};
+    use eywa_axum::controller;
+    use eywa_axum::prelude::*;
+
+    pub struct TimerController;
+
+    #[controller(path = "/timers", state = TestState, tag = "Timer", description = "Timer management")]
+    impl TimerController {
+        #[route(GET "/{id}")]
+        async fn get(Path(id): Path<u32>) -> Json<Timer> {
+            Json(Timer { id, running: false })
+        }
+    }
+}
+
+mod admin {
+    use super::TestState;
+    use eywa_axum::controller;
+    use eywa_axum::prelude::*;
+
+    pub struct AdminController;
+
+    #[controller(path = "/admin", state = TestState, tag = "Admin")]
+    impl AdminController {
+        #[route(GET "/timers/{timer_id}")]
+        async fn timer(Path(id): Path<u32>) -> Json<u32> {
+            Json(id)
+        }
+    }
+}
+
+eywa_axum::openapi_for! {
+    controllers = [timers, admin],
+    schemas = [Timer],
+    tags = [
+        (name = "Timer", description = "Timer management"),
+    ],
+    info = (
+        title = "Test API",
+        version = "1.2.3",
+    ),
+    schema_examples(Timer = timer_fixture()),
+    apply(middleware = admin_marker, to_tags = ["Admin"]),
+}
+
+#[test]
+fn builds_the_document() {
+    let openapi = eywa_axum::serde_json::to_value(ApiDoc::build_openapi()).unwrap();
+    assert_eq!(openapi["info"]["title"], "Test API");
+    assert_eq!(openapi["info"]["version"], "1.2.3");
+    assert_eq!(
+        openapi["components"]["schemas"]["Timer"]["examples"][0]["running"],
+        true
+    );
+    assert_eq!(
+        openapi["components"]["x-examples"]["Timer"]["value"]["id"],
+        1
+    );
+    let tags = openapi["tags"].as_array().unwrap();
+    assert!(
+        tags.iter()
+            .any(|tag| tag["name"] == "Timer" && tag["description"] == "Timer management")
+    );
+}
+
+#[test]
+fn finds_no_conflicts_between_distinct_routes() {
+    assert_eq!(ApiDoc::route_conflicts(), []);
+    ApiDoc::assert_no_route_conflicts();
+}
+
+#[tokio::test]
+async fn applies_middleware_by_tag() {
+    let state = TestState::default();
+    let admin = send(
+        ApiDoc::controller_router::<admin::AdminController, _>(state.clone()),
+        state.clone(),
+        get("/api/admin/timers/3"),
+    )
+    .await;
+    assert_eq!(admin.status, 200);
+    assert_eq!(admin.header("x-admin"), Some("1"));
+
+    let timer = send(
+        ApiDoc::controller_router::<timers::TimerController, _>(state.clone()),
+        state,
+        get("/api/timers/3"),
+    )
+    .await;
+    assert_eq!(timer.status, 200);
+    assert_eq!(timer.header("x-admin"), None);
+}
+
+#[test]
+fn contributes_fragments() {
+    let mut builder = eywa_axum::SpecBuilder::default();
+    timers::TimerController::contribute(&mut builder);
+    admin::AdminController::contribute(&mut builder);
+
+    let tags: Vec<&str> = builder.fragments.iter().map(|(tag, _)| *tag).collect();
+    assert_eq!(tags, ["Timer", "Admin"]);
+    let timers = (builder.fragments[0].1)();
+    assert!(timers.paths.paths.contains_key("/api/timers/{id}"));
+}
+
+/// Controllers whose routes collide; only their route tables are inspected
+#[allow(dead_code)]
+mod clashing {
+    pub mod legacy {
+        use crate::TestState;
+        use eywa_axum::controller;
+
+        pub struct LegacyController;
+
+        #[controller(path = "/files", state = TestState, tag = "Legacy")]
+        impl LegacyController {
+            #[route(ANY "/{*rest}", proxy = "http://legacy:8080/")]
+            async fn forward() {}
+        }
+    }
+
+    pub mod uploads {
+        use crate::TestState;
+        use eywa_axum::controller;
+        use eywa_axum::prelude::*;
+
+        pub struct UploadsController;
+
+        #[controller(path = "/files/uploads", state = TestState, tag = "Uploads")]
+        impl UploadsController {
+            #[route(GET "/{id}")]
+            async fn get(Path(id): Path<u32>) -> Json<u32> {
+                Json(id)
+            }
+
+            #[route(GET "/export")]
+            async fn export() -> Json<u32> {
+                Json(0)
+            }
+        }
+    }
+
+    pub mod attachments {
+        use crate::TestState;
+        use eywa_axum::controller;
+        use eywa_axum::prelude::*;
+
+        pub struct AttachmentsController;
+
+        #[controller(path = "/files/uploads", state = TestState, tag = "Attachments")]
+        impl AttachmentsController {
+            #[route(GET "/{upload_id}")]
+            async fn get(Path(id): Path<u32>) -> Json<u32> {
+                Json(id)
+            }
+
+            #[route(POST "/{upload_id}")]
+            async fn replace(Path(id): Path<u32>) -> Json<u32> {
+                Json(id)
+            }
+        }
+    }
+
+    eywa_axum::openapi_for! {
+        controllers = [legacy, uploads, attachments],
+    }
+}
+
+#[test]
+fn reports_identical_and_shadowed_routes() {
+    use clashing::{ApiDoc, ConflictKind, RouteConflict};
+
+    let conflicts = ApiDoc::route_conflicts();
+    let shadowed = |second: (&'static str, &'static str)| RouteConflict {
+        method: "ANY",
+        first: ("LegacyController", "/api/files/{*rest}"),
+        second,
+        kind: ConflictKind::Shadowed,
+    };
+    assert_eq!(
+        conflicts,
+        [
+            shadowed(("UploadsController", "/api/files/uploads/{id}")),
+            shadowed(("UploadsController", "/api/files/uploads/export")),
+            shadowed(("AttachmentsController", "/api/files/uploads/{upload_id}")),
+            RouteConflict {
+                method: "GET",
+                first: ("UploadsController", "/api/files/uploads/{id}"),
+                second: ("AttachmentsController", "/api/files/uploads/{upload_id}"),
+                kind: ConflictKind::Identical,
+            },
+        ]
+    );
+    assert_eq!(
+        conflicts[0].to_string(),
+        "ANY /api/files/{*rest} (LegacyController) is shadowed by /api/files/uploads/{id} (UploadsController)"
+    );
+    assert_eq!(
+        conflicts[3].to_string(),
+        "GET /api/files/uploads/{id} (UploadsController) is also registered by \
+         /api/files/uploads/{upload_id} (AttachmentsController)"
+    );
+}
+
+#[test]
+#[should_panic(expected = "conflicting routes")]
+fn panics_on_conflicts() {
+    clashing::ApiDoc::assert_no_route_conflicts();
+}
@@ -0,0 +1,396 @@
+//! Stand-in for the `eywa_axum` runtime crate.
+//!
+//! Exposes the items the generated code names (`eywa_axum::…`) so the macro tests
+//! compile and run real controllers. Collaborators that talk to the outside world
+//! (event publisher, transactions, proxy, mirror) record their calls instead.
+
+pub use axum;
+pub use axum::Json;
+pub use csv;
+pub use eywa_axum_macros::{ApiError, controller, openapi_for, route};
+pub use hmac;
+pub use httpdate;
+pub use quick_xml;
+pub use serde::{Deserialize, Serialize};
+pub use serde_json;
+pub use sha2;
+pub use tokio;
+pub use tower;
+pub use tower_http;
+pub use tracing;
+pub use utoipa;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Handler result with a bare status code as the default error
+pub type Result<T, E = axum::http::StatusCode> = std::result::Result<T, E>;
+
+/// Route metadata exposed by `IntoRouter::openapi_routes`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpenApiPath {
+    pub path: &'static str,
+    pub method: &'static str,
+    pub summary: &'static str,
+    pub description: &'static str,
+    pub tag: &'static str,
+}
+
+/// Controllers turned into routers by `#[controller]`
+pub trait IntoRouter<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    fn into_router(state: S) -> axum::Router<S>;
+    fn prefix() -> &'static str;
+    fn tag() -> &'static str;
+    fn openapi_routes() -> &'static [OpenApiPath];
+    fn register_schemas(components: &mut utoipa::openapi::Components);
+    fn register_paths(openapi: &mut utoipa::openapi::OpenApi);
+}
+
+pub mod prelude {
+    pub use super::{CursorPage, HateoasResponse, IntoRouter, Json, Link, Result};
+    pub use axum::extract::{Extension, Path, Query, State};
+}
+
+/// A hypermedia link in a `HateoasResponse`
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct Link {
+    pub href: String,
+    pub method: String,
+}
+
+impl Link {
+    pub fn new(href: &str) -> Self {
+        Self {
+            href: href.to_string(),
+            method: "GET".to_string(),
+        }
+    }
+
+    pub fn method(mut self, method: &str) -> Self {
+        self.method = method.to_string();
+        self
+    }
+}
+
+/// A payload with its hypermedia links
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HateoasResponse<T> {
+    pub data: T,
+    pub links: HashMap<String, Link>,
+}
+
+impl<T> HateoasResponse<T> {
+    pub fn new(data: T) -> Self {
+        Self {
+            data,
+            links: HashMap::new(),
+        }
+    }
+
+    pub fn add_link(mut self, rel: &str, link: Link) -> Self {
+        self.links.insert(rel.to_string(), link);
+        self
+    }
+}
+
+/// A page of a cursor-paginated collection
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct CursorPage<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+}
+
+/// Default status body of `async_job` routes
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct JobStatus {
+    pub id: String,
+    pub state: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Tenant(pub String);
+
+#[derive(Debug, Clone)]
+pub struct Locale(pub String);
+
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+#[derive(Debug, Clone)]
+pub struct Principal(pub String);
+
+#[derive(Debug, Clone)]
+pub struct Permissions(pub Vec<String>);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DbRouting {
+    Primary,
+    Replica,
+}
+
+/// Builds one controller's standalone OpenAPI document
+pub type Fragment = fn() -> utoipa::openapi::OpenApi;
+
+/// Collects the fragments controllers register with `contribute`
+#[derive(Default)]
+pub struct SpecBuilder {
+    pub fragments: Vec<(&'static str, Fragment)>,
+}
+
+impl SpecBuilder {
+    pub fn add(&mut self, tag: &'static str, fragment: Fragment) {
+        self.fragments.push((tag, fragment));
+    }
+}
+
+/// Shared call log of the recording collaborators below
+pub type CallLog = Arc<Mutex<Vec<String>>>;
+
+/// Records published events as `"<name> <json>"`; the event `broken` fails
+#[derive(Clone, Default)]
+pub struct EventPublisher {
+    pub log: CallLog,
+}
+
+impl EventPublisher {
+    pub async fn publish<E: Serialize>(&self, name: &str, event: &E) -> Result<(), String> {
+        if name == "broken" {
+            return Err("queue down".to_string());
+        }
+        let json = serde_json::to_string(event).map_err(|err| err.to_string())?;
+        self.log.lock().unwrap().push(format!("{name} {json}"));
+        Ok(())
+    }
+}
+
+/// Begins transactions that record `begin`, `commit` and `rollback`
+#[derive(Clone, Default)]
+pub struct TransactionFactory {
+    pub log: CallLog,
+}
+
+impl TransactionFactory {
+    pub async fn begin(&self) -> Result<Transaction, String> {
+        self.log.lock().unwrap().push("begin".to_string());
+        Ok(Transaction {
+            log: self.log.clone(),
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct Transaction {
+    log: CallLog,
+}
+
+impl Transaction {
+    pub async fn commit(self) -> Result<(), String> {
+        self.log.lock().unwrap().push("commit".to_string());
+        Ok(())
+    }
+
+    pub async fn rollback(self) -> Result<(), String> {
+        self.log.lock().unwrap().push("rollback".to_string());
+        Ok(())
+    }
+}
+
+/// Grants the policies it was built with
+#[derive(Clone, Default)]
+pub struct Authorizer {
+    pub granted: Vec<&'static str>,
+}
+
+impl Authorizer {
+    pub async fn authorize(
+        &self,
+        policy: &str,
+        params: &[(&str, &str)],
+        extensions: &axum::http::Extensions,
+    ) -> bool {
+        let _ = (params, extensions);
+        self.granted.contains(&policy)
+    }
+}
+
+/// Answers every request itself, echoing the upstream URI it was sent to
+#[derive(Clone, Default)]
+pub struct ProxyClient;
+
+impl ProxyClient {
+    pub async fn send(
+        &self,
+        req: axum::extract::Request,
+    ) -> Result<axum::response::Response, std::io::Error> {
+        let mut response =
+            axum::response::Response::new(axum::body::Body::from(req.uri().to_string()));
+        response
+            .headers_mut()
+            .insert("connection", axum::http::HeaderValue::from_static("close"));
+        Ok(response)
+    }
+}
+
+pub mod mirror {
+    use std::sync::Mutex;
+
+    /// `"<METHOD> <url><path>"` of every mirrored request
+    pub static FORWARDED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+    pub fn forward(url: &'static str, req: axum::extract::Request) {
+        FORWARDED
+            .lock()
+            .unwrap()
+            .push(format!("{} {url}{}", req.method(), req.uri()));
+    }
+}
+
+pub mod async_graphql {
+    pub mod http {
+        pub struct GraphQLPlaygroundConfig(String);
+
+        impl GraphQLPlaygroundConfig {
+            pub fn new(endpoint: &str) -> Self {
+                Self(endpoint.to_string())
+            }
+        }
+
+        pub fn playground_source(config: GraphQLPlaygroundConfig) -> String {
+            format!("<html>playground {}</html>", config.0)
+        }
+    }
+
+    pub struct Request {
+        pub query: String,
+    }
+
+    pub struct Response(pub String);
+}
+
+pub mod async_graphql_axum {
+    use super::async_graphql::{Request, Response};
+    use axum::http::StatusCode;
+
+    pub struct GraphQLRequest(Request);
+
+    impl GraphQLRequest {
+        pub fn into_inner(self) -> Request {
+            self.0
+        }
+    }
+
+    impl<S: Send + Sync> axum::extract::FromRequest<S> for GraphQLRequest {
+        type Rejection = StatusCode;
+
+        async fn from_request(req: axum::extract::Request, _: &S) -> Result<Self, StatusCode> {
+            let body = axum::body::to_bytes(req.into_body(), usize::MAX)
+                .await
+                .map_err(|_| StatusCode::BAD_REQUEST)?;
+            Ok(Self(Request {
+                query: String::from_utf8_lossy(&body).into_owned(),
+            }))
+        }
+    }
+
+    pub struct GraphQLResponse(Response);
+
+    impl From<Response> for GraphQLResponse {
+        fn from(response: Response) -> Self {
+            Self(response)
+        }
+    }
+
+    impl axum::response::IntoResponse for GraphQLResponse {
+        fn into_response(self) -> axum::response::Response {
+            self.0.0.into_response()
+        }
+    }
+}
+
+/// Helpers behind `#[controller(compact_codegen)]`
+#[doc(hidden)]
+pub mod __private {
+    use std::collections::BTreeMap;
+    use std::marker::PhantomData;
+    use utoipa::openapi::{Components, OpenApi, RefOr, path::Operation, response::Response};
+
+    pub fn register_schema<T: utoipa::ToSchema>(components: &mut Components) {
+        components
+            .schemas
+            .insert(T::name().to_string(), T::schema());
+    }
+
+    pub fn add_operation<P: utoipa::Path>(
+        openapi: &mut OpenApi,
+        tag: &str,
+        customize: impl FnOnce(&mut Operation),
+    ) {
+        let mut operation = P::operation();
+        if !tag.is_empty() {
+            operation
+                .tags
+                .get_or_insert_with(Vec::new)
+                .push(tag.to_string());
+        }
+        customize(&mut operation);
+        openapi
+            .paths
+            .add_path_operation(P::path(), P::methods(), operation);
+    }
+
+    pub fn default_responses(operation: &mut Operation, responses: &[(u16, &str)]) {
+        for (status, description) in responses {
+            operation
+                .responses
+                .responses
+                .entry(status.to_string())
+                .or_insert_with(|| Response::new(*description).into());
+        }
+    }
+
+    pub fn merge_responses(
+        operation: &mut Operation,
+        responses: BTreeMap<String, RefOr<Response>>,
+    ) {
+        for (status, response) in responses {
+            operation
+                .responses
+                .responses
+                .entry(status)
+                .or_insert(response);
+        }
+    }
+
+    /// Autoref probe for whether a handler's error type implements `IntoResponses`
+    pub struct ErrorProbe<E>(PhantomData<E>);
+
+    impl<E> ErrorProbe<E> {
+        #[allow(clippy::new_without_default)]
+        pub fn new() -> Self {
+            Self(PhantomData)
+        }
+    }
+
+    pub trait DocumentedErrors {
+        fn responses(&self) -> BTreeMap<String, RefOr<Response>>;
+    }
+
+    impl<E: utoipa::IntoResponses> DocumentedErrors for ErrorProbe<E> {
+        fn responses(&self) -> BTreeMap<String, RefOr<Response>> {
+            E::responses()
+        }
+    }
+
+    pub trait UndocumentedErrors {
+        fn responses(&self) -> BTreeMap<String, RefOr<Response>>;
+    }
+
+    impl<E> UndocumentedErrors for &ErrorProbe<E> {
+        fn responses(&self) -> BTreeMap<String, RefOr<Response>> {
+            BTreeMap::new()
+        }
+    }
+}
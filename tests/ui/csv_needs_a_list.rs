@@ -0,0 +1,15 @@
+use eywa_axum::prelude::*;
+
+pub type AppState = ();
+
+pub struct ReportsController;
+
+#[eywa_axum::controller(path = "/reports", state = AppState)]
+impl ReportsController {
+    #[route(GET "/latest", csv)]
+    async fn latest() -> Json<String> {
+        Json(String::new())
+    }
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+pub type AppState = ();
+
+pub struct RepositoryController<T>(std::marker::PhantomData<T>);
+
+#[eywa_axum::controller(path = "/repositories", state = AppState)]
+impl<T: Send + Sync + 'static> RepositoryController<T> {
+    #[route(GET "/")]
+    async fn list() -> &'static str {
+        "[]"
+    }
+}
+
+fn main() {}
@@ -0,0 +1,20 @@
+use eywa_axum::prelude::*;
+
+pub type AppState = ();
+
+#[derive(eywa_axum::Deserialize, utoipa::ToSchema)]
+pub struct Filter {
+    pub name: String,
+}
+
+pub struct SearchController;
+
+#[eywa_axum::controller(path = "/search", state = AppState)]
+impl SearchController {
+    #[route(GET "/")]
+    async fn search(Json(filter): Json<Filter>) -> Json<String> {
+        Json(filter.name)
+    }
+}
+
+fn main() {}
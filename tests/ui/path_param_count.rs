@@ -0,0 +1,15 @@
+use eywa_axum::prelude::*;
+
+pub type AppState = ();
+
+pub struct ProjectsController;
+
+#[eywa_axum::controller(path = "/projects", state = AppState)]
+impl ProjectsController {
+    #[route(GET "/{project_id}/tasks/{id}")]
+    async fn task(Path(id): Path<u32>) -> Json<u32> {
+        Json(id)
+    }
+}
+
+fn main() {}
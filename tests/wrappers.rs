@@ -0,0 +1,404 @@
+//! Handler wrappers generated for route attributes, run against the runtime's collaborators
+
+mod common;
+
+use common::{Reply, TestState, get, post_json, send};
+use eywa_axum::axum::body::Body;
+use eywa_axum::axum::http::request::Parts;
+use eywa_axum::axum::http::{Request, StatusCode};
+use eywa_axum::axum::response::{IntoResponse, Response};
+use eywa_axum::hmac::{Hmac, Mac};
+use eywa_axum::prelude::*;
+use eywa_axum::sha2::Sha256;
+use eywa_axum::{Authorizer, Deserialize, Serialize, Transaction, controller};
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Project {
+    pub id: u32,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ProjectCreated {
+    pub id: u32,
+}
+
+impl From<&Project> for ProjectCreated {
+    fn from(project: &Project) -> Self {
+        Self { id: project.id }
+    }
+}
+
+/// Async GraphQL schema stand-in answering with the query it ran
+#[derive(Clone)]
+pub struct EchoSchema;
+
+impl EchoSchema {
+    async fn execute(
+        &self,
+        request: eywa_axum::async_graphql::Request,
+    ) -> eywa_axum::async_graphql::Response {
+        eywa_axum::async_graphql::Response(format!("ran {}", request.query))
+    }
+}
+
+impl eywa_axum::axum::extract::FromRef<TestState> for EchoSchema {
+    fn from_ref(_: &TestState) -> Self {
+        EchoSchema
+    }
+}
+
+const SECRET: &[u8] = b"k3y";
+
+fn secret(_: &TestState) -> &'static [u8] {
+    SECRET
+}
+
+async fn owner_only(parts: &mut Parts, _: &TestState) -> Result<(), Response> {
+    if parts.headers.contains_key("x-owner") {
+        Ok(())
+    } else {
+        Err(StatusCode::NOT_FOUND.into_response())
+    }
+}
+
+pub struct WrappedController;
+
+#[controller(
+    path = "/wrapped",
+    state = TestState,
+    graphql(schema = EchoSchema, path = "/graphql", playground)
+)]
+impl WrappedController {
+    #[route(POST "/published", publish(event = ProjectCreated, from = "response"))]
+    async fn published(Json(project): Json<Project>) -> Json<Project> {
+        Json(project)
+    }
+
+    #[route(POST "/transactional/{ok}", transactional)]
+    async fn transactional(
+        Path(ok): Path<bool>,
+        Extension(_transaction): Extension<Transaction>,
+    ) -> StatusCode {
+        if ok {
+            StatusCode::OK
+        } else {
+            StatusCode::CONFLICT
+        }
+    }
+
+    #[route(GET "/policed/{id}", policy = "project:read")]
+    async fn policed(Path(id): Path<u32>) -> Json<u32> {
+        Json(id)
+    }
+
+    #[route(GET "/guarded", guard = owner_only, guard_status = 404)]
+    async fn guarded() -> Json<u32> {
+        Json(1)
+    }
+
+    #[route(
+        POST "/layered/{id}",
+        guard = owner_only,
+        guard_status = 404,
+        policy = "project:write",
+        transactional,
+        publish(event = ProjectCreated, from = "response")
+    )]
+    async fn layered(
+        Path(id): Path<u32>,
+        Extension(_transaction): Extension<Transaction>,
+    ) -> Json<Project> {
+        Json(Project {
+            id,
+            name: "layered".to_string(),
+        })
+    }
+
+    #[route(GET "/admin", require = ["admin"])]
+    async fn admin() -> Json<u32> {
+        Json(1)
+    }
+
+    #[route(GET "/download/{id}", signed(secret = secret))]
+    async fn download(Path(id): Path<u32>) -> Json<u32> {
+        Json(id)
+    }
+
+    #[route(POST "/hook", verify_signature(header = "X-Signature", algo = "hmac-sha256", secret = secret))]
+    async fn hook(Json(project): Json<Project>) -> Json<Project> {
+        Json(project)
+    }
+
+    #[route(POST "/mirrored", mirror_to = "http://shadow:8080")]
+    async fn mirrored(Json(project): Json<Project>) -> Json<Project> {
+        Json(project)
+    }
+
+    #[route(GET "/split", canary(stable = split_stable, canary = split_canary, percent = 50))]
+    async fn split() {}
+
+    async fn split_stable() -> Json<&'static str> {
+        Json("stable")
+    }
+
+    async fn split_canary() -> Json<&'static str> {
+        Json("canary")
+    }
+
+    #[route(ANY "/legacy/{*rest}", proxy = "http://legacy:8080/base/")]
+    async fn legacy() {}
+}
+
+async fn call(state: &TestState, request: Request<Body>) -> Reply {
+    send(
+        WrappedController::into_router(state.clone()),
+        state.clone(),
+        request,
+    )
+    .await
+}
+
+fn hmac_hex(message: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(SECRET).unwrap();
+    mac.update(message);
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+#[tokio::test]
+async fn publishes_the_event_after_success() {
+    let state = TestState::default();
+    let reply = call(
+        &state,
+        post_json("/api/wrapped/published", r#"{"id":3,"name":"a"}"#),
+    )
+    .await;
+    assert_eq!(reply.status, 200);
+    assert_eq!(
+        *state.publisher.log.lock().unwrap(),
+        [r#"ProjectCreated {"id":3}"#]
+    );
+}
+
+#[tokio::test]
+async fn commits_or_rolls_back_the_transaction() {
+    let state = TestState::default();
+    assert_eq!(
+        call(&state, post_json("/api/wrapped/transactional/true", ""))
+            .await
+            .status,
+        200
+    );
+    assert_eq!(
+        call(&state, post_json("/api/wrapped/transactional/false", ""))
+            .await
+            .status,
+        409
+    );
+    assert_eq!(
+        *state.transactions.log.lock().unwrap(),
+        ["begin", "commit", "begin", "rollback"]
+    );
+}
+
+#[tokio::test]
+async fn checks_the_policy() {
+    let denied = TestState::default();
+    assert_eq!(
+        call(&denied, get("/api/wrapped/policed/1")).await.status,
+        403
+    );
+
+    let granted = TestState {
+        authorizer: Authorizer {
+            granted: vec!["project:read"],
+        },
+        ..TestState::default()
+    };
+    let reply = call(&granted, get("/api/wrapped/policed/1")).await;
+    assert_eq!(reply.status, 200);
+    assert_eq!(reply.body, "1");
+}
+
+#[tokio::test]
+async fn runs_the_guard_first() {
+    let state = TestState::default();
+    assert_eq!(call(&state, get("/api/wrapped/guarded")).await.status, 404);
+
+    let request = Request::get("/api/wrapped/guarded")
+        .header("x-owner", "1")
+        .body(Body::empty())
+        .unwrap();
+    assert_eq!(call(&state, request).await.status, 200);
+}
+
+#[tokio::test]
+async fn runs_wrappers_in_the_documented_order() {
+    // One log for both collaborators, so their calls interleave in it
+    let log = eywa_axum::CallLog::default();
+    let state = |granted: Vec<&'static str>| TestState {
+        publisher: eywa_axum::EventPublisher { log: log.clone() },
+        transactions: eywa_axum::TransactionFactory { log: log.clone() },
+        authorizer: Authorizer { granted },
+        ..TestState::default()
+    };
+    let request = |owner: bool| {
+        let mut request = post_json("/api/wrapped/layered/7", "");
+        if owner {
+            request
+                .headers_mut()
+                .insert("x-owner", "1".parse().unwrap());
+        }
+        request
+    };
+
+    // The guard answers before the policy is asked, neither begins a transaction
+    let denied = state(vec![]);
+    assert_eq!(call(&denied, request(false)).await.status, 404);
+    assert_eq!(call(&denied, request(true)).await.status, 403);
+    assert!(log.lock().unwrap().is_empty());
+
+    // The event is published inside the transaction
+    let granted = state(vec!["project:write"]);
+    assert_eq!(call(&granted, request(true)).await.status, 200);
+    assert_eq!(
+        *log.lock().unwrap(),
+        ["begin", r#"ProjectCreated {"id":7}"#, "commit"]
+    );
+}
+
+#[tokio::test]
+async fn requires_permissions() {
+    let state = TestState::default();
+    assert_eq!(call(&state, get("/api/wrapped/admin")).await.status, 401);
+
+    let with = |permissions: &[&str]| {
+        let mut request = get("/api/wrapped/admin");
+        request.extensions_mut().insert(eywa_axum::Permissions(
+            permissions.iter().map(|p| p.to_string()).collect(),
+        ));
+        request
+    };
+    assert_eq!(call(&state, with(&["billing"])).await.status, 403);
+    assert_eq!(call(&state, with(&["admin"])).await.status, 200);
+}
+
+#[tokio::test]
+async fn serves_signed_links_only() {
+    let state = TestState::default();
+    let message = "/api/wrapped/download/5?expires=4102444800";
+    let signed = format!("{message}&sig={}", hmac_hex(message.as_bytes()));
+    let reply = call(&state, get(&signed)).await;
+    assert_eq!(reply.status, 200);
+    assert_eq!(reply.body, "5");
+
+    let forged = format!("{message}&sig={}", hmac_hex(b"/api/wrapped/download/6"));
+    assert_eq!(call(&state, get(&forged)).await.status, 403);
+    assert_eq!(
+        call(&state, get("/api/wrapped/download/5")).await.status,
+        403
+    );
+}
+
+#[tokio::test]
+async fn verifies_webhook_signatures() {
+    let state = TestState::default();
+    let body = r#"{"id":1,"name":"hook"}"#;
+    let signed = |signature: String| {
+        Request::post("/api/wrapped/hook")
+            .header("content-type", "application/json")
+            .header("x-signature", signature)
+            .body(Body::from(body))
+            .unwrap()
+    };
+
+    let reply = call(
+        &state,
+        signed(format!("sha256={}", hmac_hex(body.as_bytes()))),
+    )
+    .await;
+    assert_eq!(reply.status, 200);
+    assert_eq!(reply.body, body);
+
+    assert_eq!(call(&state, signed(hmac_hex(b"other"))).await.status, 401);
+    assert_eq!(
+        call(&state, post_json("/api/wrapped/hook", body))
+            .await
+            .status,
+        401
+    );
+}
+
+#[tokio::test]
+async fn mirrors_to_the_upstream() {
+    let state = TestState::default();
+    let reply = call(
+        &state,
+        post_json("/api/wrapped/mirrored", r#"{"id":2,"name":"m"}"#),
+    )
+    .await;
+    assert_eq!(reply.status, 200);
+
+    // The copy is sent from a spawned task
+    for _ in 0..100 {
+        if !eywa_axum::mirror::FORWARDED.lock().unwrap().is_empty() {
+            break;
+        }
+        eywa_axum::tokio::task::yield_now().await;
+    }
+    assert_eq!(
+        *eywa_axum::mirror::FORWARDED.lock().unwrap(),
+        ["POST http://shadow:8080/api/wrapped/mirrored"]
+    );
+}
+
+#[tokio::test]
+async fn splits_canary_traffic_by_key() {
+    let state = TestState::default();
+    let reply = call(&state, get("/api/wrapped/split")).await;
+    assert_eq!(reply.body, r#""stable""#);
+
+    let mut served = std::collections::BTreeSet::new();
+    for user in 0..64 {
+        let request = Request::get("/api/wrapped/split")
+            .header("x-user-id", user.to_string())
+            .body(Body::empty())
+            .unwrap();
+        let first = call(&state, request).await.body;
+        let request = Request::get("/api/wrapped/split")
+            .header("x-user-id", user.to_string())
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(call(&state, request).await.body, first, "user {user}");
+        served.insert(first);
+    }
+    assert_eq!(served.len(), 2);
+}
+
+#[tokio::test]
+async fn proxies_to_the_upstream() {
+    let state = TestState::default();
+    let reply = call(&state, get("/api/wrapped/legacy/a/b?c=d")).await;
+    assert_eq!(reply.status, 200);
+    assert_eq!(reply.body, "http://legacy:8080/base/a/b?c=d");
+    assert_eq!(reply.header("connection"), None);
+}
+
+#[tokio::test]
+async fn mounts_graphql() {
+    let state = TestState::default();
+    let reply = call(&state, post_json("/api/wrapped/graphql", "{ projects }")).await;
+    assert_eq!(reply.body, "ran { projects }");
+
+    let playground = call(&state, get("/api/wrapped/graphql")).await;
+    assert!(
+        playground.body.contains("playground"),
+        "{}",
+        playground.body
+    );
+}